@@ -1,8 +1,10 @@
+use elfio::binary::BinaryOptions;
 use elfio::ehdr::*;
 use elfio::*;
 use serde::de::DeserializeOwned;
 use std::env::args_os;
-use std::io::{BufReader, Read};
+use std::ffi::OsString;
+use std::io::{BufReader, BufWriter, Read, Write};
 use std::io::{Seek, SeekFrom};
 use std::path::Path;
 use std::process::exit;
@@ -36,15 +38,81 @@ fn deserialize<T: DeserializeOwned>(
     }
 }
 
+fn usage(args: &[OsString]) -> ! {
+    let name = args
+        .first()
+        .and_then(|s| Path::new(s).file_name())
+        .unwrap_or(OsStr::new(env!("CARGO_CRATE_NAME")));
+    println!("Usage:\n\t{} elf-file", name.to_string_lossy());
+    println!(
+        "\t{} binary [--vaddr] [--gap-fill byte] [-j section]... elf-file output-file",
+        name.to_string_lossy()
+    );
+    exit(1);
+}
+
+/// Byte given in decimal or with a `0x` prefix in hexadecimal
+fn parse_byte(arg: Option<&OsString>) -> Option<u8> {
+    let arg = arg?.to_str()?;
+    match arg.strip_prefix("0x") {
+        Some(hex) => u8::from_str_radix(hex, 16).ok(),
+        None => arg.parse().ok(),
+    }
+}
+
+/// Write the raw image of the loadable data, like `objcopy -O binary`
+fn binary(args: &[OsString]) {
+    let mut options = BinaryOptions {
+        physical: true,
+        ..Default::default()
+    };
+    let mut sections = Vec::new();
+    let mut files = Vec::new();
+    let mut rest = args[2..].iter();
+    while let Some(arg) = rest.next() {
+        match arg.to_str() {
+            Some("--vaddr") => options.physical = false,
+            Some("--gap-fill") => {
+                options.fill = parse_byte(rest.next()).unwrap_or_else(|| usage(args));
+            }
+            Some("-j") => match rest.next().and_then(|s| s.to_str()) {
+                Some(section) => sections.push(section),
+                None => usage(args),
+            },
+            Some(option) if option.starts_with('-') => usage(args),
+            _ => files.push(arg),
+        }
+    }
+    if files.len() != 2 {
+        usage(args);
+    }
+    options.sections = &sections;
+
+    let data = std::fs::read(files[0]).unwrap();
+    let file = ElfFile::new(&data).unwrap();
+    let mut output = BufWriter::new(File::create(files[1]).unwrap());
+    let start = file
+        .binary(&options, |data| {
+            output.write_all(data).unwrap();
+            Ok(())
+        })
+        .unwrap();
+    output.flush().unwrap();
+    match start {
+        Some(address) => println!("Image starts at 0x{:x}", address),
+        None => println!("No loadable data"),
+    }
+}
+
 fn main() {
     let args: Vec<_> = args_os().collect();
+    // A lone argument is always the file to read, even if it is named `binary`
+    if args.len() > 2 && args[1] == "binary" {
+        binary(&args);
+        return;
+    }
     if args.len() != 2 {
-        let name = args
-            .get(0)
-            .and_then(|s| Path::new(s).file_name())
-            .unwrap_or(OsStr::new(env!("CARGO_CRATE_NAME")));
-        println!("Usage:\n\t{} elf-file", name.to_string_lossy());
-        exit(1);
+        usage(&args);
     }
     let filename = Path::new(&args[1]);
     let f = File::open(filename).unwrap();
@@ -81,7 +149,10 @@ fn main() {
     println!("  Entry point: 0x{:x}", ehdr.e_entry);
     println!("  Phdr offset: {}", ehdr.e_phoff);
     println!("  Shdr offset: {}", ehdr.e_shoff);
-    println!("  Flags:       {:?}", ehdr.e_flags);
+    println!(
+        "  Flags:       {}",
+        ehdr.e_flags.for_target(ehdr.e_machine, ehdr.e_ident.osabi)
+    );
     println!("  Ehdr size:   {}", ehdr.e_ehsize);
     println!("  Phdr size:   {}", ehdr.e_phentsize);
     println!("  Phdr count:  {}", ehdr.e_phnum);
@@ -102,5 +173,5 @@ fn main() {
     println!("Program Headers");
     println!("---------------");
 
-    println!("");
+    println!();
 }
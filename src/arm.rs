@@ -0,0 +1,865 @@
+//! ARM specific data
+//!
+//! Build attributes of the `aeabi` vendor, as defined by the ARM "Addenda to, and Errata
+//! in, the ABI for the Arm Architecture", and the exception handling tables of the
+//! "Exception Handling ABI for the Arm Architecture" (`.ARM.exidx` and `.ARM.extab`).
+
+use crate::attributes::{Group, Value};
+use crate::de::from_bytes;
+use crate::phdr::PT;
+use crate::shdr::SHT;
+use crate::{ElfFile, Error};
+use core::convert::TryFrom;
+use core::fmt;
+
+enum_struct!(
+/// `aeabi` attribute tags
+pub struct Tag(u64) {
+    FILE                     = 1  => "Tag_File",
+    SECTION                  = 2  => "Tag_Section",
+    SYMBOL                   = 3  => "Tag_Symbol",
+    CPU_RAW_NAME             = 4  => "Tag_CPU_raw_name",
+    CPU_NAME                 = 5  => "Tag_CPU_name",
+    CPU_ARCH                 = 6  => "Tag_CPU_arch",
+    CPU_ARCH_PROFILE         = 7  => "Tag_CPU_arch_profile",
+    ARM_ISA_USE              = 8  => "Tag_ARM_ISA_use",
+    THUMB_ISA_USE            = 9  => "Tag_THUMB_ISA_use",
+    FP_ARCH                  = 10 => "Tag_FP_arch",
+    WMMX_ARCH                = 11 => "Tag_WMMX_arch",
+    ADVANCED_SIMD_ARCH       = 12 => "Tag_Advanced_SIMD_arch",
+    PCS_CONFIG               = 13 => "Tag_PCS_config",
+    ABI_PCS_R9_USE           = 14 => "Tag_ABI_PCS_R9_use",
+    ABI_PCS_RW_DATA          = 15 => "Tag_ABI_PCS_RW_data",
+    ABI_PCS_RO_DATA          = 16 => "Tag_ABI_PCS_RO_data",
+    ABI_PCS_GOT_USE          = 17 => "Tag_ABI_PCS_GOT_use",
+    ABI_PCS_WCHAR_T          = 18 => "Tag_ABI_PCS_wchar_t",
+    ABI_FP_ROUNDING          = 19 => "Tag_ABI_FP_rounding",
+    ABI_FP_DENORMAL          = 20 => "Tag_ABI_FP_denormal",
+    ABI_FP_EXCEPTIONS        = 21 => "Tag_ABI_FP_exceptions",
+    ABI_FP_USER_EXCEPTIONS   = 22 => "Tag_ABI_FP_user_exceptions",
+    ABI_FP_NUMBER_MODEL      = 23 => "Tag_ABI_FP_number_model",
+    ABI_ALIGN_NEEDED         = 24 => "Tag_ABI_align_needed",
+    ABI_ALIGN_PRESERVED      = 25 => "Tag_ABI_align_preserved",
+    ABI_ENUM_SIZE            = 26 => "Tag_ABI_enum_size",
+    ABI_HARDFP_USE           = 27 => "Tag_ABI_HardFP_use",
+    ABI_VFP_ARGS             = 28 => "Tag_ABI_VFP_args",
+    ABI_WMMX_ARGS            = 29 => "Tag_ABI_WMMX_args",
+    ABI_OPTIMIZATION_GOALS   = 30 => "Tag_ABI_optimization_goals",
+    ABI_FP_OPTIMIZATION_GOALS = 31 => "Tag_ABI_FP_optimization_goals",
+    COMPATIBILITY            = 32 => "Tag_compatibility",
+    CPU_UNALIGNED_ACCESS     = 34 => "Tag_CPU_unaligned_access",
+    FP_HP_EXTENSION          = 36 => "Tag_FP_HP_extension",
+    ABI_FP_16BIT_FORMAT      = 38 => "Tag_ABI_FP_16bit_format",
+    MPEXTENSION_USE          = 42 => "Tag_MPextension_use",
+    DIV_USE                  = 44 => "Tag_DIV_use",
+    DSP_EXTENSION            = 46 => "Tag_DSP_extension",
+    MVE_ARCH                 = 48 => "Tag_MVE_arch",
+    PAC_EXTENSION            = 50 => "Tag_PAC_extension",
+    BTI_EXTENSION            = 52 => "Tag_BTI_extension",
+    NODEFAULTS               = 64 => "Tag_nodefaults",
+    ALSO_COMPATIBLE_WITH     = 65 => "Tag_also_compatible_with",
+    T2EE_USE                 = 66 => "Tag_T2EE_use",
+    CONFORMANCE              = 67 => "Tag_conformance",
+    VIRTUALIZATION_USE       = 68 => "Tag_Virtualization_use",
+    FRAMEPOINTER_USE         = 72 => "Tag_FramePointer_use",
+    BTI_USE                  = 74 => "Tag_BTI_use",
+    PACRET_USE               = 76 => "Tag_PACRET_use",
+}
+);
+
+enum_struct!(
+/// `Tag_CPU_arch` values
+pub struct CpuArch(u64) {
+    PRE_V4          = 0  => "Pre-v4",
+    V4              = 1  => "v4",
+    V4T             = 2  => "v4T",
+    V5T             = 3  => "v5T",
+    V5TE            = 4  => "v5TE",
+    V5TEJ           = 5  => "v5TEJ",
+    V6              = 6  => "v6",
+    V6KZ            = 7  => "v6KZ",
+    V6T2            = 8  => "v6T2",
+    V6K             = 9  => "v6K",
+    V7              = 10 => "v7",
+    V6_M            = 11 => "v6-M",
+    V6S_M           = 12 => "v6S-M",
+    V7E_M           = 13 => "v7E-M",
+    V8_A            = 14 => "v8-A",
+    V8_R            = 15 => "v8-R",
+    V8_M_BASELINE   = 16 => "v8-M.baseline",
+    V8_M_MAINLINE   = 17 => "v8-M.mainline",
+    V8_1_A          = 18 => "v8.1-A",
+    V8_2_A          = 19 => "v8.2-A",
+    V8_3_A          = 20 => "v8.3-A",
+    V8_1_M_MAINLINE = 21 => "v8.1-M.mainline",
+    V9              = 22 => "v9",
+}
+);
+
+enum_struct!(
+/// `Tag_CPU_arch_profile` values
+pub struct CpuArchProfile(u64) {
+    NONE            = 0    => "None",
+    APPLICATION     = 0x41 => "Application",
+    REALTIME        = 0x52 => "Realtime",
+    MICROCONTROLLER = 0x4d => "Microcontroller",
+    CLASSIC         = 0x53 => "Application or Realtime",
+}
+);
+
+enum_struct!(
+/// `Tag_ARM_ISA_use` and `Tag_THUMB_ISA_use` values
+pub struct IsaUse(u64) {
+    NO      = 0 => "No",
+    YES     = 1 => "Yes",
+    THUMB2  = 2 => "Thumb-2",
+    ALLOWED = 3 => "Yes, derived from the architecture",
+}
+);
+
+enum_struct!(
+/// `Tag_FP_arch` values
+pub struct FpArch(u64) {
+    NONE       = 0 => "No",
+    VFPV1      = 1 => "VFPv1",
+    VFPV2      = 2 => "VFPv2",
+    VFPV3      = 3 => "VFPv3",
+    VFPV3_D16  = 4 => "VFPv3-D16",
+    VFPV4      = 5 => "VFPv4",
+    VFPV4_D16  = 6 => "VFPv4-D16",
+    FP_ARMV8   = 7 => "FP for ARMv8",
+    FPV5_D16   = 8 => "FPv5/FP-D16 for ARMv8",
+}
+);
+
+enum_struct!(
+/// `Tag_ABI_VFP_args` values
+pub struct VfpArgs(u64) {
+    BASE       = 0 => "AAPCS",
+    VFP        = 1 => "VFP registers",
+    CUSTOM     = 2 => "custom",
+    COMPATIBLE = 3 => "compatible",
+}
+);
+
+enum_struct!(
+/// `Tag_ABI_enum_size` values
+pub struct EnumSize(u64) {
+    NOT_USED  = 0 => "Unused",
+    SMALL     = 1 => "small",
+    INT       = 2 => "int",
+    FORCED    = 3 => "forced to int",
+}
+);
+
+enum_struct!(
+/// `Tag_ABI_HardFP_use` values
+pub struct HardFpUse(u64) {
+    IMPLIED      = 0 => "As Tag_FP_arch",
+    SINGLE       = 1 => "SP only",
+    DOUBLE       = 2 => "DP only",
+    SINGLE_DOUBLE = 3 => "SP and DP",
+}
+);
+
+enum_struct!(
+/// `Tag_ABI_FP_number_model` values
+pub struct FpNumberModel(u64) {
+    NONE   = 0 => "None",
+    FINITE = 1 => "Finite",
+    RTABI  = 2 => "RTABI",
+    IEEE   = 3 => "IEEE 754",
+}
+);
+
+/// Decoded `aeabi` attributes of a file
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Aeabi<'a> {
+    /// Name of the target CPU
+    pub cpu_name: Option<&'a str>,
+    /// Target architecture
+    pub cpu_arch: Option<CpuArch>,
+    /// Target architecture profile
+    pub cpu_arch_profile: Option<CpuArchProfile>,
+    /// ARM instructions used
+    pub arm_isa_use: Option<IsaUse>,
+    /// Thumb instructions used
+    pub thumb_isa_use: Option<IsaUse>,
+    /// Floating point instructions used
+    pub fp_arch: Option<FpArch>,
+    /// Size of `wchar_t` in bytes, 0 if not used
+    pub wchar_t: Option<u64>,
+    /// Floating point numbers supported
+    pub fp_number_model: Option<FpNumberModel>,
+    /// Code requires 8-byte alignment of 8-byte data, or larger alignment
+    pub align_needed: Option<u64>,
+    /// Code preserves 8-byte stack alignment, or larger alignment
+    pub align_preserved: Option<u64>,
+    /// Size of enumerated types
+    pub enum_size: Option<EnumSize>,
+    /// Floating point precision used
+    pub hardfp_use: Option<HardFpUse>,
+    /// Registers used to pass floating point arguments
+    pub vfp_args: Option<VfpArgs>,
+    /// Unaligned memory accesses are used
+    pub unaligned_access: Option<bool>,
+    /// Integer division instructions used
+    pub div_use: Option<u64>,
+}
+
+impl<'a> Aeabi<'a> {
+    /// Decode the attributes of `group`, usually [`crate::attributes::Subsection::file()`]
+    pub fn new(group: &Group<'a>) -> Result<Self, Error> {
+        let mut aeabi = Aeabi::default();
+        for attribute in group.attributes() {
+            let attribute = attribute?;
+            let integer = attribute.value.integer();
+            match Tag::from(attribute.tag) {
+                Tag::CPU_NAME => aeabi.cpu_name = attribute.value.string(),
+                Tag::CPU_ARCH => aeabi.cpu_arch = integer.map(CpuArch::from),
+                Tag::CPU_ARCH_PROFILE => aeabi.cpu_arch_profile = integer.map(CpuArchProfile::from),
+                Tag::ARM_ISA_USE => aeabi.arm_isa_use = integer.map(IsaUse::from),
+                Tag::THUMB_ISA_USE => aeabi.thumb_isa_use = integer.map(IsaUse::from),
+                Tag::FP_ARCH => aeabi.fp_arch = integer.map(FpArch::from),
+                Tag::ABI_PCS_WCHAR_T => aeabi.wchar_t = integer,
+                Tag::ABI_FP_NUMBER_MODEL => {
+                    aeabi.fp_number_model = integer.map(FpNumberModel::from)
+                }
+                Tag::ABI_ALIGN_NEEDED => aeabi.align_needed = integer,
+                Tag::ABI_ALIGN_PRESERVED => aeabi.align_preserved = integer,
+                Tag::ABI_ENUM_SIZE => aeabi.enum_size = integer.map(EnumSize::from),
+                Tag::ABI_HARDFP_USE => aeabi.hardfp_use = integer.map(HardFpUse::from),
+                Tag::ABI_VFP_ARGS => aeabi.vfp_args = integer.map(VfpArgs::from),
+                Tag::CPU_UNALIGNED_ACCESS => aeabi.unaligned_access = integer.map(|v| v != 0),
+                Tag::DIV_USE => aeabi.div_use = integer,
+                _ => {}
+            }
+        }
+        Ok(aeabi)
+    }
+
+    /// Check that code built with these attributes can be linked with `other`
+    ///
+    /// Returns the tag of the first incompatible attribute. This follows the checks the GNU
+    /// linker makes for the floating point argument passing, `enum` size and `wchar_t` size.
+    pub fn check_compatible(&self, other: &Aeabi) -> Result<(), Tag> {
+        if let (Some(a), Some(b)) = (self.vfp_args, other.vfp_args) {
+            if a != b && a != VfpArgs::COMPATIBLE && b != VfpArgs::COMPATIBLE {
+                return Err(Tag::ABI_VFP_ARGS);
+            }
+        }
+        if let (Some(a), Some(b)) = (self.enum_size, other.enum_size) {
+            if a != b && a != EnumSize::NOT_USED && b != EnumSize::NOT_USED {
+                return Err(Tag::ABI_ENUM_SIZE);
+            }
+        }
+        if let (Some(a), Some(b)) = (self.wchar_t, other.wchar_t) {
+            if a != b && a != 0 && b != 0 {
+                return Err(Tag::ABI_PCS_WCHAR_T);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Description of the typed `value` of `tag`, or `None` if the tag has no typed values
+pub fn describe(tag: Tag, value: &Value) -> Option<&'static str> {
+    let integer = value.integer()?;
+    match tag {
+        Tag::CPU_ARCH => CpuArch::from(integer).description(),
+        Tag::CPU_ARCH_PROFILE => CpuArchProfile::from(integer).description(),
+        Tag::ARM_ISA_USE | Tag::THUMB_ISA_USE => IsaUse::from(integer).description(),
+        Tag::FP_ARCH => FpArch::from(integer).description(),
+        Tag::ABI_ENUM_SIZE => EnumSize::from(integer).description(),
+        Tag::ABI_HARDFP_USE => HardFpUse::from(integer).description(),
+        Tag::ABI_VFP_ARGS => VfpArgs::from(integer).description(),
+        Tag::ABI_FP_NUMBER_MODEL => FpNumberModel::from(integer).description(),
+        _ => None,
+    }
+}
+
+/// Value of the 31-bit place-relative offset in `word`, stored at address `place`
+pub fn prel31(word: u32, place: u64) -> u64 {
+    // Sign extend bit 30
+    let offset = ((word << 1) as i32 >> 1) as i64;
+    (place as i64).wrapping_add(offset) as u64 & 0xffff_ffff
+}
+
+/// `EXIDX_CANTUNWIND`, the second word of an index entry for a function that can't be unwound
+pub const EXIDX_CANTUNWIND: u32 = 1;
+
+/// Read the 32-bit word at `offset` of `data`
+fn read_word(data: &[u8], offset: usize, big_endian: bool) -> Result<u32, Error> {
+    from_bytes(data.get(offset..).ok_or(Error::Truncated)?, big_endian)
+}
+
+/// Exception index table (`SHT::ARM_EXIDX` section or `PT::ARM_EXIDX` segment)
+///
+/// The table holds one 8-byte entry per function, sorted by function address. In relocatable
+/// objects the offsets are filled in by `R_ARM_PREL31` relocations, so they read as 0.
+#[derive(Clone, Copy, Debug)]
+pub struct Exidx<'a> {
+    data: &'a [u8],
+    address: u64,
+    big_endian: bool,
+}
+
+impl<'a> Exidx<'a> {
+    /// Exception index table with contents `data` loaded at `address`
+    pub fn new(data: &'a [u8], address: u64, big_endian: bool) -> Self {
+        Exidx {
+            data,
+            address,
+            big_endian,
+        }
+    }
+
+    /// Number of entries
+    pub fn len(&self) -> usize {
+        self.data.len() / 8
+    }
+
+    /// Returns true if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Entry `index`
+    pub fn get(&self, index: usize) -> Result<ExidxEntry<'a>, Error> {
+        if index >= self.len() {
+            return Err(Error::NotFound);
+        }
+        let offset = index * 8;
+        let place = self.address + offset as u64;
+        let first = read_word(self.data, offset, self.big_endian)?;
+        if first & 0x8000_0000 != 0 {
+            return Err(Error::Malformed);
+        }
+        let second = read_word(self.data, offset + 4, self.big_endian)?;
+        let unwind = if second == EXIDX_CANTUNWIND {
+            Unwind::CantUnwind
+        } else if second & 0x8000_0000 != 0 {
+            // Only the __aeabi_unwind_cpp_pr0 format can be stored inline
+            if second & 0x0f00_0000 != 0 {
+                return Err(Error::Malformed);
+            }
+            Unwind::Inline(Opcodes::new(second, 3, &[], self.big_endian))
+        } else {
+            Unwind::Table(prel31(second, place + 4))
+        };
+        Ok(ExidxEntry {
+            function: prel31(first, place),
+            unwind,
+        })
+    }
+
+    /// Iterate over the entries
+    pub fn entries(&self) -> impl Iterator<Item = Result<ExidxEntry<'a>, Error>> + 'a {
+        let exidx = *self;
+        (0..self.len()).map(move |index| exidx.get(index))
+    }
+
+    /// Entry of the function containing address `pc`, or `None` if `pc` is before the first
+    ///
+    /// Each function extends to the start of the next entry, as with the unwinder.
+    pub fn lookup(&self, pc: u64) -> Result<Option<ExidxEntry<'a>>, Error> {
+        // Index of the first entry after pc
+        let (mut low, mut high) = (0, self.len());
+        while low < high {
+            let middle = low + (high - low) / 2;
+            if self.get(middle)?.function <= pc {
+                low = middle + 1;
+            } else {
+                high = middle;
+            }
+        }
+        match low {
+            0 => Ok(None),
+            _ => self.get(low - 1).map(Some),
+        }
+    }
+}
+
+/// Entry of an exception index table
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct ExidxEntry<'a> {
+    /// Address of the function
+    pub function: u64,
+    /// How to unwind the function
+    pub unwind: Unwind<'a>,
+}
+
+/// How to unwind a function
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum Unwind<'a> {
+    /// The function can't be unwound (`EXIDX_CANTUNWIND`)
+    CantUnwind,
+    /// Up to three opcodes for `__aeabi_unwind_cpp_pr0` stored in the index entry
+    Inline(Opcodes<'a>),
+    /// Address of the exception table entry, see [`Extab::entry()`]
+    Table(u64),
+}
+
+/// Exception table (`.ARM.extab` section)
+#[derive(Clone, Copy, Debug)]
+pub struct Extab<'a> {
+    data: &'a [u8],
+    address: u64,
+    big_endian: bool,
+}
+
+impl<'a> Extab<'a> {
+    /// Exception table with contents `data` loaded at `address`
+    pub fn new(data: &'a [u8], address: u64, big_endian: bool) -> Self {
+        Extab {
+            data,
+            address,
+            big_endian,
+        }
+    }
+
+    /// Entry at `address`, from [`Unwind::Table`]
+    pub fn entry(&self, address: u64) -> Result<ExtabEntry<'a>, Error> {
+        let offset = address
+            .checked_sub(self.address)
+            .filter(|&offset| offset < self.data.len() as u64)
+            .ok_or(Error::NotFound)? as usize;
+        let data = &self.data[offset..];
+        let first = read_word(data, 0, self.big_endian)?;
+        if first & 0x8000_0000 == 0 {
+            return Ok(ExtabEntry::Generic {
+                routine: prel31(first, address),
+                data: &data[4..],
+                big_endian: self.big_endian,
+            });
+        }
+        let personality = ((first >> 24) & 0xf) as u8;
+        let opcodes = match personality {
+            0 => Opcodes::new(first, 3, &[], self.big_endian),
+            1 | 2 => {
+                let words = ((first >> 16) & 0xff) as usize;
+                let rest = data.get(4..4 + words * 4).ok_or(Error::Truncated)?;
+                Opcodes::new(first, 2, rest, self.big_endian)
+            }
+            _ => return Err(Error::Unsupported),
+        };
+        Ok(ExtabEntry::Compact {
+            personality,
+            opcodes,
+        })
+    }
+}
+
+/// Entry of an exception table
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub enum ExtabEntry<'a> {
+    /// ARM compact model using `__aeabi_unwind_cpp_pr0`, `pr1` or `pr2`
+    Compact {
+        /// Personality routine index
+        personality: u8,
+        /// Unwinding instructions
+        opcodes: Opcodes<'a>,
+    },
+    /// Generic model with a personality routine
+    Generic {
+        /// Address of the personality routine
+        routine: u64,
+        /// Data for the personality routine, up to the end of the table
+        data: &'a [u8],
+        /// Byte order of `data`
+        big_endian: bool,
+    },
+}
+
+impl<'a> ExtabEntry<'a> {
+    /// Unwinding instructions
+    ///
+    /// For the generic model this assumes the GNU personality routines
+    /// (`__gxx_personality_v0` and friends), which store the instructions in the same format
+    /// as `__aeabi_unwind_cpp_pr1`.
+    pub fn opcodes(&self) -> Result<Opcodes<'a>, Error> {
+        match *self {
+            ExtabEntry::Compact { ref opcodes, .. } => Ok(opcodes.clone()),
+            ExtabEntry::Generic {
+                data, big_endian, ..
+            } => {
+                let first = read_word(data, 0, big_endian)?;
+                let words = (first >> 24) as usize;
+                let rest = data.get(4..4 + words * 4).ok_or(Error::Truncated)?;
+                Ok(Opcodes::new(first, 3, rest, big_endian))
+            }
+        }
+    }
+}
+
+/// Iterator over the unwinding instructions of a function
+///
+/// Instructions are stored most significant byte first in each 32-bit word. The iterator
+/// returns every instruction including the trailing `finish` padding.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct Opcodes<'a> {
+    word: u32,
+    remaining: u8,
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Opcodes<'a> {
+    /// Instructions in the low `remaining` bytes of `word`, followed by the words in `data`
+    fn new(word: u32, remaining: u8, data: &'a [u8], big_endian: bool) -> Self {
+        Opcodes {
+            word,
+            remaining,
+            data,
+            big_endian,
+        }
+    }
+
+    fn byte(&mut self) -> Option<u8> {
+        if self.remaining == 0 {
+            self.word = read_word(self.data, 0, self.big_endian).ok()?;
+            self.data = &self.data[4..];
+            self.remaining = 4;
+        }
+        self.remaining -= 1;
+        Some((self.word >> (8 * self.remaining)) as u8)
+    }
+
+    fn read(&mut self, op: u8) -> Result<Instruction, Error> {
+        let mut operand = || self.byte().ok_or(Error::Truncated);
+        Ok(match op {
+            0x00..=0x3f => Instruction::VspAdd((u32::from(op) << 2) + 4),
+            0x40..=0x7f => Instruction::VspSub((u32::from(op & 0x3f) << 2) + 4),
+            0x80..=0x8f => match (u16::from(op & 0xf) << 8) | u16::from(operand()?) {
+                0 => Instruction::RefuseToUnwind,
+                mask => Instruction::Pop(mask << 4),
+            },
+            0x9d | 0x9f => Instruction::Reserved,
+            0x90..=0x9f => Instruction::SetVsp(op & 0xf),
+            0xa0..=0xa7 => Instruction::Pop(((1 << ((op & 7) + 1)) - 1) << 4),
+            0xa8..=0xaf => Instruction::Pop(((1 << ((op & 7) + 1)) - 1) << 4 | 1 << 14),
+            0xb0 => Instruction::Finish,
+            0xb1 => match operand()? {
+                mask @ 0x01..=0x0f => Instruction::Pop(mask.into()),
+                _ => Instruction::Spare,
+            },
+            0xb2 => {
+                // ULEB128 operand
+                let mut value = 0u64;
+                let mut shift = 0;
+                loop {
+                    let byte = operand()?;
+                    if shift < 64 {
+                        value |= u64::from(byte & 0x7f) << shift;
+                    }
+                    shift += 7;
+                    if byte & 0x80 == 0 {
+                        break;
+                    }
+                }
+                let offset = value
+                    .checked_shl(2)
+                    .filter(|offset| offset >> 2 == value)
+                    .and_then(|offset| u32::try_from(offset).ok())
+                    .and_then(|offset| offset.checked_add(0x204))
+                    .ok_or(Error::Malformed)?;
+                Instruction::VspAdd(offset)
+            }
+            0xb3 | 0xc8 | 0xc9 => {
+                let operand = operand()?;
+                let first = (operand >> 4) + if op == 0xc8 { 16 } else { 0 };
+                Instruction::PopVfp {
+                    first,
+                    count: (operand & 0xf) + 1,
+                    fstmfdx: op == 0xb3,
+                }
+            }
+            0xb8..=0xbf | 0xd0..=0xd7 => Instruction::PopVfp {
+                first: 8,
+                count: (op & 7) + 1,
+                fstmfdx: op < 0xd0,
+            },
+            0xc0..=0xc5 => Instruction::PopWmmx {
+                first: 10,
+                count: (op & 7) + 1,
+            },
+            0xc6 => {
+                let operand = operand()?;
+                Instruction::PopWmmx {
+                    first: operand >> 4,
+                    count: (operand & 0xf) + 1,
+                }
+            }
+            0xc7 => match operand()? {
+                mask @ 0x01..=0x0f => Instruction::PopWmmxControl(mask),
+                _ => Instruction::Spare,
+            },
+            _ => Instruction::Spare,
+        })
+    }
+}
+
+impl<'a> Iterator for Opcodes<'a> {
+    type Item = Result<Instruction, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let op = self.byte()?;
+        let result = self.read(op);
+        if result.is_err() {
+            self.remaining = 0;
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+/// Unwinding instruction
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Instruction {
+    /// `vsp = vsp + n`
+    VspAdd(u32),
+    /// `vsp = vsp - n`
+    VspSub(u32),
+    /// The function can't be unwound
+    RefuseToUnwind,
+    /// Pop the core registers in the mask, bit `n` for `rn`
+    Pop(u16),
+    /// `vsp = rn`
+    SetVsp(u8),
+    /// Pop `count` VFP double registers from `first`
+    PopVfp {
+        /// First register
+        first: u8,
+        /// Number of registers
+        count: u8,
+        /// Registers were saved with `FSTMFDX`, which stores an extra word
+        fstmfdx: bool,
+    },
+    /// Pop `count` iWMMXt data registers from `first`
+    PopWmmx {
+        /// First register
+        first: u8,
+        /// Number of registers
+        count: u8,
+    },
+    /// Pop the iWMMXt control registers in the mask, bit `n` for `wCGRn`
+    PopWmmxControl(u8),
+    /// Stop unwinding the frame
+    Finish,
+    /// Reserved instruction
+    Reserved,
+    /// Spare instruction
+    Spare,
+}
+
+impl fmt::Display for Instruction {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        fn list(f: &mut fmt::Formatter, prefix: &str, mask: u16) -> fmt::Result {
+            f.write_str("pop {")?;
+            let mut separator = "";
+            for n in (0..16).filter(|n| mask & (1 << n) != 0) {
+                write!(f, "{}{}{}", separator, prefix, n)?;
+                separator = ", ";
+            }
+            f.write_str("}")
+        }
+        match *self {
+            Instruction::VspAdd(n) => write!(f, "vsp = vsp + {}", n),
+            Instruction::VspSub(n) => write!(f, "vsp = vsp - {}", n),
+            Instruction::RefuseToUnwind => f.write_str("refuse to unwind"),
+            Instruction::Pop(mask) => list(f, "r", mask),
+            Instruction::SetVsp(n) => write!(f, "vsp = r{}", n),
+            Instruction::PopVfp {
+                first,
+                count,
+                fstmfdx,
+            } => {
+                write!(f, "pop {{D{}-D{}}}", first, first + count - 1)?;
+                if fstmfdx {
+                    f.write_str(" (FSTMFDX)")?;
+                }
+                Ok(())
+            }
+            Instruction::PopWmmx { first, count } => {
+                write!(f, "pop {{wR{}-wR{}}}", first, first + count - 1)
+            }
+            Instruction::PopWmmxControl(mask) => list(f, "wCGR", mask.into()),
+            Instruction::Finish => f.write_str("finish"),
+            Instruction::Reserved => f.write_str("[Reserved]"),
+            Instruction::Spare => f.write_str("[Spare]"),
+        }
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// ARM exception index table, or `None` if there isn't one
+    ///
+    /// This is the `SHT::ARM_EXIDX` section, or the `PT::ARM_EXIDX` segment if there are no
+    /// section headers.
+    pub fn arm_exidx(&self) -> Result<Option<Exidx<'a>>, Error> {
+        if let Some((_, shdr)) = self.section_by_type(SHT::ARM_EXIDX)? {
+            let data = self.section_data(&shdr)?;
+            return Ok(Some(Exidx::new(data, shdr.sh_addr, self.is_big_endian())));
+        }
+        for phdr in self.phdrs()? {
+            if phdr.p_type == PT::ARM_EXIDX {
+                let data = self.segment_data(&phdr)?;
+                return Ok(Some(Exidx::new(data, phdr.p_vaddr, self.is_big_endian())));
+            }
+        }
+        Ok(None)
+    }
+
+    /// ARM exception table (`.ARM.extab` section), or `None` if there isn't one
+    pub fn arm_extab(&self) -> Result<Option<Extab<'a>>, Error> {
+        match self.section_by_name(".ARM.extab")? {
+            Some((_, shdr)) => {
+                let data = self.section_data(&shdr)?;
+                Ok(Some(Extab::new(data, shdr.sh_addr, self.is_big_endian())))
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::attributes::test::section;
+    use crate::attributes::Attributes;
+    use crate::ehdr::{EM, ET};
+    use crate::testutil::Builder;
+    use std::vec::Vec;
+    use std::{format, panic};
+
+    #[test]
+    fn aeabi() {
+        // As emitted by GCC for -mcpu=cortex-m4 -mfloat-abi=hard -mfpu=fpv4-sp-d16
+        let file = b"\x05Cortex-M4\0\x06\x0d\x07\x4d\x09\x02\x0a\x06\x12\x04\x14\x01\x15\x01\x17\x03\x18\x01\x19\x01\x1a\x01\x1c\x01\x22\x01";
+        let data = section("aeabi", &[(1, file)]);
+        let attributes = Attributes::new(&data, false).unwrap();
+        let subsection = attributes.subsection("aeabi").unwrap().unwrap();
+        let hard = Aeabi::new(&subsection.file().unwrap().unwrap()).unwrap();
+        assert_eq!(hard.cpu_name, Some("Cortex-M4"));
+        assert_eq!(hard.cpu_arch, Some(CpuArch::V7E_M));
+        assert_eq!(hard.cpu_arch_profile, Some(CpuArchProfile::MICROCONTROLLER));
+        assert_eq!(hard.thumb_isa_use, Some(IsaUse::THUMB2));
+        assert_eq!(hard.fp_arch, Some(FpArch::VFPV4_D16));
+        assert_eq!(hard.wchar_t, Some(4));
+        assert_eq!(hard.enum_size, Some(EnumSize::SMALL));
+        assert_eq!(hard.vfp_args, Some(VfpArgs::VFP));
+        assert_eq!(hard.unaligned_access, Some(true));
+
+        let soft = Aeabi {
+            vfp_args: Some(VfpArgs::BASE),
+            ..hard
+        };
+        assert_eq!(hard.check_compatible(&soft), Err(Tag::ABI_VFP_ARGS));
+        let any = Aeabi {
+            vfp_args: Some(VfpArgs::COMPATIBLE),
+            enum_size: Some(EnumSize::NOT_USED),
+            ..hard
+        };
+        assert_eq!(hard.check_compatible(&any), Ok(()));
+        assert_eq!(soft.check_compatible(&Aeabi::default()), Ok(()));
+
+        assert_eq!(
+            describe(Tag::FP_ARCH, &Value::Integer(6)),
+            Some("VFPv4-D16")
+        );
+    }
+
+    #[test]
+    fn exidx() {
+        let words = |words: &[u32]| {
+            words
+                .iter()
+                .flat_map(|w| w.to_le_bytes())
+                .collect::<Vec<u8>>()
+        };
+        let rel = |target: u32, place: u32| target.wrapping_sub(place) & 0x7fff_ffff;
+        let mut builder = Builder::new(true, false, ET::EXEC, EM::ARM);
+        let exidx = words(&[
+            rel(0x2000, 0x1000),
+            EXIDX_CANTUNWIND,
+            rel(0x2100, 0x1008),
+            0x80a8b0b0,
+            rel(0x2200, 0x1010),
+            rel(0x3000, 0x1014),
+            rel(0x2300, 0x1018),
+            rel(0x3008, 0x101c),
+        ]);
+        let extab = words(&[0x81019784, 0x08b0b0b0, rel(0x1f00, 0x3008), 0x00a8b0b0]);
+        builder.section(".ARM.exidx", SHT::ARM_EXIDX, 0x1000, exidx);
+        builder.section(".ARM.extab", SHT::PROGBITS, 0x3000, extab);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+        let exidx = file.arm_exidx().unwrap().unwrap();
+        let extab = file.arm_extab().unwrap().unwrap();
+        assert_eq!(exidx.len(), 4);
+
+        let entries: Vec<_> = exidx.entries().map(Result::unwrap).collect();
+        assert_eq!(entries[0].function, 0x2000);
+        assert_eq!(entries[0].unwind, Unwind::CantUnwind);
+        let inline = match &entries[1].unwind {
+            Unwind::Inline(opcodes) => opcodes.clone().map(Result::unwrap).collect::<Vec<_>>(),
+            unwind => panic!("{:?}", unwind),
+        };
+        assert_eq!(
+            inline,
+            [
+                Instruction::Pop(0x4010),
+                Instruction::Finish,
+                Instruction::Finish
+            ]
+        );
+        assert_eq!(format!("{}", inline[0]), "pop {r4, r14}");
+        assert_eq!(entries[2].unwind, Unwind::Table(0x3000));
+
+        // __aeabi_unwind_cpp_pr1 with an instruction spanning two words
+        let entry = extab.entry(0x3000).unwrap();
+        let opcodes: Vec<_> = entry.opcodes().unwrap().map(Result::unwrap).collect();
+        assert_eq!(opcodes.len(), 5);
+        assert_eq!(opcodes[0], Instruction::SetVsp(7));
+        assert_eq!(format!("{}", opcodes[1]), "pop {r7, r14}");
+        assert!(matches!(entry, ExtabEntry::Compact { personality: 1, .. }));
+
+        let entry = exidx.lookup(0x2310).unwrap().unwrap();
+        assert_eq!(entry.function, 0x2300);
+        let entry = match entry.unwind {
+            Unwind::Table(address) => extab.entry(address).unwrap(),
+            unwind => panic!("{:?}", unwind),
+        };
+        assert!(matches!(
+            entry,
+            ExtabEntry::Generic {
+                routine: 0x1f00,
+                ..
+            }
+        ));
+        let mut opcodes = entry.opcodes().unwrap();
+        assert_eq!(opcodes.next(), Some(Ok(Instruction::Pop(0x4010))));
+
+        // vsp = vsp + 0x204 + (uleb128 << 2), which must fit in 32 bits
+        let mut opcodes = Opcodes::new(0x00b2_01b0, 3, &[], false);
+        assert_eq!(opcodes.next(), Some(Ok(Instruction::VspAdd(0x208))));
+        let data = words(&[0xffff_ff7f]);
+        let mut opcodes = Opcodes::new(0x00b2_ffff, 3, &data, false);
+        assert_eq!(opcodes.next(), Some(Err(Error::Malformed)));
+        assert_eq!(exidx.lookup(0x1fff).unwrap(), None);
+        assert_eq!(exidx.lookup(0x2050).unwrap().unwrap().function, 0x2000);
+
+        assert_eq!(prel31(0x7ffffffc, 0x1000), 0xffc);
+        assert_eq!(
+            format!(
+                "{}",
+                Instruction::PopVfp {
+                    first: 8,
+                    count: 2,
+                    fstmfdx: false
+                }
+            ),
+            "pop {D8-D9}"
+        );
+    }
+}
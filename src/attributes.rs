@@ -0,0 +1,442 @@
+//! Build attributes
+//!
+//! Attribute sections (`SHT::ARM_ATTRIBUTES`, `SHT::RISCV_ATTRIBUTES` and
+//! `SHT::GNU_ATTRIBUTES`) record the properties an object was built for, such as the
+//! target architecture and the ABI. The section has the layout:
+//!
+//! * format version `'A'`
+//! * subsections, each a 32-bit length, vendor name and one or more groups
+//! * groups, each a scope tag, 32-bit length, section or symbol indexes for the section and
+//!   symbol scopes, then the attributes
+//! * attributes, each a ULEB128 tag followed by a ULEB128 or NUL terminated string value
+//!
+//! The value type of each tag is defined by the vendor, see [`Vendor`].
+
+use crate::ehdr::EM;
+use crate::file::c_str;
+use crate::leb128::read_uleb128;
+use crate::shdr::SHT;
+use crate::{ElfFile, Error};
+use core::convert::TryInto;
+
+/// Attributes format version
+pub const FORMAT_VERSION: u8 = b'A';
+
+enum_struct!(
+/// Scope of a group of attributes
+pub struct Scope(u8) {
+    FILE    = 1 => "File",
+    SECTION = 2 => "Section",
+    SYMBOL  = 3 => "Symbol",
+}
+);
+
+/// Vendor of an attribute subsection, which defines the value type of each tag
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Vendor<'a> {
+    /// ARM EABI attributes (`aeabi`), see [`crate::arm`]
+    Aeabi,
+    /// RISC-V attributes (`riscv`), see [`crate::riscv`]
+    Riscv,
+    /// GNU attributes (`gnu`)
+    Gnu,
+    /// Other vendor, whose attributes are decoded with the generic rule
+    Other(&'a str),
+}
+
+impl<'a> Vendor<'a> {
+    /// Vendor called `name`
+    pub fn new(name: &'a str) -> Self {
+        match name {
+            "aeabi" => Vendor::Aeabi,
+            "riscv" => Vendor::Riscv,
+            "gnu" => Vendor::Gnu,
+            _ => Vendor::Other(name),
+        }
+    }
+
+    /// Vendor name
+    pub fn name(&self) -> &'a str {
+        match self {
+            Vendor::Aeabi => "aeabi",
+            Vendor::Riscv => "riscv",
+            Vendor::Gnu => "gnu",
+            Vendor::Other(name) => name,
+        }
+    }
+
+    /// Type of the value of attribute `tag`
+    ///
+    /// Vendors use odd tags for strings and even tags for integers, except for some early
+    /// ARM tags.
+    pub fn value_type(&self, tag: u64) -> ValueType {
+        match (self, tag) {
+            (Vendor::Aeabi, 4 | 5 | 65 | 67) => ValueType::String,
+            (Vendor::Aeabi, 32) => ValueType::IntegerString,
+            (Vendor::Aeabi, 0..=31) => ValueType::Integer,
+            _ if tag % 2 == 1 => ValueType::String,
+            _ => ValueType::Integer,
+        }
+    }
+}
+
+/// Type of an attribute value
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum ValueType {
+    /// ULEB128
+    Integer,
+    /// NUL terminated string
+    String,
+    /// ULEB128 followed by a NUL terminated string
+    IntegerString,
+}
+
+/// Attribute value
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Value<'a> {
+    /// [`ValueType::Integer`] value
+    Integer(u64),
+    /// [`ValueType::String`] value
+    String(&'a str),
+    /// [`ValueType::IntegerString`] value
+    IntegerString(u64, &'a str),
+}
+
+impl<'a> Value<'a> {
+    /// Integer value, or `None` if the value is a string
+    pub fn integer(&self) -> Option<u64> {
+        match *self {
+            Value::Integer(value) | Value::IntegerString(value, _) => Some(value),
+            Value::String(_) => None,
+        }
+    }
+
+    /// String value, or `None` if the value is an integer
+    pub fn string(&self) -> Option<&'a str> {
+        match *self {
+            Value::String(value) | Value::IntegerString(_, value) => Some(value),
+            Value::Integer(_) => None,
+        }
+    }
+}
+
+/// Attribute tag and value
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Attribute<'a> {
+    /// Tag, which is specific to the vendor
+    pub tag: u64,
+    /// Value
+    pub value: Value<'a>,
+}
+
+/// Take a 32-bit length prefixed block, where the length includes `header` bytes before it
+fn take_block<'a>(data: &mut &'a [u8], header: usize, big_endian: bool) -> Result<&'a [u8], Error> {
+    let bytes: [u8; 4] = data.get(..4).ok_or(Error::Truncated)?.try_into().unwrap();
+    let length = if big_endian {
+        u32::from_be_bytes(bytes)
+    } else {
+        u32::from_le_bytes(bytes)
+    } as usize;
+    let length = length.checked_sub(header + 4).ok_or(Error::Malformed)?;
+    let block = data.get(4..4 + length).ok_or(Error::Truncated)?;
+    *data = &data[4 + length..];
+    Ok(block)
+}
+
+/// Take a NUL terminated string
+fn take_str<'a>(data: &mut &'a [u8]) -> Result<&'a str, Error> {
+    let s = c_str(data, 0)?;
+    *data = &data[s.len() + 1..];
+    Ok(s)
+}
+
+/// Attributes section
+#[derive(Clone, Copy)]
+pub struct Attributes<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Attributes<'a> {
+    /// Attributes in section `data`
+    pub fn new(data: &'a [u8], big_endian: bool) -> Result<Self, Error> {
+        match data.split_first() {
+            Some((&FORMAT_VERSION, data)) => Ok(Attributes { data, big_endian }),
+            Some(_) => Err(Error::Unsupported),
+            None => Err(Error::Truncated),
+        }
+    }
+
+    /// Iterate over the subsections
+    pub fn subsections(&self) -> Subsections<'a> {
+        Subsections {
+            data: self.data,
+            big_endian: self.big_endian,
+        }
+    }
+
+    /// Subsection for vendor `name`, or `None` if missing
+    pub fn subsection(&self, name: &str) -> Result<Option<Subsection<'a>>, Error> {
+        for subsection in self.subsections() {
+            let subsection = subsection?;
+            if subsection.vendor.name() == name {
+                return Ok(Some(subsection));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Iterator over attribute subsections
+pub struct Subsections<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Iterator for Subsections<'a> {
+    type Item = Result<Subsection<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let result = take_block(&mut self.data, 0, self.big_endian).and_then(|mut data| {
+            let vendor = Vendor::new(take_str(&mut data)?);
+            Ok(Subsection {
+                vendor,
+                data,
+                big_endian: self.big_endian,
+            })
+        });
+        if result.is_err() {
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+/// Attributes of one vendor
+#[derive(Clone, Copy)]
+pub struct Subsection<'a> {
+    /// Vendor of the attributes
+    pub vendor: Vendor<'a>,
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Subsection<'a> {
+    /// Iterate over the groups of attributes
+    pub fn groups(&self) -> Groups<'a> {
+        Groups {
+            vendor: self.vendor,
+            data: self.data,
+            big_endian: self.big_endian,
+        }
+    }
+
+    /// Attributes applying to the whole file, or `None` if there are none
+    pub fn file(&self) -> Result<Option<Group<'a>>, Error> {
+        for group in self.groups() {
+            let group = group?;
+            if group.scope == Scope::FILE {
+                return Ok(Some(group));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Iterator over the groups of a subsection
+pub struct Groups<'a> {
+    vendor: Vendor<'a>,
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Iterator for Groups<'a> {
+    type Item = Result<Group<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&scope, rest) = self.data.split_first()?;
+        self.data = rest;
+        let result = take_block(&mut self.data, 1, self.big_endian).and_then(|mut data| {
+            let scope = Scope::from(scope);
+            let mut indexes: &[u8] = &[];
+            if scope != Scope::FILE {
+                // Indexes are terminated by 0
+                let start = data;
+                while read_uleb128(&mut data)? != 0 {}
+                indexes = &start[..start.len() - data.len() - 1];
+            }
+            Ok(Group {
+                vendor: self.vendor,
+                scope,
+                indexes,
+                data,
+            })
+        });
+        if result.is_err() {
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+/// Attributes applying to a file, sections or symbols
+#[derive(Clone, Copy)]
+pub struct Group<'a> {
+    /// Vendor of the attributes
+    pub vendor: Vendor<'a>,
+    /// What the attributes apply to
+    pub scope: Scope,
+    indexes: &'a [u8],
+    data: &'a [u8],
+}
+
+impl<'a> Group<'a> {
+    /// Indexes of the sections or symbols the attributes apply to (empty for the file scope)
+    pub fn indexes(&self) -> impl Iterator<Item = u64> + 'a {
+        let mut data = self.indexes;
+        core::iter::from_fn(move || {
+            if data.is_empty() {
+                None
+            } else {
+                read_uleb128(&mut data).ok()
+            }
+        })
+    }
+
+    /// Iterate over the attributes
+    pub fn attributes(&self) -> AttributeIter<'a> {
+        AttributeIter {
+            vendor: self.vendor,
+            data: self.data,
+        }
+    }
+
+    /// Value of attribute `tag`, or `None` if missing
+    pub fn get(&self, tag: u64) -> Result<Option<Value<'a>>, Error> {
+        for attribute in self.attributes() {
+            let attribute = attribute?;
+            if attribute.tag == tag {
+                return Ok(Some(attribute.value));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// Iterator over the attributes of a group
+pub struct AttributeIter<'a> {
+    vendor: Vendor<'a>,
+    data: &'a [u8],
+}
+
+impl<'a> AttributeIter<'a> {
+    fn read(&mut self) -> Result<Attribute<'a>, Error> {
+        let tag = read_uleb128(&mut self.data)?;
+        let value = match self.vendor.value_type(tag) {
+            ValueType::Integer => Value::Integer(read_uleb128(&mut self.data)?),
+            ValueType::String => Value::String(take_str(&mut self.data)?),
+            ValueType::IntegerString => {
+                let integer = read_uleb128(&mut self.data)?;
+                Value::IntegerString(integer, take_str(&mut self.data)?)
+            }
+        };
+        Ok(Attribute { tag, value })
+    }
+}
+
+impl<'a> Iterator for AttributeIter<'a> {
+    type Item = Result<Attribute<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let result = self.read();
+        if result.is_err() {
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// Build attributes section, or `None` if there isn't one
+    ///
+    /// This is `SHT::ARM_ATTRIBUTES` or `SHT::RISCV_ATTRIBUTES` for those machines, and
+    /// `SHT::GNU_ATTRIBUTES` otherwise.
+    pub fn attributes(&self) -> Result<Option<Attributes<'a>>, Error> {
+        let sh_type = match self.ehdr().e_machine {
+            EM::ARM => SHT::ARM_ATTRIBUTES,
+            EM::RISCV => SHT::RISCV_ATTRIBUTES,
+            _ => SHT::GNU_ATTRIBUTES,
+        };
+        match self.section_by_type(sh_type)? {
+            Some((_, shdr)) => {
+                Attributes::new(self.section_data(&shdr)?, self.is_big_endian()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    extern crate std;
+    use super::*;
+    use std::vec::Vec;
+
+    /// Attributes section with one subsection for `vendor` containing `groups`
+    pub(crate) fn section(vendor: &str, groups: &[(u8, &[u8])]) -> Vec<u8> {
+        let mut subsection = Vec::new();
+        subsection.extend(vendor.as_bytes());
+        subsection.push(0);
+        for (scope, data) in groups {
+            subsection.push(*scope);
+            subsection.extend((data.len() as u32 + 5).to_le_bytes());
+            subsection.extend(*data);
+        }
+        let mut data = std::vec![FORMAT_VERSION];
+        data.extend((subsection.len() as u32 + 4).to_le_bytes());
+        data.extend(subsection);
+        data
+    }
+
+    #[test]
+    fn parse() {
+        let data = section(
+            "gnu",
+            &[(1, b"\x04\x01\x05abc\0"), (2, b"\x03\x85\x01\0\x04\x02")],
+        );
+        let attributes = Attributes::new(&data, false).unwrap();
+        let subsection = attributes.subsection("gnu").unwrap().unwrap();
+        assert_eq!(subsection.vendor, Vendor::Gnu);
+        let groups: Vec<_> = subsection.groups().map(|g| g.unwrap()).collect();
+        assert_eq!(groups.len(), 2);
+        let attributes: Vec<_> = groups[0].attributes().map(|a| a.unwrap()).collect();
+        assert_eq!(
+            attributes,
+            [
+                Attribute {
+                    tag: 4,
+                    value: Value::Integer(1)
+                },
+                Attribute {
+                    tag: 5,
+                    value: Value::String("abc")
+                }
+            ]
+        );
+        assert_eq!(groups[1].scope, Scope::SECTION);
+        assert_eq!(groups[1].indexes().collect::<Vec<_>>(), [3, 133]);
+        assert_eq!(groups[1].get(4).unwrap(), Some(Value::Integer(2)));
+        assert_eq!(groups[0].get(6).unwrap(), None);
+
+        // Truncated values are reported
+        let data = section("gnu", &[(1, b"\x05abc")]);
+        let attributes = Attributes::new(&data, false).unwrap();
+        let group = attributes.subsection("gnu").unwrap().unwrap().file();
+        let group = group.unwrap().unwrap();
+        assert_eq!(group.attributes().next(), Some(Err(Error::Truncated)));
+        assert_eq!(Attributes::new(b"B", false).err(), Some(Error::Unsupported));
+    }
+}
@@ -0,0 +1,392 @@
+//! Auxiliary vector
+//!
+//! The kernel passes a new program an auxiliary vector on its stack after the environment:
+//! `(type, value)` word pairs ending with `AT::NULL`. It locates the program headers, entry
+//! point and interpreter, and gives the page size, hardware capabilities, user IDs and
+//! pointers to the file name, platform strings and random bytes. Core dumps keep a copy in
+//! their `NT::AUXV` note.
+//!
+//! [`AuxvEntries`] parses a vector, and [`AuxvBuilder`] builds one for a loader to pass to
+//! a program it starts.
+
+use crate::de::from_bytes;
+use crate::phdr::PT;
+use crate::ser::to_bytes;
+use crate::{ElfFile, Entry, Error};
+use core::convert::TryFrom;
+use core::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+enum_struct!(
+/// Auxiliary vector entry type
+pub struct AT(u64) {
+    NULL              = 0  => "End of vector",
+    IGNORE            = 1  => "Entry should be ignored",
+    EXECFD            = 2  => "File descriptor of program",
+    PHDR              = 3  => "Program headers for program",
+    PHENT             = 4  => "Size of program header entry",
+    PHNUM             = 5  => "Number of program headers",
+    PAGESZ            = 6  => "System page size",
+    BASE              = 7  => "Base address of interpreter",
+    FLAGS             = 8  => "Flags",
+    ENTRY             = 9  => "Entry point of program",
+    NOTELF            = 10 => "Program is not ELF",
+    UID               = 11 => "Real user ID",
+    EUID              = 12 => "Effective user ID",
+    GID               = 13 => "Real group ID",
+    EGID              = 14 => "Effective group ID",
+    PLATFORM          = 15 => "String identifying platform",
+    HWCAP             = 16 => "Machine-dependent hints about processor capabilities",
+    CLKTCK            = 17 => "Frequency of times()",
+    FPUCW             = 18 => "Used FPU control word",
+    DCACHEBSIZE       = 19 => "Data cache block size",
+    ICACHEBSIZE       = 20 => "Instruction cache block size",
+    UCACHEBSIZE       = 21 => "Unified cache block size",
+    IGNOREPPC         = 22 => "Entry should be ignored",
+    SECURE            = 23 => "Boolean, was exec setuid-like?",
+    BASE_PLATFORM     = 24 => "String identifying real platform",
+    RANDOM            = 25 => "Address of 16 random bytes",
+    HWCAP2            = 26 => "More machine-dependent hints about processor capabilities",
+    RSEQ_FEATURE_SIZE = 27 => "rseq supported feature size",
+    RSEQ_ALIGN        = 28 => "rseq allocation alignment",
+    HWCAP3            = 29 => "Extension of HWCAP",
+    HWCAP4            = 30 => "Extension of HWCAP",
+    EXECFN            = 31 => "Filename of program",
+    SYSINFO           = 32 => "Entry point of the vDSO",
+    SYSINFO_EHDR      = 33 => "Address of the vDSO",
+    L1I_CACHESHAPE    = 34 => "Shape of level 1 instruction cache",
+    L1D_CACHESHAPE    = 35 => "Shape of level 1 data cache",
+    L2_CACHESHAPE     = 36 => "Shape of level 2 cache",
+    L3_CACHESHAPE     = 37 => "Shape of level 3 cache",
+    L1I_CACHESIZE     = 40 => "Level 1 instruction cache size",
+    L1I_CACHEGEOMETRY = 41 => "Level 1 instruction cache geometry",
+    L1D_CACHESIZE     = 42 => "Level 1 data cache size",
+    L1D_CACHEGEOMETRY = 43 => "Level 1 data cache geometry",
+    L2_CACHESIZE      = 44 => "Level 2 cache size",
+    L2_CACHEGEOMETRY  = 45 => "Level 2 cache geometry",
+    L3_CACHESIZE      = 46 => "Level 3 cache size",
+    L3_CACHEGEOMETRY  = 47 => "Level 3 cache geometry",
+    MINSIGSTKSZ       = 51 => "Minimal stack size for signal delivery",
+}
+);
+
+/// 32-bit auxiliary vector entry
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Auxv32 {
+    /// Entry type (see [`AT`])
+    pub a_type: u32,
+    /// Integer value or address
+    pub a_val: u32,
+}
+
+/// 64-bit auxiliary vector entry
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Auxv64 {
+    /// Entry type
+    pub a_type: AT,
+    /// Integer value or address
+    pub a_val: u64,
+}
+impl Auxv32 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl Auxv64 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl From<Auxv32> for Auxv64 {
+    fn from(o: Auxv32) -> Auxv64 {
+        Auxv64 {
+            a_type: AT::from(o.a_type as u64),
+            a_val: o.a_val as u64,
+        }
+    }
+}
+impl TryFrom<Auxv64> for Auxv32 {
+    type Error = Error;
+    fn try_from(o: Auxv64) -> Result<Auxv32, Error> {
+        let word = |value: u64| u32::try_from(value).map_err(|_| Error::Unsupported);
+        Ok(Auxv32 {
+            a_type: word(o.a_type.into())?,
+            a_val: word(o.a_val)?,
+        })
+    }
+}
+impl Entry for Auxv64 {
+    type Elf32 = Auxv32;
+}
+
+/// Iterator over the entries of an auxiliary vector, up to the terminating `AT::NULL`
+#[derive(Clone, Debug)]
+pub struct AuxvEntries<'a> {
+    data: &'a [u8],
+    elf32: bool,
+    big_endian: bool,
+}
+
+impl<'a> AuxvEntries<'a> {
+    /// Entries in `data`, such as the `NT::AUXV` note of a core dump or `/proc/<pid>/auxv`
+    pub fn new(data: &'a [u8], elf32: bool, big_endian: bool) -> Self {
+        AuxvEntries {
+            data,
+            elf32,
+            big_endian,
+        }
+    }
+
+    /// Value of the first entry of type `a_type`
+    pub fn get(&self, a_type: AT) -> Option<u64> {
+        self.clone()
+            .filter_map(Result::ok)
+            .find(|entry| entry.a_type == a_type)
+            .map(|entry| entry.a_val)
+    }
+
+    fn parse(&self) -> Result<(Auxv64, usize), Error> {
+        if self.elf32 {
+            let entry: Auxv32 = from_bytes(self.data, self.big_endian)?;
+            Ok((entry.into(), Auxv32::SIZE))
+        } else {
+            Ok((from_bytes(self.data, self.big_endian)?, Auxv64::SIZE))
+        }
+    }
+}
+
+impl<'a> Iterator for AuxvEntries<'a> {
+    type Item = Result<Auxv64, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        match self.parse() {
+            Ok((entry, _)) if entry.a_type == AT::NULL => {
+                self.data = &[];
+                None
+            }
+            Ok((entry, size)) => {
+                self.data = &self.data[size..];
+                Some(Ok(entry))
+            }
+            Err(err) => {
+                self.data = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Auxiliary vector under construction, for starting a program
+///
+/// A loader sets the entries for the program with [`AuxvBuilder::program()`], then
+/// `AT::BASE` to the base address of the interpreter if there is one, and the entries that
+/// depend on the system, such as `AT::PAGESZ` and `AT::RANDOM`. The vector is then written
+/// to the program's initial stack.
+#[derive(Clone, Copy, Debug)]
+pub struct AuxvBuilder {
+    entries: [Auxv64; AuxvBuilder::CAPACITY],
+    len: usize,
+}
+
+impl Default for AuxvBuilder {
+    fn default() -> Self {
+        AuxvBuilder {
+            entries: [Auxv64::default(); AuxvBuilder::CAPACITY],
+            len: 0,
+        }
+    }
+}
+
+impl AuxvBuilder {
+    /// Maximum number of entries, not counting the terminating `AT::NULL`
+    pub const CAPACITY: usize = 48;
+
+    /// Empty vector
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Set the value of `a_type`, replacing any previous value
+    ///
+    /// Fails with [`Error::Unsupported`] if the vector is full, or for `AT::NULL`, which is
+    /// added when the vector is written.
+    pub fn set(&mut self, a_type: AT, a_val: u64) -> Result<&mut Self, Error> {
+        if a_type == AT::NULL {
+            return Err(Error::Unsupported);
+        }
+        if let Some(entry) = self.entries[..self.len]
+            .iter_mut()
+            .find(|entry| entry.a_type == a_type)
+        {
+            entry.a_val = a_val;
+        } else if self.len < Self::CAPACITY {
+            self.entries[self.len] = Auxv64 { a_type, a_val };
+            self.len += 1;
+        } else {
+            return Err(Error::Unsupported);
+        }
+        Ok(self)
+    }
+
+    /// Set the entries describing `file` loaded at its addresses plus `bias`: `AT::PHDR`,
+    /// `AT::PHENT`, `AT::PHNUM` and `AT::ENTRY`
+    ///
+    /// The program headers are found through `PT::PHDR`, or the `PT::LOAD` segment that
+    /// contains them, and [`Error::NotFound`] is returned if they aren't loaded.
+    pub fn program(&mut self, file: &ElfFile, bias: u64) -> Result<&mut Self, Error> {
+        let ehdr = file.ehdr();
+        let phdrs = file.phdrs()?;
+        let phnum = phdrs.len() as u64;
+        let end = phnum
+            .checked_mul(u64::from(ehdr.e_phentsize))
+            .and_then(|size| ehdr.e_phoff.checked_add(size))
+            .ok_or(Error::Malformed)?;
+        let mut phdr_addr = None;
+        for phdr in phdrs {
+            if phdr.p_type == PT::PHDR {
+                phdr_addr = Some(phdr.p_vaddr);
+                break;
+            }
+            // Segments whose end overflows can't contain the headers
+            let contains = phdr
+                .p_offset
+                .checked_add(phdr.p_filesz)
+                .is_some_and(|segment_end| end <= segment_end);
+            if phdr.p_type == PT::LOAD
+                && ehdr.e_phoff >= phdr.p_offset
+                && contains
+                && phdr_addr.is_none()
+            {
+                phdr_addr = phdr.p_vaddr.checked_add(ehdr.e_phoff - phdr.p_offset);
+            }
+        }
+        let phdr_addr = phdr_addr.ok_or(Error::NotFound)?;
+        self.set(AT::PHDR, phdr_addr.wrapping_add(bias))?
+            .set(AT::PHENT, ehdr.e_phentsize.into())?
+            .set(AT::PHNUM, phnum)?
+            .set(AT::ENTRY, ehdr.e_entry.wrapping_add(bias))
+    }
+
+    /// Value of `a_type`
+    pub fn get(&self, a_type: AT) -> Option<u64> {
+        self.entries()
+            .iter()
+            .find(|entry| entry.a_type == a_type)
+            .map(|entry| entry.a_val)
+    }
+
+    /// Entries in the order they were first set
+    pub fn entries(&self) -> &[Auxv64] {
+        &self.entries[..self.len]
+    }
+
+    /// Size of the vector when written, including the terminating `AT::NULL`
+    pub fn size(&self, elf32: bool) -> usize {
+        let size = if elf32 { Auxv32::SIZE } else { Auxv64::SIZE };
+        (self.len + 1) * size
+    }
+
+    /// Write the vector to `f`, followed by `AT::NULL`, in the given class and byte order
+    pub fn write(
+        &self,
+        elf32: bool,
+        big_endian: bool,
+        mut f: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut data = [0; Auxv64::SIZE];
+        for entry in self.entries().iter().chain(&[Auxv64::default()]) {
+            let len = if elf32 {
+                to_bytes(&Auxv32::try_from(*entry)?, big_endian, &mut data)?
+            } else {
+                to_bytes(entry, big_endian, &mut data)?
+            };
+            f(&data[..len])?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::{EM, ET};
+    use crate::phdr::PF;
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use crate::Ehdr64;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn auxv() {
+        let mut builder = Builder::new(true, true, ET::DYN, EM::ARM);
+        builder.ehdr.e_entry = 0x1040;
+        let text = builder.section(".text", SHT::PROGBITS, 0x1000, vec![0; 0x100]);
+        builder.segment(PT::PHDR, PF::R, &[]);
+        builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+        builder.phdr_mut(0).p_vaddr = 0x34;
+        let elf = builder.build();
+        let file = ElfFile::new(&elf).unwrap();
+
+        let mut auxv = AuxvBuilder::new();
+        auxv.program(&file, 0x40_0000)
+            .unwrap()
+            .set(AT::PAGESZ, 0x1000)
+            .unwrap()
+            .set(AT::ENTRY, 0x40_1000)
+            .unwrap();
+        assert_eq!(auxv.get(AT::PHDR), Some(0x40_0034));
+        assert_eq!(auxv.get(AT::PHNUM), Some(2));
+        assert_eq!(auxv.get(AT::PHENT), Some(32));
+        assert_eq!(auxv.entries().len(), 5);
+        assert_eq!(auxv.set(AT::NULL, 0).err(), Some(Error::Unsupported));
+
+        let mut data = Vec::new();
+        auxv.write(true, true, |bytes| {
+            data.extend(bytes);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(data.len(), auxv.size(true));
+        assert_eq!(
+            &data[data.len() - 16..],
+            &[0, 0, 0, 6, 0, 0, 0x10, 0, 0, 0, 0, 0, 0, 0, 0, 0]
+        );
+        let entries = AuxvEntries::new(&data, true, true);
+        assert_eq!(entries.get(AT::ENTRY), Some(0x40_1000));
+        let entries: Vec<_> = entries.map(Result::unwrap).collect();
+        assert_eq!(entries, auxv.entries());
+
+        // Without `PT::PHDR` the headers must be in a `PT::LOAD` segment
+        let mut builder = Builder::new(false, false, ET::EXEC, EM::X86_64);
+        let text = builder.section(".text", SHT::PROGBITS, 0x40_0100, vec![0; 0x100]);
+        builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+        let elf = builder.build();
+        let file = ElfFile::new(&elf).unwrap();
+        let mut auxv = AuxvBuilder::new();
+        assert_eq!(auxv.program(&file, 0).err(), Some(Error::NotFound));
+
+        // Map the start of the file at 0x400000
+        let mut builder = Builder::new(false, false, ET::EXEC, EM::X86_64);
+        builder.segment(PT::LOAD, PF::R | PF::X, &[]);
+        let phdr = builder.phdr_mut(0);
+        phdr.p_vaddr = 0x40_0000;
+        phdr.p_filesz = 0x200;
+        let elf = builder.build();
+        let file = ElfFile::new(&elf).unwrap();
+        auxv.program(&file, 0).unwrap();
+        assert_eq!(auxv.get(AT::PHDR), Some(0x40_0000 + Ehdr64::SIZE as u64));
+
+        // A segment that wraps around doesn't contain them
+        let mut builder = Builder::new(false, false, ET::EXEC, EM::X86_64);
+        builder.segment(PT::LOAD, PF::R | PF::X, &[]);
+        let phdr = builder.phdr_mut(0);
+        phdr.p_offset = 0x10;
+        phdr.p_filesz = u64::MAX;
+        let elf = builder.build();
+        let file = ElfFile::new(&elf).unwrap();
+        assert_eq!(auxv.program(&file, 0).err(), Some(Error::NotFound));
+    }
+}
@@ -0,0 +1,417 @@
+//! Flattening loadable data into a raw binary image (`objcopy -O binary`)
+//!
+//! [`ElfFile::regions()`] lists the file data that occupies memory, either the `PT::LOAD`
+//! segments or a selection of sections, at its virtual or physical address.
+//! [`ElfFile::binary()`] writes those regions in address order from the lowest one,
+//! filling the gaps between them, which is the image flashed to a microcontroller.
+//! Zero-initialised memory such as `.bss` isn't part of the image.
+//!
+//! In the other direction, [`write_elf()`] turns data at addresses back into a minimal ELF
+//! file, for formats such as Intel HEX that lose the headers.
+
+use crate::ehdr::EIC;
+use crate::phdr::{PF, PT};
+use crate::ser::{write_ehdr, write_phdr};
+use crate::shdr::{SHF64, SHT};
+use crate::{Ehdr32, Ehdr64, ElfFile, Error, Phdr, Phdr32, Phdr64};
+
+/// Options for [`ElfFile::binary()`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct BinaryOptions<'s> {
+    /// Value of the bytes in gaps between regions
+    pub fill: u8,
+    /// Place data at its physical (load) address `p_paddr` rather than `p_vaddr`
+    pub physical: bool,
+    /// Names of the sections to copy, or empty to copy every `PT::LOAD` segment
+    pub sections: &'s [&'s str],
+}
+
+/// File data and the address it's placed at
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Region<'a> {
+    /// Address of the first byte
+    pub address: u64,
+    /// Contents
+    pub data: &'a [u8],
+}
+
+/// Iterator over the regions of [`ElfFile::regions()`]
+#[derive(Clone)]
+pub struct Regions<'a, 's> {
+    file: ElfFile<'a>,
+    physical: bool,
+    sections: &'s [&'s str],
+    index: usize,
+}
+
+impl<'a, 's> Regions<'a, 's> {
+    /// Region of the `PT::LOAD` segment `index`, if it's one with file data
+    fn segment(&self, index: usize) -> Result<Option<Region<'a>>, Error> {
+        let phdr = self.file.phdrs()?.get(index).ok_or(Error::Malformed)?;
+        if phdr.p_type != PT::LOAD || phdr.p_filesz == 0 {
+            return Ok(None);
+        }
+        Ok(Some(Region {
+            address: if self.physical {
+                phdr.p_paddr
+            } else {
+                phdr.p_vaddr
+            },
+            data: self.file.segment_data(&phdr)?,
+        }))
+    }
+
+    /// Region of section `index`, if it's one of the selected sections with file data
+    fn section(&self, index: usize) -> Result<Option<Region<'a>>, Error> {
+        let shdr = self.file.shdrs()?.get(index).ok_or(Error::Malformed)?;
+        if shdr.sh_flags & SHF64::ALLOC != SHF64::ALLOC
+            || shdr.sh_type == SHT::NOBITS
+            || shdr.sh_size == 0
+            || !self.sections.contains(&self.file.section_name(&shdr)?)
+        {
+            return Ok(None);
+        }
+        Ok(Some(Region {
+            address: if self.physical {
+                self.file.physical_address(shdr.sh_offset, shdr.sh_addr)?
+            } else {
+                shdr.sh_addr
+            },
+            data: self.file.section_data(&shdr)?,
+        }))
+    }
+
+    fn next_region(&mut self) -> Result<Option<Region<'a>>, Error> {
+        let count = if self.sections.is_empty() {
+            self.file.ehdr().e_phnum as usize
+        } else {
+            self.file.shnum()?
+        };
+        while self.index < count {
+            let index = self.index;
+            self.index += 1;
+            let region = if self.sections.is_empty() {
+                self.segment(index)?
+            } else {
+                self.section(index)?
+            };
+            if region.is_some() {
+                return Ok(region);
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a, 's> Iterator for Regions<'a, 's> {
+    type Item = Result<Region<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_region();
+        if result.is_err() {
+            self.index = usize::MAX;
+        }
+        result.transpose()
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// Physical address of the file data at `offset`, whose virtual address is `vaddr`
+    ///
+    /// This is `vaddr` moved by the difference between `p_paddr` and `p_vaddr` of the
+    /// `PT::LOAD` segment containing `offset`, or `vaddr` itself if there's none, which is
+    /// how `objcopy` finds the load address of a section.
+    pub fn physical_address(&self, offset: u64, vaddr: u64) -> Result<u64, Error> {
+        for phdr in self.phdrs()? {
+            if phdr.p_type == PT::LOAD
+                && offset >= phdr.p_offset
+                && offset - phdr.p_offset < phdr.p_filesz
+            {
+                return Ok(vaddr.wrapping_sub(phdr.p_vaddr).wrapping_add(phdr.p_paddr));
+            }
+        }
+        Ok(vaddr)
+    }
+
+    /// File data occupying memory, in file header order, for the segments or sections
+    /// selected by `options`
+    pub fn regions<'s>(&self, options: &BinaryOptions<'s>) -> Regions<'a, 's> {
+        Regions {
+            file: *self,
+            physical: options.physical,
+            sections: options.sections,
+            index: 0,
+        }
+    }
+
+    /// Call `f` with each region selected by `options`, in order of address
+    ///
+    /// Overlapping regions, or regions wrapping the address space, are
+    /// [`Error::Malformed`].
+    pub(crate) fn sorted_regions(
+        &self,
+        options: &BinaryOptions,
+        mut f: impl FnMut(Region<'a>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut end = None;
+        // Regions are selected in order of (address, index), without allocating to sort
+        let mut previous: Option<(u64, usize)> = None;
+        loop {
+            let mut next: Option<((u64, usize), Region)> = None;
+            for (index, region) in self.regions(options).enumerate() {
+                let region = region?;
+                let key = (region.address, index);
+                if previous.map_or(true, |previous| key > previous)
+                    && next.as_ref().map_or(true, |(next, _)| key < *next)
+                {
+                    next = Some((key, region));
+                }
+            }
+            let (key, region) = match next {
+                Some(next) => next,
+                None => return Ok(()),
+            };
+            if matches!(end, Some(end) if region.address < end) {
+                return Err(Error::Malformed);
+            }
+            end = Some(
+                region
+                    .address
+                    .checked_add(region.data.len() as u64)
+                    .ok_or(Error::Malformed)?,
+            );
+            f(region)?;
+            previous = Some(key);
+        }
+    }
+
+    /// Write the raw image of the regions selected by `options` to `f`, in chunks, and
+    /// return the address of its first byte, or `None` if it's empty
+    ///
+    /// Regions are written in address order with gaps between them filled with
+    /// `options.fill`. Overlapping regions are [`Error::Malformed`].
+    pub fn binary(
+        &self,
+        options: &BinaryOptions,
+        mut f: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<Option<u64>, Error> {
+        let fill = [options.fill; 256];
+        let mut start = None;
+        let mut end = 0;
+        self.sorted_regions(options, |region| {
+            let mut gap = match start {
+                None => {
+                    start = Some(region.address);
+                    0
+                }
+                Some(_) => region.address - end,
+            };
+            while gap != 0 {
+                let len = gap.min(fill.len() as u64);
+                f(&fill[..len as usize])?;
+                gap -= len;
+            }
+            f(region.data)?;
+            end = region.address + region.data.len() as u64;
+            Ok(())
+        })?;
+        Ok(start)
+    }
+}
+
+/// Write a minimal ELF file to `f` holding `chunks` of data at their addresses, with one
+/// `PT::LOAD` segment per run of contiguous chunks
+///
+/// `ehdr` gives the identification, type, machine and entry point, see [`Ehdr64::new()`];
+/// the program header table is filled in and there are no sections. `chunks` is iterated
+/// three times. Segments are readable, writable and executable, since the permissions
+/// aren't known.
+pub fn write_elf<D, I>(
+    ehdr: &Ehdr64,
+    chunks: I,
+    mut f: impl FnMut(&[u8]) -> Result<(), Error>,
+) -> Result<(), Error>
+where
+    D: AsRef<[u8]>,
+    I: Iterator<Item = Result<(u64, D), Error>> + Clone,
+{
+    let (ehsize, phentsize) = match ehdr.e_ident.class {
+        EIC::ELF32 => (Ehdr32::SIZE, Phdr32::SIZE),
+        EIC::ELF64 => (Ehdr64::SIZE, Phdr64::SIZE),
+        _ => return Err(Error::Unsupported),
+    };
+    let mut phnum = 0;
+    let mut end = None;
+    for chunk in chunks.clone() {
+        let (address, data) = chunk?;
+        let len = data.as_ref().len() as u64;
+        if len != 0 {
+            if end != Some(address) {
+                phnum += 1;
+            }
+            end = Some(address.checked_add(len).ok_or(Error::Malformed)?);
+        }
+    }
+    // `e_phnum` of 0xffff would mean the count is in a section header
+    if phnum >= 0xffff {
+        return Err(Error::Unsupported);
+    }
+
+    let mut header = *ehdr;
+    header.e_phoff = if phnum == 0 { 0 } else { ehsize as u64 };
+    header.e_shoff = 0;
+    header.e_ehsize = ehsize as u16;
+    header.e_phentsize = phentsize as u16;
+    header.e_phnum = phnum as u16;
+    header.e_shentsize = 0;
+    header.e_shnum = 0;
+    header.e_shstrndx = 0;
+    write_ehdr(&header, &mut f)?;
+
+    let mut offset = (ehsize + phentsize * phnum) as u64;
+    let mut run: Option<Phdr64> = None;
+    for chunk in chunks.clone() {
+        let (address, data) = chunk?;
+        let len = data.as_ref().len() as u64;
+        match &mut run {
+            _ if len == 0 => {}
+            Some(phdr) if phdr.p_vaddr + phdr.p_filesz == address => {
+                phdr.p_filesz += len;
+                phdr.p_memsz += len;
+            }
+            _ => {
+                if let Some(phdr) = run.take() {
+                    write_phdr(&header, &phdr, &mut f)?;
+                    offset += phdr.p_filesz;
+                }
+                run = Some(Phdr64 {
+                    p_type: PT::LOAD,
+                    p_flags: PF::R | PF::W | PF::X,
+                    p_offset: offset,
+                    p_vaddr: address,
+                    p_paddr: address,
+                    p_filesz: len,
+                    p_memsz: len,
+                    p_align: 1,
+                });
+            }
+        }
+    }
+    if let Some(phdr) = run {
+        write_phdr(&header, &phdr, &mut f)?;
+    }
+
+    for chunk in chunks {
+        f(chunk?.1.as_ref())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::{EM, ET};
+    use crate::phdr::PF;
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    /// Text and data at virtual addresses 0x1000 and 0x1010, with the data loaded from
+    /// flash after the text at 0x8008
+    fn image() -> Vec<u8> {
+        let mut builder = Builder::new(true, false, ET::EXEC, EM::ARM);
+        let text = builder.section(".text", SHT::PROGBITS, 0x1000, vec![1; 8]);
+        let data = builder.section(".data", SHT::PROGBITS, 0x1010, vec![2; 4]);
+        let bss = builder.section(".bss", SHT::NOBITS, 0x1014, vec![0; 4]);
+        let text = builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+        let data = builder.segment(PT::LOAD, PF::R | PF::W, &[data, bss]);
+        builder.phdr_mut(text).p_paddr = 0x8000;
+        builder.phdr_mut(data).p_paddr = 0x8008;
+        builder.build()
+    }
+
+    fn flatten(file: &ElfFile, options: &BinaryOptions) -> (Option<u64>, Vec<u8>) {
+        let mut image = Vec::new();
+        let start = file
+            .binary(options, |data| {
+                image.extend(data);
+                Ok(())
+            })
+            .unwrap();
+        (start, image)
+    }
+
+    #[test]
+    fn binary() {
+        let data = image();
+        let file = ElfFile::new(&data).unwrap();
+        let mut options = BinaryOptions {
+            fill: 0xff,
+            ..Default::default()
+        };
+        let (start, image) = flatten(&file, &options);
+        assert_eq!(start, Some(0x1000));
+        let mut expected = vec![1; 8];
+        expected.extend(&[0xff; 8]);
+        expected.extend(&[2; 4]);
+        assert_eq!(image, expected);
+
+        // Load addresses are contiguous
+        options.physical = true;
+        let (start, image) = flatten(&file, &options);
+        assert_eq!(start, Some(0x8000));
+        assert_eq!(image, [&[1; 8][..], &[2; 4][..]].concat());
+
+        // Sections are placed at their load address too
+        options.sections = &[".data", ".bss"];
+        let regions: Vec<_> = file.regions(&options).map(Result::unwrap).collect();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].address, 0x8008);
+        assert_eq!(file.physical_address(0, 0x1234), Ok(0x1234));
+        options.sections = &[".text", ".data"];
+        assert_eq!(flatten(&file, &options), (Some(0x8000), image));
+        options.sections = &[".missing"];
+        assert_eq!(flatten(&file, &options), (None, Vec::new()));
+    }
+
+    #[test]
+    fn elf() {
+        let chunks = [
+            (0x100, &[1, 2][..]),
+            (0x102, &[3][..]),
+            (0x200, &[]),
+            (0x80, &[4]),
+        ];
+        let ehdr = Ehdr64::new(EIC::ELF32, crate::ehdr::EID::MSB, ET::EXEC, EM::ARM);
+        let mut data = Vec::new();
+        let result = write_elf(&ehdr, chunks.iter().map(|&chunk| Ok(chunk)), |bytes| {
+            data.extend(bytes);
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        let file = ElfFile::new(&data).unwrap();
+        assert!(file.is_elf32() && file.is_big_endian());
+        let regions: Vec<_> = file
+            .regions(&BinaryOptions::default())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(regions.len(), 2);
+        assert_eq!(regions[0].address, 0x100);
+        assert_eq!(regions[0].data, &[1, 2, 3][..]);
+        assert_eq!(regions[1].address, 0x80);
+        assert_eq!(regions[1].data, &[4][..]);
+    }
+
+    #[test]
+    fn overlap() {
+        let mut builder = Builder::new(true, false, ET::EXEC, EM::ARM);
+        let a = builder.section(".a", SHT::PROGBITS, 0x1000, vec![1; 8]);
+        let b = builder.section(".b", SHT::PROGBITS, 0x1004, vec![2; 8]);
+        builder.segment(PT::LOAD, PF::R, &[a]);
+        builder.segment(PT::LOAD, PF::R, &[b]);
+        let overlapping = builder.build();
+        let overlapping = ElfFile::new(&overlapping).unwrap();
+        let result = overlapping.binary(&BinaryOptions::default(), |_| Ok(()));
+        assert_eq!(result, Err(Error::Malformed));
+    }
+}
@@ -0,0 +1,738 @@
+//! eBPF object files
+//!
+//! BPF objects, as produced by clang for [`EM::BPF`](crate::ehdr::EM::BPF), follow the
+//! conventions of libbpf:
+//!
+//! * each executable section other than `.text` is a program, whose type and attach point
+//!   come from the section name, such as `kprobe/do_unlinkat` or `xdp`
+//! * `.text` holds subprograms called from the programs
+//! * maps are defined in the `.maps` section, described by BTF, or as `struct bpf_map_def`
+//!   in the legacy `maps` section
+//! * the `license` section holds the license string, and the `version` section the kernel
+//!   version for old kernels
+//! * relocations against instructions refer to maps, subprograms, global data or externs
+//!
+//! Type information is in [`crate::btf`].
+
+use crate::btf::{Btf, Kind};
+use crate::de::from_bytes;
+use crate::reloc::BPF;
+use crate::shdr::{SHF64, SHN, SHT};
+use crate::{ElfFile, Error, Relocation, Shdr64, Sym64, SymbolTable};
+use core::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+enum_struct!(
+/// Program type (`enum bpf_prog_type`)
+pub struct ProgramType(u32) {
+    UNSPEC                  = 0  => "unspec",
+    SOCKET_FILTER           = 1  => "socket_filter",
+    KPROBE                  = 2  => "kprobe",
+    SCHED_CLS               = 3  => "sched_cls",
+    SCHED_ACT               = 4  => "sched_act",
+    TRACEPOINT              = 5  => "tracepoint",
+    XDP                     = 6  => "xdp",
+    PERF_EVENT              = 7  => "perf_event",
+    CGROUP_SKB              = 8  => "cgroup_skb",
+    CGROUP_SOCK             = 9  => "cgroup_sock",
+    LWT_IN                  = 10 => "lwt_in",
+    LWT_OUT                 = 11 => "lwt_out",
+    LWT_XMIT                = 12 => "lwt_xmit",
+    SOCK_OPS                = 13 => "sock_ops",
+    SK_SKB                  = 14 => "sk_skb",
+    CGROUP_DEVICE           = 15 => "cgroup_device",
+    SK_MSG                  = 16 => "sk_msg",
+    RAW_TRACEPOINT          = 17 => "raw_tracepoint",
+    CGROUP_SOCK_ADDR        = 18 => "cgroup_sock_addr",
+    LWT_SEG6LOCAL           = 19 => "lwt_seg6local",
+    LIRC_MODE2              = 20 => "lirc_mode2",
+    SK_REUSEPORT            = 21 => "sk_reuseport",
+    FLOW_DISSECTOR          = 22 => "flow_dissector",
+    CGROUP_SYSCTL           = 23 => "cgroup_sysctl",
+    RAW_TRACEPOINT_WRITABLE = 24 => "raw_tracepoint_writable",
+    CGROUP_SOCKOPT          = 25 => "cgroup_sockopt",
+    TRACING                 = 26 => "tracing",
+    STRUCT_OPS              = 27 => "struct_ops",
+    EXT                     = 28 => "ext",
+    LSM                     = 29 => "lsm",
+    SK_LOOKUP               = 30 => "sk_lookup",
+    SYSCALL                 = 31 => "syscall",
+    NETFILTER               = 32 => "netfilter",
+}
+);
+
+enum_struct!(
+/// Map type (`enum bpf_map_type`)
+pub struct MapType(u32) {
+    UNSPEC                = 0  => "unspec",
+    HASH                  = 1  => "hash",
+    ARRAY                 = 2  => "array",
+    PROG_ARRAY            = 3  => "prog_array",
+    PERF_EVENT_ARRAY      = 4  => "perf_event_array",
+    PERCPU_HASH           = 5  => "percpu_hash",
+    PERCPU_ARRAY          = 6  => "percpu_array",
+    STACK_TRACE           = 7  => "stack_trace",
+    CGROUP_ARRAY          = 8  => "cgroup_array",
+    LRU_HASH              = 9  => "lru_hash",
+    LRU_PERCPU_HASH       = 10 => "lru_percpu_hash",
+    LPM_TRIE              = 11 => "lpm_trie",
+    ARRAY_OF_MAPS         = 12 => "array_of_maps",
+    HASH_OF_MAPS          = 13 => "hash_of_maps",
+    DEVMAP                = 14 => "devmap",
+    SOCKMAP               = 15 => "sockmap",
+    CPUMAP                = 16 => "cpumap",
+    XSKMAP                = 17 => "xskmap",
+    SOCKHASH              = 18 => "sockhash",
+    CGROUP_STORAGE        = 19 => "cgroup_storage",
+    REUSEPORT_SOCKARRAY   = 20 => "reuseport_sockarray",
+    PERCPU_CGROUP_STORAGE = 21 => "percpu_cgroup_storage",
+    QUEUE                 = 22 => "queue",
+    STACK                 = 23 => "stack",
+    SK_STORAGE            = 24 => "sk_storage",
+    DEVMAP_HASH           = 25 => "devmap_hash",
+    STRUCT_OPS            = 26 => "struct_ops",
+    RINGBUF               = 27 => "ringbuf",
+    INODE_STORAGE         = 28 => "inode_storage",
+    TASK_STORAGE          = 29 => "task_storage",
+    BLOOM_FILTER          = 30 => "bloom_filter",
+    USER_RINGBUF          = 31 => "user_ringbuf",
+    CGRP_STORAGE          = 32 => "cgrp_storage",
+    ARENA                 = 33 => "arena",
+}
+);
+
+/// Section name conventions: prefix, program type, and whether an attach point may follow
+/// a '/'
+const SECTIONS: &[(&str, ProgramType, bool)] = &[
+    ("socket", ProgramType::SOCKET_FILTER, true),
+    ("sk_reuseport", ProgramType::SK_REUSEPORT, true),
+    ("kprobe", ProgramType::KPROBE, true),
+    ("kretprobe", ProgramType::KPROBE, true),
+    ("uprobe", ProgramType::KPROBE, true),
+    ("uretprobe", ProgramType::KPROBE, true),
+    ("ksyscall", ProgramType::KPROBE, true),
+    ("kretsyscall", ProgramType::KPROBE, true),
+    ("usdt", ProgramType::KPROBE, true),
+    ("tc", ProgramType::SCHED_CLS, true),
+    ("classifier", ProgramType::SCHED_CLS, true),
+    ("action", ProgramType::SCHED_ACT, true),
+    ("tracepoint", ProgramType::TRACEPOINT, true),
+    ("tp", ProgramType::TRACEPOINT, true),
+    ("raw_tracepoint", ProgramType::RAW_TRACEPOINT, true),
+    ("raw_tp", ProgramType::RAW_TRACEPOINT, true),
+    (
+        "raw_tracepoint.w",
+        ProgramType::RAW_TRACEPOINT_WRITABLE,
+        true,
+    ),
+    ("raw_tp.w", ProgramType::RAW_TRACEPOINT_WRITABLE, true),
+    ("tp_btf", ProgramType::TRACING, true),
+    ("fentry", ProgramType::TRACING, true),
+    ("fexit", ProgramType::TRACING, true),
+    ("fmod_ret", ProgramType::TRACING, true),
+    ("iter", ProgramType::TRACING, true),
+    ("freplace", ProgramType::EXT, true),
+    ("lsm", ProgramType::LSM, true),
+    ("lsm_cgroup", ProgramType::LSM, true),
+    ("xdp", ProgramType::XDP, true),
+    ("xdp.frags", ProgramType::XDP, true),
+    ("perf_event", ProgramType::PERF_EVENT, false),
+    ("lwt_in", ProgramType::LWT_IN, false),
+    ("lwt_out", ProgramType::LWT_OUT, false),
+    ("lwt_xmit", ProgramType::LWT_XMIT, false),
+    ("lwt_seg6local", ProgramType::LWT_SEG6LOCAL, false),
+    ("sockops", ProgramType::SOCK_OPS, false),
+    ("sk_skb", ProgramType::SK_SKB, true),
+    ("sk_msg", ProgramType::SK_MSG, false),
+    ("lirc_mode2", ProgramType::LIRC_MODE2, false),
+    ("flow_dissector", ProgramType::FLOW_DISSECTOR, false),
+    ("cgroup_skb", ProgramType::CGROUP_SKB, true),
+    ("cgroup/skb", ProgramType::CGROUP_SKB, false),
+    ("cgroup/sock", ProgramType::CGROUP_SOCK, false),
+    ("cgroup/sock_create", ProgramType::CGROUP_SOCK, false),
+    ("cgroup/sock_release", ProgramType::CGROUP_SOCK, false),
+    ("cgroup/post_bind4", ProgramType::CGROUP_SOCK, false),
+    ("cgroup/post_bind6", ProgramType::CGROUP_SOCK, false),
+    ("cgroup/bind4", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/bind6", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/connect4", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/connect6", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/connect_unix", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/sendmsg4", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/sendmsg6", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/recvmsg4", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/recvmsg6", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/getpeername4", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/getpeername6", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/getsockname4", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/getsockname6", ProgramType::CGROUP_SOCK_ADDR, false),
+    ("cgroup/sysctl", ProgramType::CGROUP_SYSCTL, false),
+    ("cgroup/getsockopt", ProgramType::CGROUP_SOCKOPT, false),
+    ("cgroup/setsockopt", ProgramType::CGROUP_SOCKOPT, false),
+    ("cgroup/dev", ProgramType::CGROUP_DEVICE, false),
+    ("struct_ops", ProgramType::STRUCT_OPS, true),
+    ("struct_ops.s", ProgramType::STRUCT_OPS, true),
+    ("sk_lookup", ProgramType::SK_LOOKUP, false),
+    ("syscall", ProgramType::SYSCALL, false),
+    ("netfilter", ProgramType::NETFILTER, false),
+];
+
+/// Program type and attach point for section `name`, or `None` if it follows no convention
+///
+/// Sleepable variants such as `fentry.s/` are accepted as their base type.
+pub fn program_type(name: &str) -> Option<(ProgramType, Option<&str>)> {
+    let (prefix, attach) = match name.find('/') {
+        // cgroup sections include a '/' in the prefix
+        Some(_) if name.starts_with("cgroup/") => (name, None),
+        Some(slash) => (&name[..slash], Some(&name[slash + 1..])),
+        None => (name, None),
+    };
+    let base = prefix.strip_suffix(".s").unwrap_or(prefix);
+    let (_, program_type, can_attach) = SECTIONS
+        .iter()
+        .find(|(section, _, _)| *section == prefix || *section == base)?;
+    if attach.is_some() && !can_attach {
+        return None;
+    }
+    Some((*program_type, attach.filter(|attach| !attach.is_empty())))
+}
+
+/// `BPF_LD | BPF_IMM | BPF_DW`, a 16-byte instruction loading a 64-bit immediate
+pub const LD_IMM64: u8 = 0x18;
+/// `BPF_JMP | BPF_CALL`
+pub const CALL: u8 = 0x85;
+/// `src_reg` of a [`LD_IMM64`] loading a map file descriptor
+pub const PSEUDO_MAP_FD: u8 = 1;
+/// `src_reg` of a [`CALL`] to a subprogram
+pub const PSEUDO_CALL: u8 = 1;
+/// `src_reg` of a [`LD_IMM64`] loading the address of a subprogram
+pub const PSEUDO_FUNC: u8 = 4;
+
+/// Raw instruction (`struct bpf_insn`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct RawInsn {
+    /// Opcode
+    pub code: u8,
+    /// Destination and source registers, in an order depending on the byte order
+    pub regs: u8,
+    /// Offset
+    pub off: i16,
+    /// Immediate value
+    pub imm: i32,
+}
+impl RawInsn {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+/// Instruction with decoded registers
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Insn {
+    /// Opcode
+    pub code: u8,
+    /// Destination register
+    pub dst_reg: u8,
+    /// Source register
+    pub src_reg: u8,
+    /// Offset
+    pub off: i16,
+    /// Immediate value
+    pub imm: i32,
+}
+
+impl Insn {
+    /// Decode the instruction at the start of `data`
+    pub fn read(data: &[u8], big_endian: bool) -> Result<Self, Error> {
+        let raw: RawInsn = from_bytes(data, big_endian)?;
+        let (dst_reg, src_reg) = if big_endian {
+            (raw.regs >> 4, raw.regs & 0xf)
+        } else {
+            (raw.regs & 0xf, raw.regs >> 4)
+        };
+        Ok(Insn {
+            code: raw.code,
+            dst_reg,
+            src_reg,
+            off: raw.off,
+            imm: raw.imm,
+        })
+    }
+}
+
+/// Program section
+#[derive(Clone, Copy)]
+pub struct Program<'a> {
+    /// Index of the section
+    pub index: usize,
+    /// Section name
+    pub section: &'a str,
+    /// Program type from the section name, [`ProgramType::UNSPEC`] if unknown
+    pub program_type: ProgramType,
+    /// Attach point from the section name, such as the kprobe function
+    pub attach: Option<&'a str>,
+    /// Instructions
+    pub data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Program<'a> {
+    /// Iterate over the instructions, the second half of a [`LD_IMM64`] included
+    pub fn instructions(&self) -> impl Iterator<Item = Insn> + 'a {
+        let big_endian = self.big_endian;
+        self.data
+            .chunks_exact(RawInsn::SIZE)
+            .filter_map(move |insn| Insn::read(insn, big_endian).ok())
+    }
+}
+
+/// Legacy map definition (`struct bpf_map_def` in the `maps` section)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct MapDef {
+    /// Map type
+    pub map_type: MapType,
+    /// Key size in bytes
+    pub key_size: u32,
+    /// Value size in bytes
+    pub value_size: u32,
+    /// Maximum number of entries
+    pub max_entries: u32,
+    /// Flags
+    pub map_flags: u32,
+}
+impl MapDef {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+/// Map definition
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Map<'a> {
+    /// Map name, from the symbol or BTF variable
+    pub name: &'a str,
+    /// Offset of the definition in its section
+    pub offset: u64,
+    /// Definition, with 0 for unspecified fields
+    pub def: MapDef,
+}
+
+/// Kind of a relocation against an instruction
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum RelocationKind {
+    /// Reference to a map
+    Map,
+    /// Call to a subprogram
+    Call,
+    /// Address of a subprogram, for callbacks
+    Func,
+    /// Reference to global data in `.data`, `.rodata` or `.bss`
+    Data,
+    /// Reference to an extern resolved by the loader, in `.kconfig` or `.ksyms`
+    Extern,
+    /// Anything else, such as relocations in DWARF sections
+    Other,
+}
+
+/// Relocation against a program section
+#[derive(Clone, Copy)]
+pub struct ProgramRelocation<'a> {
+    /// Relocation
+    pub relocation: Relocation,
+    /// Relocation type
+    pub r_type: BPF,
+    /// Symbol referred to
+    pub sym: Sym64,
+    /// Symbol name
+    pub name: &'a str,
+    /// Instruction at the relocated location
+    pub insn: Insn,
+    /// What the relocation refers to
+    pub kind: RelocationKind,
+}
+
+/// Returns true if `name` is a legacy or BTF-defined maps section
+fn is_maps(name: &str) -> bool {
+    name == "maps" || name == ".maps" || name.starts_with("maps/")
+}
+
+/// Returns true if `name` is a global data section
+fn is_data(name: &str) -> bool {
+    [".data", ".rodata", ".bss"].iter().any(|prefix| {
+        name == *prefix || name.starts_with(prefix) && name[prefix.len()..].starts_with('.')
+    })
+}
+
+impl<'a> ElfFile<'a> {
+    /// Iterate over the BPF programs, each executable section other than `.text`
+    pub fn bpf_programs(
+        &self,
+    ) -> Result<impl Iterator<Item = Result<Program<'a>, Error>> + 'a, Error> {
+        let file = *self;
+        Ok(self.shdrs()?.enumerate().filter_map(move |(index, shdr)| {
+            if shdr.sh_type != SHT::PROGBITS
+                || shdr.sh_flags & SHF64::EXECINSTR != SHF64::EXECINSTR
+                || shdr.sh_size == 0
+            {
+                return None;
+            }
+            let program = |section| {
+                let (program_type, attach) =
+                    program_type(section).unwrap_or((ProgramType::UNSPEC, None));
+                Ok(Program {
+                    index,
+                    section,
+                    program_type,
+                    attach,
+                    data: file.section_data(&shdr)?,
+                    big_endian: file.is_big_endian(),
+                })
+            };
+            match file.section_name(&shdr) {
+                Ok(".text") => None,
+                Ok(section) => Some(program(section)),
+                Err(err) => Some(Err(err)),
+            }
+        }))
+    }
+
+    /// License string from the `license` section, or `None` if there isn't one
+    pub fn bpf_license(&self) -> Result<Option<&'a str>, Error> {
+        match self.section_by_name("license")? {
+            Some((_, shdr)) => crate::file::c_str(self.section_data(&shdr)?, 0).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Kernel version from the `version` section, or `None` if there isn't one
+    pub fn bpf_kernel_version(&self) -> Result<Option<u32>, Error> {
+        match self.section_by_name("version")? {
+            Some((_, shdr)) if shdr.sh_size < 4 => Err(Error::Truncated),
+            Some((_, shdr)) => self.read(shdr.sh_offset).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Map definitions, from the legacy `maps` section and the BTF-defined `.maps` section
+    ///
+    /// Legacy definitions are named by the symbols in the section, and each takes the
+    /// section size divided by the number of symbols. BTF-defined maps are variables of the
+    /// `.maps` data section, whose members encode integers as pointers to arrays with that
+    /// many elements.
+    pub fn bpf_maps(&self, mut f: impl FnMut(Map<'a>)) -> Result<(), Error> {
+        if let Some((index, shdr)) = self.section_by_name("maps")? {
+            let symbols = self.symbol_table()?.ok_or(Error::Malformed)?;
+            let in_maps = |sym: &Sym64| sym.st_shndx == SHN::from(index as u16) && sym.st_name != 0;
+            let count = symbols.iter().filter(in_maps).count() as u64;
+            if let Some(def_size) = shdr.sh_size.checked_div(count) {
+                let data = self.section_data(&shdr)?;
+                for sym in symbols.iter().filter(in_maps) {
+                    let def = data
+                        .get(sym.st_value as usize..sym.st_value.saturating_add(def_size) as usize)
+                        .ok_or(Error::Truncated)?;
+                    // Definitions may be shorter or longer than MapDef
+                    let mut padded = [0; MapDef::SIZE];
+                    let len = def.len().min(MapDef::SIZE);
+                    padded[..len].copy_from_slice(&def[..len]);
+                    f(Map {
+                        name: symbols.name(&sym)?,
+                        offset: sym.st_value,
+                        def: from_bytes(&padded, self.is_big_endian())?,
+                    });
+                }
+            }
+        }
+        if let Some(btf) = self.btf()? {
+            if let Some(datasec) = btf.by_name(Kind::DATASEC, ".maps")? {
+                for secinfo in datasec.secinfos() {
+                    let var = btf.get(secinfo.type_id)?;
+                    f(Map {
+                        name: btf.name(&var)?,
+                        offset: secinfo.offset.into(),
+                        def: btf_map_def(&btf, var.header.size_or_type)?,
+                    });
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Relocations against the instructions of program section `index`, from every
+    /// relocation section that applies to it
+    pub fn bpf_relocations(
+        &self,
+        index: usize,
+    ) -> Result<impl Iterator<Item = Result<ProgramRelocation<'a>, Error>> + 'a, Error> {
+        let target = self.shdr(index)?;
+        let file = *self;
+        let sections = self.shdrs()?.filter(move |shdr| {
+            (shdr.sh_type == SHT::REL || shdr.sh_type == SHT::RELA)
+                && shdr.sh_info as usize == index
+        });
+        Ok(sections.flat_map(move |rel| {
+            let tables = file
+                .relocations(&rel)
+                .and_then(|relocations| Ok((relocations, file.symbols(rel.sh_link as usize)?)));
+            let (relocations, symbols, error) = match tables {
+                Ok((relocations, symbols)) => (Some(relocations), Some(symbols), None),
+                Err(err) => (None, None, Some(Err(err))),
+            };
+            error
+                .into_iter()
+                .chain(
+                    relocations
+                        .into_iter()
+                        .flatten()
+                        .map(move |relocation| match &symbols {
+                            Some(symbols) => file.bpf_relocation(&target, symbols, relocation),
+                            None => Err(Error::Malformed),
+                        }),
+                )
+        }))
+    }
+
+    /// Classify `relocation` against the instructions of section `target`
+    fn bpf_relocation(
+        &self,
+        target: &Shdr64,
+        symbols: &SymbolTable<'a>,
+        relocation: Relocation,
+    ) -> Result<ProgramRelocation<'a>, Error> {
+        let sym = symbols.get(relocation.r_sym as usize)?;
+        let data = self.section_data(target)?;
+        let insn = Insn::read(
+            data.get(relocation.r_offset as usize..)
+                .ok_or(Error::Truncated)?,
+            self.is_big_endian(),
+        )?;
+        let section: Option<Shdr64> =
+            if sym.st_shndx == SHN::UNDEF || sym.st_shndx >= SHN::LORESERVE {
+                None
+            } else {
+                Some(self.shdr(u16::from(sym.st_shndx) as usize)?)
+            };
+        let section_name = match &section {
+            Some(shdr) => self.section_name(shdr)?,
+            None => "",
+        };
+        let executable = section
+            .map(|shdr| shdr.sh_flags & SHF64::EXECINSTR == SHF64::EXECINSTR)
+            .unwrap_or(false);
+        let kind = if insn.code == CALL && insn.src_reg == PSEUDO_CALL {
+            RelocationKind::Call
+        } else if insn.code != LD_IMM64 {
+            RelocationKind::Other
+        } else if is_maps(section_name) {
+            RelocationKind::Map
+        } else if executable {
+            RelocationKind::Func
+        } else if section.is_none() || section_name == ".kconfig" || section_name == ".ksyms" {
+            RelocationKind::Extern
+        } else if is_data(section_name) {
+            RelocationKind::Data
+        } else {
+            RelocationKind::Other
+        };
+        Ok(ProgramRelocation {
+            relocation,
+            r_type: BPF::from(relocation.r_type),
+            sym,
+            name: symbols.name(&sym)?,
+            insn,
+            kind,
+        })
+    }
+}
+
+/// Integer encoded in a BTF map definition member as a pointer to an array
+fn btf_map_int(btf: &Btf, type_id: u32) -> Result<u32, Error> {
+    let ptr = btf.strip(type_id)?.ok_or(Error::Malformed)?;
+    if ptr.kind() != Kind::PTR {
+        return Err(Error::Malformed);
+    }
+    let array = btf
+        .strip(ptr.header.size_or_type)?
+        .and_then(|ty| ty.array())
+        .ok_or(Error::Malformed)?;
+    Ok(array.nelems)
+}
+
+/// Size of the type pointed to by a BTF map definition member
+fn btf_map_size(btf: &Btf, type_id: u32) -> Result<u32, Error> {
+    let ptr = btf.strip(type_id)?.ok_or(Error::Malformed)?;
+    if ptr.kind() != Kind::PTR {
+        return Err(Error::Malformed);
+    }
+    Ok(btf.size_of(ptr.header.size_or_type)? as u32)
+}
+
+/// Map definition from the BTF struct `type_id`
+fn btf_map_def(btf: &Btf, type_id: u32) -> Result<MapDef, Error> {
+    let ty = btf.strip(type_id)?.ok_or(Error::Malformed)?;
+    let mut def = MapDef::default();
+    for member in ty.members() {
+        let id = member.type_id;
+        match btf.string(member.name_off)? {
+            "type" => def.map_type = MapType::from(btf_map_int(btf, id)?),
+            "max_entries" => def.max_entries = btf_map_int(btf, id)?,
+            "map_flags" => def.map_flags = btf_map_int(btf, id)?,
+            "key_size" => def.key_size = btf_map_int(btf, id)?,
+            "value_size" => def.value_size = btf_map_int(btf, id)?,
+            "key" => def.key_size = btf_map_size(btf, id)?,
+            "value" => def.value_size = btf_map_size(btf, id)?,
+            _ => {}
+        }
+    }
+    Ok(def)
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::btf;
+    use crate::ehdr::{EM, ET};
+    use crate::testutil::Builder;
+    use std::vec::Vec;
+    use std::{format, vec};
+
+    #[test]
+    fn section_names() {
+        assert_eq!(
+            program_type("kprobe/do_unlinkat"),
+            Some((ProgramType::KPROBE, Some("do_unlinkat")))
+        );
+        assert_eq!(program_type("xdp"), Some((ProgramType::XDP, None)));
+        assert_eq!(
+            program_type("tracepoint/syscalls/sys_enter_openat"),
+            Some((ProgramType::TRACEPOINT, Some("syscalls/sys_enter_openat")))
+        );
+        assert_eq!(
+            program_type("fentry.s/vfs_read"),
+            Some((ProgramType::TRACING, Some("vfs_read")))
+        );
+        assert_eq!(
+            program_type("cgroup/connect4"),
+            Some((ProgramType::CGROUP_SOCK_ADDR, None))
+        );
+        assert_eq!(program_type("sockops/foo"), None);
+        assert_eq!(program_type(".text"), None);
+        assert_eq!(format!("{}", ProgramType::KPROBE), "kprobe");
+    }
+
+    #[test]
+    fn object() {
+        let mut builder = Builder::new(false, false, ET::REL, EM::BPF);
+        // r1 = map ll; call 1; call subprogram; exit
+        let mut insns = vec![0x18, 0x01, 0, 0, 0, 0, 0, 0];
+        insns.extend([0; 8]);
+        insns.extend([0x85, 0, 0, 0, 1, 0, 0, 0]);
+        insns.extend([0x85, 0x10, 0, 0, 0xff, 0xff, 0xff, 0xff]);
+        insns.extend([0x95, 0, 0, 0, 0, 0, 0, 0]);
+        let text = builder.section(".text", SHT::PROGBITS, 0, vec![0x95, 0, 0, 0, 0, 0, 0, 0]);
+        builder.shdr_mut(text).sh_flags = SHF64::ALLOC | SHF64::EXECINSTR;
+        let prog = builder.section("kprobe/do_unlinkat", SHT::PROGBITS, 0, insns);
+        builder.shdr_mut(prog).sh_flags = SHF64::ALLOC | SHF64::EXECINSTR;
+        let mut legacy: Vec<u8> = [1u32, 4, 8, 1024, 0]
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect();
+        legacy.extend(legacy.clone());
+        let maps = builder.section("maps", SHT::PROGBITS, 0, legacy);
+        builder.section("license", SHT::PROGBITS, 0, b"GPL\0".to_vec());
+        builder.section(
+            "version",
+            SHT::PROGBITS,
+            0,
+            0x50a00u32.to_le_bytes().to_vec(),
+        );
+
+        // struct { int (*type)[BPF_MAP_TYPE_RINGBUF]; int (*max_entries)[4096]; } events
+        let mut btf = btf::test::Builder::new();
+        let int = btf.add("int", Kind::INT, 0, 4, &[0x0100_0020]);
+        let index = btf.add("__ARRAY_SIZE_TYPE__", Kind::INT, 0, 4, &[32]);
+        let ringbuf = btf.add("", Kind::ARRAY, 0, 0, &[int, index, 27]);
+        let ringbuf_ptr = btf.add("", Kind::PTR, 0, ringbuf, &[]);
+        let entries = btf.add("", Kind::ARRAY, 0, 0, &[int, index, 4096]);
+        let entries_ptr = btf.add("", Kind::PTR, 0, entries, &[]);
+        let type_name = btf.string("type");
+        let max_entries = btf.string("max_entries");
+        let def = btf.add(
+            "",
+            Kind::STRUCT,
+            2,
+            16,
+            &[type_name, ringbuf_ptr, 0, max_entries, entries_ptr, 64],
+        );
+        let var = btf.add("events", Kind::VAR, 0, def, &[1]);
+        btf.add(".maps", Kind::DATASEC, 1, 16, &[var, 0, 16]);
+        builder.section(".BTF", SHT::PROGBITS, 0, btf.build());
+
+        let (strtab, names) = Builder::strings(&["counts", "hist", "helper"]);
+        let strtab = builder.section(".strtab", SHT::STRTAB, 0, strtab);
+        let mut symtab = builder.sym(0, 0, 0, 0, 0);
+        symtab.extend(builder.sym(names[0], 0, 20, 0x11, maps as u16));
+        symtab.extend(builder.sym(names[1], 20, 20, 0x11, maps as u16));
+        symtab.extend(builder.sym(names[2], 0, 8, 0x12, text as u16));
+        let symtab = builder.section(".symtab", SHT::SYMTAB, 0, symtab);
+        builder.shdr_mut(symtab).sh_link = strtab as u32;
+        // Relocations split over two sections, as after partial linking
+        let rel = builder.serialize(&crate::Rel64 {
+            r_offset: 0,
+            r_info: 2 << 32 | 1,
+        });
+        let rel = builder.section(".relkprobe/do_unlinkat", SHT::REL, 0, rel);
+        builder.shdr_mut(rel).sh_link = symtab as u32;
+        builder.shdr_mut(rel).sh_info = prog as u32;
+        let rel = builder.serialize(&crate::Rel64 {
+            r_offset: 24,
+            r_info: 3 << 32 | 10,
+        });
+        let rel = builder.section(".relkprobe/do_unlinkat.1", SHT::REL, 0, rel);
+        builder.shdr_mut(rel).sh_link = symtab as u32;
+        builder.shdr_mut(rel).sh_info = prog as u32;
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+
+        let programs: Vec<_> = file.bpf_programs().unwrap().map(Result::unwrap).collect();
+        assert_eq!(programs.len(), 1);
+        assert_eq!(programs[0].section, "kprobe/do_unlinkat");
+        assert_eq!(programs[0].program_type, ProgramType::KPROBE);
+        assert_eq!(programs[0].attach, Some("do_unlinkat"));
+        let insns: Vec<_> = programs[0].instructions().collect();
+        assert_eq!(insns.len(), 5);
+        assert_eq!((insns[0].code, insns[0].dst_reg), (LD_IMM64, 1));
+        assert_eq!((insns[3].src_reg, insns[3].imm), (PSEUDO_CALL, -1));
+
+        assert_eq!(file.bpf_license(), Ok(Some("GPL")));
+        assert_eq!(file.bpf_kernel_version(), Ok(Some(0x50a00)));
+
+        let mut maps = Vec::new();
+        file.bpf_maps(|map| maps.push(map)).unwrap();
+        assert_eq!(maps.len(), 3);
+        assert_eq!(maps[1].name, "hist");
+        assert_eq!(maps[1].offset, 20);
+        assert_eq!(maps[1].def.map_type, MapType::HASH);
+        assert_eq!(maps[1].def.max_entries, 1024);
+        assert_eq!(maps[2].name, "events");
+        assert_eq!(maps[2].def.map_type, MapType::RINGBUF);
+        assert_eq!(maps[2].def.max_entries, 4096);
+
+        let relocations: Vec<_> = file
+            .bpf_relocations(prog)
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(relocations.len(), 2);
+        assert_eq!(relocations[0].kind, RelocationKind::Map);
+        assert_eq!(relocations[0].name, "hist");
+        assert_eq!(relocations[0].r_type, BPF::R_BPF_64_64);
+        assert_eq!(relocations[1].kind, RelocationKind::Call);
+        assert_eq!(relocations[1].name, "helper");
+        assert_eq!(relocations[1].r_type, BPF::R_BPF_64_32);
+
+        let mut builder = Builder::new(false, false, ET::REL, EM::BPF);
+        builder.section("version", SHT::PROGBITS, 0, vec![0; 2]);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+        assert_eq!(file.bpf_kernel_version(), Err(Error::Truncated));
+    }
+}
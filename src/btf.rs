@@ -0,0 +1,1053 @@
+//! BPF Type Format
+//!
+//! The `.BTF` section describes the types used by a BPF object (or by the kernel, for
+//! `vmlinux`). It starts with a [`Header`], followed by the type records and a string table.
+//! Types are numbered from 1 in the order they appear, 0 being `void`. The `.BTF.ext`
+//! section ([`BtfExt`]) maps instructions to function prototypes, source lines and CO-RE
+//! relocations.
+//!
+//! Types are read on demand. [`Btf::new()`] keeps the offsets of a bounded number of types,
+//! so [`Btf::get()`] only scans the records following the nearest of them.
+
+use crate::de::from_bytes;
+use crate::{ElfFile, Error};
+use core::convert::TryFrom;
+use core::marker::PhantomData;
+use core::mem::size_of;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+/// BTF magic number, in the byte order of the file
+pub const MAGIC: u16 = 0xeb9f;
+
+/// `.BTF` section header
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Header {
+    /// [`MAGIC`]
+    pub magic: u16,
+    /// Version, 1
+    pub version: u8,
+    /// Flags, 0
+    pub flags: u8,
+    /// Size of the header
+    pub hdr_len: u32,
+    /// Offset of the type records after the header
+    pub type_off: u32,
+    /// Size of the type records
+    pub type_len: u32,
+    /// Offset of the string table after the header
+    pub str_off: u32,
+    /// Size of the string table
+    pub str_len: u32,
+}
+impl Header {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+enum_struct!(
+/// Kind of a type
+pub struct Kind(u8) {
+    UNKN       = 0  => "UNKNOWN",
+    INT        = 1  => "INT",
+    PTR        = 2  => "PTR",
+    ARRAY      = 3  => "ARRAY",
+    STRUCT     = 4  => "STRUCT",
+    UNION      = 5  => "UNION",
+    ENUM       = 6  => "ENUM",
+    FWD        = 7  => "FWD",
+    TYPEDEF    = 8  => "TYPEDEF",
+    VOLATILE   = 9  => "VOLATILE",
+    CONST      = 10 => "CONST",
+    RESTRICT   = 11 => "RESTRICT",
+    FUNC       = 12 => "FUNC",
+    FUNC_PROTO = 13 => "FUNC_PROTO",
+    VAR        = 14 => "VAR",
+    DATASEC    = 15 => "DATASEC",
+    FLOAT      = 16 => "FLOAT",
+    DECL_TAG   = 17 => "DECL_TAG",
+    TYPE_TAG   = 18 => "TYPE_TAG",
+    ENUM64     = 19 => "ENUM64",
+}
+);
+
+/// Common part of a type record (`struct btf_type`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct TypeHeader {
+    /// Offset of the name in the string table, 0 for anonymous types
+    pub name_off: u32,
+    /// `vlen` in bits 0-15, kind in bits 24-28 and `kind_flag` in bit 31
+    pub info: u32,
+    /// Size in bytes for `INT`, `ENUM`, `STRUCT`, `UNION`, `DATASEC` and `FLOAT`, otherwise
+    /// the referenced type
+    pub size_or_type: u32,
+}
+impl TypeHeader {
+    pub const SIZE: usize = size_of::<Self>();
+
+    /// Kind of the type
+    pub fn kind(&self) -> Kind {
+        Kind::from(((self.info >> 24) & 0x1f) as u8)
+    }
+
+    /// Number of members, enumerators, parameters or variables following the record
+    pub fn vlen(&self) -> u16 {
+        self.info as u16
+    }
+
+    /// Kind specific flag, such as bitfield offsets in struct members
+    pub fn kind_flag(&self) -> bool {
+        self.info & 0x8000_0000 != 0
+    }
+}
+
+/// Array (`struct btf_array`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Array {
+    /// Element type
+    pub type_id: u32,
+    /// Index type
+    pub index_type: u32,
+    /// Number of elements
+    pub nelems: u32,
+}
+
+/// Struct or union member (`struct btf_member`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Member {
+    /// Offset of the name in the string table
+    pub name_off: u32,
+    /// Member type
+    pub type_id: u32,
+    /// Offset in bits, or with `kind_flag` the bitfield size in bits 24-31 and offset in
+    /// bits 0-23
+    pub offset: u32,
+}
+
+/// Enumerator (`struct btf_enum`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Enum {
+    /// Offset of the name in the string table
+    pub name_off: u32,
+    /// Value
+    pub val: i32,
+}
+
+/// 64-bit enumerator (`struct btf_enum64`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Enum64 {
+    /// Offset of the name in the string table
+    pub name_off: u32,
+    /// Low 32 bits of the value
+    pub val_lo32: u32,
+    /// High 32 bits of the value
+    pub val_hi32: u32,
+}
+
+/// Function parameter (`struct btf_param`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Param {
+    /// Offset of the name in the string table
+    pub name_off: u32,
+    /// Parameter type, 0 for a trailing `...`
+    pub type_id: u32,
+}
+
+/// Variable of a data section (`struct btf_var_secinfo`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct VarSecinfo {
+    /// `VAR` or `FUNC` type
+    pub type_id: u32,
+    /// Offset of the variable in the section
+    pub offset: u32,
+    /// Size of the variable
+    pub size: u32,
+}
+
+/// Type record with its identifier and kind specific data
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Type<'a> {
+    /// Type identifier
+    pub id: u32,
+    /// Common part of the record
+    pub header: TypeHeader,
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Type<'a> {
+    /// Kind of the type
+    pub fn kind(&self) -> Kind {
+        self.header.kind()
+    }
+
+    /// Size for kinds that have one, see [`TypeHeader::size_or_type`]
+    pub fn size(&self) -> Option<u32> {
+        match self.kind() {
+            Kind::INT | Kind::ENUM | Kind::ENUM64 | Kind::STRUCT | Kind::UNION => {
+                Some(self.header.size_or_type)
+            }
+            Kind::DATASEC | Kind::FLOAT => Some(self.header.size_or_type),
+            _ => None,
+        }
+    }
+
+    /// Referenced type for kinds that have one, see [`TypeHeader::size_or_type`]
+    pub fn referenced(&self) -> Option<u32> {
+        match self.kind() {
+            Kind::PTR | Kind::TYPEDEF | Kind::VOLATILE | Kind::CONST | Kind::RESTRICT => {
+                Some(self.header.size_or_type)
+            }
+            Kind::FUNC | Kind::VAR | Kind::DECL_TAG | Kind::TYPE_TAG => {
+                Some(self.header.size_or_type)
+            }
+            _ => None,
+        }
+    }
+
+    /// `INT` encoding: bits 24-27 signedness/char/bool, bits 16-23 offset, bits 0-7 size
+    pub fn int_encoding(&self) -> Option<u32> {
+        match self.kind() {
+            Kind::INT => from_bytes(self.data, self.big_endian).ok(),
+            _ => None,
+        }
+    }
+
+    /// `ARRAY` description
+    pub fn array(&self) -> Option<Array> {
+        match self.kind() {
+            Kind::ARRAY => from_bytes(self.data, self.big_endian).ok(),
+            _ => None,
+        }
+    }
+
+    /// `VAR` linkage, or `DECL_TAG` component index
+    pub fn extra(&self) -> Option<u32> {
+        match self.kind() {
+            Kind::VAR | Kind::DECL_TAG => from_bytes(self.data, self.big_endian).ok(),
+            _ => None,
+        }
+    }
+
+    /// Records following the type, of type `T`
+    fn records<T: DeserializeOwned>(&self, kinds: &[Kind]) -> Records<'a, T> {
+        let data = if kinds.contains(&self.kind()) {
+            self.data
+        } else {
+            &[]
+        };
+        Records {
+            data,
+            big_endian: self.big_endian,
+            _record: PhantomData,
+        }
+    }
+
+    /// `STRUCT` or `UNION` members
+    pub fn members(&self) -> Records<'a, Member> {
+        self.records(&[Kind::STRUCT, Kind::UNION])
+    }
+
+    /// `ENUM` enumerators
+    pub fn enums(&self) -> Records<'a, Enum> {
+        self.records(&[Kind::ENUM])
+    }
+
+    /// `ENUM64` enumerators
+    pub fn enums64(&self) -> Records<'a, Enum64> {
+        self.records(&[Kind::ENUM64])
+    }
+
+    /// `FUNC_PROTO` parameters
+    pub fn params(&self) -> Records<'a, Param> {
+        self.records(&[Kind::FUNC_PROTO])
+    }
+
+    /// `DATASEC` variables
+    pub fn secinfos(&self) -> Records<'a, VarSecinfo> {
+        self.records(&[Kind::DATASEC])
+    }
+}
+
+/// Iterator over fixed size records
+#[derive(Clone)]
+pub struct Records<'a, T> {
+    data: &'a [u8],
+    big_endian: bool,
+    _record: PhantomData<T>,
+}
+
+impl<'a, T: DeserializeOwned> Iterator for Records<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        let size = size_of::<T>();
+        if self.data.len() < size {
+            return None;
+        }
+        let record = from_bytes(self.data, self.big_endian).ok();
+        self.data = &self.data[size..];
+        record
+    }
+}
+
+/// Size of the data following a type record
+fn data_size(header: &TypeHeader) -> Result<usize, Error> {
+    let vlen = header.vlen() as usize;
+    Ok(match header.kind() {
+        Kind::INT | Kind::VAR | Kind::DECL_TAG => 4,
+        Kind::ARRAY => size_of::<Array>(),
+        Kind::STRUCT | Kind::UNION => vlen * size_of::<Member>(),
+        Kind::ENUM => vlen * size_of::<Enum>(),
+        Kind::ENUM64 => vlen * size_of::<Enum64>(),
+        Kind::FUNC_PROTO => vlen * size_of::<Param>(),
+        Kind::DATASEC => vlen * size_of::<VarSecinfo>(),
+        Kind::PTR | Kind::FWD | Kind::TYPEDEF | Kind::VOLATILE | Kind::CONST => 0,
+        Kind::RESTRICT | Kind::FUNC | Kind::FLOAT | Kind::TYPE_TAG => 0,
+        _ => return Err(Error::Unsupported),
+    })
+}
+
+/// Split `size` bytes at `offset` after a header of `hdr_len` bytes from `data`
+fn subsection(data: &[u8], hdr_len: u32, offset: u32, size: u32) -> Result<&[u8], Error> {
+    let start = u64::from(hdr_len) + u64::from(offset);
+    let end = start + u64::from(size);
+    let start = usize::try_from(start).map_err(|_| Error::Truncated)?;
+    let end = usize::try_from(end).map_err(|_| Error::Truncated)?;
+    data.get(start..end).ok_or(Error::Truncated)
+}
+
+/// Read the magic number and determine the byte order from it
+fn byte_order(data: &[u8], big_endian: bool) -> Result<bool, Error> {
+    let magic: u16 = from_bytes(data, big_endian)?;
+    if magic == MAGIC {
+        Ok(big_endian)
+    } else if magic.swap_bytes() == MAGIC {
+        Ok(!big_endian)
+    } else {
+        Err(Error::BadMagic)
+    }
+}
+
+/// Number of type offsets kept by [`Btf`]
+const INDEX_LEN: usize = 256;
+
+/// Offsets of every `stride`th type, starting with type 1
+#[derive(Clone, Copy)]
+struct Index {
+    offsets: [u32; INDEX_LEN],
+    len: usize,
+    stride: u32,
+}
+
+impl Index {
+    /// Record the offset of type `id`, doubling the stride when the index is full
+    fn push(&mut self, id: u32, offset: u32) {
+        if (id - 1) % self.stride != 0 {
+            return;
+        }
+        if self.len == INDEX_LEN {
+            for i in 0..INDEX_LEN / 2 {
+                self.offsets[i] = self.offsets[2 * i];
+            }
+            self.len = INDEX_LEN / 2;
+            self.stride *= 2;
+            if (id - 1) % self.stride != 0 {
+                return;
+            }
+        }
+        self.offsets[self.len] = offset;
+        self.len += 1;
+    }
+}
+
+impl core::fmt::Debug for Index {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        f.debug_struct("Index")
+            .field("len", &self.len)
+            .field("stride", &self.stride)
+            .finish()
+    }
+}
+
+/// `.BTF` section
+#[derive(Clone, Copy, Debug)]
+pub struct Btf<'a> {
+    /// Section header
+    pub header: Header,
+    types: &'a [u8],
+    strings: &'a [u8],
+    big_endian: bool,
+    index: Index,
+    pointer_size: u8,
+}
+
+impl<'a> Btf<'a> {
+    /// BTF in section `data`
+    ///
+    /// The byte order is taken from the magic number, `big_endian` is the expected order.
+    pub fn new(data: &'a [u8], big_endian: bool) -> Result<Self, Error> {
+        let big_endian = byte_order(data, big_endian)?;
+        let header: Header = from_bytes(data, big_endian)?;
+        if (header.hdr_len as usize) < Header::SIZE {
+            return Err(Error::Malformed);
+        }
+        let mut btf = Btf {
+            header,
+            types: subsection(data, header.hdr_len, header.type_off, header.type_len)?,
+            strings: subsection(data, header.hdr_len, header.str_off, header.str_len)?,
+            big_endian,
+            index: Index {
+                offsets: [0; INDEX_LEN],
+                len: 0,
+                stride: 1,
+            },
+            pointer_size: 8,
+        };
+        // Index the types, stopping at the first bad one so get() reports its error, and
+        // guess the pointer size from `long` like libbpf
+        let mut long = None;
+        let mut types = btf.types();
+        loop {
+            let offset = (btf.types.len() - types.data.len()) as u32;
+            let ty = match types.next() {
+                Some(Ok(ty)) => ty,
+                _ => break,
+            };
+            btf.index.push(ty.id, offset);
+            if long.is_none() && ty.kind() == Kind::INT {
+                let name = btf.name(&ty);
+                if let Ok("long" | "long int" | "unsigned long" | "long unsigned int") = name {
+                    long = ty.size();
+                }
+            }
+        }
+        if let Some(size @ (4 | 8)) = long {
+            btf.pointer_size = size as u8;
+        }
+        Ok(btf)
+    }
+
+    /// Size of pointers in bytes
+    ///
+    /// This is taken from the ELF class for [`ElfFile::btf()`], otherwise from the size of
+    /// `long`, and is 8 if there is no `long`.
+    pub fn pointer_size(&self) -> u8 {
+        self.pointer_size
+    }
+
+    /// String at `offset` in the string table
+    pub fn string(&self, offset: u32) -> Result<&'a str, Error> {
+        crate::file::c_str(self.strings, offset)
+    }
+
+    /// Name of `ty`, empty for anonymous types
+    pub fn name(&self, ty: &Type) -> Result<&'a str, Error> {
+        self.string(ty.header.name_off)
+    }
+
+    /// Iterate over the types, starting with type 1
+    pub fn types(&self) -> Types<'a> {
+        Types {
+            data: self.types,
+            id: 1,
+            big_endian: self.big_endian,
+        }
+    }
+
+    /// Type `id`
+    pub fn get(&self, id: u32) -> Result<Type<'a>, Error> {
+        if id == 0 {
+            return Err(Error::NotFound);
+        }
+        // Start from the nearest indexed type, or from type 1 if none could be read
+        let mut types = self.types();
+        if self.index.len > 0 {
+            let slot = (((id - 1) / self.index.stride) as usize).min(self.index.len - 1);
+            types.data = &self.types[self.index.offsets[slot] as usize..];
+            types.id = slot as u32 * self.index.stride + 1;
+        }
+        for ty in types {
+            let ty = ty?;
+            if ty.id == id {
+                return Ok(ty);
+            }
+        }
+        Err(Error::NotFound)
+    }
+
+    /// First type of kind `kind` called `name`
+    pub fn by_name(&self, kind: Kind, name: &str) -> Result<Option<Type<'a>>, Error> {
+        for ty in self.types() {
+            let ty = ty?;
+            if ty.kind() == kind && self.name(&ty)? == name {
+                return Ok(Some(ty));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Type `id` with typedefs and type modifiers removed, `None` for `void`
+    pub fn strip(&self, mut id: u32) -> Result<Option<Type<'a>>, Error> {
+        // Bound the walk in case of a reference loop
+        for _ in 0..32 {
+            if id == 0 {
+                return Ok(None);
+            }
+            let ty = self.get(id)?;
+            match ty.kind() {
+                Kind::TYPEDEF | Kind::VOLATILE | Kind::CONST | Kind::RESTRICT | Kind::TYPE_TAG => {
+                    id = ty.header.size_or_type
+                }
+                _ => return Ok(Some(ty)),
+            }
+        }
+        Err(Error::Malformed)
+    }
+
+    /// Size in bytes of type `id`
+    pub fn size_of(&self, id: u32) -> Result<u64, Error> {
+        self.size_of_nested(id, 0)
+    }
+
+    fn size_of_nested(&self, id: u32, depth: u32) -> Result<u64, Error> {
+        // Bound the recursion in case of a reference loop, like strip()
+        if depth >= 32 {
+            return Err(Error::Malformed);
+        }
+        let ty = self.strip(id)?.ok_or(Error::Malformed)?;
+        match ty.kind() {
+            Kind::PTR => Ok(u64::from(self.pointer_size)),
+            Kind::ARRAY => {
+                let array = ty.array().ok_or(Error::Truncated)?;
+                let size = self.size_of_nested(array.type_id, depth + 1)?;
+                u64::from(array.nelems)
+                    .checked_mul(size)
+                    .ok_or(Error::Malformed)
+            }
+            Kind::VAR => self.size_of_nested(ty.header.size_or_type, depth + 1),
+            _ => ty.size().map(u64::from).ok_or(Error::Malformed),
+        }
+    }
+}
+
+/// Iterator over BTF types
+#[derive(Clone)]
+pub struct Types<'a> {
+    data: &'a [u8],
+    id: u32,
+    big_endian: bool,
+}
+
+impl<'a> Types<'a> {
+    fn read(&mut self) -> Result<Type<'a>, Error> {
+        let header: TypeHeader = from_bytes(self.data, self.big_endian)?;
+        let size = data_size(&header)?;
+        let data = self
+            .data
+            .get(TypeHeader::SIZE..TypeHeader::SIZE + size)
+            .ok_or(Error::Truncated)?;
+        self.data = &self.data[TypeHeader::SIZE + size..];
+        self.id += 1;
+        Ok(Type {
+            id: self.id - 1,
+            header,
+            data,
+            big_endian: self.big_endian,
+        })
+    }
+}
+
+impl<'a> Iterator for Types<'a> {
+    type Item = Result<Type<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let result = self.read();
+        if result.is_err() {
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+/// `.BTF.ext` section header
+///
+/// Older headers end after the line information, without the CO-RE relocations.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct ExtHeader {
+    /// [`MAGIC`]
+    pub magic: u16,
+    /// Version, 1
+    pub version: u8,
+    /// Flags, 0
+    pub flags: u8,
+    /// Size of the header
+    pub hdr_len: u32,
+    /// Offset of the function information after the header
+    pub func_info_off: u32,
+    /// Size of the function information
+    pub func_info_len: u32,
+    /// Offset of the line information after the header
+    pub line_info_off: u32,
+    /// Size of the line information
+    pub line_info_len: u32,
+    /// Offset of the CO-RE relocations after the header
+    pub core_relo_off: u32,
+    /// Size of the CO-RE relocations
+    pub core_relo_len: u32,
+}
+impl ExtHeader {
+    pub const SIZE: usize = size_of::<Self>();
+    /// Size of the header without the CO-RE relocations
+    pub const SIZE_V1: usize = 24;
+}
+
+/// Function information record (`struct bpf_func_info`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct FuncInfo {
+    /// Byte offset of the first instruction of the function in the section
+    pub insn_off: u32,
+    /// `FUNC` type of the function
+    pub type_id: u32,
+}
+
+/// Line information record (`struct bpf_line_info`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct LineInfo {
+    /// Byte offset of the instruction in the section
+    pub insn_off: u32,
+    /// Offset of the file name in the BTF string table
+    pub file_name_off: u32,
+    /// Offset of the source line in the BTF string table
+    pub line_off: u32,
+    /// Line number in bits 10-31 and column in bits 0-9
+    pub line_col: u32,
+}
+
+impl LineInfo {
+    /// Line number
+    pub fn line(&self) -> u32 {
+        self.line_col >> 10
+    }
+
+    /// Column number
+    pub fn column(&self) -> u32 {
+        self.line_col & 0x3ff
+    }
+}
+
+enum_struct!(
+/// Kind of a CO-RE relocation
+pub struct CoreReloKind(u32) {
+    FIELD_BYTE_OFFSET = 0  => "byte_off",
+    FIELD_BYTE_SIZE   = 1  => "byte_sz",
+    FIELD_EXISTS      = 2  => "field_exists",
+    FIELD_SIGNED      = 3  => "signed",
+    FIELD_LSHIFT_U64  = 4  => "lshift_u64",
+    FIELD_RSHIFT_U64  = 5  => "rshift_u64",
+    TYPE_ID_LOCAL     = 6  => "local_type_id",
+    TYPE_ID_TARGET    = 7  => "target_type_id",
+    TYPE_EXISTS       = 8  => "type_exists",
+    TYPE_SIZE         = 9  => "type_size",
+    ENUMVAL_EXISTS    = 10 => "enumval_exists",
+    ENUMVAL_VALUE     = 11 => "enumval_value",
+    TYPE_MATCHES      = 12 => "type_matches",
+}
+);
+
+/// CO-RE relocation record (`struct bpf_core_relo`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct CoreRelo {
+    /// Byte offset of the instruction in the section
+    pub insn_off: u32,
+    /// Root type of the access
+    pub type_id: u32,
+    /// Offset of the access string, such as `0:1:2`, in the BTF string table
+    pub access_str_off: u32,
+    /// What to relocate
+    pub kind: CoreReloKind,
+}
+
+/// `.BTF.ext` section
+#[derive(Clone, Copy, Debug)]
+pub struct BtfExt<'a> {
+    /// Section header, with zero CO-RE fields for older headers
+    pub header: ExtHeader,
+    func_info: &'a [u8],
+    line_info: &'a [u8],
+    core_relo: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> BtfExt<'a> {
+    /// BTF.ext in section `data`
+    ///
+    /// The byte order is taken from the magic number, `big_endian` is the expected order.
+    pub fn new(data: &'a [u8], big_endian: bool) -> Result<Self, Error> {
+        let big_endian = byte_order(data, big_endian)?;
+        let mut header: ExtHeader = if data.len() >= ExtHeader::SIZE {
+            from_bytes(data, big_endian)?
+        } else {
+            let mut padded = [0; ExtHeader::SIZE];
+            let len = data.len().min(ExtHeader::SIZE_V1);
+            padded[..len].copy_from_slice(&data[..len]);
+            from_bytes(&padded, big_endian)?
+        };
+        let hdr_len = header.hdr_len;
+        if (hdr_len as usize) < ExtHeader::SIZE_V1 {
+            return Err(Error::Malformed);
+        }
+        if (hdr_len as usize) < ExtHeader::SIZE {
+            header.core_relo_off = 0;
+            header.core_relo_len = 0;
+        }
+        Ok(BtfExt {
+            header,
+            func_info: subsection(data, hdr_len, header.func_info_off, header.func_info_len)?,
+            line_info: subsection(data, hdr_len, header.line_info_off, header.line_info_len)?,
+            core_relo: subsection(data, hdr_len, header.core_relo_off, header.core_relo_len)?,
+            big_endian,
+        })
+    }
+
+    fn sections<T>(&self, data: &'a [u8]) -> Result<InfoSections<'a, T>, Error> {
+        if data.is_empty() {
+            return Ok(InfoSections::new(&[], 0, self.big_endian));
+        }
+        let rec_size: u32 = from_bytes(data, self.big_endian)?;
+        if (rec_size as usize) < size_of::<T>() {
+            return Err(Error::Malformed);
+        }
+        Ok(InfoSections::new(&data[4..], rec_size, self.big_endian))
+    }
+
+    /// Function information, per section
+    pub fn func_info(&self) -> Result<InfoSections<'a, FuncInfo>, Error> {
+        self.sections(self.func_info)
+    }
+
+    /// Line information, per section
+    pub fn line_info(&self) -> Result<InfoSections<'a, LineInfo>, Error> {
+        self.sections(self.line_info)
+    }
+
+    /// CO-RE relocations, per section
+    pub fn core_relos(&self) -> Result<InfoSections<'a, CoreRelo>, Error> {
+        self.sections(self.core_relo)
+    }
+}
+
+/// Iterator over the per-section records of a `.BTF.ext` subsection
+#[derive(Clone)]
+pub struct InfoSections<'a, T> {
+    data: &'a [u8],
+    rec_size: u32,
+    big_endian: bool,
+    _record: PhantomData<T>,
+}
+
+impl<'a, T> InfoSections<'a, T> {
+    fn new(data: &'a [u8], rec_size: u32, big_endian: bool) -> Self {
+        InfoSections {
+            data,
+            rec_size,
+            big_endian,
+            _record: PhantomData,
+        }
+    }
+
+    fn read(&mut self) -> Result<InfoSection<'a, T>, Error> {
+        let (sec_name_off, num_info): (u32, u32) = from_bytes(self.data, self.big_endian)?;
+        let size = (num_info as usize)
+            .checked_mul(self.rec_size as usize)
+            .ok_or(Error::Malformed)?;
+        let records = self.data.get(8..8 + size).ok_or(Error::Truncated)?;
+        self.data = &self.data[8 + size..];
+        Ok(InfoSection {
+            sec_name_off,
+            num_info,
+            rec_size: self.rec_size,
+            data: records,
+            big_endian: self.big_endian,
+            _record: PhantomData,
+        })
+    }
+}
+
+impl<'a, T> Iterator for InfoSections<'a, T> {
+    type Item = Result<InfoSection<'a, T>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let result = self.read();
+        if result.is_err() {
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+/// Records of one ELF section in a `.BTF.ext` subsection
+#[derive(Clone)]
+pub struct InfoSection<'a, T> {
+    /// Offset of the ELF section name in the BTF string table
+    pub sec_name_off: u32,
+    /// Number of records
+    pub num_info: u32,
+    rec_size: u32,
+    data: &'a [u8],
+    big_endian: bool,
+    _record: PhantomData<T>,
+}
+
+impl<'a, T: DeserializeOwned> InfoSection<'a, T> {
+    /// Iterate over the records
+    ///
+    /// Records may be larger than `T` in newer versions, the extra data is skipped.
+    pub fn records(&self) -> impl Iterator<Item = T> + 'a {
+        let (data, rec_size, big_endian) = (self.data, self.rec_size as usize, self.big_endian);
+        data.chunks_exact(rec_size)
+            .filter_map(move |record| from_bytes(record, big_endian).ok())
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// BPF Type Format (`.BTF` section), or `None` if there isn't one
+    pub fn btf(&self) -> Result<Option<Btf<'a>>, Error> {
+        match self.section_by_name(".BTF")? {
+            Some((_, shdr)) => {
+                let mut btf = Btf::new(self.section_data(&shdr)?, self.is_big_endian())?;
+                btf.pointer_size = self.word_size() as u8;
+                Ok(Some(btf))
+            }
+            None => Ok(None),
+        }
+    }
+
+    /// BTF extension (`.BTF.ext` section), or `None` if there isn't one
+    pub fn btf_ext(&self) -> Result<Option<BtfExt<'a>>, Error> {
+        match self.section_by_name(".BTF.ext")? {
+            Some((_, shdr)) => {
+                BtfExt::new(self.section_data(&shdr)?, self.is_big_endian()).map(Some)
+            }
+            None => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    extern crate std;
+    use super::*;
+    use std::string::String;
+    use std::vec::Vec;
+
+    /// Little-endian BTF builder for tests
+    #[derive(Default)]
+    pub(crate) struct Builder {
+        types: Vec<u8>,
+        strings: Vec<u8>,
+        count: u32,
+    }
+
+    impl Builder {
+        pub(crate) fn new() -> Self {
+            Builder {
+                strings: std::vec![0],
+                ..Default::default()
+            }
+        }
+
+        pub(crate) fn string(&mut self, s: &str) -> u32 {
+            let offset = self.strings.len() as u32;
+            self.strings.extend(s.as_bytes());
+            self.strings.push(0);
+            offset
+        }
+
+        /// Add a type, returning its id
+        pub(crate) fn add(
+            &mut self,
+            name: &str,
+            kind: Kind,
+            vlen: u16,
+            size_or_type: u32,
+            data: &[u32],
+        ) -> u32 {
+            let name_off = if name.is_empty() {
+                0
+            } else {
+                self.string(name)
+            };
+            let info = u32::from(u8::from(kind)) << 24 | u32::from(vlen);
+            for word in [name_off, info, size_or_type].iter().chain(data) {
+                self.types.extend(word.to_le_bytes());
+            }
+            self.count += 1;
+            self.count
+        }
+
+        pub(crate) fn build(&self) -> Vec<u8> {
+            let header = [
+                u32::from(MAGIC) | 1 << 16,
+                Header::SIZE as u32,
+                0,
+                self.types.len() as u32,
+                self.types.len() as u32,
+                self.strings.len() as u32,
+            ];
+            let mut data: Vec<u8> = header.iter().flat_map(|w| w.to_le_bytes()).collect();
+            data.extend(&self.types);
+            data.extend(&self.strings);
+            data
+        }
+    }
+
+    #[test]
+    fn types() {
+        let mut builder = Builder::new();
+        let int = builder.add("int", Kind::INT, 0, 4, &[0x0100_0020]);
+        let array = builder.add("", Kind::ARRAY, 0, 0, &[int, int, 4]);
+        let x = builder.string("x");
+        let y = builder.string("y");
+        let point = builder.add("point", Kind::STRUCT, 2, 20, &[x, int, 0, y, array, 32]);
+        let typedef = builder.add("point_t", Kind::TYPEDEF, 0, point, &[]);
+        let constant = builder.add("", Kind::CONST, 0, typedef, &[]);
+        let data = builder.build();
+        let btf = Btf::new(&data, false).unwrap();
+        assert_eq!(btf.types().count(), 5);
+
+        let ty = btf.get(point).unwrap();
+        assert_eq!(ty.kind(), Kind::STRUCT);
+        assert_eq!(btf.name(&ty), Ok("point"));
+        let names: Vec<String> = ty
+            .members()
+            .map(|m| btf.string(m.name_off).unwrap().into())
+            .collect();
+        assert_eq!(names, ["x", "y"]);
+        assert_eq!(btf.get(array).unwrap().array().unwrap().nelems, 4);
+        assert_eq!(btf.strip(constant).unwrap().unwrap().id, point);
+        assert_eq!(btf.size_of(array), Ok(16));
+        assert_eq!(btf.size_of(constant), Ok(20));
+        assert_eq!(
+            btf.by_name(Kind::TYPEDEF, "point_t").unwrap().unwrap().id,
+            typedef
+        );
+        assert_eq!(btf.get(6).err(), Some(Error::NotFound));
+        assert_eq!(Btf::new(&data[1..], false).err(), Some(Error::BadMagic));
+
+        // Big-endian data is detected from the magic number
+        let mut swapped = data.clone();
+        for word in swapped.chunks_exact_mut(4).take(6) {
+            word.reverse();
+        }
+        swapped[..4].copy_from_slice(&[0xeb, 0x9f, 1, 0]);
+        assert_eq!(
+            Btf::new(&swapped, false).unwrap().header.type_len,
+            btf.header.type_len
+        );
+
+        // Arrays of themselves and huge arrays have no size
+        let mut builder = Builder::new();
+        let int = builder.add("int", Kind::INT, 0, 4, &[0x0100_0020]);
+        let looped = builder.add("", Kind::ARRAY, 0, 0, &[2, int, 2]);
+        let huge = builder.add("", Kind::ARRAY, 0, 0, &[int, int, u32::MAX]);
+        let huger = builder.add("", Kind::ARRAY, 0, 0, &[huge, int, u32::MAX]);
+        let data = builder.build();
+        let btf = Btf::new(&data, false).unwrap();
+        assert_eq!(btf.size_of(looped), Err(Error::Malformed));
+        assert_eq!(btf.size_of(huge), Ok(4 * u64::from(u32::MAX)));
+        assert_eq!(btf.size_of(huger), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn index() {
+        let mut builder = Builder::new();
+        let long = builder.add("long", Kind::INT, 0, 4, &[0x0100_0020]);
+        let pointer = builder.add("", Kind::PTR, 0, long, &[]);
+        for i in 0..1000 {
+            builder.add("", Kind::ARRAY, 0, 0, &[long, long, i]);
+        }
+        let data = builder.build();
+        let btf = Btf::new(&data, false).unwrap();
+        assert_eq!(btf.index.stride, 4);
+        for id in 3..1003 {
+            let ty = btf.get(id).unwrap();
+            assert_eq!(ty.id, id);
+            assert_eq!(ty.array().unwrap().nelems, id - 3);
+        }
+        assert_eq!(btf.get(1003).err(), Some(Error::NotFound));
+        // Pointers are the size of `long` without an ELF class
+        assert_eq!(btf.pointer_size(), 4);
+        assert_eq!(btf.size_of(pointer), Ok(4));
+
+        // Types after a bad one are reported as bad
+        let mut data = data;
+        let offset = Header::SIZE + 16 + 12 + 500 * 24 + 7;
+        data[offset] = 0x7f;
+        let btf = Btf::new(&data, false).unwrap();
+        assert_eq!(btf.get(502).unwrap().id, 502);
+        assert_eq!(btf.get(503).err(), Some(Error::Unsupported));
+        assert_eq!(btf.get(900).err(), Some(Error::Unsupported));
+    }
+
+    #[test]
+    fn ext() {
+        let mut data: Vec<u8> = Vec::new();
+        let words: &[u32] = &[
+            // Header without CO-RE relocations
+            u32::from(MAGIC) | 1 << 16,
+            24,
+            0,
+            20,
+            20,
+            28,
+            // Function information: record size, then one section with one record
+            8,
+            1,
+            1,
+            0,
+            3,
+            // Line information: record size, then one section with one record
+            16,
+            1,
+            1,
+            8,
+            5,
+            7,
+            12 << 10 | 3,
+        ];
+        for word in words {
+            data.extend(word.to_le_bytes());
+        }
+        let ext = BtfExt::new(&data, false).unwrap();
+        assert_eq!(ext.header.core_relo_len, 0);
+        let section = ext.func_info().unwrap().next().unwrap().unwrap();
+        assert_eq!(section.sec_name_off, 1);
+        let funcs: Vec<FuncInfo> = section.records().collect();
+        assert_eq!(
+            funcs,
+            [FuncInfo {
+                insn_off: 0,
+                type_id: 3
+            }]
+        );
+        let section = ext.line_info().unwrap().next().unwrap().unwrap();
+        let line = section.records().next().unwrap();
+        assert_eq!((line.insn_off, line.line(), line.column()), (8, 12, 3));
+        assert_eq!(ext.core_relos().unwrap().count(), 0);
+    }
+}
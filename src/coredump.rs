@@ -0,0 +1,1828 @@
+//! Core dumps
+//!
+//! A Linux core dump (`ET::CORE`) has a `PT::LOAD` segment for each memory mapping of the
+//! process, holding the contents of the mappings that were dumped, and a `PT::NOTE` segment
+//! describing the process. The notes start with the [`NT::PRSTATUS`] of the thread that
+//! received the signal, followed by the process-wide notes ([`NT::PRPSINFO`],
+//! [`NT::SIGINFO`], [`NT::AUXV`] and [`NT::FILE`]) and the thread's other register sets.
+//! Every other thread has its own `NT::PRSTATUS` followed by its register sets.
+//!
+//! Register sets are decoded for x86-64, AArch64, ARM and RISC-V. [`CoreDump`] writes core
+//! dumps in the same layout.
+
+use crate::auxv::{Auxv64, AuxvEntries};
+use crate::de::from_bytes;
+use crate::ehdr::{EIC, EID, EM, ET};
+use crate::note::{Nhdr, Note, Notes, NT};
+use crate::phdr::{PF, PT};
+use crate::ser::{to_bytes, write_ehdr, write_phdr};
+use crate::{Ehdr32, Ehdr64, ElfFile, Entry, Error, Phdr, Phdr32, Phdr64, Table};
+use core::convert::TryFrom;
+use core::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+/// Read `T` from the start of `data` in the class and byte order of `file`
+fn read<T: Entry>(file: &ElfFile, data: &[u8]) -> Result<T, Error> {
+    if file.is_elf32() {
+        from_bytes::<T::Elf32>(data, file.is_big_endian()).map(Into::into)
+    } else {
+        from_bytes(data, file.is_big_endian())
+    }
+}
+
+/// Time value (`struct timeval`) in a 32-bit core dump
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Timeval32 {
+    /// Seconds
+    pub tv_sec: i32,
+    /// Microseconds
+    pub tv_usec: i32,
+}
+
+/// Time value (`struct timeval`) in a 64-bit core dump
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Timeval64 {
+    /// Seconds
+    pub tv_sec: i64,
+    /// Microseconds
+    pub tv_usec: i64,
+}
+impl From<Timeval32> for Timeval64 {
+    fn from(o: Timeval32) -> Timeval64 {
+        Timeval64 {
+            tv_sec: o.tv_sec.into(),
+            tv_usec: o.tv_usec.into(),
+        }
+    }
+}
+
+/// Start of a 32-bit `NT::PRSTATUS` descriptor (`struct elf_prstatus`), up to the registers
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Prstatus32 {
+    /// Signal number
+    pub si_signo: i32,
+    /// Signal code
+    pub si_code: i32,
+    /// Error number
+    pub si_errno: i32,
+    /// Current signal
+    pub pr_cursig: i16,
+    /// Padding
+    pub pr_pad: u16,
+    /// Pending signals
+    pub pr_sigpend: u32,
+    /// Blocked signals
+    pub pr_sighold: u32,
+    /// Thread ID
+    pub pr_pid: i32,
+    /// Parent process ID
+    pub pr_ppid: i32,
+    /// Process group ID
+    pub pr_pgrp: i32,
+    /// Session ID
+    pub pr_sid: i32,
+    /// User time
+    pub pr_utime: Timeval32,
+    /// System time
+    pub pr_stime: Timeval32,
+    /// Cumulative user time of children
+    pub pr_cutime: Timeval32,
+    /// Cumulative system time of children
+    pub pr_cstime: Timeval32,
+}
+
+/// Start of a 64-bit `NT::PRSTATUS` descriptor (`struct elf_prstatus`), up to the registers
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Prstatus64 {
+    /// Signal number
+    pub si_signo: i32,
+    /// Signal code
+    pub si_code: i32,
+    /// Error number
+    pub si_errno: i32,
+    /// Current signal
+    pub pr_cursig: i16,
+    /// Padding
+    pub pr_pad: u16,
+    /// Pending signals
+    pub pr_sigpend: u64,
+    /// Blocked signals
+    pub pr_sighold: u64,
+    /// Thread ID
+    pub pr_pid: i32,
+    /// Parent process ID
+    pub pr_ppid: i32,
+    /// Process group ID
+    pub pr_pgrp: i32,
+    /// Session ID
+    pub pr_sid: i32,
+    /// User time
+    pub pr_utime: Timeval64,
+    /// System time
+    pub pr_stime: Timeval64,
+    /// Cumulative user time of children
+    pub pr_cutime: Timeval64,
+    /// Cumulative system time of children
+    pub pr_cstime: Timeval64,
+}
+impl Prstatus32 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl Prstatus64 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl From<Prstatus32> for Prstatus64 {
+    fn from(o: Prstatus32) -> Prstatus64 {
+        Prstatus64 {
+            si_signo: o.si_signo,
+            si_code: o.si_code,
+            si_errno: o.si_errno,
+            pr_cursig: o.pr_cursig,
+            pr_pad: o.pr_pad,
+            pr_sigpend: o.pr_sigpend.into(),
+            pr_sighold: o.pr_sighold.into(),
+            pr_pid: o.pr_pid,
+            pr_ppid: o.pr_ppid,
+            pr_pgrp: o.pr_pgrp,
+            pr_sid: o.pr_sid,
+            pr_utime: o.pr_utime.into(),
+            pr_stime: o.pr_stime.into(),
+            pr_cutime: o.pr_cutime.into(),
+            pr_cstime: o.pr_cstime.into(),
+        }
+    }
+}
+impl TryFrom<Prstatus64> for Prstatus32 {
+    type Error = Error;
+    fn try_from(o: Prstatus64) -> Result<Prstatus32, Error> {
+        let word = |value: u64| u32::try_from(value).map_err(|_| Error::Unsupported);
+        let time = |value: Timeval64| -> Result<Timeval32, Error> {
+            Ok(Timeval32 {
+                tv_sec: i32::try_from(value.tv_sec).map_err(|_| Error::Unsupported)?,
+                tv_usec: i32::try_from(value.tv_usec).map_err(|_| Error::Unsupported)?,
+            })
+        };
+        Ok(Prstatus32 {
+            si_signo: o.si_signo,
+            si_code: o.si_code,
+            si_errno: o.si_errno,
+            pr_cursig: o.pr_cursig,
+            pr_pad: o.pr_pad,
+            pr_sigpend: word(o.pr_sigpend)?,
+            pr_sighold: word(o.pr_sighold)?,
+            pr_pid: o.pr_pid,
+            pr_ppid: o.pr_ppid,
+            pr_pgrp: o.pr_pgrp,
+            pr_sid: o.pr_sid,
+            pr_utime: time(o.pr_utime)?,
+            pr_stime: time(o.pr_stime)?,
+            pr_cutime: time(o.pr_cutime)?,
+            pr_cstime: time(o.pr_cstime)?,
+        })
+    }
+}
+impl Entry for Prstatus64 {
+    type Elf32 = Prstatus32;
+}
+
+/// x86-64 general purpose registers (`struct user_regs_struct`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct X86_64Registers {
+    pub r15: u64,
+    pub r14: u64,
+    pub r13: u64,
+    pub r12: u64,
+    pub rbp: u64,
+    pub rbx: u64,
+    pub r11: u64,
+    pub r10: u64,
+    pub r9: u64,
+    pub r8: u64,
+    pub rax: u64,
+    pub rcx: u64,
+    pub rdx: u64,
+    pub rsi: u64,
+    pub rdi: u64,
+    /// System call number
+    pub orig_rax: u64,
+    pub rip: u64,
+    pub cs: u64,
+    pub eflags: u64,
+    pub rsp: u64,
+    pub ss: u64,
+    pub fs_base: u64,
+    pub gs_base: u64,
+    pub ds: u64,
+    pub es: u64,
+    pub fs: u64,
+    pub gs: u64,
+}
+
+/// AArch64 general purpose registers (`struct user_pt_regs`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct AArch64Registers {
+    /// `x0` to `x30`
+    pub regs: [u64; 31],
+    /// Stack pointer
+    pub sp: u64,
+    /// Program counter
+    pub pc: u64,
+    /// Processor state
+    pub pstate: u64,
+}
+
+/// ARM general purpose registers (`struct user_regs`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct ArmRegisters {
+    /// `r0` to `r15`
+    pub regs: [u32; 16],
+    /// Current program status register
+    pub cpsr: u32,
+    /// `r0` on entry to a system call
+    pub orig_r0: u32,
+}
+
+/// 32-bit RISC-V general purpose registers (`struct user_regs_struct`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct RiscvRegisters32 {
+    /// Program counter
+    pub pc: u32,
+    /// `x1` to `x31`
+    pub regs: [u32; 31],
+}
+
+/// 64-bit RISC-V general purpose registers (`struct user_regs_struct`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct RiscvRegisters64 {
+    /// Program counter
+    pub pc: u64,
+    /// `x1` to `x31`
+    pub regs: [u64; 31],
+}
+impl From<RiscvRegisters32> for RiscvRegisters64 {
+    fn from(o: RiscvRegisters32) -> RiscvRegisters64 {
+        let mut regs = [0; 31];
+        for (reg, &value) in regs.iter_mut().zip(&o.regs) {
+            *reg = value.into();
+        }
+        RiscvRegisters64 {
+            pc: o.pc.into(),
+            regs,
+        }
+    }
+}
+impl TryFrom<RiscvRegisters64> for RiscvRegisters32 {
+    type Error = Error;
+    fn try_from(o: RiscvRegisters64) -> Result<RiscvRegisters32, Error> {
+        let word = |value: u64| u32::try_from(value).map_err(|_| Error::Unsupported);
+        let mut regs = [0; 31];
+        for (reg, &value) in regs.iter_mut().zip(&o.regs) {
+            *reg = word(value)?;
+        }
+        Ok(RiscvRegisters32 {
+            pc: word(o.pc)?,
+            regs,
+        })
+    }
+}
+impl Entry for RiscvRegisters64 {
+    type Elf32 = RiscvRegisters32;
+}
+
+/// General purpose registers of a thread
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Registers {
+    /// x86-64 registers
+    X86_64(X86_64Registers),
+    /// AArch64 registers
+    AArch64(AArch64Registers),
+    /// ARM registers
+    Arm(ArmRegisters),
+    /// RISC-V registers, widened to 64 bits for 32-bit files
+    Riscv(RiscvRegisters64),
+}
+
+impl Registers {
+    /// Registers at the start of `data`, in the format for the machine of `file`
+    pub fn parse(file: &ElfFile, data: &[u8]) -> Result<Self, Error> {
+        let big_endian = file.is_big_endian();
+        match (file.ehdr().e_machine, file.is_elf32()) {
+            (EM::X86_64, false) => from_bytes(data, big_endian).map(Registers::X86_64),
+            (EM::AARCH64, false) => from_bytes(data, big_endian).map(Registers::AArch64),
+            (EM::ARM, true) => from_bytes(data, big_endian).map(Registers::Arm),
+            (EM::RISCV, _) => read(file, data).map(Registers::Riscv),
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// Program counter
+    pub fn pc(&self) -> u64 {
+        match self {
+            Registers::X86_64(regs) => regs.rip,
+            Registers::AArch64(regs) => regs.pc,
+            Registers::Arm(regs) => regs.regs[15].into(),
+            Registers::Riscv(regs) => regs.pc,
+        }
+    }
+
+    /// Stack pointer
+    pub fn sp(&self) -> u64 {
+        match self {
+            Registers::X86_64(regs) => regs.rsp,
+            Registers::AArch64(regs) => regs.sp,
+            Registers::Arm(regs) => regs.regs[13].into(),
+            Registers::Riscv(regs) => regs.regs[1],
+        }
+    }
+}
+
+/// x86-64 floating point and SSE registers in the `FXSAVE` format
+/// (`struct user_fpregs_struct`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Fxsave {
+    /// x87 control word
+    pub cwd: u16,
+    /// x87 status word
+    pub swd: u16,
+    /// x87 abridged tag word
+    pub ftw: u16,
+    /// Last x87 opcode
+    pub fop: u16,
+    /// Last x87 instruction pointer
+    pub rip: u64,
+    /// Last x87 data pointer
+    pub rdp: u64,
+    /// SSE control and status
+    pub mxcsr: u32,
+    /// Supported `mxcsr` bits
+    pub mxcsr_mask: u32,
+    /// `st0` to `st7`, 80 bits in 16 bytes each
+    pub st_space: [[u64; 2]; 8],
+    /// `xmm0` to `xmm15`
+    pub xmm_space: [[u64; 2]; 16],
+    /// Reserved, bytes 464 to 511 are available to software
+    pub padding: [u64; 12],
+}
+impl Fxsave {
+    pub const SIZE: usize = size_of::<Self>();
+
+    /// x87 register `st<index>`, in the low 80 bits
+    pub fn st(&self, index: usize) -> u128 {
+        let [low, high] = self.st_space[index];
+        u128::from(high) << 64 | u128::from(low)
+    }
+
+    /// SSE register `xmm<index>`
+    pub fn xmm(&self, index: usize) -> u128 {
+        let [low, high] = self.xmm_space[index];
+        u128::from(high) << 64 | u128::from(low)
+    }
+}
+
+/// x86 extended state (`NT::X86_XSTATE`), in the standard `XSAVE` format
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Xstate<'a> {
+    /// Legacy floating point and SSE state
+    pub fxsave: Fxsave,
+    /// Enabled state components (`XCR0`), from the software reserved bytes
+    pub xcr0: u64,
+    /// State components that are saved, the others are in their initial state
+    pub xstate_bv: u64,
+    data: &'a [u8],
+}
+
+impl<'a> Xstate<'a> {
+    /// Offset of the `XSAVE` header
+    const HEADER: usize = Fxsave::SIZE;
+    /// Offset of the upper halves of the AVX registers
+    const YMM_HI: usize = 576;
+    /// AVX state component
+    const AVX: u64 = 1 << 2;
+
+    /// Extended state at the start of `data`
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let fxsave: Fxsave = from_bytes(data, false)?;
+        let xstate_bv = from_bytes(data.get(Self::HEADER..).ok_or(Error::Truncated)?, false)?;
+        Ok(Xstate {
+            fxsave,
+            xcr0: fxsave.padding[6],
+            xstate_bv,
+            data,
+        })
+    }
+
+    /// AVX register `ymm<index>` as its lower and upper halves, or `None` if AVX isn't
+    /// enabled
+    ///
+    /// Only `ymm0` to `ymm15` exist, others are [`Error::NotFound`].
+    pub fn ymm(&self, index: usize) -> Result<Option<[u128; 2]>, Error> {
+        if index >= self.fxsave.xmm_space.len() {
+            return Err(Error::NotFound);
+        }
+        if self.xcr0 & Self::AVX == 0 {
+            return Ok(None);
+        }
+        let high = if self.xstate_bv & Self::AVX != 0 {
+            let offset = Self::YMM_HI + 16 * index;
+            let data = self.data.get(offset..).ok_or(Error::Truncated)?;
+            let [low, high]: [u64; 2] = from_bytes(data, false)?;
+            u128::from(high) << 64 | u128::from(low)
+        } else {
+            0
+        };
+        Ok(Some([self.fxsave.xmm(index), high]))
+    }
+}
+
+/// AArch64 floating point and SIMD registers (`struct user_fpsimd_state`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Fpsimd {
+    /// `v0` to `v31`
+    pub vregs: [[u64; 2]; 32],
+    /// Floating point status register
+    pub fpsr: u32,
+    /// Floating point control register
+    pub fpcr: u32,
+    /// Reserved
+    pub reserved: [u32; 2],
+}
+impl Fpsimd {
+    /// SIMD register `v<index>`
+    pub fn v(&self, index: usize) -> u128 {
+        let [low, high] = self.vregs[index];
+        u128::from(high) << 64 | u128::from(low)
+    }
+}
+
+/// ARM VFP registers (`struct user_vfp`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Vfp {
+    /// `d0` to `d31`
+    pub fpregs: [u64; 32],
+    /// Floating point status and control register
+    pub fpscr: u32,
+}
+
+/// RISC-V double precision floating point registers (`struct __riscv_d_ext_state`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct RiscvFpRegisters {
+    /// `f0` to `f31`
+    pub f: [u64; 32],
+    /// Floating point control and status register
+    pub fcsr: u32,
+}
+
+/// Floating point registers of a thread
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum FpRegisters {
+    /// x86-64 x87 and SSE registers
+    X86_64(Fxsave),
+    /// AArch64 floating point and SIMD registers
+    AArch64(Fpsimd),
+    /// ARM VFP registers
+    Arm(Vfp),
+    /// RISC-V floating point registers
+    Riscv(RiscvFpRegisters),
+}
+
+/// Process information (`NT::PRPSINFO`, `struct elf_prpsinfo`)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Prpsinfo<'a> {
+    /// Numeric process state
+    pub pr_state: u8,
+    /// Process state character, as in `ps`
+    pub pr_sname: u8,
+    /// Whether the process is a zombie
+    pub pr_zomb: u8,
+    /// Nice value
+    pub pr_nice: i8,
+    /// Process flags
+    pub pr_flag: u64,
+    /// User ID
+    pub pr_uid: u32,
+    /// Group ID
+    pub pr_gid: u32,
+    /// Process ID
+    pub pr_pid: i32,
+    /// Parent process ID
+    pub pr_ppid: i32,
+    /// Process group ID
+    pub pr_pgrp: i32,
+    /// Session ID
+    pub pr_sid: i32,
+    /// Name of the executable
+    pub pr_fname: &'a str,
+    /// Start of the command line, with arguments separated by spaces
+    pub pr_psargs: &'a str,
+}
+
+impl<'a> Prpsinfo<'a> {
+    /// Size of `pr_fname`
+    const FNAME_SIZE: usize = 16;
+    /// Size of `pr_psargs`
+    const PSARGS_SIZE: usize = 80;
+
+    /// Size of a word and of a user or group ID for `machine`
+    fn field_sizes(machine: EM, elf32: bool) -> (usize, usize) {
+        match machine {
+            // 32-bit ARM and x86 have 16-bit user and group IDs
+            EM::ARM | EM::X86 if elf32 => (4, 2),
+            _ if elf32 => (4, 4),
+            _ => (8, 4),
+        }
+    }
+
+    /// Process information from the `NT::PRPSINFO` descriptor `data` in `file`
+    pub fn parse(file: &ElfFile, data: &'a [u8]) -> Result<Self, Error> {
+        let big_endian = file.is_big_endian();
+        let (word_size, id_size) = Self::field_sizes(file.ehdr().e_machine, file.is_elf32());
+        let field = |offset: usize, size: usize| -> Result<u64, Error> {
+            let data = data.get(offset..).ok_or(Error::Truncated)?;
+            match size {
+                2 => from_bytes::<u16>(data, big_endian).map(Into::into),
+                4 => from_bytes::<u32>(data, big_endian).map(Into::into),
+                _ => from_bytes::<u64>(data, big_endian),
+            }
+        };
+        let string = |offset: usize, size: usize| -> Result<&'a str, Error> {
+            let data = data.get(offset..offset + size).ok_or(Error::Truncated)?;
+            let data = data.split(|&c| c == 0).next().unwrap_or(data);
+            core::str::from_utf8(data).map_err(|_| Error::Malformed)
+        };
+        let uid = word_size * 2;
+        let pid = uid + id_size * 2;
+        let fname = pid + 16;
+        Ok(Prpsinfo {
+            pr_state: *data.first().ok_or(Error::Truncated)?,
+            pr_sname: *data.get(1).ok_or(Error::Truncated)?,
+            pr_zomb: *data.get(2).ok_or(Error::Truncated)?,
+            pr_nice: *data.get(3).ok_or(Error::Truncated)? as i8,
+            pr_flag: field(word_size, word_size)?,
+            pr_uid: field(uid, id_size)? as u32,
+            pr_gid: field(uid + id_size, id_size)? as u32,
+            pr_pid: field(pid, 4)? as i32,
+            pr_ppid: field(pid + 4, 4)? as i32,
+            pr_pgrp: field(pid + 8, 4)? as i32,
+            pr_sid: field(pid + 12, 4)? as i32,
+            pr_fname: string(fname, Self::FNAME_SIZE)?,
+            pr_psargs: string(fname + Self::FNAME_SIZE, Self::PSARGS_SIZE)?,
+        })
+    }
+
+    /// Largest size of the descriptor
+    const MAX_SIZE: usize = 136;
+
+    /// Write the descriptor to `data`, truncating the strings, and return its size
+    fn encode(
+        &self,
+        machine: EM,
+        elf32: bool,
+        big_endian: bool,
+        data: &mut [u8],
+    ) -> Result<usize, Error> {
+        let (word_size, id_size) = Self::field_sizes(machine, elf32);
+        let uid = word_size * 2;
+        let pid = uid + id_size * 2;
+        let fname = pid + 16;
+        let fields = [
+            (word_size, word_size, self.pr_flag),
+            (uid, id_size, self.pr_uid.into()),
+            (uid + id_size, id_size, self.pr_gid.into()),
+            (pid, 4, self.pr_pid as u64),
+            (pid + 4, 4, self.pr_ppid as u64),
+            (pid + 8, 4, self.pr_pgrp as u64),
+            (pid + 12, 4, self.pr_sid as u64),
+        ];
+        data[..4].copy_from_slice(&[
+            self.pr_state,
+            self.pr_sname,
+            self.pr_zomb,
+            self.pr_nice as u8,
+        ]);
+        for (offset, size, value) in fields {
+            let data = &mut data[offset..];
+            match size {
+                2 => to_bytes(&(value as u16), big_endian, data)?,
+                4 => to_bytes(&(value as u32), big_endian, data)?,
+                _ => to_bytes(&value, big_endian, data)?,
+            };
+        }
+        let strings = [
+            (fname, Self::FNAME_SIZE, self.pr_fname),
+            (fname + Self::FNAME_SIZE, Self::PSARGS_SIZE, self.pr_psargs),
+        ];
+        for (offset, size, string) in strings {
+            let len = string.len().min(size);
+            data[offset..offset + len].copy_from_slice(&string.as_bytes()[..len]);
+        }
+        Ok(fname + Self::FNAME_SIZE + Self::PSARGS_SIZE)
+    }
+}
+
+/// Signal that caused the dump (`NT::SIGINFO`, `siginfo_t`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Siginfo {
+    /// Signal number
+    pub si_signo: i32,
+    /// Error number
+    pub si_errno: i32,
+    /// Signal code
+    pub si_code: i32,
+    /// Faulting address for `SIGILL`, `SIGFPE`, `SIGSEGV`, `SIGBUS` and `SIGTRAP`
+    ///
+    /// This is the first word of the signal specific fields, which hold other values for
+    /// other signals.
+    pub si_addr: u64,
+}
+
+impl Siginfo {
+    /// Size of the descriptor
+    const SIZE: usize = 128;
+
+    /// Signal information from the `NT::SIGINFO` descriptor `data` in `file`
+    pub fn parse(file: &ElfFile, data: &[u8]) -> Result<Self, Error> {
+        let big_endian = file.is_big_endian();
+        let [si_signo, si_errno, si_code]: [i32; 3] = from_bytes(data, big_endian)?;
+        // The signal specific fields are aligned to a word
+        let offset = if file.is_elf32() { 12 } else { 16 };
+        let data = data.get(offset..).ok_or(Error::Truncated)?;
+        let si_addr = if file.is_elf32() {
+            from_bytes::<u32>(data, big_endian)?.into()
+        } else {
+            from_bytes(data, big_endian)?
+        };
+        Ok(Siginfo {
+            si_signo,
+            si_errno,
+            si_code,
+            si_addr,
+        })
+    }
+
+    /// Write the descriptor to `data`
+    fn encode(&self, elf32: bool, big_endian: bool, data: &mut [u8]) -> Result<(), Error> {
+        let codes = [self.si_signo, self.si_errno, self.si_code];
+        to_bytes(&codes, big_endian, data)?;
+        if elf32 {
+            let si_addr = u32::try_from(self.si_addr).map_err(|_| Error::Unsupported)?;
+            to_bytes(&si_addr, big_endian, &mut data[12..])?;
+        } else {
+            to_bytes(&self.si_addr, big_endian, &mut data[16..])?;
+        }
+        Ok(())
+    }
+}
+
+/// File mapped into memory
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MappedFile<'a> {
+    /// Start address of the mapping
+    pub start: u64,
+    /// End address of the mapping
+    pub end: u64,
+    /// Offset in the file of the start of the mapping
+    pub offset: u64,
+    /// Path of the file
+    pub name: &'a str,
+}
+
+/// Iterator over the mapped files (`NT::FILE`)
+#[derive(Clone, Debug)]
+pub struct MappedFiles<'a> {
+    entries: &'a [u8],
+    names: &'a [u8],
+    page_size: u64,
+    elf32: bool,
+    big_endian: bool,
+}
+
+impl<'a> MappedFiles<'a> {
+    /// Mapped files in the `NT::FILE` descriptor `data` of `file`
+    ///
+    /// The descriptor has the number of mappings and the page size, a start, end and page
+    /// offset for each mapping, and then the nul-terminated path of each mapping.
+    pub fn parse(file: &ElfFile, data: &'a [u8]) -> Result<Self, Error> {
+        let word_size = file.word_size();
+        let word = |offset: usize| -> Result<u64, Error> {
+            let data = data.get(offset..).ok_or(Error::Truncated)?;
+            if file.is_elf32() {
+                from_bytes::<u32>(data, file.is_big_endian()).map(Into::into)
+            } else {
+                from_bytes(data, file.is_big_endian())
+            }
+        };
+        let count = word(0)?;
+        let page_size = word(word_size)?;
+        let names = count
+            .checked_mul(3 * word_size as u64)
+            .and_then(|size| size.checked_add(2 * word_size as u64))
+            .ok_or(Error::Malformed)?;
+        if names > data.len() as u64 {
+            return Err(Error::Truncated);
+        }
+        Ok(MappedFiles {
+            entries: &data[2 * word_size..names as usize],
+            names: &data[names as usize..],
+            page_size,
+            elf32: file.is_elf32(),
+            big_endian: file.is_big_endian(),
+        })
+    }
+
+    /// Page size that the offsets in the descriptor are in
+    pub fn page_size(&self) -> u64 {
+        self.page_size
+    }
+
+    fn parse_entry(&self) -> Result<(MappedFile<'a>, usize), Error> {
+        let (start, end, offset, size) = if self.elf32 {
+            let (start, end, offset): (u32, u32, u32) = from_bytes(self.entries, self.big_endian)?;
+            (start.into(), end.into(), offset.into(), 12)
+        } else {
+            let (start, end, offset): (u64, u64, u64) = from_bytes(self.entries, self.big_endian)?;
+            (start, end, offset, 24)
+        };
+        let name = crate::file::c_str(self.names, 0)?;
+        let file = MappedFile {
+            start,
+            end,
+            offset: offset.checked_mul(self.page_size).ok_or(Error::Malformed)?,
+            name,
+        };
+        Ok((file, size))
+    }
+}
+
+impl<'a> Iterator for MappedFiles<'a> {
+    type Item = Result<MappedFile<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.entries.is_empty() {
+            return None;
+        }
+        match self.parse_entry() {
+            Ok((file, size)) => {
+                self.entries = &self.entries[size..];
+                self.names = &self.names[file.name.len() + 1..];
+                Some(Ok(file))
+            }
+            Err(err) => {
+                self.entries = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Iterator over the notes in all of the `PT::NOTE` segments of a core dump
+#[derive(Clone)]
+pub struct CoreNotes<'a> {
+    file: ElfFile<'a>,
+    phdrs: Option<Table<'a, Phdr64>>,
+    notes: Notes<'a>,
+}
+
+impl<'a> Iterator for CoreNotes<'a> {
+    type Item = Result<Note<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(note) = self.notes.next() {
+                if note.is_err() {
+                    self.phdrs = None;
+                }
+                return Some(note);
+            }
+            let phdr = self.phdrs.as_mut()?.find(|p| p.p_type == PT::NOTE)?;
+            match self.file.segment_notes(&phdr) {
+                Ok(notes) => self.notes = notes,
+                Err(err) => {
+                    self.phdrs = None;
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+/// Thread in a core dump
+#[derive(Clone)]
+pub struct Thread<'a> {
+    /// Status of the thread
+    pub prstatus: Prstatus64,
+    /// General purpose registers
+    pub registers: Registers,
+    file: ElfFile<'a>,
+    notes: CoreNotes<'a>,
+}
+
+impl<'a> Thread<'a> {
+    /// Notes following the thread's `NT::PRSTATUS`, up to the next thread
+    ///
+    /// For the first thread these include the process-wide notes.
+    pub fn notes(&self) -> impl Iterator<Item = Result<Note<'a>, Error>> {
+        self.notes
+            .clone()
+            .take_while(|note| !matches!(note, Ok(note) if note.is_core(NT::PRSTATUS)))
+    }
+
+    /// The thread's first core dump note of type `n_type`
+    pub fn note(&self, n_type: NT) -> Result<Option<Note<'a>>, Error> {
+        for note in self.notes() {
+            let note = note?;
+            if note.is_core(n_type) {
+                return Ok(Some(note));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Floating point registers, from `NT::FPREGSET`, or `NT::ARM_VFP` for ARM
+    pub fn fp_registers(&self) -> Result<Option<FpRegisters>, Error> {
+        let file = &self.file;
+        let big_endian = file.is_big_endian();
+        let n_type = match file.ehdr().e_machine {
+            EM::ARM => NT::ARM_VFP,
+            _ => NT::FPREGSET,
+        };
+        let data = match self.note(n_type)? {
+            Some(note) => note.desc,
+            None => return Ok(None),
+        };
+        let registers = match (file.ehdr().e_machine, file.is_elf32()) {
+            (EM::X86_64, false) => FpRegisters::X86_64(from_bytes(data, big_endian)?),
+            (EM::AARCH64, false) => FpRegisters::AArch64(from_bytes(data, big_endian)?),
+            (EM::ARM, true) => FpRegisters::Arm(from_bytes(data, big_endian)?),
+            (EM::RISCV, _) => FpRegisters::Riscv(from_bytes(data, big_endian)?),
+            _ => return Err(Error::Unsupported),
+        };
+        Ok(Some(registers))
+    }
+
+    /// x86 extended state, from `NT::X86_XSTATE`
+    pub fn xstate(&self) -> Result<Option<Xstate<'a>>, Error> {
+        match self.note(NT::X86_XSTATE)? {
+            Some(note) => Xstate::parse(note.desc).map(Some),
+            None => Ok(None),
+        }
+    }
+}
+
+/// Iterator over the threads of a core dump
+#[derive(Clone)]
+pub struct Threads<'a> {
+    file: ElfFile<'a>,
+    notes: CoreNotes<'a>,
+}
+
+impl<'a> Threads<'a> {
+    fn parse(&self, data: &[u8]) -> Result<Thread<'a>, Error> {
+        let prstatus: Prstatus64 = read(&self.file, data)?;
+        let size = if self.file.is_elf32() {
+            Prstatus32::SIZE
+        } else {
+            Prstatus64::SIZE
+        };
+        let registers = Registers::parse(&self.file, data.get(size..).ok_or(Error::Truncated)?)?;
+        Ok(Thread {
+            prstatus,
+            registers,
+            file: self.file,
+            notes: self.notes.clone(),
+        })
+    }
+}
+
+impl<'a> Iterator for Threads<'a> {
+    type Item = Result<Thread<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            match self.notes.next()? {
+                Ok(note) if note.is_core(NT::PRSTATUS) => return Some(self.parse(note.desc)),
+                Ok(_) => {}
+                Err(err) => return Some(Err(err)),
+            }
+        }
+    }
+}
+
+/// Core dump, see [`ElfFile::core()`]
+#[derive(Clone, Copy)]
+pub struct Core<'a> {
+    file: ElfFile<'a>,
+}
+
+impl<'a> ElfFile<'a> {
+    /// Read the file as a core dump
+    ///
+    /// Fails with [`Error::Unsupported`] if it isn't an `ET::CORE` file.
+    pub fn core(&self) -> Result<Core<'a>, Error> {
+        if self.ehdr().e_type != ET::CORE {
+            return Err(Error::Unsupported);
+        }
+        self.phdrs()?;
+        Ok(Core { file: *self })
+    }
+}
+
+impl<'a> Core<'a> {
+    /// The core dump as an ELF file
+    pub fn file(&self) -> &ElfFile<'a> {
+        &self.file
+    }
+
+    /// All notes
+    pub fn notes(&self) -> CoreNotes<'a> {
+        CoreNotes {
+            file: self.file,
+            phdrs: self.file.phdrs().ok(),
+            notes: Notes::new(&[], 4, self.file.is_big_endian()),
+        }
+    }
+
+    /// First core dump note of type `n_type`
+    pub fn note(&self, n_type: NT) -> Result<Option<Note<'a>>, Error> {
+        for note in self.notes() {
+            let note = note?;
+            if note.is_core(n_type) {
+                return Ok(Some(note));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Process information, from `NT::PRPSINFO`
+    pub fn prpsinfo(&self) -> Result<Option<Prpsinfo<'a>>, Error> {
+        match self.note(NT::PRPSINFO)? {
+            Some(note) => Prpsinfo::parse(&self.file, note.desc).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Signal that caused the dump, from `NT::SIGINFO`
+    pub fn siginfo(&self) -> Result<Option<Siginfo>, Error> {
+        match self.note(NT::SIGINFO)? {
+            Some(note) => Siginfo::parse(&self.file, note.desc).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Auxiliary vector of the process, from `NT::AUXV`
+    pub fn auxv(&self) -> Result<Option<AuxvEntries<'a>>, Error> {
+        let (elf32, big_endian) = (self.file.is_elf32(), self.file.is_big_endian());
+        Ok(self
+            .note(NT::AUXV)?
+            .map(|note| AuxvEntries::new(note.desc, elf32, big_endian)))
+    }
+
+    /// Files mapped into memory, from `NT::FILE`
+    pub fn mapped_files(&self) -> Result<Option<MappedFiles<'a>>, Error> {
+        match self.note(NT::FILE)? {
+            Some(note) => MappedFiles::parse(&self.file, note.desc).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Threads, starting with the one that received the signal
+    pub fn threads(&self) -> Threads<'a> {
+        Threads {
+            file: self.file,
+            notes: self.notes(),
+        }
+    }
+
+    /// Copy the dumped memory at `address` to `data`
+    ///
+    /// Fails with [`Error::NotFound`] if any of it isn't in the file, such as memory of
+    /// mappings that weren't dumped.
+    pub fn read_memory(&self, mut address: u64, mut data: &mut [u8]) -> Result<(), Error> {
+        while !data.is_empty() {
+            let phdr = self
+                .file
+                .phdrs()?
+                .filter(|p| p.p_type == PT::LOAD)
+                .find(|p| address >= p.p_vaddr && address - p.p_vaddr < p.p_filesz)
+                .ok_or(Error::NotFound)?;
+            let offset = address - phdr.p_vaddr;
+            let len = (phdr.p_filesz - offset).min(data.len() as u64);
+            let start = phdr.p_offset.checked_add(offset).ok_or(Error::Malformed)?;
+            let bytes = self.file.bytes(start, len)?;
+            let (head, tail) = core::mem::take(&mut data).split_at_mut(len as usize);
+            head.copy_from_slice(bytes);
+            data = tail;
+            if !data.is_empty() {
+                address = address.checked_add(len).ok_or(Error::Malformed)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Memory mapping to write to a core dump
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct CoreRegion<'a> {
+    /// Start address
+    pub start: u64,
+    /// End address
+    pub end: u64,
+    /// Access permissions
+    pub flags: PF,
+    /// Number of bytes from the start that are written, the rest of the segment has no
+    /// file data, like mappings of files that weren't modified
+    pub dump_size: u64,
+    /// Path of the mapped file and offset in it of the start of the mapping, for `NT::FILE`
+    pub file: Option<(&'a str, u64)>,
+}
+
+/// Thread to write to a core dump
+#[derive(Clone, Copy, Debug)]
+pub struct CoreThread<'a> {
+    /// Status of the thread
+    pub prstatus: Prstatus64,
+    /// General purpose registers
+    pub registers: Registers,
+    /// Floating point registers
+    pub fp_registers: Option<FpRegisters>,
+    /// Other notes, such as `NT::X86_XSTATE`, written after the register sets
+    pub notes: &'a [Note<'a>],
+}
+
+/// Contents of a core dump to write with [`CoreDump::write()`]
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CoreDump<'a> {
+    /// Threads, starting with the one that received the signal
+    pub threads: &'a [CoreThread<'a>],
+    /// Process information
+    pub prpsinfo: Option<Prpsinfo<'a>>,
+    /// Signal that caused the dump
+    pub siginfo: Option<Siginfo>,
+    /// Auxiliary vector, without the terminating `AT_NULL`
+    pub auxv: &'a [Auxv64],
+    /// Memory mappings, in order of address
+    pub regions: &'a [CoreRegion<'a>],
+    /// Page size, which the file data of each mapping is aligned to, relative to its start
+    pub page_size: u64,
+}
+
+/// Writes notes to `f`, counting the bytes written
+struct NoteWriter<F> {
+    f: F,
+    big_endian: bool,
+    len: u64,
+}
+
+impl<F: FnMut(&[u8]) -> Result<(), Error>> NoteWriter<F> {
+    fn write(&mut self, data: &[u8]) -> Result<(), Error> {
+        self.len += data.len() as u64;
+        (self.f)(data)
+    }
+
+    fn pad(&mut self) -> Result<(), Error> {
+        let len = self.len.wrapping_neg() % 4;
+        self.write(&[0; 3][..len as usize])
+    }
+
+    /// Write a note with the descriptor written by `desc`, which is called twice, first to
+    /// find its size
+    fn note(
+        &mut self,
+        name: &str,
+        n_type: NT,
+        desc: impl Fn(&mut dyn FnMut(&[u8]) -> Result<(), Error>) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut size = 0;
+        desc(&mut |data| {
+            size += data.len();
+            Ok(())
+        })?;
+        let nhdr = Nhdr {
+            n_namesz: name.len() as u32 + 1,
+            n_descsz: u32::try_from(size).map_err(|_| Error::Unsupported)?,
+            n_type: n_type.into(),
+        };
+        let mut header = [0; Nhdr::SIZE];
+        to_bytes(&nhdr, self.big_endian, &mut header)?;
+        self.write(&header)?;
+        self.write(name.as_bytes())?;
+        self.write(&[0])?;
+        self.pad()?;
+        desc(&mut |data| self.write(data))?;
+        self.pad()
+    }
+}
+
+/// Write `len` zero bytes to `f`
+fn write_zeros(f: &mut impl FnMut(&[u8]) -> Result<(), Error>, mut len: u64) -> Result<(), Error> {
+    let zeros = [0; 4096];
+    while len != 0 {
+        let chunk = len.min(zeros.len() as u64);
+        f(&zeros[..chunk as usize])?;
+        len -= chunk;
+    }
+    Ok(())
+}
+
+impl<'a> CoreDump<'a> {
+    /// Write the core dump to `f`, for a process of the class, byte order and machine of
+    /// `ehdr`
+    ///
+    /// The file has a `PT::NOTE` segment with the notes in the order Linux writes them,
+    /// followed by a `PT::LOAD` segment per region. The contents of the regions being
+    /// dumped are copied a page at a time with `read_memory`, so they're never buffered.
+    pub fn write(
+        &self,
+        ehdr: &Ehdr64,
+        mut read_memory: impl FnMut(u64, &mut [u8]) -> Result<(), Error>,
+        mut f: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let (ehsize, phentsize) = match ehdr.e_ident.class {
+            EIC::ELF32 => (Ehdr32::SIZE, Phdr32::SIZE),
+            EIC::ELF64 => (Ehdr64::SIZE, Phdr64::SIZE),
+            _ => return Err(Error::Unsupported),
+        };
+        if !self.page_size.is_power_of_two() {
+            return Err(Error::Malformed);
+        }
+        let phnum = self.regions.len() + 1;
+        // `e_phnum` of 0xffff would mean the count is in a section header
+        if phnum >= 0xffff {
+            return Err(Error::Unsupported);
+        }
+        let mut notes = NoteWriter {
+            f: |_: &[u8]| Ok(()),
+            big_endian: ehdr.e_ident.data == EID::MSB,
+            len: 0,
+        };
+        self.write_notes(ehdr, &mut notes)?;
+
+        let mut header = *ehdr;
+        header.e_type = ET::CORE;
+        header.e_phoff = ehsize as u64;
+        header.e_shoff = 0;
+        header.e_ehsize = ehsize as u16;
+        header.e_phentsize = phentsize as u16;
+        header.e_phnum = phnum as u16;
+        header.e_shentsize = 0;
+        header.e_shnum = 0;
+        header.e_shstrndx = 0;
+        write_ehdr(&header, &mut f)?;
+
+        // Each region's data starts at an offset congruent to its address modulo the page
+        // size, so the first one starts on a page boundary after the notes
+        let mask = self.page_size - 1;
+        let region_offset = |offset: u64, start: u64| {
+            offset
+                .checked_add(mask)
+                .and_then(|offset| (offset & !mask).checked_add(start & mask))
+                .ok_or(Error::Malformed)
+        };
+        let mut offset = (ehsize + phentsize * phnum) as u64;
+        let note = Phdr64 {
+            p_type: PT::NOTE,
+            p_offset: offset,
+            p_filesz: notes.len,
+            p_align: 4,
+            ..Default::default()
+        };
+        write_phdr(&header, &note, &mut f)?;
+        let data = region_offset(offset + notes.len, 0)?;
+        offset = data;
+        for region in self.regions {
+            let size = region
+                .end
+                .checked_sub(region.start)
+                .ok_or(Error::Malformed)?;
+            if region.dump_size > size {
+                return Err(Error::Malformed);
+            }
+            let phdr = Phdr64 {
+                p_type: PT::LOAD,
+                p_flags: region.flags,
+                p_offset: region_offset(offset, region.start)?,
+                p_vaddr: region.start,
+                p_filesz: region.dump_size,
+                p_memsz: size,
+                p_align: self.page_size,
+                ..Default::default()
+            };
+            write_phdr(&header, &phdr, &mut f)?;
+            offset = phdr
+                .p_offset
+                .checked_add(phdr.p_filesz)
+                .ok_or(Error::Malformed)?;
+        }
+
+        let mut notes = NoteWriter {
+            f: &mut f,
+            big_endian: notes.big_endian,
+            len: 0,
+        };
+        self.write_notes(ehdr, &mut notes)?;
+
+        // Memory is copied a page at a time, after padding up to the offset of each region
+        let padding = data - note.p_offset - notes.len;
+        let mut page = [0; 4096];
+        write_zeros(&mut f, padding)?;
+        offset = data;
+        for region in self.regions {
+            let start = region_offset(offset, region.start)?;
+            write_zeros(&mut f, start - offset)?;
+            let mut address = region.start;
+            let end = region.start + region.dump_size;
+            while address < end {
+                let len = (end - address).min(page.len() as u64);
+                let data = &mut page[..len as usize];
+                read_memory(address, data)?;
+                f(data)?;
+                address += len;
+            }
+            offset = start + region.dump_size;
+        }
+        Ok(())
+    }
+
+    fn write_notes<F: FnMut(&[u8]) -> Result<(), Error>>(
+        &self,
+        ehdr: &Ehdr64,
+        notes: &mut NoteWriter<F>,
+    ) -> Result<(), Error> {
+        // The process-wide notes follow the first thread's status
+        if let Some((first, others)) = self.threads.split_first() {
+            self.write_status(ehdr, first, notes)?;
+            self.write_process_notes(ehdr, notes)?;
+            self.write_registers(ehdr, first, notes)?;
+            for thread in others {
+                self.write_status(ehdr, thread, notes)?;
+                self.write_registers(ehdr, thread, notes)?;
+            }
+            Ok(())
+        } else {
+            self.write_process_notes(ehdr, notes)
+        }
+    }
+
+    fn write_status<F: FnMut(&[u8]) -> Result<(), Error>>(
+        &self,
+        ehdr: &Ehdr64,
+        thread: &CoreThread,
+        notes: &mut NoteWriter<F>,
+    ) -> Result<(), Error> {
+        let big_endian = notes.big_endian;
+        let elf32 = ehdr.e_ident.class == EIC::ELF32;
+        let mut data = [0; 512];
+        let mut len = if elf32 {
+            to_bytes(
+                &Prstatus32::try_from(thread.prstatus)?,
+                big_endian,
+                &mut data,
+            )?
+        } else {
+            to_bytes(&thread.prstatus, big_endian, &mut data)?
+        };
+        let registers = &mut data[len..];
+        len += match (thread.registers, ehdr.e_machine, elf32) {
+            (Registers::X86_64(regs), EM::X86_64, false) => to_bytes(&regs, big_endian, registers)?,
+            (Registers::AArch64(regs), EM::AARCH64, false) => {
+                to_bytes(&regs, big_endian, registers)?
+            }
+            (Registers::Arm(regs), EM::ARM, true) => to_bytes(&regs, big_endian, registers)?,
+            (Registers::Riscv(regs), EM::RISCV, false) => to_bytes(&regs, big_endian, registers)?,
+            (Registers::Riscv(regs), EM::RISCV, true) => {
+                to_bytes(&RiscvRegisters32::try_from(regs)?, big_endian, registers)?
+            }
+            _ => return Err(Error::Unsupported),
+        };
+        // `pr_fpvalid`, then padding to the alignment of the structure
+        let fpvalid = thread.fp_registers.is_some() as i32;
+        len += to_bytes(&fpvalid, big_endian, &mut data[len..])?;
+        let word_size = if elf32 { 4 } else { 8 };
+        let len = (len + word_size - 1) & !(word_size - 1);
+        notes.note("CORE", NT::PRSTATUS, |f| f(&data[..len]))
+    }
+
+    fn write_registers<F: FnMut(&[u8]) -> Result<(), Error>>(
+        &self,
+        ehdr: &Ehdr64,
+        thread: &CoreThread,
+        notes: &mut NoteWriter<F>,
+    ) -> Result<(), Error> {
+        if let Some(registers) = thread.fp_registers {
+            let big_endian = notes.big_endian;
+            let elf32 = ehdr.e_ident.class == EIC::ELF32;
+            let mut data = [0; 528];
+            let (name, n_type, len) = match (registers, ehdr.e_machine, elf32) {
+                (FpRegisters::X86_64(regs), EM::X86_64, false) => (
+                    "CORE",
+                    NT::FPREGSET,
+                    to_bytes(&regs, big_endian, &mut data)?,
+                ),
+                (FpRegisters::AArch64(regs), EM::AARCH64, false) => (
+                    "CORE",
+                    NT::FPREGSET,
+                    to_bytes(&regs, big_endian, &mut data)?,
+                ),
+                (FpRegisters::Arm(regs), EM::ARM, true) => (
+                    "LINUX",
+                    NT::ARM_VFP,
+                    to_bytes(&regs, big_endian, &mut data)?,
+                ),
+                // The structure is padded to 8 bytes after `fcsr`, Linux writes 264 bytes
+                (FpRegisters::Riscv(regs), EM::RISCV, _) => (
+                    "CORE",
+                    NT::FPREGSET,
+                    to_bytes(&regs, big_endian, &mut data)? + 4,
+                ),
+                _ => return Err(Error::Unsupported),
+            };
+            notes.note(name, n_type, |f| f(&data[..len]))?;
+        }
+        for note in thread.notes {
+            notes.note(note.name, NT::from(note.n_type), |f| f(note.desc))?;
+        }
+        Ok(())
+    }
+
+    fn write_process_notes<F: FnMut(&[u8]) -> Result<(), Error>>(
+        &self,
+        ehdr: &Ehdr64,
+        notes: &mut NoteWriter<F>,
+    ) -> Result<(), Error> {
+        let big_endian = notes.big_endian;
+        let elf32 = ehdr.e_ident.class == EIC::ELF32;
+        let word = |value: u64, f: &mut dyn FnMut(&[u8]) -> Result<(), Error>| {
+            let mut data = [0; 8];
+            let len = if elf32 {
+                let value = u32::try_from(value).map_err(|_| Error::Unsupported)?;
+                to_bytes(&value, big_endian, &mut data)?
+            } else {
+                to_bytes(&value, big_endian, &mut data)?
+            };
+            f(&data[..len])
+        };
+
+        if let Some(info) = &self.prpsinfo {
+            let mut data = [0; Prpsinfo::MAX_SIZE];
+            let len = info.encode(ehdr.e_machine, elf32, big_endian, &mut data)?;
+            notes.note("CORE", NT::PRPSINFO, |f| f(&data[..len]))?;
+        }
+        if let Some(siginfo) = &self.siginfo {
+            let mut data = [0; Siginfo::SIZE];
+            siginfo.encode(elf32, big_endian, &mut data)?;
+            notes.note("CORE", NT::SIGINFO, |f| f(&data))?;
+        }
+        if !self.auxv.is_empty() {
+            notes.note("CORE", NT::AUXV, |f| {
+                for entry in self.auxv.iter().chain(&[Auxv64::default()]) {
+                    word(entry.a_type.into(), f)?;
+                    word(entry.a_val, f)?;
+                }
+                Ok(())
+            })?;
+        }
+        let files = || {
+            self.regions
+                .iter()
+                .filter_map(|r| r.file.map(|file| (r, file)))
+        };
+        if files().next().is_some() {
+            notes.note("CORE", NT::FILE, |f| {
+                word(files().count() as u64, f)?;
+                word(self.page_size, f)?;
+                for (region, (_, offset)) in files() {
+                    word(region.start, f)?;
+                    word(region.end, f)?;
+                    word(offset / self.page_size, f)?;
+                }
+                for (_, (name, _)) in files() {
+                    f(name.as_bytes())?;
+                    f(&[0])?;
+                }
+                Ok(())
+            })?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::auxv::AT;
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn note(builder: &Builder, name: &str, n_type: NT, desc: &[u8]) -> Vec<u8> {
+        let mut data = builder.serialize(&Nhdr {
+            n_namesz: name.len() as u32 + 1,
+            n_descsz: desc.len() as u32,
+            n_type: n_type.into(),
+        });
+        data.extend(name.as_bytes());
+        data.push(0);
+        data.resize((data.len() + 3) & !3, 0);
+        data.extend(desc);
+        data.resize((data.len() + 3) & !3, 0);
+        data
+    }
+
+    #[test]
+    fn x86_64() {
+        let mut builder = Builder::new(false, false, ET::CORE, EM::X86_64);
+        let prstatus = |pid, rip| {
+            let mut data = builder.serialize(&Prstatus64 {
+                si_signo: 11,
+                pr_cursig: 11,
+                pr_pid: pid,
+                pr_ppid: 1,
+                ..Default::default()
+            });
+            data.extend(builder.serialize(&X86_64Registers {
+                rip,
+                rsp: 0x7ffd_0008,
+                ..Default::default()
+            }));
+            data.extend([1, 0, 0, 0, 0, 0, 0, 0]);
+            note(&builder, "CORE", NT::PRSTATUS, &data)
+        };
+        let mut notes = prstatus(100, 0x40_1000);
+
+        let mut prpsinfo = vec![0, b'R', 0, 0, 0, 0, 0, 0];
+        prpsinfo.extend(builder.serialize(&(0x40u64, [1000u32, 1000, 100, 1, 100, 100])));
+        prpsinfo.extend(b"crash\0\0\0\0\0\0\0\0\0\0\0crash -x");
+        prpsinfo.resize(136, 0);
+        notes.extend(note(&builder, "CORE", NT::PRPSINFO, &prpsinfo));
+
+        let mut siginfo = builder.serialize(&([11u32, 0, 1, 0], 0xdeadu64));
+        siginfo.resize(128, 0);
+        notes.extend(note(&builder, "CORE", NT::SIGINFO, &siginfo));
+
+        let auxv = builder.serialize(&[6u64, 0x1000, 9, 0x40_1000, 0, 0]);
+        notes.extend(note(&builder, "CORE", NT::AUXV, &auxv));
+
+        let mut files = builder.serialize(&[2u64, 0x1000, 0x40_0000, 0x40_1000, 0]);
+        files.extend(builder.serialize(&[0x7f00_0000u64, 0x7f00_2000, 3]));
+        files.extend(b"/bin/crash\0/lib/libc.so.6\0");
+        notes.extend(note(&builder, "CORE", NT::FILE, &files));
+
+        let mut fxsave = Fxsave {
+            mxcsr: 0x1f80,
+            ..Default::default()
+        };
+        fxsave.xmm_space[1] = [1, 2];
+        notes.extend(note(
+            &builder,
+            "CORE",
+            NT::FPREGSET,
+            &builder.serialize(&fxsave),
+        ));
+        fxsave.padding[6] = 0x7;
+        let mut xstate = builder.serialize(&fxsave);
+        xstate.extend(builder.serialize(&0x7u64));
+        xstate.resize(Xstate::YMM_HI + 16, 0);
+        xstate.extend(builder.serialize(&[3u64, 4]));
+        xstate.resize(832, 0);
+        notes.extend(note(&builder, "LINUX", NT::X86_XSTATE, &xstate));
+
+        notes.extend(prstatus(101, 0x40_2000));
+
+        let notes = builder.section(".note", SHT::NOTE, 0, notes);
+        builder.segment(PT::NOTE, PF::NONE, &[notes]);
+        let stack = builder.section("stack", SHT::PROGBITS, 0x7ffd_0000, vec![1; 0x10]);
+        builder.segment(PT::LOAD, PF::R | PF::W, &[stack]);
+        let stack = builder.section("stack", SHT::PROGBITS, 0x7ffd_0010, vec![2; 0x10]);
+        builder.segment(PT::LOAD, PF::R | PF::W, &[stack]);
+        let elf = builder.build();
+        let file = ElfFile::new(&elf).unwrap();
+        let core = file.core().unwrap();
+
+        let info = core.prpsinfo().unwrap().unwrap();
+        assert_eq!(info.pr_sname, b'R');
+        assert_eq!(info.pr_flag, 0x40);
+        assert_eq!((info.pr_uid, info.pr_pid, info.pr_sid), (1000, 100, 100));
+        assert_eq!(info.pr_fname, "crash");
+        assert_eq!(info.pr_psargs, "crash -x");
+
+        let siginfo = core.siginfo().unwrap().unwrap();
+        assert_eq!((siginfo.si_signo, siginfo.si_code), (11, 1));
+        assert_eq!(siginfo.si_addr, 0xdead);
+
+        let auxv = core.auxv().unwrap().unwrap();
+        assert_eq!(auxv.get(AT::PAGESZ), Some(0x1000));
+        assert_eq!(auxv.get(AT::ENTRY), Some(0x40_1000));
+        assert_eq!(auxv.count(), 2);
+
+        let files: Vec<_> = core
+            .mapped_files()
+            .unwrap()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(files.len(), 2);
+        assert_eq!(files[1].start, 0x7f00_0000);
+        assert_eq!(files[1].offset, 0x3000);
+        assert_eq!(files[1].name, "/lib/libc.so.6");
+
+        let threads: Vec<_> = core.threads().map(Result::unwrap).collect();
+        assert_eq!(threads.len(), 2);
+        assert_eq!(threads[0].prstatus.pr_pid, 100);
+        assert_eq!(threads[0].registers.pc(), 0x40_1000);
+        assert_eq!(threads[1].registers.pc(), 0x40_2000);
+        assert_eq!(threads[1].registers.sp(), 0x7ffd_0008);
+        match threads[0].fp_registers().unwrap() {
+            Some(FpRegisters::X86_64(fxsave)) => {
+                assert_eq!(fxsave.mxcsr, 0x1f80);
+                assert_eq!(fxsave.xmm(1), 2 << 64 | 1);
+            }
+            registers => panic!("{:?}", registers),
+        }
+        let xstate = threads[0].xstate().unwrap().unwrap();
+        assert_eq!(xstate.ymm(1), Ok(Some([2 << 64 | 1, 4 << 64 | 3])));
+        assert_eq!(xstate.ymm(16), Err(Error::NotFound));
+        assert_eq!(xstate.ymm(usize::MAX), Err(Error::NotFound));
+        assert!(threads[1].fp_registers().unwrap().is_none());
+
+        // Memory spanning both segments
+        let mut data = [0; 4];
+        assert_eq!(core.read_memory(0x7ffd_000e, &mut data), Ok(()));
+        assert_eq!(data, [1, 1, 2, 2]);
+        assert_eq!(
+            core.read_memory(0x7ffd_001e, &mut data),
+            Err(Error::NotFound)
+        );
+    }
+
+    #[test]
+    fn arm() {
+        let mut builder = Builder::new(true, false, ET::CORE, EM::ARM);
+        let mut prstatus = builder.serialize(&Prstatus32 {
+            pr_pid: 42,
+            ..Default::default()
+        });
+        let mut registers = ArmRegisters::default();
+        registers.regs[13] = 0xbeef_0000;
+        registers.regs[15] = 0x1_0000;
+        prstatus.extend(builder.serialize(&registers));
+        prstatus.extend([0; 4]);
+        assert_eq!(prstatus.len(), 148);
+        let mut notes = note(&builder, "CORE", NT::PRSTATUS, &prstatus);
+
+        let mut prpsinfo = vec![0, b'S', 0, 0, 0, 0, 0, 0];
+        prpsinfo.extend(builder.serialize(&([1000u16, 100], [42u32, 1, 42, 42])));
+        prpsinfo.extend(b"daemon");
+        prpsinfo.resize(124, 0);
+        notes.extend(note(&builder, "CORE", NT::PRPSINFO, &prpsinfo));
+
+        let vfp = Vfp {
+            fpscr: 0x1000_0000,
+            ..Default::default()
+        };
+        notes.extend(note(
+            &builder,
+            "LINUX",
+            NT::ARM_VFP,
+            &builder.serialize(&vfp),
+        ));
+
+        let notes = builder.section(".note", SHT::NOTE, 0, notes);
+        builder.segment(PT::NOTE, PF::NONE, &[notes]);
+        let elf = builder.build();
+        let core = ElfFile::new(&elf).unwrap().core().unwrap();
+
+        let info = core.prpsinfo().unwrap().unwrap();
+        assert_eq!((info.pr_uid, info.pr_gid), (1000, 100));
+        assert_eq!((info.pr_pid, info.pr_sid), (42, 42));
+        assert_eq!(info.pr_fname, "daemon");
+        let thread = core.threads().next().unwrap().unwrap();
+        assert_eq!(thread.prstatus.pr_pid, 42);
+        assert_eq!(thread.registers.sp(), 0xbeef_0000);
+        assert_eq!(thread.registers.pc(), 0x1_0000);
+        assert_eq!(thread.fp_registers(), Ok(Some(FpRegisters::Arm(vfp))));
+        assert!(core.siginfo().unwrap().is_none());
+        assert_eq!(core.read_memory(0, &mut [0]), Err(Error::NotFound));
+    }
+
+    #[test]
+    fn wrapping() {
+        let mut builder = Builder::new(false, false, ET::CORE, EM::X86_64);
+        let top = builder.segment(PT::LOAD, PF::R, &[]);
+        let bottom = builder.segment(PT::LOAD, PF::R, &[]);
+        let far = builder.segment(PT::LOAD, PF::R, &[]);
+        for (segment, vaddr, offset) in [
+            (top, u64::MAX - 7, 0),
+            (bottom, 0, 0),
+            (far, 0x1000, u64::MAX),
+        ] {
+            let phdr = builder.phdr_mut(segment);
+            phdr.p_vaddr = vaddr;
+            phdr.p_offset = offset;
+            phdr.p_filesz = 8;
+            phdr.p_memsz = 8;
+        }
+        let elf = builder.build();
+        let core = ElfFile::new(&elf).unwrap().core().unwrap();
+
+        // The last bytes of the address space can be read, but not past them
+        let mut data = [0; 8];
+        assert_eq!(core.read_memory(u64::MAX - 7, &mut data), Ok(()));
+        assert_eq!(data, elf[..8]);
+        let mut data = [0; 16];
+        let result = core.read_memory(u64::MAX - 7, &mut data);
+        assert_eq!(result, Err(Error::Malformed));
+        let result = core.read_memory(0x1004, &mut [0]);
+        assert_eq!(result, Err(Error::Malformed));
+    }
+
+    #[test]
+    fn write() {
+        let xstate = [0x55; 832];
+        let extra = [Note {
+            name: "LINUX",
+            n_type: NT::X86_XSTATE.into(),
+            desc: &xstate,
+        }];
+        let mut fxsave = Fxsave::default();
+        fxsave.xmm_space[2] = [3, 4];
+        let thread = |pid, rip, fp_registers, notes| CoreThread {
+            prstatus: Prstatus64 {
+                si_signo: 6,
+                pr_cursig: 6,
+                pr_pid: pid,
+                ..Default::default()
+            },
+            registers: Registers::X86_64(X86_64Registers {
+                rip,
+                rsp: 0x7000_0ff0,
+                ..Default::default()
+            }),
+            fp_registers,
+            notes,
+        };
+        let threads = [
+            thread(10, 0x1000, Some(FpRegisters::X86_64(fxsave)), &extra[..]),
+            thread(11, 0x2000, None, &[]),
+        ];
+        let regions = [
+            CoreRegion {
+                start: 0x40_0000,
+                end: 0x40_1000,
+                flags: PF::R | PF::X,
+                dump_size: 0,
+                file: Some(("/bin/app", 0)),
+            },
+            CoreRegion {
+                start: 0x7000_0000,
+                end: 0x7000_1800,
+                flags: PF::R | PF::W,
+                dump_size: 0x1800,
+                file: None,
+            },
+            CoreRegion {
+                start: 0x7000_2000,
+                end: 0x7000_3000,
+                flags: PF::R | PF::W,
+                dump_size: 0x1000,
+                file: None,
+            },
+        ];
+        let dump = CoreDump {
+            threads: &threads,
+            prpsinfo: Some(Prpsinfo {
+                pr_state: 0,
+                pr_sname: b'R',
+                pr_zomb: 0,
+                pr_nice: 0,
+                pr_flag: 0,
+                pr_uid: 1000,
+                pr_gid: 1000,
+                pr_pid: 10,
+                pr_ppid: 1,
+                pr_pgrp: 10,
+                pr_sid: 10,
+                pr_fname: "app",
+                pr_psargs: "app --crash",
+            }),
+            siginfo: Some(Siginfo {
+                si_signo: 6,
+                si_code: -6,
+                ..Default::default()
+            }),
+            auxv: &[
+                Auxv64 {
+                    a_type: AT::PAGESZ,
+                    a_val: 0x1000,
+                },
+                Auxv64 {
+                    a_type: AT::ENTRY,
+                    a_val: 0x40_0100,
+                },
+            ],
+            regions: &regions,
+            page_size: 0x1000,
+        };
+        let ehdr = Ehdr64::new(EIC::ELF64, EID::LSB, ET::EXEC, EM::X86_64);
+        let mut elf = Vec::new();
+        let mut reads = 0;
+        let mut read_memory = |address: u64, data: &mut [u8]| {
+            reads += 1;
+            data.fill((address >> 12) as u8);
+            Ok(())
+        };
+        let result = dump.write(&ehdr, &mut read_memory, |data| {
+            elf.extend(data);
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        assert_eq!(reads, 3);
+
+        let file = ElfFile::new(&elf).unwrap();
+        let core = file.core().unwrap();
+        let phdrs: Vec<_> = file.phdrs().unwrap().collect();
+        assert_eq!(phdrs.len(), 4);
+        assert!(phdrs[1..]
+            .iter()
+            .all(|p| p.p_offset % 0x1000 == p.p_vaddr % 0x1000));
+        assert_eq!(phdrs[3].p_offset, phdrs[2].p_offset + 0x2000);
+        assert_eq!(elf.len() as u64, phdrs[3].p_offset + 0x1000);
+        assert_eq!(core.prpsinfo(), Ok(Some(dump.prpsinfo.unwrap())));
+        assert_eq!(core.siginfo(), Ok(dump.siginfo));
+        let auxv: Vec<_> = core.auxv().unwrap().unwrap().map(Result::unwrap).collect();
+        assert_eq!(auxv, dump.auxv);
+        let files: Vec<_> = core
+            .mapped_files()
+            .unwrap()
+            .unwrap()
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(files.len(), 1);
+        assert_eq!((files[0].start, files[0].name), (0x40_0000, "/bin/app"));
+
+        let parsed: Vec<_> = core.threads().map(Result::unwrap).collect();
+        assert_eq!(parsed.len(), 2);
+        for (parsed, thread) in parsed.iter().zip(&threads) {
+            assert_eq!(parsed.prstatus, thread.prstatus);
+            assert_eq!(parsed.registers, thread.registers);
+            assert_eq!(parsed.fp_registers(), Ok(thread.fp_registers));
+        }
+        assert_eq!(parsed[0].note(NT::X86_XSTATE), Ok(Some(extra[0])));
+        assert_eq!(parsed[1].note(NT::X86_XSTATE), Ok(None));
+
+        let mut data = [0; 2];
+        assert_eq!(core.read_memory(0x7000_0fff, &mut data), Ok(()));
+        assert_eq!(data, [0, 1]);
+        assert_eq!(core.read_memory(0x7000_2000, &mut data), Ok(()));
+        assert_eq!(data, [2, 2]);
+        assert_eq!(core.read_memory(0x40_0000, &mut data), Err(Error::NotFound));
+
+        // Registers must match the machine
+        let ehdr = Ehdr64::new(EIC::ELF64, EID::LSB, ET::CORE, EM::AARCH64);
+        assert_eq!(
+            dump.write(&ehdr, |_, _| Ok(()), |_| Ok(())),
+            Err(Error::Unsupported)
+        );
+
+        // RISC-V floating point registers are padded to 264 bytes like Linux does
+        let threads = [CoreThread {
+            prstatus: Prstatus64::default(),
+            registers: Registers::Riscv(RiscvRegisters64::default()),
+            fp_registers: Some(FpRegisters::Riscv(RiscvFpRegisters::default())),
+            notes: &[],
+        }];
+        let dump = CoreDump {
+            threads: &threads,
+            page_size: 0x1000,
+            ..Default::default()
+        };
+        let ehdr = Ehdr64::new(EIC::ELF64, EID::LSB, ET::CORE, EM::RISCV);
+        let mut elf = Vec::new();
+        let result = dump.write(
+            &ehdr,
+            |_, _| Ok(()),
+            |data| {
+                elf.extend(data);
+                Ok(())
+            },
+        );
+        assert_eq!(result, Ok(()));
+        let file = ElfFile::new(&elf).unwrap();
+        let thread = file.core().unwrap().threads().next().unwrap().unwrap();
+        let fpregset = thread.note(NT::FPREGSET).unwrap().unwrap();
+        assert_eq!(fpregset.desc.len(), 264);
+    }
+}
@@ -0,0 +1,161 @@
+//! Minimal `no_std` serde deserializer for fixed-size ELF structures
+//!
+//! All of the ELF structures in this crate are `#[repr(C)]` and laid out so that
+//! serializing each field in order, with fixed-width integers and no padding,
+//! produces the on-disk format. This is the same layout as `bincode` with
+//! `with_fixint_encoding()`, but works without `std`.
+
+use crate::Error;
+use serde::de::{DeserializeOwned, DeserializeSeed, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+
+/// Read a structure from the start of `data`
+pub(crate) fn from_bytes<T: DeserializeOwned>(data: &[u8], big_endian: bool) -> Result<T, Error> {
+    let mut deserializer = Deserializer { data, big_endian };
+    T::deserialize(&mut deserializer)
+}
+
+struct Deserializer<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Deserializer<'a> {
+    fn take<const N: usize>(&mut self) -> Result<[u8; N], Error> {
+        if self.data.len() < N {
+            return Err(Error::Truncated);
+        }
+        let (head, tail) = self.data.split_at(N);
+        self.data = tail;
+        let mut bytes = [0; N];
+        bytes.copy_from_slice(head);
+        Ok(bytes)
+    }
+}
+
+macro_rules! deserialize_int {
+    ($($method:ident => $visit:ident($ty:ty),)*) => {
+        $(
+            fn $method<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Error> {
+                let bytes = self.take()?;
+                visitor.$visit(if self.big_endian {
+                    <$ty>::from_be_bytes(bytes)
+                } else {
+                    <$ty>::from_le_bytes(bytes)
+                })
+            }
+        )*
+    };
+}
+
+impl<'de, 'a> serde::Deserializer<'de> for &mut Deserializer<'a> {
+    type Error = Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, _visitor: V) -> Result<V::Value, Error> {
+        Err(Error::Unsupported)
+    }
+
+    deserialize_int! {
+        deserialize_u8 => visit_u8(u8),
+        deserialize_u16 => visit_u16(u16),
+        deserialize_u32 => visit_u32(u32),
+        deserialize_u64 => visit_u64(u64),
+        deserialize_i8 => visit_i8(i8),
+        deserialize_i16 => visit_i16(i16),
+        deserialize_i32 => visit_i32(i32),
+        deserialize_i64 => visit_i64(i64),
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(self, len: usize, visitor: V) -> Result<V::Value, Error> {
+        visitor.visit_seq(Access { de: self, len })
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        len: usize,
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(len, visitor)
+    }
+
+    fn deserialize_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        fields: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Error> {
+        self.deserialize_tuple(fields.len(), visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i128 u128 f32 f64 char str string bytes byte_buf option unit unit_struct
+        seq map enum identifier ignored_any
+    }
+}
+
+struct Access<'a, 'b> {
+    de: &'a mut Deserializer<'b>,
+    len: usize,
+}
+
+impl<'de, 'a, 'b> SeqAccess<'de> for Access<'a, 'b> {
+    type Error = Error;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, Error> {
+        if self.len == 0 {
+            return Ok(None);
+        }
+        self.len -= 1;
+        seed.deserialize(&mut *self.de).map(Some)
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.len)
+    }
+}
+
+impl serde::de::StdError for Error {}
+
+impl serde::de::Error for Error {
+    fn custom<T: core::fmt::Display>(_msg: T) -> Self {
+        Error::Unsupported
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Ehdr32, Phdr64};
+
+    #[test]
+    fn matches_bincode() {
+        use bincode::Options;
+        let phdr = Phdr64 {
+            p_vaddr: 0x1122334455667788,
+            p_align: 0x1000,
+            ..Default::default()
+        };
+        let options = bincode::DefaultOptions::new().with_fixint_encoding();
+        let le = options.with_little_endian().serialize(&phdr).unwrap();
+        let be = options.with_big_endian().serialize(&phdr).unwrap();
+        assert!(from_bytes::<Phdr64>(&le, false).unwrap() == phdr);
+        assert!(from_bytes::<Phdr64>(&be, true).unwrap() == phdr);
+        assert!(from_bytes::<Ehdr32>(&le, false).is_ok());
+        assert_eq!(
+            from_bytes::<Phdr64>(&le[1..], false).err(),
+            Some(Error::Truncated)
+        );
+    }
+}
@@ -0,0 +1,789 @@
+use crate::de::from_bytes;
+use crate::dynamic::DT;
+use crate::ehdr::*;
+use crate::phdr::PT;
+use crate::shdr::{SHF64, SHN, SHT};
+use crate::sym::STT;
+use crate::*;
+use core::marker::PhantomData;
+use serde::de::DeserializeOwned;
+
+/// Structure stored in a 32-bit or 64-bit form, which is always read as the 64-bit form
+pub trait Entry: DeserializeOwned {
+    /// The 32-bit form of the structure
+    type Elf32: DeserializeOwned + Into<Self>;
+}
+impl Entry for Ehdr64 {
+    type Elf32 = Ehdr32;
+}
+impl Entry for Phdr64 {
+    type Elf32 = Phdr32;
+}
+impl Entry for Shdr64 {
+    type Elf32 = Shdr32;
+}
+impl Entry for Sym64 {
+    type Elf32 = Sym32;
+}
+impl Entry for Rel64 {
+    type Elf32 = Rel32;
+}
+impl Entry for Rela64 {
+    type Elf32 = Rela32;
+}
+impl Entry for Dyn64 {
+    type Elf32 = Dyn32;
+}
+
+/// ELF file stored in memory
+///
+/// All 32-bit structures are converted to their 64-bit form when read.
+#[derive(Clone, Copy)]
+pub struct ElfFile<'a> {
+    data: &'a [u8],
+    ehdr: Ehdr64,
+    elf32: bool,
+    big_endian: bool,
+}
+
+impl<'a> ElfFile<'a> {
+    /// Parse the ELF header of `data`
+    pub fn new(data: &'a [u8]) -> Result<Self, Error> {
+        let ident: Eident = from_bytes(data, false)?;
+        if ident.magic != Eident::MAGIC {
+            return Err(Error::BadMagic);
+        }
+        let elf32 = match ident.class {
+            EIC::ELF32 => true,
+            EIC::ELF64 => false,
+            _ => return Err(Error::Unsupported),
+        };
+        let big_endian = match ident.data {
+            EID::LSB => false,
+            EID::MSB => true,
+            _ => return Err(Error::Unsupported),
+        };
+        if ident.version != EIV::CURRENT {
+            return Err(Error::Unsupported);
+        }
+        let mut file = ElfFile {
+            data,
+            ehdr: Ehdr64::default(),
+            elf32,
+            big_endian,
+        };
+        file.ehdr = file.read_entry(0)?;
+        if file.ehdr.e_phnum != 0 && (file.ehdr.e_phentsize as usize) < file.entry_size::<Phdr64>()
+        {
+            return Err(Error::Malformed);
+        }
+        if file.ehdr.e_shoff != 0 && (file.ehdr.e_shentsize as usize) < file.entry_size::<Shdr64>()
+        {
+            return Err(Error::Malformed);
+        }
+        Ok(file)
+    }
+
+    /// Raw file data
+    pub fn data(&self) -> &'a [u8] {
+        self.data
+    }
+
+    /// ELF file header
+    pub fn ehdr(&self) -> &Ehdr64 {
+        &self.ehdr
+    }
+
+    /// Whether the file is a 32-bit ELF file
+    pub fn is_elf32(&self) -> bool {
+        self.elf32
+    }
+
+    /// Whether the file is stored big-endian
+    pub fn is_big_endian(&self) -> bool {
+        self.big_endian
+    }
+
+    /// Size of an address in bytes
+    pub fn word_size(&self) -> usize {
+        if self.elf32 {
+            4
+        } else {
+            8
+        }
+    }
+
+    /// Size of structure `T` in this file's class
+    pub fn entry_size<T: Entry>(&self) -> usize {
+        if self.elf32 {
+            core::mem::size_of::<T::Elf32>()
+        } else {
+            core::mem::size_of::<T>()
+        }
+    }
+
+    /// Bytes from `offset` to `offset + size`
+    pub fn bytes(&self, offset: u64, size: u64) -> Result<&'a [u8], Error> {
+        let end = offset.checked_add(size).ok_or(Error::Malformed)?;
+        if end > self.data.len() as u64 {
+            return Err(Error::Truncated);
+        }
+        Ok(&self.data[offset as usize..end as usize])
+    }
+
+    /// Read a fixed layout structure at `offset`, using the file's byte order
+    pub fn read<T: DeserializeOwned>(&self, offset: u64) -> Result<T, Error> {
+        if offset > self.data.len() as u64 {
+            return Err(Error::Truncated);
+        }
+        from_bytes(&self.data[offset as usize..], self.big_endian)
+    }
+
+    /// Read a structure stored in the file's class at `offset`
+    pub fn read_entry<T: Entry>(&self, offset: u64) -> Result<T, Error> {
+        if self.elf32 {
+            self.read::<T::Elf32>(offset).map(Into::into)
+        } else {
+            self.read::<T>(offset)
+        }
+    }
+
+    /// Read an address sized value at `offset`
+    pub fn read_word(&self, offset: u64) -> Result<u64, Error> {
+        if self.elf32 {
+            self.read::<u32>(offset).map(Into::into)
+        } else {
+            self.read::<u64>(offset)
+        }
+    }
+
+    /// Program headers
+    pub fn phdrs(&self) -> Result<Table<'a, Phdr64>, Error> {
+        let ehdr = &self.ehdr;
+        Table::new(
+            *self,
+            ehdr.e_phoff,
+            ehdr.e_phentsize as u64,
+            ehdr.e_phnum as u64,
+        )
+    }
+
+    /// Number of section headers
+    pub fn shnum(&self) -> Result<usize, Error> {
+        if self.ehdr.e_shoff == 0 {
+            Ok(0)
+        } else if self.ehdr.e_shnum == 0 {
+            // Section count too large for `e_shnum` is stored in the initial section
+            let shdr0: Shdr64 = self.read_entry(self.ehdr.e_shoff)?;
+            Ok(shdr0.sh_size as usize)
+        } else {
+            Ok(self.ehdr.e_shnum as usize)
+        }
+    }
+
+    /// Section headers
+    pub fn shdrs(&self) -> Result<Table<'a, Shdr64>, Error> {
+        let ehdr = &self.ehdr;
+        Table::new(
+            *self,
+            ehdr.e_shoff,
+            ehdr.e_shentsize as u64,
+            self.shnum()? as u64,
+        )
+    }
+
+    /// Section header at `index`
+    pub fn shdr(&self, index: usize) -> Result<Shdr64, Error> {
+        self.shdrs()?.get(index).ok_or(Error::NotFound)
+    }
+
+    /// Index of the section header string table
+    pub fn shstrndx(&self) -> Result<usize, Error> {
+        if self.ehdr.e_shstrndx == SHN::XINDEX.into() {
+            // Index too large for `e_shstrndx` is stored in the initial section
+            Ok(self.shdr(0)?.sh_link as usize)
+        } else {
+            Ok(self.ehdr.e_shstrndx as usize)
+        }
+    }
+
+    /// Name of a section
+    pub fn section_name(&self, shdr: &Shdr64) -> Result<&'a str, Error> {
+        let strtab = self.shdr(self.shstrndx()?)?;
+        self.string(&strtab, shdr.sh_name)
+    }
+
+    /// First section named `name`, and its index
+    pub fn section_by_name(&self, name: &str) -> Result<Option<(usize, Shdr64)>, Error> {
+        for (index, shdr) in self.shdrs()?.enumerate() {
+            if self.section_name(&shdr)? == name {
+                return Ok(Some((index, shdr)));
+            }
+        }
+        Ok(None)
+    }
+
+    /// First section of type `sh_type`, and its index
+    pub fn section_by_type(&self, sh_type: SHT) -> Result<Option<(usize, Shdr64)>, Error> {
+        Ok(self
+            .shdrs()?
+            .enumerate()
+            .find(|(_, shdr)| shdr.sh_type == sh_type))
+    }
+
+    /// Contents of a section, empty for sections without file data
+    pub fn section_data(&self, shdr: &Shdr64) -> Result<&'a [u8], Error> {
+        if shdr.sh_type == SHT::NOBITS || shdr.sh_type == SHT::NULL {
+            Ok(&[])
+        } else {
+            self.bytes(shdr.sh_offset, shdr.sh_size)
+        }
+    }
+
+    /// Contents of a segment stored in the file (`p_filesz` bytes)
+    pub fn segment_data(&self, phdr: &Phdr64) -> Result<&'a [u8], Error> {
+        self.bytes(phdr.p_offset, phdr.p_filesz)
+    }
+
+    /// Index of the allocated section containing virtual address `vaddr`
+    pub fn section_by_address(&self, vaddr: u64) -> Result<Option<usize>, Error> {
+        Ok(self.shdrs()?.position(|shdr| {
+            shdr.sh_flags & SHF64::ALLOC == SHF64::ALLOC
+                && vaddr >= shdr.sh_addr
+                && vaddr - shdr.sh_addr < shdr.sh_size
+        }))
+    }
+
+    /// Nul-terminated string at `offset` in the string table section `strtab`
+    pub fn string(&self, strtab: &Shdr64, offset: u32) -> Result<&'a str, Error> {
+        c_str(self.section_data(strtab)?, offset)
+    }
+
+    /// Symbol at `index` in the dynamic symbol table (`DT::SYMTAB`)
+    ///
+    /// Unlike [`ElfFile::symbols`] this doesn't need section headers.
+    pub fn dynamic_symbol(&self, index: usize) -> Result<Sym64, Error> {
+        let addr = self.dynamic_value(DT::SYMTAB)?.ok_or(Error::NotFound)?;
+        let size = self.entry_size::<Sym64>() as u64;
+        let entsize = self.dynamic_value(DT::SYMENT)?.unwrap_or(size);
+        let vaddr = addr
+            .checked_add(entsize * index as u64)
+            .ok_or(Error::Malformed)?;
+        let offset = self.vaddr_to_offset(vaddr, size).ok_or(Error::Malformed)?;
+        self.read_entry(offset)
+    }
+
+    /// Nul-terminated string at `offset` in the dynamic string table (`DT::STRTAB`)
+    pub fn dynamic_string(&self, offset: u32) -> Result<&'a str, Error> {
+        let addr = self.dynamic_value(DT::STRTAB)?.ok_or(Error::NotFound)?;
+        let size = self.dynamic_value(DT::STRSZ)?.unwrap_or(0);
+        let start = self.vaddr_to_offset(addr, size).ok_or(Error::Malformed)?;
+        c_str(self.bytes(start, size)?, offset)
+    }
+
+    /// Symbol table in the section at `index`
+    pub fn symbols(&self, index: usize) -> Result<SymbolTable<'a>, Error> {
+        let shdr = self.shdr(index)?;
+        if shdr.sh_type != SHT::SYMTAB && shdr.sh_type != SHT::DYNSYM {
+            return Err(Error::Malformed);
+        }
+        let strtab = self.shdr(shdr.sh_link as usize)?;
+        let table = self.table(shdr.sh_offset, shdr.sh_size, shdr.sh_entsize)?;
+        Ok(SymbolTable {
+            file: *self,
+            table,
+            strtab,
+        })
+    }
+
+    /// Static symbol table (`.symtab`), or dynamic symbol table (`.dynsym`) for stripped files
+    pub fn symbol_table(&self) -> Result<Option<SymbolTable<'a>>, Error> {
+        let found = match self.section_by_type(SHT::SYMTAB)? {
+            None => self.section_by_type(SHT::DYNSYM)?,
+            found => found,
+        };
+        found.map(|(index, _)| self.symbols(index)).transpose()
+    }
+
+    /// Relocations in the `SHT::REL`, `SHT::RELA`, `SHT::RELR` or Android packed section `shdr`
+    pub fn relocations(&self, shdr: &Shdr64) -> Result<Relocations<'a>, Error> {
+        match shdr.sh_type {
+            SHT::REL => self.relocations_at(shdr.sh_offset, shdr.sh_size, shdr.sh_entsize, false),
+            SHT::RELA => self.relocations_at(shdr.sh_offset, shdr.sh_size, shdr.sh_entsize, true),
+            SHT::ANDROID_REL | SHT::ANDROID_RELA => {
+                Ok(Relocations::Android(packed::AndroidRelocations::new(
+                    self.section_data(shdr)?,
+                    self.elf32,
+                    shdr.sh_type == SHT::ANDROID_RELA,
+                )?))
+            }
+            SHT::RELR | SHT::ANDROID_RELR => self.relr(self.section_data(shdr)?),
+            _ => Err(Error::Malformed),
+        }
+    }
+
+    /// Relative relocations encoded in the RELR table `data`
+    fn relr(&self, data: &'a [u8]) -> Result<Relocations<'a>, Error> {
+        Ok(Relocations::Relr {
+            offsets: packed::Relr::new(data, self.word_size(), self.big_endian),
+            r_type: reloc::relative(self.ehdr.e_machine).ok_or(Error::Unsupported)?,
+        })
+    }
+
+    /// Relocations stored at `offset`, with or without addends
+    pub fn relocations_at(
+        &self,
+        offset: u64,
+        size: u64,
+        entsize: u64,
+        rela: bool,
+    ) -> Result<Relocations<'a>, Error> {
+        Ok(if rela {
+            Relocations::Rela(self.table(offset, size, entsize)?)
+        } else {
+            Relocations::Rel(self.table(offset, size, entsize)?)
+        })
+    }
+
+    /// Entries of the dynamic section, up to the terminating `DT::NULL`
+    ///
+    /// Uses the `PT::DYNAMIC` segment, or the `SHT::DYNAMIC` section if there are no program
+    /// headers. Returns an empty iterator if there is no dynamic section.
+    pub fn dynamic(&self) -> Result<impl Iterator<Item = Dyn64> + 'a, Error> {
+        let table = if let Some(phdr) = self.phdrs()?.find(|p| p.p_type == PT::DYNAMIC) {
+            self.table(phdr.p_offset, phdr.p_filesz, 0)?
+        } else if let Some((_, shdr)) = self.section_by_type(SHT::DYNAMIC)? {
+            self.table(shdr.sh_offset, shdr.sh_size, shdr.sh_entsize)?
+        } else {
+            Table::empty(*self)
+        };
+        Ok(table.take_while(|d: &Dyn64| d.d_tag != DT::NULL))
+    }
+
+    /// Value of the first dynamic entry with type `tag`
+    pub fn dynamic_value(&self, tag: DT) -> Result<Option<u64>, Error> {
+        Ok(self.dynamic()?.find(|d| d.d_tag == tag).map(|d| d.d_val))
+    }
+
+    /// Relocations processed by the dynamic linker
+    ///
+    /// These are `DT::RELR`, `DT::RELA`, `DT::REL`, Android's packed `DT::ANDROID_RELA` and
+    /// `DT::ANDROID_REL` except in Solaris files, and `DT::JMPREL`, in that order.
+    pub fn dynamic_relocations(&self) -> Result<impl Iterator<Item = Relocation> + 'a, Error> {
+        let mut relr = Relocations::Rel(Table::empty(*self));
+        let mut rela = Relocations::Rela(Table::empty(*self));
+        let mut rel = Relocations::Rel(Table::empty(*self));
+        let mut android = Relocations::Rel(Table::empty(*self));
+        let mut jmprel = Relocations::Rela(Table::empty(*self));
+        let (mut relr_addr, mut relr_size) = (None, 0);
+        let (mut rela_addr, mut rela_size, mut rela_ent) = (None, 0, 0);
+        let (mut rel_addr, mut rel_size, mut rel_ent) = (None, 0, 0);
+        let (mut android_addr, mut android_size, mut android_rela) = (None, 0, false);
+        let (mut jmprel_addr, mut jmprel_size, mut jmprel_rela) = (None, 0, false);
+        // Solaris uses the values of the Android tags for DT::SUNW_SYMTAB and others
+        let solaris = self.ehdr.e_ident.osabi == EIOSABI::SOLARIS;
+        for d in self.dynamic()? {
+            match d.d_tag {
+                DT::RELR | DT::ANDROID_RELR => relr_addr = Some(d.d_val),
+                DT::RELRSZ | DT::ANDROID_RELRSZ => relr_size = d.d_val,
+                DT::RELA => rela_addr = Some(d.d_val),
+                DT::RELASZ => rela_size = d.d_val,
+                DT::RELAENT => rela_ent = d.d_val,
+                DT::REL => rel_addr = Some(d.d_val),
+                DT::RELSZ => rel_size = d.d_val,
+                DT::RELENT => rel_ent = d.d_val,
+                DT::ANDROID_RELA if !solaris => {
+                    (android_addr, android_rela) = (Some(d.d_val), true)
+                }
+                DT::ANDROID_REL if !solaris => {
+                    (android_addr, android_rela) = (Some(d.d_val), false)
+                }
+                DT::ANDROID_RELASZ | DT::ANDROID_RELSZ if !solaris => android_size = d.d_val,
+                DT::JMPREL => jmprel_addr = Some(d.d_val),
+                DT::PLTRELSZ => jmprel_size = d.d_val,
+                DT::PLTREL => jmprel_rela = d.d_val == i64::from(DT::RELA) as u64,
+                _ => {}
+            }
+        }
+        let offset = |addr, size| self.vaddr_to_offset(addr, size).ok_or(Error::Malformed);
+        if let Some(addr) = relr_addr {
+            relr = self.relr(self.bytes(offset(addr, relr_size)?, relr_size)?)?;
+        }
+        if let Some(addr) = rela_addr {
+            rela = self.relocations_at(offset(addr, rela_size)?, rela_size, rela_ent, true)?;
+        }
+        if let Some(addr) = rel_addr {
+            rel = self.relocations_at(offset(addr, rel_size)?, rel_size, rel_ent, false)?;
+        }
+        if let Some(addr) = android_addr {
+            let data = self.bytes(offset(addr, android_size)?, android_size)?;
+            android = Relocations::Android(packed::AndroidRelocations::new(
+                data,
+                self.elf32,
+                android_rela,
+            )?);
+        }
+        if let Some(addr) = jmprel_addr {
+            jmprel =
+                self.relocations_at(offset(addr, jmprel_size)?, jmprel_size, 0, jmprel_rela)?;
+        }
+        Ok(relr.chain(rela).chain(rel).chain(android).chain(jmprel))
+    }
+
+    /// File offset of `size` bytes at virtual address `vaddr`
+    ///
+    /// Uses the `PT::LOAD` segments, or allocated sections if there are no program headers.
+    pub fn vaddr_to_offset(&self, vaddr: u64, size: u64) -> Option<u64> {
+        let end = vaddr.checked_add(size)?;
+        let phdrs = self.phdrs().ok()?;
+        if phdrs.len() != 0 {
+            for phdr in phdrs.filter(|p| p.p_type == PT::LOAD) {
+                let contains = phdr.p_vaddr.checked_add(phdr.p_filesz).map(|e| end <= e);
+                if vaddr >= phdr.p_vaddr && contains == Some(true) {
+                    return phdr.p_offset.checked_add(vaddr - phdr.p_vaddr);
+                }
+            }
+            return None;
+        }
+        for shdr in self.shdrs().ok()? {
+            if shdr.sh_flags & SHF64::ALLOC != SHF64::ALLOC || shdr.sh_type == SHT::NOBITS {
+                continue;
+            }
+            let contains = shdr.sh_addr.checked_add(shdr.sh_size).map(|e| end <= e);
+            if vaddr >= shdr.sh_addr && contains == Some(true) {
+                return shdr.sh_offset.checked_add(vaddr - shdr.sh_addr);
+            }
+        }
+        None
+    }
+
+    /// Table of `size / entsize` entries at `offset`, `entsize` of 0 uses the size of `T`
+    pub fn table<T: Entry>(
+        &self,
+        offset: u64,
+        size: u64,
+        entsize: u64,
+    ) -> Result<Table<'a, T>, Error> {
+        let entsize = if entsize == 0 {
+            self.entry_size::<T>() as u64
+        } else {
+            entsize
+        };
+        Table::new(*self, offset, entsize, size / entsize)
+    }
+}
+
+/// Nul-terminated string at `offset` in `data`
+pub(crate) fn c_str(data: &[u8], offset: u32) -> Result<&str, Error> {
+    let data = data.get(offset as usize..).ok_or(Error::Truncated)?;
+    let len = data.iter().position(|&c| c == 0).ok_or(Error::Truncated)?;
+    core::str::from_utf8(&data[..len]).map_err(|_| Error::Malformed)
+}
+
+/// Iterator over a table of fixed size entries in an [`ElfFile`]
+#[derive(Clone)]
+pub struct Table<'a, T> {
+    file: ElfFile<'a>,
+    offset: u64,
+    entsize: u64,
+    start: u64,
+    end: u64,
+    _entry: PhantomData<T>,
+}
+
+impl<'a, T: Entry> Table<'a, T> {
+    fn new(file: ElfFile<'a>, offset: u64, entsize: u64, count: u64) -> Result<Self, Error> {
+        if count != 0 {
+            if entsize < file.entry_size::<T>() as u64 {
+                return Err(Error::Malformed);
+            }
+            let size = entsize.checked_mul(count).ok_or(Error::Malformed)?;
+            file.bytes(offset, size)?;
+        }
+        Ok(Table {
+            file,
+            offset,
+            entsize,
+            start: 0,
+            end: count,
+            _entry: PhantomData,
+        })
+    }
+
+    fn empty(file: ElfFile<'a>) -> Self {
+        Table {
+            file,
+            offset: 0,
+            entsize: 0,
+            start: 0,
+            end: 0,
+            _entry: PhantomData,
+        }
+    }
+
+    /// Entry at `index` from the start of the table
+    pub fn get(&self, index: usize) -> Option<T> {
+        if (index as u64) < self.end {
+            self.file
+                .read_entry(self.offset + index as u64 * self.entsize)
+                .ok()
+        } else {
+            None
+        }
+    }
+
+    /// File offset of entry at `index` from the start of the table
+    pub fn offset_of(&self, index: usize) -> u64 {
+        self.offset + index as u64 * self.entsize
+    }
+}
+
+impl<'a, T: Entry> Iterator for Table<'a, T> {
+    type Item = T;
+    fn next(&mut self) -> Option<T> {
+        if self.start >= self.end {
+            return None;
+        }
+        self.start += 1;
+        self.get(self.start as usize - 1)
+    }
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = (self.end - self.start) as usize;
+        (len, Some(len))
+    }
+}
+
+impl<'a, T: Entry> DoubleEndedIterator for Table<'a, T> {
+    fn next_back(&mut self) -> Option<T> {
+        if self.start >= self.end {
+            return None;
+        }
+        self.end -= 1;
+        self.get(self.end as usize)
+    }
+}
+
+impl<'a, T: Entry> ExactSizeIterator for Table<'a, T> {}
+
+/// Symbol table and its associated string table
+#[derive(Clone)]
+pub struct SymbolTable<'a> {
+    file: ElfFile<'a>,
+    table: Table<'a, Sym64>,
+    strtab: Shdr64,
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Iterate over all symbols, including the initial null symbol
+    pub fn iter(&self) -> Table<'a, Sym64> {
+        self.table.clone()
+    }
+
+    /// Number of symbols
+    pub fn len(&self) -> usize {
+        self.table.len()
+    }
+
+    /// Whether the table has no symbols
+    pub fn is_empty(&self) -> bool {
+        self.table.len() == 0
+    }
+
+    /// Symbol at `index`
+    pub fn get(&self, index: usize) -> Result<Sym64, Error> {
+        self.table.get(index).ok_or(Error::NotFound)
+    }
+
+    /// Name of a symbol from this table
+    pub fn name(&self, sym: &Sym64) -> Result<&'a str, Error> {
+        self.file.string(&self.strtab, sym.st_name)
+    }
+
+    /// First symbol named `name`, and its index
+    pub fn by_name(&self, name: &str) -> Option<(usize, Sym64)> {
+        self.iter()
+            .enumerate()
+            .find(|(_, sym)| self.name(sym) == Ok(name))
+    }
+
+    /// Defined function or data symbol containing `address`
+    ///
+    /// The symbol starting nearest to `address` is preferred, so symbols starting exactly at
+    /// `address` win over symbols containing it, and nested symbols over their parents.
+    pub fn by_address(&self, address: u64) -> Option<Sym64> {
+        self.containing(address, |_| true)
+    }
+
+    /// Defined function or data symbol containing `address` that matches `filter`
+    pub fn containing(&self, address: u64, filter: impl Fn(&Sym64) -> bool) -> Option<Sym64> {
+        let mut best: Option<Sym64> = None;
+        for sym in self.iter() {
+            let sym_type = sym.st_type();
+            if sym_type != STT::FUNC && sym_type != STT::OBJECT && sym_type != STT::NOTYPE {
+                continue;
+            }
+            if sym.st_shndx == SHN::UNDEF || sym.st_name == 0 || !filter(&sym) {
+                continue;
+            }
+            let offset = address.wrapping_sub(sym.st_value);
+            if sym.st_value > address || (offset != 0 && offset >= sym.st_size) {
+                continue;
+            }
+            let better = best.map_or(true, |b| {
+                sym.st_value > b.st_value
+                    || (sym.st_value == b.st_value && b.st_type() == STT::NOTYPE)
+            });
+            if better {
+                best = Some(sym);
+            }
+        }
+        best
+    }
+}
+
+/// Relocation entry, with or without an explicit addend
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Relocation {
+    /// Location to apply the relocation
+    pub r_offset: u64,
+    /// Symbol table index
+    pub r_sym: u32,
+    /// Relocation type (machine specific)
+    ///
+    /// MIPS64 packs three types and a special symbol here, see
+    /// [`crate::mips::RelocationTypes`].
+    pub r_type: u32,
+    /// Constant addend, or `None` if the addend is stored at the relocated location
+    pub r_addend: Option<i64>,
+}
+
+/// Iterator over a table of relocations
+#[derive(Clone)]
+pub enum Relocations<'a> {
+    /// Relocations without addends
+    Rel(Table<'a, Rel64>),
+    /// Relocations with addends
+    Rela(Table<'a, Rela64>),
+    /// Android packed relocations, with or without addends
+    Android(packed::AndroidRelocations<'a>),
+    /// Relative relocations without addends
+    Relr {
+        /// Relocated offsets
+        offsets: packed::Relr<'a>,
+        /// Relative relocation type of the machine
+        r_type: u32,
+    },
+}
+
+impl<'a> Relocations<'a> {
+    /// Symbol index and type of `r_info`
+    ///
+    /// Little-endian MIPS64 stores `r_info` as a 32-bit symbol index followed by the bytes
+    /// `r_ssym`, `r_type3`, `r_type2` and `r_type`, so the generic split doesn't apply. The
+    /// type is returned in the big-endian layout, see [`crate::mips::RelocationTypes`].
+    fn split(file: &ElfFile, r_info: u64) -> (u32, u32) {
+        let (r_sym, r_type) = ((r_info >> 32) as u32, r_info as u32);
+        if !file.elf32 && !file.big_endian && file.ehdr.e_machine == EM::MIPS {
+            (r_type, r_sym.swap_bytes())
+        } else {
+            (r_sym, r_type)
+        }
+    }
+}
+
+impl<'a> Iterator for Relocations<'a> {
+    type Item = Relocation;
+    fn next(&mut self) -> Option<Relocation> {
+        match self {
+            Relocations::Rel(table) => table.next().map(|r| {
+                let (r_sym, r_type) = Self::split(&table.file, r.r_info);
+                Relocation {
+                    r_offset: r.r_offset,
+                    r_sym,
+                    r_type,
+                    r_addend: None,
+                }
+            }),
+            Relocations::Rela(table) => table.next().map(|r| {
+                let (r_sym, r_type) = Self::split(&table.file, r.r_info);
+                Relocation {
+                    r_offset: r.r_offset,
+                    r_sym,
+                    r_type,
+                    r_addend: Some(r.r_addend),
+                }
+            }),
+            Relocations::Android(packed) => packed.next(),
+            Relocations::Relr { offsets, r_type } => offsets.next().map(|r_offset| Relocation {
+                r_offset,
+                r_sym: 0,
+                r_type: *r_type,
+                r_addend: None,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::testutil::Builder;
+    use std::vec;
+
+    #[test]
+    fn header() {
+        for &(elf32, big_endian) in &[(false, false), (false, true), (true, false), (true, true)] {
+            let mut builder = Builder::new(elf32, big_endian, ET::DYN, EM::RISCV);
+            let text = builder.section(".text", SHT::PROGBITS, 0x1000, vec![0x13; 16]);
+            builder.segment(PT::LOAD, phdr::PF::R | phdr::PF::X, &[text]);
+            let data = builder.build();
+            let file = ElfFile::new(&data).unwrap();
+            assert_eq!(file.is_elf32(), elf32);
+            assert_eq!(file.is_big_endian(), big_endian);
+            assert!(file.ehdr().e_machine == EM::RISCV);
+            assert_eq!(file.phdrs().unwrap().len(), 1);
+            let (index, shdr) = file.section_by_name(".text").unwrap().unwrap();
+            assert_eq!(index, text);
+            assert_eq!(file.section_data(&shdr).unwrap(), &[0x13; 16][..]);
+            assert_eq!(file.vaddr_to_offset(0x1008, 8), Some(shdr.sh_offset + 8));
+            assert_eq!(file.vaddr_to_offset(0x1008, 9), None);
+        }
+        // A segment that wraps around the address space contains nothing
+        let mut builder = Builder::new(false, false, ET::DYN, EM::RISCV);
+        builder.segment(PT::LOAD, phdr::PF::R, &[]);
+        builder.phdr_mut(0).p_vaddr = u64::MAX - 0xff;
+        builder.phdr_mut(0).p_filesz = 0x1000;
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+        assert_eq!(file.vaddr_to_offset(u64::MAX - 0x10, 8), None);
+        assert_eq!(ElfFile::new(b"\x7fELF").err(), Some(Error::Truncated));
+        assert_eq!(ElfFile::new(&[0; 64]).err(), Some(Error::BadMagic));
+    }
+
+    #[test]
+    fn symbols() {
+        let mut builder = Builder::new(false, false, ET::REL, EM::X86_64);
+        let text = builder.section(".text", SHT::PROGBITS, 0, vec![0xc3; 16]);
+        let (strtab, names) = Builder::strings(&["first", "second", "table"]);
+        let strtab = builder.section(".strtab", SHT::STRTAB, 0, strtab);
+        let mut symtab = builder.sym(0, 0, 0, 0, 0);
+        symtab.extend(builder.sym(names[2], 0, 16, 0x10, text as u16));
+        symtab.extend(builder.sym(names[0], 0, 8, 0x12, text as u16));
+        symtab.extend(builder.sym(names[1], 8, 8, 0x12, text as u16));
+        let symtab = builder.section(".symtab", SHT::SYMTAB, 0, symtab);
+        builder.shdr_mut(symtab).sh_link = strtab as u32;
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+        let symbols = file.symbol_table().unwrap().unwrap();
+        assert_eq!(symbols.len(), 4);
+        let (index, sym) = symbols.by_name("second").unwrap();
+        assert_eq!(index, 3);
+        assert_eq!(sym.st_value, 8);
+        assert!(sym.st_type() == STT::FUNC);
+        let sym = symbols.by_address(4).unwrap();
+        assert_eq!(symbols.name(&sym), Ok("first"));
+        let sym = symbols.by_address(12).unwrap();
+        assert_eq!(symbols.name(&sym), Ok("second"));
+    }
+}
@@ -0,0 +1,613 @@
+//! Machine specific decoding of `e_flags`
+//!
+//! Most architectures pack several fields into [`EF`], rather than independent bits, so the
+//! flags are decoded into a structure per machine with [`EF::decode()`]. Formatting an
+//! [`EF`] wrapped with [`EF::for_target()`] gives the same description as `readelf -h`.
+
+use crate::ehdr::{EF, EM};
+use crate::Targeted;
+use core::fmt;
+
+enum_struct!(
+/// ARM floating point ABI
+pub struct ArmFloatAbi(u8) {
+    SOFT = 1 => "soft-float ABI",
+    HARD = 2 => "hard-float ABI",
+}
+);
+
+/// ARM flags
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct ArmFlags {
+    /// EABI version, 0 for files using the legacy GNU flags
+    pub eabi_version: u8,
+    /// Floating point ABI, if specified (EABI version 5)
+    pub float_abi: Option<ArmFloatAbi>,
+    /// Uses BE8 byte order for code
+    pub be8: bool,
+    /// Legacy flags, which are only meaningful without an EABI version
+    pub legacy: u32,
+}
+
+enum_struct!(
+/// RISC-V floating point ABI
+pub struct RiscvFloatAbi(u8) {
+    SOFT   = 0 => "soft-float ABI",
+    SINGLE = 1 => "single-float ABI",
+    DOUBLE = 2 => "double-float ABI",
+    QUAD   = 3 => "quad-float ABI",
+}
+);
+
+/// RISC-V flags
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct RiscvFlags {
+    /// Floating point ABI
+    pub float_abi: RiscvFloatAbi,
+    /// Uses compressed instructions
+    pub rvc: bool,
+    /// Uses the reduced register ABI
+    pub rve: bool,
+    /// Requires total store ordering
+    pub tso: bool,
+}
+
+enum_struct!(
+/// MIPS instruction set architecture
+pub struct MipsArch(u8) {
+    MIPS1    = 0x0 => "mips1",
+    MIPS2    = 0x1 => "mips2",
+    MIPS3    = 0x2 => "mips3",
+    MIPS4    = 0x3 => "mips4",
+    MIPS5    = 0x4 => "mips5",
+    MIPS32   = 0x5 => "mips32",
+    MIPS64   = 0x6 => "mips64",
+    MIPS32R2 = 0x7 => "mips32r2",
+    MIPS64R2 = 0x8 => "mips64r2",
+    MIPS32R6 = 0x9 => "mips32r6",
+    MIPS64R6 = 0xa => "mips64r6",
+}
+);
+
+enum_struct!(
+/// MIPS ABI
+pub struct MipsAbi(u8) {
+    NONE    = 0 => "no ABI",
+    O32     = 1 => "o32",
+    O64     = 2 => "o64",
+    EABI32  = 3 => "eabi32",
+    EABI64  = 4 => "eabi64",
+}
+);
+
+/// MIPS flags
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct MipsFlags {
+    /// Instruction set architecture
+    pub arch: MipsArch,
+    /// ABI (`EF_MIPS_ABI`)
+    pub abi: MipsAbi,
+    /// Uses the N32 ABI (`EF_MIPS_ABI2`)
+    pub n32: bool,
+    /// Machine variant (`EF_MIPS_MACH`), 0 if none
+    pub mach: u8,
+    /// Position independent code
+    pub pic: bool,
+    /// Calls through PIC sequences
+    pub cpic: bool,
+    /// Assembler must not reorder instructions
+    pub noreorder: bool,
+    /// Uses the IEEE 754-2008 NaN encoding
+    pub nan2008: bool,
+    /// Uses 64-bit floating point registers
+    pub fp64: bool,
+}
+
+enum_struct!(
+/// PowerPC 64-bit ABI
+pub struct Ppc64Abi(u8) {
+    UNSPECIFIED = 0 => "unspecified or ELFv1 ABI",
+    ELFV1       = 1 => "abiv1",
+    ELFV2       = 2 => "abiv2",
+}
+);
+
+enum_struct!(
+/// AVR architecture
+pub struct AvrArch(u8) {
+    AVR1    = 1   => "avr:1",
+    AVR2    = 2   => "avr:2",
+    AVR25   = 25  => "avr:25",
+    AVR3    = 3   => "avr:3",
+    AVR31   = 31  => "avr:31",
+    AVR35   = 35  => "avr:35",
+    AVR4    = 4   => "avr:4",
+    AVR5    = 5   => "avr:5",
+    AVR51   = 51  => "avr:51",
+    AVR6    = 6   => "avr:6",
+    AVRTINY = 100 => "avr:100",
+    XMEGA1  = 101 => "avr:101",
+    XMEGA2  = 102 => "avr:102",
+    XMEGA3  = 103 => "avr:103",
+    XMEGA4  = 104 => "avr:104",
+    XMEGA5  = 105 => "avr:105",
+    XMEGA6  = 106 => "avr:106",
+    XMEGA7  = 107 => "avr:107",
+}
+);
+
+/// AVR flags
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AvrFlags {
+    /// Architecture
+    pub arch: AvrArch,
+    /// Linker relaxation is possible
+    pub link_relax: bool,
+}
+
+enum_struct!(
+/// SPARC V9 memory model
+pub struct SparcMemoryModel(u8) {
+    TSO = 0 => "tso",
+    PSO = 1 => "pso",
+    RMO = 2 => "rmo",
+}
+);
+
+/// SPARC flags
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct SparcFlags {
+    /// Memory model, SPARC V9 only
+    pub memory_model: Option<SparcMemoryModel>,
+    /// Uses V8+ features
+    pub v8plus: bool,
+    /// Uses UltraSPARC I extensions
+    pub ultrasparc1: bool,
+    /// Uses HAL R1 extensions
+    pub hal_r1: bool,
+    /// Uses UltraSPARC III extensions
+    pub ultrasparc3: bool,
+}
+
+/// Xtensa flags
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct XtensaFlags {
+    /// Machine variant (`EF_XTENSA_MACH`)
+    pub mach: u8,
+    /// Instructions are aligned to allow relaxation
+    pub xt_insn: bool,
+    /// Literals are kept separate to allow relaxation
+    pub xt_lit: bool,
+}
+
+/// Hexagon flags
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct HexagonFlags {
+    /// Architecture version (`EF_HEXAGON_MACH`), such as `0x68` for V68
+    pub mach: u16,
+}
+
+impl HexagonFlags {
+    /// Architecture version number, such as 68 for V68
+    pub fn version(&self) -> Option<u16> {
+        match self.mach {
+            1..=4 => Some(self.mach + 1),
+            5 => Some(55),
+            0x60..=0x62
+            | 0x65..=0x69
+            | 0x71
+            | 0x73
+            | 0x75
+            | 0x77
+            | 0x79
+            | 0x81
+            | 0x83
+            | 0x85
+            | 0x87
+            | 0x89
+            | 0x91 => Some((self.mach >> 4) * 10 + (self.mach & 0xf)),
+            _ => None,
+        }
+    }
+}
+
+/// Decoded `e_flags`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Flags {
+    /// [`EM::ARM`] flags
+    Arm(ArmFlags),
+    /// [`EM::RISCV`] flags
+    Riscv(RiscvFlags),
+    /// [`EM::MIPS`] flags
+    Mips(MipsFlags),
+    /// [`EM::PPC64`] ABI version
+    Ppc64(Ppc64Abi),
+    /// [`EM::AVR`] flags
+    Avr(AvrFlags),
+    /// [`EM::SPARC`] and [`EM::SPARCV9`] flags
+    Sparc(SparcFlags),
+    /// [`EM::XTENSA`] flags
+    Xtensa(XtensaFlags),
+    /// [`EM::HEXAGON`] flags
+    Hexagon(HexagonFlags),
+    /// Flags of machines without a decoder
+    Other(EF),
+}
+
+// Field masks
+const ARM_EABIMASK: u32 = 0xff000000;
+const ARM_LEGACY: u32 = 0xfff;
+const RISCV_FLOAT_ABI: u32 = 0x6;
+const MIPS_ARCH: u32 = 0xf0000000;
+const MIPS_ABI: u32 = 0xf000;
+const MIPS_MACH: u32 = 0xff0000;
+const PPC64_ABI: u32 = 0x3;
+const AVR_ARCH: u32 = 0x7f;
+const AVR_LINK_RELAX: u32 = 0x80;
+const SPARCV9_MM: u32 = 0x3;
+const XTENSA_MACH: u32 = 0xf;
+const XTENSA_XT_INSN: u32 = 0x100;
+const XTENSA_XT_LIT: u32 = 0x200;
+const HEXAGON_MACH: u32 = 0x3ff;
+
+impl EF {
+    /// Decode the flags of a file for `machine`
+    pub fn decode(self, machine: EM) -> Flags {
+        let bits = u32::from(self);
+        let has = |flag: EF| self & flag == flag;
+        match machine {
+            EM::ARM => {
+                let eabi_version = ((bits & ARM_EABIMASK) >> 24) as u8;
+                let float_abi = if eabi_version < 5 {
+                    None
+                } else if has(EF::ARM_ABI_FLOAT_HARD) {
+                    Some(ArmFloatAbi::HARD)
+                } else if has(EF::ARM_ABI_FLOAT_SOFT) {
+                    Some(ArmFloatAbi::SOFT)
+                } else {
+                    None
+                };
+                Flags::Arm(ArmFlags {
+                    eabi_version,
+                    float_abi,
+                    be8: eabi_version >= 4 && has(EF::ARM_BE8),
+                    legacy: if eabi_version == 0 {
+                        bits & ARM_LEGACY
+                    } else {
+                        0
+                    },
+                })
+            }
+            EM::RISCV => Flags::Riscv(RiscvFlags {
+                float_abi: RiscvFloatAbi::from(((bits & RISCV_FLOAT_ABI) >> 1) as u8),
+                rvc: has(EF::RISCV_RVC),
+                rve: has(EF::RISCV_RVE),
+                tso: has(EF::RISCV_TSO),
+            }),
+            EM::MIPS => Flags::Mips(MipsFlags {
+                arch: MipsArch::from(((bits & MIPS_ARCH) >> 28) as u8),
+                abi: MipsAbi::from(((bits & MIPS_ABI) >> 12) as u8),
+                n32: has(EF::MIPS_ABI2),
+                mach: ((bits & MIPS_MACH) >> 16) as u8,
+                pic: has(EF::MIPS_PIC),
+                cpic: has(EF::MIPS_CPIC),
+                noreorder: has(EF::MIPS_NOREORDER),
+                nan2008: has(EF::MIPS_NAN2008),
+                fp64: has(EF::MIPS_FP64),
+            }),
+            EM::PPC64 => Flags::Ppc64(Ppc64Abi::from((bits & PPC64_ABI) as u8)),
+            EM::AVR => Flags::Avr(AvrFlags {
+                arch: AvrArch::from((bits & AVR_ARCH) as u8),
+                link_relax: bits & AVR_LINK_RELAX != 0,
+            }),
+            EM::SPARC | EM::SPARCV9 => Flags::Sparc(SparcFlags {
+                memory_model: if machine == EM::SPARCV9 {
+                    Some(SparcMemoryModel::from((bits & SPARCV9_MM) as u8))
+                } else {
+                    None
+                },
+                v8plus: has(EF::SPARC_32PLUS),
+                ultrasparc1: has(EF::SPARC_SUN_US1),
+                hal_r1: has(EF::SPARC_HAL_R1),
+                ultrasparc3: has(EF::SPARC_SUN_US3),
+            }),
+            EM::XTENSA => Flags::Xtensa(XtensaFlags {
+                mach: (bits & XTENSA_MACH) as u8,
+                xt_insn: bits & XTENSA_XT_INSN != 0,
+                xt_lit: bits & XTENSA_XT_LIT != 0,
+            }),
+            EM::HEXAGON => Flags::Hexagon(HexagonFlags {
+                mach: (bits & HEXAGON_MACH) as u16,
+            }),
+            _ => Flags::Other(self),
+        }
+    }
+}
+
+/// Names of the legacy ARM flags, in bit order
+const ARM_LEGACY_NAMES: [&str; 12] = [
+    "relocatable executable",
+    "has entry point",
+    "interworking enabled",
+    "uses APCS/26",
+    "uses APCS/float",
+    "position independent",
+    "8 bit structure alignment",
+    "uses new ABI",
+    "uses old ABI",
+    "software FP",
+    "VFP",
+    "Maverick FP",
+];
+
+/// Names of the MIPS machine variants, by `EF_MIPS_MACH` value
+fn mips_mach_name(mach: u8) -> Option<&'static str> {
+    Some(match mach {
+        0x81 => "3900",
+        0x82 => "4010",
+        0x83 => "4100",
+        0x85 => "4650",
+        0x87 => "4120",
+        0x88 => "4111",
+        0x8a => "sb1",
+        0x8b => "octeon",
+        0x8c => "xlr",
+        0x8d => "octeon2",
+        0x8e => "octeon3",
+        0x91 => "5400",
+        0x92 => "5900",
+        0x98 => "5500",
+        0x99 => "9000",
+        0xa0 => "loongson-2e",
+        0xa1 => "loongson-2f",
+        0xa2 => "gs464",
+        0xa3 => "gs464e",
+        0xa4 => "gs264e",
+        _ => return None,
+    })
+}
+
+impl fmt::Display for Flags {
+    /// Comma separated description of the flags, in the style of `readelf -h`
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        // Write each part with a separator
+        let mut first = true;
+        let mut part = |f: &mut fmt::Formatter, part: &dyn fmt::Display| {
+            if !first {
+                write!(f, ", ")?;
+            }
+            first = false;
+            write!(f, "{}", part)
+        };
+        match self {
+            Flags::Arm(arm) => {
+                if arm.eabi_version == 0 {
+                    part(f, &"GNU EABI")?;
+                    for (bit, name) in ARM_LEGACY_NAMES.iter().enumerate() {
+                        if arm.legacy & (1 << bit) != 0 {
+                            part(f, name)?;
+                        }
+                    }
+                    return Ok(());
+                }
+                if arm.eabi_version <= 5 {
+                    part(f, &format_args!("Version{} EABI", arm.eabi_version))?;
+                } else {
+                    part(f, &"<unrecognized EABI>")?;
+                }
+                if let Some(float_abi) = arm.float_abi {
+                    part(f, &float_abi)?;
+                }
+                if arm.be8 {
+                    part(f, &"BE8")?;
+                }
+            }
+            Flags::Riscv(riscv) => {
+                if riscv.rvc {
+                    part(f, &"RVC")?;
+                }
+                if riscv.rve {
+                    part(f, &"RVE")?;
+                }
+                if riscv.tso {
+                    part(f, &"TSO")?;
+                }
+                part(f, &riscv.float_abi)?;
+            }
+            Flags::Mips(mips) => {
+                if mips.noreorder {
+                    part(f, &"noreorder")?;
+                }
+                if mips.pic {
+                    part(f, &"pic")?;
+                }
+                if mips.cpic {
+                    part(f, &"cpic")?;
+                }
+                if mips.n32 {
+                    part(f, &"abi2")?;
+                }
+                if mips.nan2008 {
+                    part(f, &"nan2008")?;
+                }
+                if mips.fp64 {
+                    part(f, &"fp64")?;
+                }
+                if let Some(mach) = mips_mach_name(mips.mach) {
+                    part(f, &mach)?;
+                }
+                if mips.abi != MipsAbi::NONE {
+                    part(f, &mips.abi)?;
+                }
+                part(f, &mips.arch)?;
+            }
+            Flags::Ppc64(abi) => {
+                if *abi != Ppc64Abi::UNSPECIFIED {
+                    part(f, abi)?;
+                }
+            }
+            Flags::Avr(avr) => {
+                part(f, &avr.arch)?;
+                if avr.link_relax {
+                    part(f, &"link-relax")?;
+                }
+            }
+            Flags::Sparc(sparc) => {
+                if sparc.v8plus {
+                    part(f, &"v8+")?;
+                }
+                if sparc.ultrasparc1 {
+                    part(f, &"ultrasparcI")?;
+                }
+                if sparc.hal_r1 {
+                    part(f, &"halr1")?;
+                }
+                if sparc.ultrasparc3 {
+                    part(f, &"ultrasparcIII")?;
+                }
+                if let Some(memory_model) = sparc.memory_model {
+                    part(f, &memory_model)?;
+                }
+            }
+            Flags::Xtensa(xtensa) => {
+                if xtensa.mach == 0 {
+                    part(f, &"Xtensa")?;
+                }
+                if xtensa.xt_insn {
+                    part(f, &"XT_INSN")?;
+                }
+                if xtensa.xt_lit {
+                    part(f, &"XT_LIT")?;
+                }
+            }
+            Flags::Hexagon(hexagon) => match hexagon.version() {
+                Some(version) => part(f, &format_args!("Hexagon V{}", version))?,
+                None => part(f, &"Hexagon unknown machine")?,
+            },
+            Flags::Other(_) => {}
+        }
+        Ok(())
+    }
+}
+
+impl fmt::Display for Targeted<EF> {
+    /// Raw value followed by the decoded flags, like the `Flags:` line of `readelf -h`
+    ///
+    /// As with `readelf`, flags of zero aren't decoded.
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "0x{:x}", u32::from(self.value))?;
+        if self.value == EF::NONE {
+            return Ok(());
+        }
+        let flags = self.value.decode(self.machine);
+        let mut decoded = Decoded { empty: true };
+        fmt::write(&mut decoded, format_args!("{}", flags))?;
+        if !decoded.empty {
+            write!(f, ", {}", flags)?;
+        }
+        Ok(())
+    }
+}
+
+/// Writer that only records whether anything was written
+struct Decoded {
+    empty: bool,
+}
+
+impl fmt::Write for Decoded {
+    fn write_str(&mut self, s: &str) -> fmt::Result {
+        self.empty &= s.is_empty();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::EIOSABI;
+    use std::format;
+
+    fn readelf(machine: EM, flags: u32) -> std::string::String {
+        let flags = EF::from(flags).for_target(machine, EIOSABI::SYSV);
+        format!("{}", flags)
+    }
+
+    #[test]
+    fn arm() {
+        let flags = EF::from(0x5000400).decode(EM::ARM);
+        let arm = match flags {
+            Flags::Arm(arm) => arm,
+            _ => panic!(),
+        };
+        assert_eq!(arm.eabi_version, 5);
+        assert_eq!(arm.float_abi, Some(ArmFloatAbi::HARD));
+        assert!(!arm.be8);
+        assert_eq!(
+            readelf(EM::ARM, 0x5000400),
+            "0x5000400, Version5 EABI, hard-float ABI"
+        );
+        assert_eq!(
+            readelf(EM::ARM, 0x5800200),
+            "0x5800200, Version5 EABI, soft-float ABI, BE8"
+        );
+        assert_eq!(
+            readelf(EM::ARM, 0x6),
+            "0x6, GNU EABI, has entry point, interworking enabled"
+        );
+    }
+
+    #[test]
+    fn riscv() {
+        assert_eq!(readelf(EM::RISCV, 0x5), "0x5, RVC, double-float ABI");
+        assert_eq!(readelf(EM::RISCV, 0x0), "0x0");
+        assert_eq!(
+            readelf(EM::RISCV, 0x19),
+            "0x19, RVC, RVE, TSO, soft-float ABI"
+        );
+        // Like readelf, the float ABI comes after RVE and TSO
+        assert_eq!(
+            readelf(EM::RISCV, 0x1d),
+            "0x1d, RVC, RVE, TSO, double-float ABI"
+        );
+        assert_eq!(readelf(EM::RISCV, 0x13), "0x13, RVC, TSO, single-float ABI");
+    }
+
+    #[test]
+    fn mips() {
+        let flags = EF::from(0x70001007).decode(EM::MIPS);
+        let mips = match flags {
+            Flags::Mips(mips) => mips,
+            _ => panic!(),
+        };
+        assert_eq!(mips.arch, MipsArch::MIPS32R2);
+        assert_eq!(mips.abi, MipsAbi::O32);
+        assert!(mips.pic && mips.cpic && mips.noreorder && !mips.n32);
+        assert_eq!(
+            readelf(EM::MIPS, 0x70001007),
+            "0x70001007, noreorder, pic, cpic, o32, mips32r2"
+        );
+        assert_eq!(
+            readelf(EM::MIPS, 0x80000027),
+            "0x80000027, noreorder, pic, cpic, abi2, mips64r2"
+        );
+        match EF::from(0x80000027).decode(EM::MIPS) {
+            Flags::Mips(mips) => assert!(mips.n32 && mips.abi == MipsAbi::NONE),
+            _ => panic!(),
+        }
+    }
+
+    #[test]
+    fn others() {
+        assert_eq!(readelf(EM::PPC64, 0x2), "0x2, abiv2");
+        assert_eq!(readelf(EM::PPC64, 0x0), "0x0");
+        assert_eq!(readelf(EM::AVR, 0x85), "0x85, avr:5, link-relax");
+        assert_eq!(readelf(EM::SPARCV9, 0x202), "0x202, ultrasparcI, rmo");
+        assert_eq!(readelf(EM::HEXAGON, 0x68), "0x68, Hexagon V68");
+        assert_eq!(readelf(EM::HEXAGON, 0x4), "0x4, Hexagon V5");
+        assert_eq!(readelf(EM::HEXAGON, 0x73), "0x73, Hexagon V73");
+        assert_eq!(readelf(EM::HEXAGON, 0x6a), "0x6a, Hexagon unknown machine");
+        assert_eq!(readelf(EM::XTENSA, 0x300), "0x300, Xtensa, XT_INSN, XT_LIT");
+        assert_eq!(readelf(EM::X86_64, 0x0), "0x0");
+    }
+}
@@ -0,0 +1,578 @@
+//! Intel HEX and Motorola S-record files
+//!
+//! Flash programmers take these text formats, which list the data to program as records
+//! of up to 255 bytes, each with its address and a checksum. [`ElfFile::intel_hex()`] and
+//! [`ElfFile::srec()`] write the regions selected like [`ElfFile::binary()`] as records,
+//! followed by a start address record for `e_entry`.
+//!
+//! [`IntelHex`] and [`Srec`] parse the records back, and [`intel_hex_to_elf()`] and
+//! [`srec_to_elf()`] convert a file into a minimal ELF file with [`write_elf()`].
+
+use crate::binary::{write_elf, BinaryOptions};
+use crate::{Ehdr64, ElfFile, Error};
+use core::convert::TryFrom;
+
+/// Data bytes per record written
+const RECORD_LEN: usize = 16;
+
+/// Data of a record, and its address
+#[derive(Clone, Copy)]
+pub struct Chunk {
+    /// Address of the first byte
+    pub address: u64,
+    len: usize,
+    bytes: [u8; 255],
+}
+
+impl Chunk {
+    fn new(address: u64, data: &[u8]) -> Self {
+        let mut bytes = [0; 255];
+        bytes[..data.len()].copy_from_slice(data);
+        Chunk {
+            address,
+            len: data.len(),
+            bytes,
+        }
+    }
+
+    /// Contents
+    pub fn data(&self) -> &[u8] {
+        &self.bytes[..self.len]
+    }
+}
+
+impl AsRef<[u8]> for Chunk {
+    fn as_ref(&self) -> &[u8] {
+        self.data()
+    }
+}
+
+/// Record of an Intel HEX or S-record file, with extended addresses applied
+// Data is held inline, as there's no allocator to box it
+#[allow(clippy::large_enum_variant)]
+#[derive(Clone, Copy)]
+pub enum Record {
+    /// Data to program
+    Data(Chunk),
+    /// Entry point
+    Start(u64),
+}
+
+/// `line` without leading and trailing ASCII whitespace
+fn trim(mut line: &[u8]) -> &[u8] {
+    while let [first, rest @ ..] = line {
+        if !first.is_ascii_whitespace() {
+            break;
+        }
+        line = rest;
+    }
+    while let [rest @ .., last] = line {
+        if !last.is_ascii_whitespace() {
+            break;
+        }
+        line = rest;
+    }
+    line
+}
+
+/// Next non-empty line of `text`, without surrounding whitespace
+fn next_line<'a>(text: &mut &'a [u8]) -> Option<&'a [u8]> {
+    while !text.is_empty() {
+        let end = text.iter().position(|&b| b == b'\n').unwrap_or(text.len());
+        let line = trim(&text[..end]);
+        *text = &text[(end + 1).min(text.len())..];
+        if !line.is_empty() {
+            return Some(line);
+        }
+    }
+    None
+}
+
+/// Decode the hexadecimal digits `hex` into `bytes`, returning the number of bytes
+fn decode(hex: &[u8], bytes: &mut [u8]) -> Result<usize, Error> {
+    let len = hex.len() / 2;
+    if hex.len() % 2 != 0 || len > bytes.len() {
+        return Err(Error::Malformed);
+    }
+    let digit = |c: u8| {
+        (c as char)
+            .to_digit(16)
+            .map(|d| d as u8)
+            .ok_or(Error::Malformed)
+    };
+    for (byte, pair) in bytes.iter_mut().zip(hex.chunks(2)) {
+        *byte = digit(pair[0])? << 4 | digit(pair[1])?;
+    }
+    Ok(len)
+}
+
+/// Big-endian value of `bytes`
+fn big_endian(bytes: &[u8]) -> u64 {
+    bytes
+        .iter()
+        .fold(0, |value, &byte| value << 8 | u64::from(byte))
+}
+
+/// Records of an Intel HEX file, up to the end of file record
+///
+/// Extended segment and linear address records are applied to the following data
+/// records, and both kinds of start address record give [`Record::Start`].
+#[derive(Clone)]
+pub struct IntelHex<'a> {
+    text: &'a [u8],
+    base: u64,
+}
+
+impl<'a> IntelHex<'a> {
+    /// Records of the file `text`
+    pub fn new(text: &'a [u8]) -> Self {
+        IntelHex { text, base: 0 }
+    }
+
+    fn next_record(&mut self) -> Result<Option<Record>, Error> {
+        while let Some(line) = next_line(&mut self.text) {
+            let hex = line.strip_prefix(b":").ok_or(Error::Malformed)?;
+            let mut bytes = [0; 260];
+            let len = decode(hex, &mut bytes)?;
+            let bytes = &bytes[..len];
+            if len < 5 || bytes[0] as usize + 5 != len {
+                return Err(Error::Malformed);
+            }
+            if bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0 {
+                return Err(Error::Malformed);
+            }
+            let offset = big_endian(&bytes[1..3]);
+            let data = &bytes[4..len - 1];
+            match (bytes[3], data.len()) {
+                (0, _) => return Ok(Some(Record::Data(Chunk::new(self.base + offset, data)))),
+                (1, _) => {
+                    self.text = &[];
+                    return Ok(None);
+                }
+                (2, 2) => self.base = big_endian(data) << 4,
+                (3, 4) => {
+                    let (cs, ip) = (big_endian(&data[..2]), big_endian(&data[2..]));
+                    return Ok(Some(Record::Start((cs << 4) + ip)));
+                }
+                (4, 2) => self.base = big_endian(data) << 16,
+                (5, 4) => return Ok(Some(Record::Start(big_endian(data)))),
+                _ => return Err(Error::Malformed),
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a> Iterator for IntelHex<'a> {
+    type Item = Result<Record, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_record();
+        if result.is_err() {
+            self.text = &[];
+        }
+        result.transpose()
+    }
+}
+
+/// Records of a Motorola S-record file, up to the termination record
+///
+/// Header and record count records are skipped, and the termination record gives
+/// [`Record::Start`].
+#[derive(Clone)]
+pub struct Srec<'a> {
+    text: &'a [u8],
+}
+
+impl<'a> Srec<'a> {
+    /// Records of the file `text`
+    pub fn new(text: &'a [u8]) -> Self {
+        Srec { text }
+    }
+
+    fn next_record(&mut self) -> Result<Option<Record>, Error> {
+        while let Some(line) = next_line(&mut self.text) {
+            let (kind, hex) = match line {
+                [b'S', kind, hex @ ..] => (*kind, hex),
+                _ => return Err(Error::Malformed),
+            };
+            let mut bytes = [0; 256];
+            let len = decode(hex, &mut bytes)?;
+            let bytes = &bytes[..len];
+            let address_len = match kind {
+                b'0' | b'1' | b'5' | b'9' => 2,
+                b'2' | b'6' | b'8' => 3,
+                b'3' | b'7' => 4,
+                _ => return Err(Error::Malformed),
+            };
+            if len < address_len + 2 || bytes[0] as usize + 1 != len {
+                return Err(Error::Malformed);
+            }
+            if bytes.iter().fold(0u8, |sum, &byte| sum.wrapping_add(byte)) != 0xff {
+                return Err(Error::Malformed);
+            }
+            let address = big_endian(&bytes[1..1 + address_len]);
+            let data = &bytes[1 + address_len..len - 1];
+            match kind {
+                b'1' | b'2' | b'3' => return Ok(Some(Record::Data(Chunk::new(address, data)))),
+                b'7' | b'8' | b'9' => {
+                    self.text = &[];
+                    return Ok(Some(Record::Start(address)));
+                }
+                _ => {}
+            }
+        }
+        Ok(None)
+    }
+}
+
+impl<'a> Iterator for Srec<'a> {
+    type Item = Result<Record, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        let result = self.next_record();
+        if result.is_err() {
+            self.text = &[];
+        }
+        result.transpose()
+    }
+}
+
+/// Text of a record being written, and the sum of its bytes
+struct Line {
+    text: [u8; 2 * 260 + 4],
+    len: usize,
+    sum: u8,
+}
+
+impl Line {
+    fn new(start: &[u8]) -> Self {
+        let mut text = [0; 2 * 260 + 4];
+        text[..start.len()].copy_from_slice(start);
+        Line {
+            text,
+            len: start.len(),
+            sum: 0,
+        }
+    }
+
+    fn bytes(&mut self, bytes: &[u8]) {
+        const DIGITS: &[u8; 16] = b"0123456789ABCDEF";
+        for &byte in bytes {
+            self.text[self.len] = DIGITS[(byte >> 4) as usize];
+            self.text[self.len + 1] = DIGITS[(byte & 0xf) as usize];
+            self.len += 2;
+            self.sum = self.sum.wrapping_add(byte);
+        }
+    }
+
+    /// Append `checksum` and the end of line
+    fn finish(&mut self, checksum: u8) -> &[u8] {
+        self.bytes(&[checksum]);
+        self.text[self.len] = b'\n';
+        &self.text[..self.len + 1]
+    }
+}
+
+/// Write an Intel HEX record of type `kind`
+fn ihex_record(
+    f: &mut impl FnMut(&[u8]) -> Result<(), Error>,
+    kind: u8,
+    offset: u16,
+    data: &[u8],
+) -> Result<(), Error> {
+    let mut line = Line::new(b":");
+    line.bytes(&[data.len() as u8]);
+    line.bytes(&offset.to_be_bytes());
+    line.bytes(&[kind]);
+    line.bytes(data);
+    let checksum = line.sum.wrapping_neg();
+    f(line.finish(checksum))
+}
+
+/// Write an S-record of type `kind` with an `address_len` byte address
+fn srec_record(
+    f: &mut impl FnMut(&[u8]) -> Result<(), Error>,
+    kind: u8,
+    address_len: usize,
+    address: u32,
+    data: &[u8],
+) -> Result<(), Error> {
+    let mut line = Line::new(&[b'S', b'0' + kind]);
+    line.bytes(&[(address_len + data.len() + 1) as u8]);
+    line.bytes(&address.to_be_bytes()[4 - address_len..]);
+    line.bytes(data);
+    let checksum = !line.sum;
+    f(line.finish(checksum))
+}
+
+/// 32-bit address, as the formats have no larger ones
+fn address32(address: u64) -> Result<u32, Error> {
+    u32::try_from(address).map_err(|_| Error::Unsupported)
+}
+
+/// 32-bit address of the last of `len` bytes at `address`, where `len` isn't 0
+fn last_address32(address: u64, len: usize) -> Result<u32, Error> {
+    address32(
+        address
+            .checked_add(len as u64 - 1)
+            .ok_or(Error::Unsupported)?,
+    )
+}
+
+impl<'a> ElfFile<'a> {
+    /// Write the regions selected by `options` as an Intel HEX file to `f`, a line at a
+    /// time
+    ///
+    /// Extended linear address records are written whenever the upper 16 bits of the
+    /// address change, and a start linear address record if `e_entry` isn't 0.
+    pub fn intel_hex(
+        &self,
+        options: &BinaryOptions,
+        mut f: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut upper = 0;
+        for region in self.regions(options) {
+            let region = region?;
+            let (mut address, mut data) = (region.address, region.data);
+            if !data.is_empty() {
+                last_address32(address, data.len())?;
+            }
+            while !data.is_empty() {
+                if address >> 16 != upper {
+                    upper = address >> 16;
+                    ihex_record(&mut f, 4, 0, &(upper as u16).to_be_bytes())?;
+                }
+                // Records don't cross 64KiB boundaries
+                let len = RECORD_LEN
+                    .min(data.len())
+                    .min(0x10000 - (address & 0xffff) as usize);
+                ihex_record(&mut f, 0, address as u16, &data[..len])?;
+                address += len as u64;
+                data = &data[len..];
+            }
+        }
+        let entry = self.ehdr().e_entry;
+        if entry != 0 {
+            ihex_record(&mut f, 5, 0, &address32(entry)?.to_be_bytes())?;
+        }
+        ihex_record(&mut f, 1, 0, &[])
+    }
+
+    /// Write the regions selected by `options` as a Motorola S-record file to `f`, a line
+    /// at a time
+    ///
+    /// Data records are S1, S2 or S3, for 16, 24 or 32-bit addresses, whichever is the
+    /// smallest to hold every address and `e_entry`. A record count and the matching
+    /// termination record with `e_entry` follow.
+    pub fn srec(
+        &self,
+        options: &BinaryOptions,
+        mut f: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let entry = self.ehdr().e_entry;
+        let mut highest = entry;
+        for region in self.regions(options) {
+            let region = region?;
+            if !region.data.is_empty() {
+                let last = last_address32(region.address, region.data.len())?;
+                highest = highest.max(last.into());
+            }
+        }
+        let (kind, address_len) = match address32(highest)? {
+            0..=0xffff => (1, 2),
+            0x1_0000..=0xff_ffff => (2, 3),
+            _ => (3, 4),
+        };
+
+        // Header without a module name
+        srec_record(&mut f, 0, 2, 0, &[])?;
+        let mut count = 0;
+        for region in self.regions(options) {
+            let region = region?;
+            let mut address = region.address as u32;
+            for data in region.data.chunks(RECORD_LEN) {
+                srec_record(&mut f, kind, address_len, address, data)?;
+                address = address.wrapping_add(data.len() as u32);
+                count += 1;
+            }
+        }
+        match count {
+            0..=0xffff => srec_record(&mut f, 5, 2, count, &[])?,
+            0x1_0000..=0xff_ffff => srec_record(&mut f, 6, 3, count, &[])?,
+            _ => {}
+        }
+        srec_record(&mut f, 10 - kind, address_len, entry as u32, &[])
+    }
+}
+
+/// Write the data of `records` as a minimal ELF file, with the entry point of the last
+/// start address record
+fn to_elf(
+    records: impl Iterator<Item = Result<Record, Error>> + Clone,
+    ehdr: &Ehdr64,
+    f: impl FnMut(&[u8]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut header = *ehdr;
+    for record in records.clone() {
+        if let Record::Start(entry) = record? {
+            header.e_entry = entry;
+        }
+    }
+    let chunks = records.filter_map(|record| match record {
+        Ok(Record::Data(chunk)) => Some(Ok((chunk.address, chunk))),
+        Ok(Record::Start(_)) => None,
+        Err(err) => Some(Err(err)),
+    });
+    write_elf(&header, chunks, f)
+}
+
+/// Convert the Intel HEX file `text` into a minimal ELF file with a `PT::LOAD` segment per
+/// contiguous region, see [`write_elf()`]
+pub fn intel_hex_to_elf(
+    text: &[u8],
+    ehdr: &Ehdr64,
+    f: impl FnMut(&[u8]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    to_elf(IntelHex::new(text), ehdr, f)
+}
+
+/// Convert the S-record file `text` into a minimal ELF file with a `PT::LOAD` segment per
+/// contiguous region, see [`write_elf()`]
+pub fn srec_to_elf(
+    text: &[u8],
+    ehdr: &Ehdr64,
+    f: impl FnMut(&[u8]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    to_elf(Srec::new(text), ehdr, f)
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::binary::Region;
+    use crate::ehdr::{EIC, EID, EM, ET};
+    use crate::phdr::{PF, PT};
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    /// Text at 0x8000000 and data crossing a 64KiB boundary at 0x801fff8
+    fn image() -> Vec<u8> {
+        let mut builder = Builder::new(true, false, ET::EXEC, EM::ARM);
+        builder.ehdr.e_entry = 0x0800_0005;
+        let text = (0..20).collect();
+        let text = builder.section(".text", SHT::PROGBITS, 0x0800_0000, text);
+        let data = builder.section(".data", SHT::PROGBITS, 0x0801_fff8, vec![0xaa; 16]);
+        builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+        builder.segment(PT::LOAD, PF::R | PF::W, &[data]);
+        builder.build()
+    }
+
+    fn collect(
+        write: impl FnOnce(&mut dyn FnMut(&[u8]) -> Result<(), Error>) -> Result<(), Error>,
+    ) -> Vec<u8> {
+        let mut text = Vec::new();
+        write(&mut |line| {
+            text.extend(line);
+            Ok(())
+        })
+        .unwrap();
+        text
+    }
+
+    /// Regions of an ELF file
+    fn regions(data: &[u8]) -> Vec<(u64, Vec<u8>)> {
+        let file = ElfFile::new(data).unwrap();
+        file.regions(&BinaryOptions::default())
+            .map(|region| {
+                let Region { address, data } = region.unwrap();
+                (address, data.to_vec())
+            })
+            .collect()
+    }
+
+    #[test]
+    fn records() {
+        let mut data = [0; 16];
+        decode(b"214601360121470136007EFE09D21901", &mut data).unwrap();
+        let text = collect(|f| ihex_record(&mut |line| f(line), 0, 0x100, &data));
+        assert_eq!(text, b":10010000214601360121470136007EFE09D2190140\n");
+        let mut data = [0; 16];
+        data[..3].copy_from_slice(&[0x0a, 0x0a, 0x0d]);
+        let text = collect(|f| srec_record(&mut |line| f(line), 1, 2, 0x7af0, &data));
+        assert_eq!(text, b"S1137AF00A0A0D0000000000000000000000000061\n");
+
+        let mut records = IntelHex::new(b":0400000500001234B1\n:0100000200FD\n");
+        assert!(matches!(records.next(), Some(Ok(Record::Start(0x1234)))));
+        assert_eq!(records.next().unwrap().err(), Some(Error::Malformed));
+        assert!(records.next().is_none());
+    }
+
+    #[test]
+    fn intel_hex() {
+        let data = image();
+        let file = ElfFile::new(&data).unwrap();
+        let text = collect(|f| file.intel_hex(&BinaryOptions::default(), f));
+        let lines: Vec<_> = text.split(|&b| b == b'\n').collect();
+        assert_eq!(lines[0], b":020000040800F2");
+        assert_eq!(lines[1], b":10000000000102030405060708090A0B0C0D0E0F78");
+        // The second region is split at the 64KiB boundary
+        assert_eq!(lines[3], b":020000040801F1");
+        assert_eq!(lines[4], b":08FFF800AAAAAAAAAAAAAAAAB1");
+        assert_eq!(lines[5], b":020000040802F0");
+        assert_eq!(lines[7], b":0400000508000005EA");
+        assert_eq!(lines[8], b":00000001FF");
+
+        let ehdr = Ehdr64::new(EIC::ELF32, EID::LSB, ET::EXEC, EM::ARM);
+        let elf = collect(|f| intel_hex_to_elf(&text, &ehdr, f));
+        assert_eq!(regions(&elf), regions(&data));
+        assert_eq!(ElfFile::new(&elf).unwrap().ehdr().e_entry, 0x0800_0005);
+    }
+
+    #[test]
+    fn srec() {
+        let data = image();
+        let file = ElfFile::new(&data).unwrap();
+        let text = collect(|f| file.srec(&BinaryOptions::default(), f));
+        let lines: Vec<_> = text.split(|&b| b == b'\n').collect();
+        assert_eq!(lines[0], b"S0030000FC");
+        assert_eq!(&lines[1][..12], b"S31508000000");
+        assert_eq!(lines[4], b"S5030003F9");
+        assert_eq!(lines[5], b"S70508000005ED");
+
+        let ehdr = Ehdr64::new(EIC::ELF64, EID::LSB, ET::EXEC, EM::AARCH64);
+        let elf = collect(|f| srec_to_elf(&text, &ehdr, f));
+        assert_eq!(regions(&elf), regions(&data));
+        assert_eq!(ElfFile::new(&elf).unwrap().ehdr().e_entry, 0x0800_0005);
+
+        // Corrupted checksum
+        let mut text = text;
+        text[24] = b'F';
+        let result = srec_to_elf(&text, &ehdr, |_| Ok(()));
+        assert_eq!(result, Err(Error::Malformed));
+    }
+
+    #[test]
+    fn wrapping() {
+        // The last byte is past the end of the 64-bit address space
+        let mut builder = Builder::new(false, false, ET::EXEC, EM::X86_64);
+        let data = vec![0; 0x20];
+        let data = builder.section(".data", SHT::PROGBITS, 0xffff_ffff_ffff_fff0, data);
+        builder.segment(PT::LOAD, PF::R, &[data]);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+        let options = BinaryOptions::default();
+        assert_eq!(
+            file.intel_hex(&options, |_| Ok(())),
+            Err(Error::Unsupported)
+        );
+        assert_eq!(file.srec(&options, |_| Ok(())), Err(Error::Unsupported));
+
+        assert_eq!(next_line(&mut &b" \t\r\n"[..]), None);
+        assert_eq!(
+            next_line(&mut &b"\r\n :00000001FF \r\n"[..]),
+            Some(&b":00000001FF"[..])
+        );
+    }
+}
@@ -0,0 +1,495 @@
+//! Initialization and termination functions
+//!
+//! Functions are found in the `DT::PREINIT_ARRAY`, `DT::INIT`, `DT::INIT_ARRAY`,
+//! `DT::FINI_ARRAY` and `DT::FINI` dynamic entries, or in the `SHT::PREINIT_ARRAY`,
+//! `SHT::INIT_ARRAY` and `SHT::FINI_ARRAY` sections for relocatable objects and static
+//! executables. Array entries are relocated, so addresses are correct for position
+//! independent executables (relative to a load address of 0) and relocatable objects
+//! (relative to the start of the function's section).
+
+use crate::dynamic::DT;
+use crate::ehdr::ET;
+use crate::shdr::{SHN, SHT};
+use crate::sym::STT;
+use crate::{ElfFile, Error, Relocation, Sym64, SymbolTable};
+
+enum_struct!(
+/// Type of initialization or termination function
+pub struct Kind(u8) {
+    PREINIT_ARRAY = 0 => "Pre-initialization function",
+    INIT          = 1 => "Initialization function",
+    INIT_ARRAY    = 2 => "Constructor",
+    FINI_ARRAY    = 3 => "Destructor",
+    FINI          = 4 => "Termination function",
+}
+);
+
+impl Kind {
+    /// Order the dynamic linker runs each type of function
+    const ORDER: [Kind; 5] = [
+        Kind::PREINIT_ARRAY,
+        Kind::INIT,
+        Kind::INIT_ARRAY,
+        Kind::FINI_ARRAY,
+        Kind::FINI,
+    ];
+
+    /// Section type holding arrays of this type
+    fn section_type(self) -> Option<SHT> {
+        match self {
+            Kind::PREINIT_ARRAY => Some(SHT::PREINIT_ARRAY),
+            Kind::INIT_ARRAY => Some(SHT::INIT_ARRAY),
+            Kind::FINI_ARRAY => Some(SHT::FINI_ARRAY),
+            _ => None,
+        }
+    }
+}
+
+/// Initialization or termination function
+#[derive(Clone, Copy, Debug)]
+pub struct Function<'a> {
+    /// Type of function
+    pub kind: Kind,
+    /// Address of the function (offset into `section` for relocatable objects)
+    pub address: u64,
+    /// Index of the section containing the function (relocatable objects only)
+    pub section: Option<usize>,
+    /// Address of the array entry pointing to the function, `None` for `DT::INIT` and `DT::FINI`
+    pub entry: Option<u64>,
+    /// Name of the function
+    pub symbol: Option<&'a str>,
+    /// Function is an undefined symbol, and `address` is only the addend
+    pub undefined: bool,
+}
+
+/// Array of function pointers, or a single function
+#[derive(Clone, Copy)]
+struct Array {
+    /// File offset of array
+    offset: u64,
+    /// Address of array (offset into section for relocatable objects)
+    base: u64,
+    /// Next entry to return
+    start: u64,
+    /// End of entries to return
+    end: u64,
+    /// Array is run from last to first entry
+    reverse: bool,
+    /// Section containing array
+    section: Option<usize>,
+    /// Address of a single function (`DT::INIT` and `DT::FINI`)
+    value: Option<u64>,
+}
+
+impl Array {
+    fn next(&mut self) -> Option<u64> {
+        if self.start >= self.end {
+            None
+        } else if self.reverse {
+            self.end -= 1;
+            Some(self.end)
+        } else {
+            self.start += 1;
+            Some(self.start - 1)
+        }
+    }
+}
+
+/// Relocation of an array entry, and the symbol it refers to
+type EntryRelocation<'a> = (Relocation, Option<(Sym64, &'a str)>);
+
+/// Number of array entries whose relocations are found in one pass over the relocations
+const CHUNK: usize = 32;
+
+/// Iterator over initialization and termination functions, in the order they are run
+pub struct Functions<'a> {
+    file: ElfFile<'a>,
+    symbols: Option<SymbolTable<'a>>,
+    /// Take arrays from the dynamic section rather than from section headers
+    dynamic: bool,
+    /// Index into [`Kind::ORDER`]
+    kind: usize,
+    /// Number of sections searched for the current kind
+    cursor: usize,
+    current: Option<Array>,
+    /// First entry of the current array covered by `relocations`
+    chunk: Option<u64>,
+    /// Relocations of the entries in the chunk, with the section of their symbol table
+    relocations: [Option<(Relocation, usize)>; CHUNK],
+}
+
+impl<'a> ElfFile<'a> {
+    /// Initialization and termination functions, in the order they are run
+    pub fn init_functions(&self) -> Result<Functions<'a>, Error> {
+        let dynamic = self.ehdr().e_type != ET::REL
+            && self.dynamic()?.any(|d| {
+                [
+                    DT::PREINIT_ARRAY,
+                    DT::INIT,
+                    DT::INIT_ARRAY,
+                    DT::FINI_ARRAY,
+                    DT::FINI,
+                ]
+                .contains(&d.d_tag)
+            });
+        Ok(Functions {
+            file: *self,
+            symbols: self.symbol_table()?,
+            dynamic,
+            kind: 0,
+            cursor: 0,
+            current: None,
+            chunk: None,
+            relocations: [None; CHUNK],
+        })
+    }
+}
+
+impl<'a> Functions<'a> {
+    /// Find the next array for the current kind
+    fn next_array(&mut self) -> Result<Option<Array>, Error> {
+        let kind = Kind::ORDER[self.kind];
+        let file = &self.file;
+        let word = file.word_size() as u64;
+        if self.dynamic {
+            if self.cursor > 0 {
+                return Ok(None);
+            }
+            self.cursor += 1;
+            let (addr, size) = match kind {
+                Kind::PREINIT_ARRAY => (DT::PREINIT_ARRAY, DT::PREINIT_ARRAYSZ),
+                Kind::INIT_ARRAY => (DT::INIT_ARRAY, DT::INIT_ARRAYSZ),
+                Kind::FINI_ARRAY => (DT::FINI_ARRAY, DT::FINI_ARRAYSZ),
+                Kind::INIT | Kind::FINI => {
+                    let tag = if kind == Kind::INIT {
+                        DT::INIT
+                    } else {
+                        DT::FINI
+                    };
+                    return Ok(file.dynamic_value(tag)?.map(|value| Array {
+                        offset: 0,
+                        base: 0,
+                        start: 0,
+                        end: 1,
+                        reverse: false,
+                        section: None,
+                        value: Some(value),
+                    }));
+                }
+                _ => unreachable!(),
+            };
+            let addr = match file.dynamic_value(addr)? {
+                Some(addr) => addr,
+                None => return Ok(None),
+            };
+            let size = file.dynamic_value(size)?.unwrap_or(0);
+            let offset = file.vaddr_to_offset(addr, size).ok_or(Error::Malformed)?;
+            return Ok(Some(Array {
+                offset,
+                base: addr,
+                start: 0,
+                end: size / word,
+                reverse: kind == Kind::FINI_ARRAY,
+                section: file.section_by_address(addr)?,
+                value: None,
+            }));
+        }
+
+        let sh_type = match kind.section_type() {
+            Some(sh_type) => sh_type,
+            None => return Ok(None),
+        };
+        let shdrs = file.shdrs()?;
+        let shnum = shdrs.len();
+        while self.cursor < shnum {
+            self.cursor += 1;
+            let index = if kind == Kind::FINI_ARRAY {
+                shnum - self.cursor
+            } else {
+                self.cursor - 1
+            };
+            let shdr = file.shdr(index)?;
+            if shdr.sh_type != sh_type {
+                continue;
+            }
+            file.section_data(&shdr)?;
+            let relocatable = file.ehdr().e_type == ET::REL;
+            return Ok(Some(Array {
+                offset: shdr.sh_offset,
+                base: if relocatable { 0 } else { shdr.sh_addr },
+                start: 0,
+                end: shdr.sh_size / word,
+                reverse: kind == Kind::FINI_ARRAY,
+                section: Some(index),
+                value: None,
+            }));
+        }
+        Ok(None)
+    }
+
+    /// Find the relocations of the entries of `array` around `index`, in one pass over the
+    /// relocations however many entries there are
+    fn load_chunk(&mut self, array: &Array, index: u64) -> Result<u64, Error> {
+        let start = if array.reverse {
+            (index + 1).saturating_sub(CHUNK as u64)
+        } else {
+            index
+        };
+        self.chunk = Some(start);
+        self.relocations = [None; CHUNK];
+        let word = self.file.word_size() as u64;
+        let first = array.base.wrapping_add(start * word);
+        let relocations = &mut self.relocations;
+        let mut record = |reloc: Relocation, link: usize| {
+            let offset = reloc.r_offset.wrapping_sub(first);
+            if offset % word == 0 && offset / word < CHUNK as u64 {
+                // The first relocation of an entry applies
+                let slot = &mut relocations[(offset / word) as usize];
+                if slot.is_none() {
+                    *slot = Some((reloc, link));
+                }
+            }
+        };
+        let file = &self.file;
+        if file.ehdr().e_type != ET::REL {
+            for reloc in file.dynamic_relocations()? {
+                record(reloc, 0);
+            }
+        } else if let Some(section) = array.section {
+            for shdr in file.shdrs()? {
+                if (shdr.sh_type == SHT::REL || shdr.sh_type == SHT::RELA)
+                    && shdr.sh_info as usize == section
+                {
+                    for reloc in file.relocations(&shdr)? {
+                        record(reloc, shdr.sh_link as usize);
+                    }
+                }
+            }
+        }
+        Ok(start)
+    }
+
+    /// Relocation applied to entry `index` of `array`, and the symbol it refers to
+    fn relocation(
+        &mut self,
+        array: &Array,
+        index: u64,
+    ) -> Result<Option<EntryRelocation<'a>>, Error> {
+        let start = match self.chunk {
+            Some(start) if index >= start && index - start < CHUNK as u64 => start,
+            _ => self.load_chunk(array, index)?,
+        };
+        let (reloc, link) = match self.relocations[(index - start) as usize] {
+            Some(relocation) => relocation,
+            None => return Ok(None),
+        };
+        if reloc.r_sym == 0 {
+            return Ok(Some((reloc, None)));
+        }
+        let file = &self.file;
+        let sym = if file.ehdr().e_type != ET::REL {
+            let sym = file.dynamic_symbol(reloc.r_sym as usize)?;
+            (sym, file.dynamic_string(sym.st_name)?)
+        } else {
+            let symbols = file.symbols(link)?;
+            let sym = symbols.get(reloc.r_sym as usize)?;
+            (sym, symbols.name(&sym)?)
+        };
+        Ok(Some((reloc, Some(sym))))
+    }
+
+    /// Resolve the function for entry `index` of `array`
+    fn function(&mut self, kind: Kind, array: &Array, index: u64) -> Result<Function<'a>, Error> {
+        let word = self.file.word_size() as u64;
+        let mut function = Function {
+            kind,
+            address: 0,
+            section: None,
+            entry: None,
+            symbol: None,
+            undefined: false,
+        };
+        if let Some(value) = array.value {
+            function.address = value;
+        } else {
+            let address = array.base.wrapping_add(index * word);
+            let offset = array
+                .offset
+                .checked_add(index * word)
+                .ok_or(Error::Malformed)?;
+            let raw = self.file.read_word(offset)?;
+            function.entry = Some(address);
+            function.address = raw;
+            if let Some((reloc, sym)) = self.relocation(array, index)? {
+                // Without an explicit addend, the addend is the value stored in the array
+                let addend = reloc.r_addend.unwrap_or(raw as i64) as u64;
+                function.address = addend;
+                if let Some((sym, name)) = sym {
+                    if sym.st_shndx == SHN::UNDEF {
+                        function.undefined = true;
+                        function.symbol = Some(name);
+                    } else {
+                        function.address = sym.st_value.wrapping_add(addend);
+                        if sym.st_type() != STT::SECTION && addend == 0 && !name.is_empty() {
+                            function.symbol = Some(name);
+                        }
+                        // Absolute and common symbols aren't in a section
+                        if self.file.ehdr().e_type == ET::REL && sym.st_shndx < SHN::LORESERVE {
+                            function.section = Some(u16::from(sym.st_shndx) as usize);
+                        }
+                    }
+                }
+            }
+            if self.file.is_elf32() {
+                function.address &= 0xffff_ffff;
+            }
+        }
+        if function.symbol.is_none() && !function.undefined {
+            if let Some(symbols) = &self.symbols {
+                let section = function.section.map(|s| SHN::from(s as u16));
+                let sym = symbols.containing(function.address, |s| {
+                    section.map_or(true, |shndx| s.st_shndx == shndx)
+                });
+                function.symbol = sym.and_then(|s| symbols.name(&s).ok());
+            }
+        }
+        Ok(function)
+    }
+}
+
+impl<'a> Iterator for Functions<'a> {
+    type Item = Result<Function<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(array) = &mut self.current {
+                if let Some(index) = array.next() {
+                    let array = *array;
+                    let result = self.function(Kind::ORDER[self.kind], &array, index);
+                    if result.is_err() {
+                        self.current = None;
+                        self.kind = Kind::ORDER.len();
+                    }
+                    return Some(result);
+                }
+                self.current = None;
+            }
+            if self.kind >= Kind::ORDER.len() {
+                return None;
+            }
+            match self.next_array() {
+                Ok(Some(array)) => {
+                    self.current = Some(array);
+                    self.chunk = None;
+                }
+                Ok(None) => {
+                    self.kind += 1;
+                    self.cursor = 0;
+                }
+                Err(err) => {
+                    self.kind = Kind::ORDER.len();
+                    return Some(Err(err));
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::EM;
+    use crate::phdr::{PF, PT};
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn relocatable() {
+        // Constructor and destructor arrays in an x86-64 object file, relocated against
+        // the .text section symbol and a function symbol
+        let mut builder = Builder::new(false, false, ET::REL, EM::X86_64);
+        let text = builder.section(".text", SHT::PROGBITS, 0, vec![0xc3; 32]);
+        let init = builder.section(".init_array", SHT::INIT_ARRAY, 0, vec![0; 16]);
+        let fini = builder.section(".fini_array", SHT::FINI_ARRAY, 0, vec![0; 8]);
+        let (strtab, names) = Builder::strings(&["ctor_a", "ctor_b", "dtor"]);
+        let strtab = builder.section(".strtab", SHT::STRTAB, 0, strtab);
+        let mut symtab = builder.sym(0, 0, 0, 0, 0);
+        symtab.extend(builder.sym(0, 0, 0, 0x03, text as u16));
+        symtab.extend(builder.sym(names[0], 0, 8, 0x02, text as u16));
+        symtab.extend(builder.sym(names[1], 8, 8, 0x02, text as u16));
+        symtab.extend(builder.sym(names[2], 16, 8, 0x12, text as u16));
+        let symtab = builder.section(".symtab", SHT::SYMTAB, 0, symtab);
+        builder.shdr_mut(symtab).sh_link = strtab as u32;
+        let mut rela = builder.rela(0, 1, 1, 0);
+        rela.extend(builder.rela(8, 1, 1, 8));
+        let rela_init = builder.section(".rela.init_array", SHT::RELA, 0, rela);
+        builder.shdr_mut(rela_init).sh_link = symtab as u32;
+        builder.shdr_mut(rela_init).sh_info = init as u32;
+        let rela = builder.rela(0, 4, 1, 0);
+        let rela_fini = builder.section(".rela.fini_array", SHT::RELA, 0, rela);
+        builder.shdr_mut(rela_fini).sh_link = symtab as u32;
+        builder.shdr_mut(rela_fini).sh_info = fini as u32;
+        let data = builder.build();
+
+        let file = ElfFile::new(&data).unwrap();
+        let functions: Vec<_> = file.init_functions().unwrap().map(Result::unwrap).collect();
+        assert_eq!(functions.len(), 3);
+        assert!(functions[0].kind == Kind::INIT_ARRAY);
+        assert_eq!(functions[0].address, 0);
+        assert_eq!(functions[0].section, Some(text));
+        assert_eq!(functions[0].symbol, Some("ctor_a"));
+        assert_eq!(functions[1].address, 8);
+        assert_eq!(functions[1].symbol, Some("ctor_b"));
+        assert_eq!(functions[1].entry, Some(8));
+        assert!(functions[2].kind == Kind::FINI_ARRAY);
+        assert_eq!(functions[2].address, 16);
+        assert_eq!(functions[2].symbol, Some("dtor"));
+    }
+
+    #[test]
+    fn position_independent() {
+        // 32-bit big-endian shared object using relative relocations with implicit addends
+        let mut builder = Builder::new(true, true, ET::DYN, EM::PPC32);
+        let text = builder.section(".text", SHT::PROGBITS, 0x1000, vec![0; 0x20]);
+        let mut array = builder.word(0x1010);
+        array.extend(builder.word(0x1000));
+        let init = builder.section(".init_array", SHT::INIT_ARRAY, 0x2000, array);
+        let fini = builder.section(".fini_array", SHT::FINI_ARRAY, 0x2008, builder.word(0x1018));
+        let mut rel = builder.rel(0x2000, 0, 22);
+        rel.extend(builder.rel(0x2004, 0, 22));
+        rel.extend(builder.rel(0x2008, 0, 22));
+        let rel = builder.section(".rel.dyn", SHT::REL, 0x2010, rel);
+        let entries = [
+            (DT::INIT, 0x1004),
+            (DT::INIT_ARRAY, 0x2000),
+            (DT::INIT_ARRAYSZ, 8),
+            (DT::FINI_ARRAY, 0x2008),
+            (DT::FINI_ARRAYSZ, 4),
+            (DT::REL, 0x2010),
+            (DT::RELSZ, 24),
+        ];
+        let dynamic = builder.section(".dynamic", SHT::DYNAMIC, 0x2028, builder.dynamic(&entries));
+        builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+        builder.segment(PT::LOAD, PF::R | PF::W, &[init, fini, rel, dynamic]);
+        builder.segment(PT::DYNAMIC, PF::R | PF::W, &[dynamic]);
+        let data = builder.build();
+
+        let file = ElfFile::new(&data).unwrap();
+        let functions: Vec<_> = file
+            .init_functions()
+            .unwrap()
+            .map(|f| f.map(|f| (f.kind, f.address)).unwrap())
+            .collect();
+        assert!(
+            functions
+                == [
+                    (Kind::INIT, 0x1004),
+                    (Kind::INIT_ARRAY, 0x1010),
+                    (Kind::INIT_ARRAY, 0x1000),
+                    (Kind::FINI_ARRAY, 0x1018),
+                ]
+        );
+    }
+}
@@ -0,0 +1,526 @@
+//! Linux kernel modules
+//!
+//! A kernel module (`.ko`) is a relocatable object with a few special sections:
+//!
+//! * `.modinfo` holds `key=value` strings such as `license`, `vermagic`, `depends` and `alias`
+//! * `__versions` holds the CRC of each symbol the module imports, when built with
+//!   `CONFIG_MODVERSIONS`
+//! * `.gnu.linkonce.this_module` holds the module's `struct module`, which starts with its name
+//!
+//! A signed module has a signature appended after the ELF data, ending with [`MAGIC`].
+
+use crate::de::from_bytes;
+use crate::file::c_str;
+use crate::shdr::SHN;
+use crate::sym::STB;
+use crate::{ElfFile, Error};
+use core::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+/// Size of the `name` field of `struct module` and of a `__versions` entry
+const NAME_SIZE: usize = 64;
+
+/// `.modinfo` strings
+#[derive(Clone, Debug)]
+pub struct ModInfo<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> ModInfo<'a> {
+    /// Strings from the contents of the `.modinfo` section
+    pub fn new(data: &'a [u8]) -> Self {
+        ModInfo { data }
+    }
+
+    /// First value for `key`
+    pub fn get(&self, key: &str) -> Option<&'a str> {
+        self.values(key).next()
+    }
+
+    /// All values for `key`, such as every `alias`
+    pub fn values<'k>(&self, key: &'k str) -> impl Iterator<Item = &'a str> + 'k
+    where
+        'a: 'k,
+    {
+        self.clone()
+            .filter_map(Result::ok)
+            .filter(move |(k, _)| *k == key)
+            .map(|(_, value)| value)
+    }
+
+    /// Modules this module depends on, from the comma-separated `depends` value
+    pub fn depends(&self) -> impl Iterator<Item = &'a str> {
+        self.get("depends")
+            .unwrap_or("")
+            .split(',')
+            .filter(|name| !name.is_empty())
+    }
+}
+
+impl<'a> Iterator for ModInfo<'a> {
+    type Item = Result<(&'a str, &'a str), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        // Strings are separated by one or more NULs, for alignment
+        let start = self.data.iter().position(|&c| c != 0)?;
+        self.data = &self.data[start..];
+        let string = match c_str(self.data, 0) {
+            Ok(string) => string,
+            Err(err) => {
+                self.data = &[];
+                return Some(Err(err));
+            }
+        };
+        self.data = &self.data[string.len()..];
+        match string.find('=') {
+            Some(equals) => Some(Ok((&string[..equals], &string[equals + 1..]))),
+            None => Some(Err(Error::Malformed)),
+        }
+    }
+}
+
+/// `__versions` entry (`struct modversion_info`)
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Version<'a> {
+    /// CRC of the symbol's prototype
+    pub crc: u32,
+    /// Symbol name
+    pub name: &'a str,
+}
+
+/// Iterator over the `__versions` entries
+#[derive(Clone, Debug)]
+pub struct Versions<'a> {
+    data: &'a [u8],
+    word_size: usize,
+    big_endian: bool,
+}
+
+impl<'a> Versions<'a> {
+    /// Entries in `data`, the contents of the `__versions` section
+    pub fn new(data: &'a [u8], word_size: usize, big_endian: bool) -> Self {
+        Versions {
+            data,
+            word_size,
+            big_endian,
+        }
+    }
+
+    /// Entry for symbol `name`
+    pub fn get(&self, name: &str) -> Option<Version<'a>> {
+        self.clone()
+            .filter_map(Result::ok)
+            .find(|version| version.name == name)
+    }
+}
+
+impl<'a> Iterator for Versions<'a> {
+    type Item = Result<Version<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let version = || {
+            let entry = self.data.get(..NAME_SIZE).ok_or(Error::Truncated)?;
+            // The CRC is an unsigned long, but only 32 bits are used
+            let crc: u64 = if self.word_size == 8 {
+                from_bytes(entry, self.big_endian)?
+            } else {
+                from_bytes::<u32>(entry, self.big_endian)?.into()
+            };
+            Ok(Version {
+                crc: crc as u32,
+                name: c_str(&entry[self.word_size..], 0)?,
+            })
+        };
+        match version() {
+            Ok(version) => {
+                self.data = &self.data[NAME_SIZE..];
+                Some(Ok(version))
+            }
+            Err(err) => {
+                self.data = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+/// Marker at the end of a signed module
+pub const MAGIC: &[u8] = b"~Module signature appended~\n";
+
+enum_struct!(
+/// Signature format (`enum pkey_id_type`)
+pub struct IdType(u8) {
+    PGP   = 0 => "OpenPGP",
+    X509  = 1 => "X.509",
+    PKCS7 = 2 => "PKCS#7",
+}
+);
+
+/// Signature information preceding [`MAGIC`] (`struct module_signature`)
+///
+/// `sig_len` is always big-endian. For [`IdType::PKCS7`], the only format the kernel
+/// accepts, the other fields are 0 as the signature data is self-describing.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct SignatureInfo {
+    /// Public key algorithm
+    pub algo: u8,
+    /// Digest algorithm
+    pub hash: u8,
+    /// Signature format
+    pub id_type: IdType,
+    /// Length of the signer's name
+    pub signer_len: u8,
+    /// Length of the key identifier
+    pub key_id_len: u8,
+    /// Padding
+    pub pad: [u8; 3],
+    /// Length of the signature data
+    pub sig_len: u32,
+}
+impl SignatureInfo {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+/// Signature appended to a module
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Signature<'a> {
+    /// Signature information
+    pub info: SignatureInfo,
+    /// Signer's name, empty for PKCS#7
+    pub signer: &'a [u8],
+    /// Key identifier, empty for PKCS#7
+    pub key_id: &'a [u8],
+    /// Signature data, a DER-encoded PKCS#7 message for PKCS#7
+    pub signature: &'a [u8],
+    /// Length of the signed data, the module without its signature
+    pub module_len: usize,
+}
+
+impl<'a> Signature<'a> {
+    /// Signature at the end of `data`, or `None` if the module isn't signed
+    pub fn parse(data: &'a [u8]) -> Result<Option<Self>, Error> {
+        let data = match data.strip_suffix(MAGIC) {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        let info_offset = data
+            .len()
+            .checked_sub(SignatureInfo::SIZE)
+            .ok_or(Error::Truncated)?;
+        let info: SignatureInfo = from_bytes(&data[info_offset..], true)?;
+        let mut end = info_offset;
+        let mut take = |len: usize| {
+            let start = end.checked_sub(len).ok_or(Error::Truncated)?;
+            end = start;
+            Ok(&data[start..start + len])
+        };
+        let signature = take(info.sig_len as usize)?;
+        let key_id = take(info.key_id_len.into())?;
+        let signer = take(info.signer_len.into())?;
+        Ok(Some(Signature {
+            info,
+            signer,
+            key_id,
+            signature,
+            module_len: end,
+        }))
+    }
+}
+
+/// Exported symbol, a line of `Module.symvers`
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Export<'a> {
+    /// CRC of the symbol's prototype
+    pub crc: u32,
+    /// Symbol name
+    pub name: &'a str,
+    /// Path of the exporting module, or `vmlinux`
+    pub module: &'a str,
+    /// Export type, such as `EXPORT_SYMBOL_GPL`
+    pub export_type: &'a str,
+    /// Symbol namespace, empty if none
+    pub namespace: &'a str,
+}
+
+/// `Module.symvers` contents
+///
+/// Each line is a tab-separated CRC, symbol, module, export type and optional namespace.
+/// Lookups scan the text, so for many of them collect the exports into a slice sorted by
+/// name and use [`Symvers::sorted()`].
+#[derive(Clone, Copy, Debug)]
+pub struct Symvers<'a> {
+    text: &'a str,
+    sorted: &'a [Export<'a>],
+}
+
+impl<'a> Symvers<'a> {
+    /// Exports listed in `text`
+    pub fn new(text: &'a str) -> Self {
+        Symvers { text, sorted: &[] }
+    }
+
+    /// Exports in `exports`, which must be sorted by name
+    pub fn sorted(exports: &'a [Export<'a>]) -> Self {
+        Symvers {
+            text: "",
+            sorted: exports,
+        }
+    }
+
+    /// Iterate over the exports
+    pub fn exports(&self) -> impl Iterator<Item = Result<Export<'a>, Error>> + 'a {
+        self.text
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                let mut fields = line.split('\t');
+                let crc = fields.next().ok_or(Error::Malformed)?;
+                let crc = crc.strip_prefix("0x").ok_or(Error::Malformed)?;
+                Ok(Export {
+                    crc: u32::from_str_radix(crc, 16).map_err(|_| Error::Malformed)?,
+                    name: fields.next().ok_or(Error::Malformed)?,
+                    module: fields.next().ok_or(Error::Malformed)?,
+                    export_type: fields.next().ok_or(Error::Malformed)?,
+                    namespace: fields.next().unwrap_or(""),
+                })
+            })
+            .chain(self.sorted.iter().map(|export| Ok(*export)))
+    }
+
+    /// Export of symbol `name`
+    pub fn get(&self, name: &str) -> Option<Export<'a>> {
+        if !self.sorted.is_empty() {
+            return self
+                .sorted
+                .binary_search_by(|export| export.name.cmp(name))
+                .ok()
+                .map(|index| self.sorted[index]);
+        }
+        self.exports()
+            .filter_map(Result::ok)
+            .find(|export| export.name == name)
+    }
+}
+
+/// Problem found by [`ElfFile::module_check()`]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Problem<'a> {
+    /// Undefined symbol that isn't exported
+    Unresolved(&'a str),
+    /// Symbol whose `__versions` CRC differs from the exported one
+    CrcMismatch {
+        /// Symbol name
+        name: &'a str,
+        /// CRC recorded in the module
+        crc: u32,
+        /// CRC of the export
+        expected: u32,
+    },
+}
+
+impl<'a> ElfFile<'a> {
+    /// `.modinfo` strings, or `None` if there is no `.modinfo` section
+    pub fn modinfo(&self) -> Result<Option<ModInfo<'a>>, Error> {
+        match self.section_by_name(".modinfo")? {
+            Some((_, shdr)) => Ok(Some(ModInfo::new(self.section_data(&shdr)?))),
+            None => Ok(None),
+        }
+    }
+
+    /// `__versions` entries, or `None` if the module wasn't built with `CONFIG_MODVERSIONS`
+    pub fn module_versions(&self) -> Result<Option<Versions<'a>>, Error> {
+        match self.section_by_name("__versions")? {
+            Some((_, shdr)) => Ok(Some(Versions::new(
+                self.section_data(&shdr)?,
+                self.word_size(),
+                self.is_big_endian(),
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// Module name from `struct module`, or `None` if there is no
+    /// `.gnu.linkonce.this_module` section
+    pub fn module_name(&self) -> Result<Option<&'a str>, Error> {
+        let shdr = match self.section_by_name(".gnu.linkonce.this_module")? {
+            Some((_, shdr)) => shdr,
+            None => return Ok(None),
+        };
+        // The name follows an enum and a list_head
+        let offset = 4 + 2 * self.word_size() as u64;
+        let offset = (offset + self.word_size() as u64 - 1) & !(self.word_size() as u64 - 1);
+        let data = self.section_data(&shdr)?;
+        let name = data
+            .get(offset as usize..)
+            .and_then(|data| data.get(..NAME_SIZE - self.word_size()))
+            .ok_or(Error::Truncated)?;
+        c_str(name, 0).map(Some)
+    }
+
+    /// Signature appended to the module, or `None` if it isn't signed
+    pub fn module_signature(&self) -> Result<Option<Signature<'a>>, Error> {
+        Signature::parse(self.data())
+    }
+
+    /// Check the module's imports against the exports of `symvers`
+    ///
+    /// Undefined symbols that aren't weak or exported are reported as unresolved, and
+    /// `__versions` entries whose CRC differs from the export as mismatched.
+    pub fn module_check(
+        &self,
+        symvers: &Symvers,
+        mut f: impl FnMut(Problem<'a>),
+    ) -> Result<(), Error> {
+        let symbols = self.symbol_table()?.ok_or(Error::NotFound)?;
+        for sym in symbols.iter().skip(1) {
+            if sym.st_shndx != SHN::UNDEF || sym.st_bind() == STB::WEAK || sym.st_name == 0 {
+                continue;
+            }
+            let name = symbols.name(&sym)?;
+            if symvers.get(name).is_none() {
+                f(Problem::Unresolved(name));
+            }
+        }
+        if let Some(versions) = self.module_versions()? {
+            for version in versions {
+                let version = version?;
+                match symvers.get(version.name) {
+                    Some(export) if export.crc != version.crc => f(Problem::CrcMismatch {
+                        name: version.name,
+                        crc: version.crc,
+                        expected: export.crc,
+                    }),
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::{EM, ET};
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use std::vec::Vec;
+    use std::{format, vec};
+
+    fn version(builder: &Builder, crc: u32, name: &str) -> Vec<u8> {
+        let mut entry = builder.word(crc.into());
+        entry.extend(name.as_bytes());
+        entry.resize(NAME_SIZE, 0);
+        entry
+    }
+
+    #[test]
+    fn module() {
+        let mut builder = Builder::new(false, false, ET::REL, EM::X86_64);
+        builder.section(
+            ".modinfo",
+            SHT::PROGBITS,
+            0,
+            b"license=GPL\0\0\0depends=usbcore,hid\0alias=usb:v046Dp*\0alias=hid:b0003\0vermagic=6.8.0 SMP mod_unload \0".to_vec(),
+        );
+        let mut versions = version(&builder, 0x1234_5678, "module_layout");
+        versions.extend(version(&builder, 0xdead_beef, "usb_register_driver"));
+        builder.section("__versions", SHT::PROGBITS, 0, versions);
+        let mut this_module = vec![0; 24];
+        this_module.extend(b"mydrv\0");
+        this_module.resize(0x400, 0);
+        let this_module =
+            builder.section(".gnu.linkonce.this_module", SHT::PROGBITS, 0, this_module);
+        let (strtab, names) = Builder::strings(&[
+            "__this_module",
+            "usb_register_driver",
+            "secret_helper",
+            "optional",
+        ]);
+        let strtab = builder.section(".strtab", SHT::STRTAB, 0, strtab);
+        let mut symtab = builder.sym(0, 0, 0, 0, 0);
+        symtab.extend(builder.sym(names[0], 0, 0x400, 0x11, this_module as u16));
+        symtab.extend(builder.sym(names[1], 0, 0, 0x10, 0));
+        symtab.extend(builder.sym(names[2], 0, 0, 0x10, 0));
+        symtab.extend(builder.sym(names[3], 0, 0, 0x20, 0));
+        let symtab = builder.section(".symtab", SHT::SYMTAB, 0, symtab);
+        builder.shdr_mut(symtab).sh_link = strtab as u32;
+        let mut data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+
+        let modinfo = file.modinfo().unwrap().unwrap();
+        assert_eq!(modinfo.get("license"), Some("GPL"));
+        assert_eq!(modinfo.get("vermagic"), Some("6.8.0 SMP mod_unload "));
+        assert_eq!(modinfo.depends().collect::<Vec<_>>(), ["usbcore", "hid"]);
+        assert_eq!(
+            modinfo.values("alias").collect::<Vec<_>>(),
+            ["usb:v046Dp*", "hid:b0003"]
+        );
+        assert_eq!(modinfo.count(), 5);
+
+        let versions = file.module_versions().unwrap().unwrap();
+        assert_eq!(versions.clone().count(), 2);
+        assert_eq!(
+            versions.get("usb_register_driver"),
+            Some(Version {
+                crc: 0xdead_beef,
+                name: "usb_register_driver"
+            })
+        );
+        assert_eq!(file.module_name(), Ok(Some("mydrv")));
+        assert_eq!(file.module_signature(), Ok(None));
+
+        let symvers = Symvers::new(
+            "0x12345678\tmodule_layout\tvmlinux\tEXPORT_SYMBOL\t\n\
+             0xcafef00d\tusb_register_driver\tdrivers/usb/core/usbcore\tEXPORT_SYMBOL_GPL\tUSB\n",
+        );
+        assert_eq!(symvers.get("usb_register_driver").unwrap().namespace, "USB");
+        let mut problems = Vec::new();
+        file.module_check(&symvers, |problem| problems.push(problem))
+            .unwrap();
+        assert_eq!(
+            problems,
+            [
+                Problem::Unresolved("secret_helper"),
+                Problem::CrcMismatch {
+                    name: "usb_register_driver",
+                    crc: 0xdead_beef,
+                    expected: 0xcafe_f00d
+                }
+            ]
+        );
+
+        // The same problems are found through sorted exports
+        let mut exports: Vec<_> = symvers.exports().map(Result::unwrap).collect();
+        exports.reverse();
+        exports.sort_by_key(|export| export.name);
+        let sorted = Symvers::sorted(&exports);
+        assert_eq!(sorted.get("module_layout").unwrap().crc, 0x1234_5678);
+        assert_eq!(sorted.get("secret_helper"), None);
+        let mut sorted_problems = Vec::new();
+        file.module_check(&sorted, |problem| sorted_problems.push(problem))
+            .unwrap();
+        assert_eq!(sorted_problems, problems);
+
+        // Sign with a PKCS#7 signature
+        let module_len = data.len();
+        data.extend([0x30, 0x82, 0, 2, 5, 0]);
+        data.extend([0, 0, 2, 0, 0, 0, 0, 0, 0, 0, 0, 6]);
+        data.extend(MAGIC);
+        let file = ElfFile::new(&data).unwrap();
+        let signature = file.module_signature().unwrap().unwrap();
+        assert_eq!(signature.info.id_type, IdType::PKCS7);
+        assert_eq!(signature.signature, [0x30, 0x82, 0, 2, 5, 0]);
+        assert!(signature.signer.is_empty());
+        assert_eq!(signature.module_len, module_len);
+        assert_eq!(format!("{}", signature.info.id_type), "PKCS#7");
+        assert_eq!(SignatureInfo::SIZE, 12);
+        let truncated = [&[0; 4][..], &data[data.len() - 40..]].concat();
+        assert_eq!(Signature::parse(&truncated), Err(Error::Truncated));
+    }
+}
@@ -0,0 +1,61 @@
+//! LEB128 variable length integers
+
+use crate::Error;
+
+/// Read an unsigned LEB128 value from the start of `data`, advancing past it
+pub(crate) fn read_uleb128(data: &mut &[u8]) -> Result<u64, Error> {
+    let mut value = 0u64;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = data.split_first().ok_or(Error::Truncated)?;
+        *data = rest;
+        if shift >= 64 || (shift == 63 && byte & 0x7e != 0) {
+            return Err(Error::Malformed);
+        }
+        value |= u64::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+}
+
+/// Read a signed LEB128 value from the start of `data`, advancing past it
+pub(crate) fn read_sleb128(data: &mut &[u8]) -> Result<i64, Error> {
+    let mut value = 0i64;
+    let mut shift = 0;
+    loop {
+        let (&byte, rest) = data.split_first().ok_or(Error::Truncated)?;
+        *data = rest;
+        if shift >= 64 {
+            return Err(Error::Malformed);
+        }
+        value |= i64::from(byte & 0x7f) << shift;
+        shift += 7;
+        if byte & 0x80 == 0 {
+            if shift < 64 && byte & 0x40 != 0 {
+                value |= -1 << shift;
+            }
+            return Ok(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn leb128() {
+        let mut data: &[u8] = &[0xe5, 0x8e, 0x26, 0x02];
+        assert_eq!(read_uleb128(&mut data), Ok(624485));
+        assert_eq!(read_uleb128(&mut data), Ok(2));
+        assert_eq!(read_uleb128(&mut data), Err(Error::Truncated));
+        let mut data: &[u8] = &[0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0xff, 0x01];
+        assert_eq!(read_uleb128(&mut data), Ok(u64::MAX));
+        let mut data: &[u8] = &[0xc0, 0xbb, 0x78, 0x3f, 0x40];
+        assert_eq!(read_sleb128(&mut data), Ok(-123456));
+        assert_eq!(read_sleb128(&mut data), Ok(63));
+        assert_eq!(read_sleb128(&mut data), Ok(-64));
+    }
+}
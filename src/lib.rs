@@ -3,6 +3,40 @@
 #[macro_use]
 mod macros;
 
+pub mod arm;
+pub mod attributes;
+pub mod auxv;
+pub mod binary;
+pub mod bpf;
+pub mod btf;
+pub mod coredump;
+mod de;
+mod file;
+pub mod flags;
+pub mod hex;
+pub mod init;
+pub mod kmod;
+mod leb128;
+pub mod link;
+pub mod load;
+pub mod machine;
+pub mod mips;
+pub mod note;
+pub mod packed;
+pub mod ppc64;
+pub mod property;
+pub mod reloc;
+pub mod relocate;
+pub mod riscv;
+mod ser;
+#[cfg(test)]
+mod testutil;
+pub mod tls;
+pub mod uf2;
+
+pub use file::*;
+
+use core::convert::TryFrom;
 use core::mem::size_of;
 use serde::{de::DeserializeOwned, Deserialize, Serialize};
 
@@ -10,6 +44,47 @@ pub trait ElfType: Clone + Copy + Default + Eq + PartialEq + DeserializeOwned +
 impl ElfType for u32 {}
 impl ElfType for u64 {}
 
+/// Errors that can occur while reading ELF data
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Error {
+    /// Data ended before the structure being read
+    Truncated,
+    /// File doesn't start with [`ehdr::Eident::MAGIC`]
+    BadMagic,
+    /// Class, data encoding, version or structure that isn't supported
+    Unsupported,
+    /// Header values that are inconsistent with each other or the data
+    Malformed,
+    /// Requested item doesn't exist
+    NotFound,
+    /// Relocated value doesn't fit in its field
+    Overflow,
+}
+impl core::fmt::Display for Error {
+    fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        match self {
+            Error::Truncated => write!(f, "Unexpected end of data"),
+            Error::BadMagic => write!(f, "Not an ELF file"),
+            Error::Unsupported => write!(f, "Unsupported ELF data"),
+            Error::Malformed => write!(f, "Malformed ELF data"),
+            Error::NotFound => write!(f, "Not found"),
+            Error::Overflow => write!(f, "Relocation out of range"),
+        }
+    }
+}
+
+/// Value paired with the machine and OS ABI of its file, so that processor and operating
+/// system specific values are formatted with the right names
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Targeted<T> {
+    /// Value to format
+    pub value: T,
+    /// Machine of the file containing the value
+    pub machine: ehdr::EM,
+    /// OS ABI of the file containing the value
+    pub osabi: ehdr::EIOSABI,
+}
+
 /// ELF file header types
 pub mod ehdr {
     use super::*;
@@ -99,28 +174,213 @@ pub mod ehdr {
         LOPROC = 0xff00 => "First processor specific type",
         HIPROC = 0xffff => "Last processor specific type",
     }
+    ranges {
+        os: LOOS..=HIOS,
+        processor: LOPROC..=HIPROC,
+    }
     );
 
     enum_struct!(
     /// Object file machine architecture
     pub struct EM(u16) {
-        NONE        = 0     => "No machine",
-        SPARC       = 2     => "Sun SPARC",
-        X86         = 3     => "Intel 80386",
-        M68K        = 4     => "Motorola 68000",
-        MIPS        = 8     => "MIPS big-endian",
-        PPC32       = 20    => "PowerPC 32-bit",
-        PPC64       = 21    => "PowerPC 64-bit",
-        ARM         = 40    => "ARM 32-bit",
-        SPARCV9     = 43    => "Sun SPARC v9 (64-bit)",
-        X86_64      = 62    => "AMD x86-64",
-        AVR         = 83    => "Atmel AVR",
-        OPENRISC    = 92    => "OpenRISC",
-        XTENSA      = 94    => "Tensilica Xtensa",
-        HEXAGON     = 164   => "Qualcomm Hexagon DSP",
-        AARCH64     = 183   => "ARM 64-bit",
-        RISCV       = 243   => "RISC-V",
-        BPF         = 247   => "Linux BPF",
+        NONE            = 0      => "No machine",
+        M32             = 1      => "AT&T WE 32100",
+        SPARC           = 2      => "Sun SPARC",
+        X86             = 3      => "Intel 80386",
+        M68K            = 4      => "Motorola 68000",
+        M88K            = 5      => "Motorola m88k family",
+        IAMCU           = 6      => "Intel MCU",
+        I860            = 7      => "Intel 80860",
+        MIPS            = 8      => "MIPS big-endian",
+        S370            = 9      => "IBM System/370",
+        MIPS_RS3_LE     = 10     => "MIPS little-endian",
+        PARISC          = 15     => "HPPA",
+        VPP500          = 17     => "Fujitsu VPP500",
+        SPARC32PLUS     = 18     => "Sun SPARC v8+",
+        I960            = 19     => "Intel 80960",
+        PPC32           = 20     => "PowerPC 32-bit",
+        PPC64           = 21     => "PowerPC 64-bit",
+        S390            = 22     => "IBM S390",
+        SPU             = 23     => "IBM SPU/SPC",
+        V800            = 36     => "NEC V800 series",
+        FR20            = 37     => "Fujitsu FR20",
+        RH32            = 38     => "TRW RH-32",
+        RCE             = 39     => "Motorola RCE",
+        ARM             = 40     => "ARM 32-bit",
+        OLD_ALPHA       = 41     => "Digital Alpha (old value)",
+        SH              = 42     => "Hitachi SH",
+        SPARCV9         = 43     => "Sun SPARC v9 (64-bit)",
+        TRICORE         = 44     => "Siemens Tricore",
+        ARC             = 45     => "Argonaut RISC Core",
+        H8_300          = 46     => "Hitachi H8/300",
+        H8_300H         = 47     => "Hitachi H8/300H",
+        H8S             = 48     => "Hitachi H8S",
+        H8_500          = 49     => "Hitachi H8/500",
+        IA_64           = 50     => "Intel IA-64",
+        MIPS_X          = 51     => "Stanford MIPS-X",
+        COLDFIRE        = 52     => "Motorola Coldfire",
+        M68HC12         = 53     => "Motorola M68HC12",
+        MMA             = 54     => "Fujitsu MMA Multimedia Accelerator",
+        PCP             = 55     => "Siemens PCP",
+        NCPU            = 56     => "Sony nCPU embedded RISC",
+        NDR1            = 57     => "Denso NDR1 microprocessor",
+        STARCORE        = 58     => "Motorola Start*Core processor",
+        ME16            = 59     => "Toyota ME16 processor",
+        ST100           = 60     => "STMicroelectronics ST100",
+        TINYJ           = 61     => "Advanced Logic Corp. TinyJ",
+        X86_64          = 62     => "AMD x86-64",
+        PDSP            = 63     => "Sony DSP Processor",
+        PDP10           = 64     => "Digital PDP-10",
+        PDP11           = 65     => "Digital PDP-11",
+        FX66            = 66     => "Siemens FX66 microcontroller",
+        ST9PLUS         = 67     => "STMicroelectronics ST9+ 8/16-bit microcontroller",
+        ST7             = 68     => "STMicroelectronics ST7 8-bit microcontroller",
+        M68HC16         = 69     => "Motorola MC68HC16 microcontroller",
+        M68HC11         = 70     => "Motorola MC68HC11 microcontroller",
+        M68HC08         = 71     => "Motorola MC68HC08 microcontroller",
+        M68HC05         = 72     => "Motorola MC68HC05 microcontroller",
+        SVX             = 73     => "Silicon Graphics SVx",
+        ST19            = 74     => "STMicroelectronics ST19 8-bit microcontroller",
+        VAX             = 75     => "Digital VAX",
+        CRIS            = 76     => "Axis Communications 32-bit embedded processor",
+        JAVELIN         = 77     => "Infineon Technologies 32-bit embedded processor",
+        FIREPATH        = 78     => "Element 14 64-bit DSP Processor",
+        ZSP             = 79     => "LSI Logic 16-bit DSP Processor",
+        MMIX            = 80     => "Donald Knuth's educational 64-bit processor",
+        HUANY           = 81     => "Harvard University machine-independent object files",
+        PRISM           = 82     => "SiTera Prism",
+        AVR             = 83     => "Atmel AVR",
+        FR30            = 84     => "Fujitsu FR30",
+        D10V            = 85     => "Mitsubishi D10V",
+        D30V            = 86     => "Mitsubishi D30V",
+        V850            = 87     => "NEC v850",
+        M32R            = 88     => "Mitsubishi M32R",
+        MN10300         = 89     => "Matsushita MN10300",
+        MN10200         = 90     => "Matsushita MN10200",
+        PJ              = 91     => "picoJava",
+        OPENRISC        = 92     => "OpenRISC",
+        ARC_COMPACT     = 93     => "ARC International ARCompact",
+        XTENSA          = 94     => "Tensilica Xtensa",
+        VIDEOCORE       = 95     => "Alphamosaic VideoCore",
+        TMM_GPP         = 96     => "Thomson Multimedia General Purpose Processor",
+        NS32K           = 97     => "National Semiconductor 32000",
+        TPC             = 98     => "Tenor Network TPC",
+        SNP1K           = 99     => "Trebia SNP 1000",
+        ST200           = 100    => "STMicroelectronics ST200",
+        IP2K            = 101    => "Ubicom IP2xxx",
+        MAX             = 102    => "MAX processor",
+        CR              = 103    => "National Semiconductor CompactRISC",
+        F2MC16          = 104    => "Fujitsu F2MC16",
+        MSP430          = 105    => "Texas Instruments msp430",
+        BLACKFIN        = 106    => "Analog Devices Blackfin DSP",
+        SE_C33          = 107    => "Seiko Epson S1C33 family",
+        SEP             = 108    => "Sharp embedded microprocessor",
+        ARCA            = 109    => "Arca RISC",
+        UNICORE         = 110    => "PKU-Unity & MPRC Peking University microcontroller",
+        EXCESS          = 111    => "eXcess configurable CPU",
+        DXP             = 112    => "Icera Semiconductor Deep Execution Processor",
+        NIOS2           = 113    => "Altera Nios II",
+        CRX             = 114    => "National Semiconductor CompactRISC CRX",
+        XGATE           = 115    => "Motorola XGATE",
+        C166            = 116    => "Infineon C16x/XC16x",
+        M16C            = 117    => "Renesas M16C",
+        DSPIC30F        = 118    => "Microchip Technology dsPIC30F",
+        CE              = 119    => "Freescale Communication Engine RISC",
+        M32C            = 120    => "Renesas M32C",
+        TSK3000         = 131    => "Altium TSK3000",
+        RS08            = 132    => "Freescale RS08",
+        SHARC           = 133    => "Analog Devices SHARC family",
+        ECOG2           = 134    => "Cyan Technology eCOG2",
+        SCORE7          = 135    => "Sunplus S+core7 RISC",
+        DSP24           = 136    => "New Japan Radio (NJR) 24-bit DSP",
+        VIDEOCORE3      = 137    => "Broadcom VideoCore III",
+        LATTICEMICO32   = 138    => "RISC for Lattice FPGA",
+        SE_C17          = 139    => "Seiko Epson C17",
+        TI_C6000        = 140    => "Texas Instruments TMS320C6000 DSP",
+        TI_C2000        = 141    => "Texas Instruments TMS320C2000 DSP",
+        TI_C5500        = 142    => "Texas Instruments TMS320C55x DSP",
+        TI_ARP32        = 143    => "Texas Instruments Application Specific RISC",
+        TI_PRU          = 144    => "Texas Instruments Programmable Realtime Unit",
+        MMDSP_PLUS      = 160    => "STMicroelectronics 64bit VLIW DSP",
+        CYPRESS_M8C     = 161    => "Cypress M8C",
+        R32C            = 162    => "Renesas R32C",
+        TRIMEDIA        = 163    => "NXP Semiconductors TriMedia",
+        HEXAGON         = 164    => "Qualcomm Hexagon DSP",
+        I8051           = 165    => "Intel 8051 and variants",
+        STXP7X          = 166    => "STMicroelectronics STxP7x",
+        NDS32           = 167    => "Andes Tech. compact code emb. RISC",
+        ECOG1X          = 168    => "Cyan Technology eCOG1X",
+        MAXQ30          = 169    => "Dallas Semiconductor MAXQ30",
+        XIMO16          = 170    => "New Japan Radio (NJR) 16-bit DSP",
+        MANIK           = 171    => "M2000 Reconfigurable RISC",
+        CRAYNV2         = 172    => "Cray NV2 vector architecture",
+        RX              = 173    => "Renesas RX",
+        METAG           = 174    => "Imagination Technologies META",
+        MCST_ELBRUS     = 175    => "MCST Elbrus",
+        ECOG16          = 176    => "Cyan Technology eCOG16",
+        CR16            = 177    => "National Semiconductor CompactRISC CR16",
+        ETPU            = 178    => "Freescale Extended Time Processing Unit",
+        SLE9X           = 179    => "Infineon Technologies SLE9X",
+        L10M            = 180    => "Intel L10M",
+        K10M            = 181    => "Intel K10M",
+        AARCH64         = 183    => "ARM 64-bit",
+        AVR32           = 185    => "Atmel AVR32",
+        STM8            = 186    => "STMicroelectronics STM8",
+        TILE64          = 187    => "Tilera TILE64",
+        TILEPRO         = 188    => "Tilera TILEPro",
+        MICROBLAZE      = 189    => "Xilinx MicroBlaze",
+        CUDA            = 190    => "NVIDIA CUDA",
+        TILEGX          = 191    => "Tilera TILE-Gx",
+        CLOUDSHIELD     = 192    => "CloudShield",
+        COREA_1ST       = 193    => "KIPO-KAIST Core-A 1st gen",
+        COREA_2ND       = 194    => "KIPO-KAIST Core-A 2nd gen",
+        ARCV2           = 195    => "Synopsys ARCv2 ISA",
+        OPEN8           = 196    => "Open8 RISC",
+        RL78            = 197    => "Renesas RL78",
+        VIDEOCORE5      = 198    => "Broadcom VideoCore V",
+        RENESAS_78KOR   = 199    => "Renesas 78KOR",
+        DSC_56800EX     = 200    => "Freescale 56800EX DSC",
+        BA1             = 201    => "Beyond BA1",
+        BA2             = 202    => "Beyond BA2",
+        XCORE           = 203    => "XMOS xCORE",
+        MCHP_PIC        = 204    => "Microchip 8-bit PIC",
+        INTELGT         = 205    => "Intel Graphics Technology",
+        KM32            = 210    => "KM211 KM32",
+        KMX32           = 211    => "KM211 KMX32",
+        EMX16           = 212    => "KM211 KMX16",
+        EMX8            = 213    => "KM211 KMX8",
+        KVARC           = 214    => "KM211 KVARC",
+        CDP             = 215    => "Paneve CDP",
+        COGE            = 216    => "Cognitive Smart Memory Processor",
+        COOL            = 217    => "Bluechip CoolEngine",
+        NORC            = 218    => "Nanoradio Optimized RISC",
+        CSR_KALIMBA     = 219    => "CSR Kalimba",
+        Z80             = 220    => "Zilog Z80",
+        VISIUM          = 221    => "Controls and Data Services VISIUMcore",
+        FT32            = 222    => "FTDI Chip FT32",
+        MOXIE           = 223    => "Moxie processor",
+        AMDGPU          = 224    => "AMD GPU",
+        RISCV           = 243    => "RISC-V",
+        LANAI           = 244    => "Lanai 32-bit processor",
+        CEVA            = 245    => "CEVA Processor Architecture Family",
+        CEVA_X2         = 246    => "CEVA X2 Processor Family",
+        BPF             = 247    => "Linux BPF",
+        GRAPHCORE_IPU   = 248    => "Graphcore Intelligent Processing Unit",
+        IMG1            = 249    => "Imagination Technologies",
+        NFP             = 250    => "Netronome Flow Processor",
+        VE              = 251    => "NEC Vector Engine",
+        CSKY            = 252    => "C-SKY",
+        ARC_COMPACT3_64 = 253    => "Synopsys ARCv3 64-bit",
+        MCS6502         = 254    => "MOS Technology MCS 6502",
+        ARC_COMPACT3    = 255    => "Synopsys ARCv3 32-bit",
+        KVX             = 256    => "Kalray VLIW core of the MPPA processor family",
+        WDC_65816       = 257    => "WDC 65816/65C816",
+        LOONGARCH       = 258    => "LoongArch",
+        KF32            = 259    => "ChipON KungFu 32",
+        U16_U8CORE      = 260    => "LAPIS nX-U16/U8",
+        TACHYUM         = 261    => "Tachyum",
+        DSC_56800EF     = 262    => "NXP 56800EF Digital Signal Controller",
+        ALPHA           = 0x9026 => "Digital Alpha",
     }
     );
 
@@ -137,6 +397,43 @@ pub mod ehdr {
     pub struct EF(u32) {
         NONE = 0 => "No flags",
     }
+    machines {
+        ARM {
+            ARM_ABI_FLOAT_SOFT     = 0x200      => "Soft float ABI",
+            ARM_ABI_FLOAT_HARD     = 0x400      => "Hard float ABI",
+            ARM_BE8                = 0x800000   => "BE8 byte order",
+        }
+        RISCV {
+            RISCV_RVC              = 0x1        => "Compressed instructions",
+            RISCV_FLOAT_ABI_SINGLE = 0x2        => "Single-precision float ABI",
+            RISCV_FLOAT_ABI_DOUBLE = 0x4        => "Double-precision float ABI",
+            RISCV_RVE              = 0x8        => "Reduced register ABI",
+            RISCV_TSO              = 0x10       => "Total store ordering",
+        }
+        MIPS {
+            MIPS_NOREORDER         = 0x1        => "No reordering",
+            MIPS_PIC               = 0x2        => "Position independent code",
+            MIPS_CPIC              = 0x4        => "Calls through PIC sequences",
+            MIPS_XGOT              = 0x8        => "Large GOT",
+            MIPS_UCODE             = 0x10       => "UCODE",
+            MIPS_ABI2              = 0x20       => "N32 ABI",
+            MIPS_OPTIONS_FIRST     = 0x80       => "Options section first",
+            MIPS_32BITMODE         = 0x100      => "64-bit code in 32-bit mode",
+            MIPS_FP64              = 0x200      => "64-bit floating point registers",
+            MIPS_NAN2008           = 0x400      => "IEEE 754-2008 NaN encoding",
+        }
+        PPC32 {
+            PPC_EMB                = 0x80000000 => "Embedded",
+            PPC_RELOCATABLE        = 0x10000    => "Relocatable",
+            PPC_RELOCATABLE_LIB    = 0x8000     => "Relocatable library",
+        }
+        SPARC | SPARCV9 {
+            SPARC_32PLUS           = 0x100      => "Generic V8+ features",
+            SPARC_SUN_US1          = 0x200      => "Sun UltraSPARC 1 extensions",
+            SPARC_HAL_R1           = 0x400      => "HAL R1 extensions",
+            SPARC_SUN_US3          = 0x800      => "Sun UltraSPARC 3 extensions",
+        }
+    }
     );
 }
 
@@ -179,6 +476,23 @@ pub struct Ehdr<T: ElfType> {
 impl<T: ElfType> Ehdr<T> {
     pub const SIZE: usize = size_of::<Self>();
 }
+impl Ehdr64 {
+    /// Header of a file of the given class, byte order, type and machine, for writing files
+    ///
+    /// The remaining fields are zero, and the header and table sizes are filled in when the
+    /// file is written.
+    pub fn new(class: ehdr::EIC, data: ehdr::EID, e_type: ehdr::ET, e_machine: ehdr::EM) -> Self {
+        let mut ehdr = Ehdr64::default();
+        ehdr.e_ident.magic = ehdr::Eident::MAGIC;
+        ehdr.e_ident.class = class;
+        ehdr.e_ident.data = data;
+        ehdr.e_ident.version = ehdr::EIV::CURRENT;
+        ehdr.e_type = e_type;
+        ehdr.e_machine = e_machine;
+        ehdr.e_version = ehdr::EV::CURRENT;
+        ehdr
+    }
+}
 impl From<Ehdr32> for Ehdr64 {
     fn from(o: Ehdr32) -> Ehdr64 {
         Ehdr64 {
@@ -200,6 +514,29 @@ impl From<Ehdr32> for Ehdr64 {
     }
 }
 
+impl TryFrom<Ehdr64> for Ehdr32 {
+    type Error = Error;
+    fn try_from(o: Ehdr64) -> Result<Ehdr32, Error> {
+        let word = |value: u64| u32::try_from(value).map_err(|_| Error::Unsupported);
+        Ok(Ehdr32 {
+            e_ident: o.e_ident,
+            e_type: o.e_type,
+            e_machine: o.e_machine,
+            e_version: o.e_version,
+            e_entry: word(o.e_entry)?,
+            e_phoff: word(o.e_phoff)?,
+            e_shoff: word(o.e_shoff)?,
+            e_flags: o.e_flags,
+            e_ehsize: o.e_ehsize,
+            e_phentsize: o.e_phentsize,
+            e_phnum: o.e_phnum,
+            e_shentsize: o.e_shentsize,
+            e_shnum: o.e_shnum,
+            e_shstrndx: o.e_shstrndx,
+        })
+    }
+}
+
 pub type Ehdr32 = Ehdr<u32>;
 pub type Ehdr64 = Ehdr<u64>;
 
@@ -208,19 +545,59 @@ pub mod phdr {
     enum_struct!(
     /// Segment type
     pub struct PT(u32) {
-        NULL    = 0          => "Unused",
-        LOAD    = 1          => "Loadable",
-        DYNAMIC = 2          => "Dynamic linking information",
-        INTERP  = 3          => "Program interpreter",
-        NOTE    = 4          => "Auxiliary information",
-        SHLIB   = 5          => "Reserved",
-        PHDR    = 6          => "Program header table",
-        LOOS    = 0x60000000 => "First operating system specific type",
-        HIOS    = 0x6fffffff => "Last operating system specific type",
-        LOPROC  = 0x70000000 => "First processor specific type",
-        HIPROC  = 0x7fffffff => "Last processor specific type",
+        NULL              = 0          => "Unused",
+        LOAD              = 1          => "Loadable",
+        DYNAMIC           = 2          => "Dynamic linking information",
+        INTERP            = 3          => "Program interpreter",
+        NOTE              = 4          => "Auxiliary information",
+        SHLIB             = 5          => "Reserved",
+        PHDR              = 6          => "Program header table",
+        TLS               = 7          => "Thread-local storage template",
+        LOOS              = 0x60000000 => "First operating system specific type",
+        SUNW_UNWIND       = 0x6464e550 => "Stack unwind tables",
+        GNU_EH_FRAME      = 0x6474e550 => "Exception handling frame table",
+        GNU_STACK         = 0x6474e551 => "Stack flags",
+        GNU_RELRO         = 0x6474e552 => "Read-only after relocation",
+        GNU_PROPERTY      = 0x6474e553 => "GNU property notes",
+        OPENBSD_RANDOMIZE = 0x65a3dbe6 => "Fill with random data",
+        OPENBSD_WXNEEDED  = 0x65a3dbe7 => "Program violates W^X",
+        OPENBSD_NOBTCFI   = 0x65a3dbe8 => "No branch target CFI",
+        OPENBSD_BOOTDATA  = 0x65a41be6 => "Boot arguments",
+        SUNWBSS           = 0x6ffffffa => "Sun specific segment",
+        SUNWSTACK         = 0x6ffffffb => "Stack segment",
+        HIOS              = 0x6fffffff => "Last operating system specific type",
+        LOPROC            = 0x70000000 => "First processor specific type",
+        HIPROC            = 0x7fffffff => "Last processor specific type",
+    }
+    ranges {
+        os: LOOS..=HIOS,
+        processor: LOPROC..=HIPROC,
+    }
+    machines {
+        ARM {
+            ARM_ARCHEXT       = 0x70000000 => "Architecture compatibility information",
+            ARM_EXIDX         = 0x70000001 => "Exception unwind index table",
+        }
+        AARCH64 {
+            AARCH64_ARCHEXT   = 0x70000000 => "Architecture compatibility information",
+            AARCH64_UNWIND    = 0x70000001 => "Exception unwind tables",
+            AARCH64_MEMTAG_MTE = 0x70000002 => "Memory tagging extension tag storage",
+        }
+        MIPS {
+            MIPS_REGINFO      = 0x70000000 => "Register usage information",
+            MIPS_RTPROC       = 0x70000001 => "Runtime procedure table",
+            MIPS_OPTIONS      = 0x70000002 => "Options",
+            MIPS_ABIFLAGS     = 0x70000003 => "ABI flags",
+        }
+        RISCV {
+            RISCV_ATTRIBUTES  = 0x70000003 => "RISC-V attributes",
+        }
     }
     );
+    impl PT {
+        /// Exception handling frame table (Solaris name for [`PT::GNU_EH_FRAME`])
+        pub const SUNW_EH_FRAME: PT = PT(0x6474e550);
+    }
 
     flag_struct!(
     /// Segment flags
@@ -302,56 +679,228 @@ impl From<Phdr32> for Phdr64 {
         }
     }
 }
+impl TryFrom<Phdr64> for Phdr32 {
+    type Error = Error;
+    fn try_from(o: Phdr64) -> Result<Phdr32, Error> {
+        let word = |value: u64| u32::try_from(value).map_err(|_| Error::Unsupported);
+        Ok(Phdr32 {
+            p_type: o.p_type,
+            p_offset: word(o.p_offset)?,
+            p_vaddr: word(o.p_vaddr)?,
+            p_paddr: word(o.p_paddr)?,
+            p_filesz: word(o.p_filesz)?,
+            p_memsz: word(o.p_memsz)?,
+            p_flags: o.p_flags,
+            p_align: word(o.p_align)?,
+        })
+    }
+}
 
 /// Section header types
 pub mod shdr {
     enum_struct!(
     /// Section type
     pub struct SHT(u32) {
-        NULL          = 0          => "Unused",
-        PROGBITS      = 1          => "Program data",
-        SYMTAB        = 2          => "Symbol table",
-        STRTAB        = 3          => "String table",
-        RELA          = 4          => "Relocation entries, with addends",
-        HASH          = 5          => "Symbol hash table",
-        DYNAMIC       = 6          => "Dynamic linking information",
-        NOTE          = 7          => "Notes",
-        NOBITS        = 8          => "Program space with no data (BSS)",
-        REL           = 9          => "Relocation entries, no addends",
-        SHLIB         = 10         => "Reserved",
-        DYNSYM        = 11         => "Dynamic linker symbol table",
-        INIT_ARRAY    = 14         => "Constructors",
-        FINI_ARRAY    = 15         => "Destructors",
-        PREINIT_ARRAY = 16         => "Pre-constructors",
-        GROUP         = 17         => "Section group",
-        SYMTAB_SHNDX  = 18         => "Extended",
-        LOOS          = 0x60000000 => "First operating system specific type",
-        HIOS          = 0x6fffffff => "Last operating system specific type",
-        LOPROC        = 0x70000000 => "First processor specific type",
-        HIPROC        = 0x7fffffff => "Last processor specific type",
-        LOUSER        = 0x80000000 => "First user specific type",
-        HIUSER        = 0x8fffffff => "Last user specific type",
+        NULL           = 0          => "Unused",
+        PROGBITS       = 1          => "Program data",
+        SYMTAB         = 2          => "Symbol table",
+        STRTAB         = 3          => "String table",
+        RELA           = 4          => "Relocation entries, with addends",
+        HASH           = 5          => "Symbol hash table",
+        DYNAMIC        = 6          => "Dynamic linking information",
+        NOTE           = 7          => "Notes",
+        NOBITS         = 8          => "Program space with no data (BSS)",
+        REL            = 9          => "Relocation entries, no addends",
+        SHLIB          = 10         => "Reserved",
+        DYNSYM         = 11         => "Dynamic linker symbol table",
+        INIT_ARRAY     = 14         => "Constructors",
+        FINI_ARRAY     = 15         => "Destructors",
+        PREINIT_ARRAY  = 16         => "Pre-constructors",
+        GROUP          = 17         => "Section group",
+        SYMTAB_SHNDX   = 18         => "Extended",
+        RELR           = 19         => "Relative relocations",
+        LOOS           = 0x60000000 => "First operating system specific type",
+        GNU_ATTRIBUTES = 0x6ffffff5 => "Object attributes",
+        GNU_HASH       = 0x6ffffff6 => "GNU symbol hash table",
+        GNU_LIBLIST    = 0x6ffffff7 => "Prelink library list",
+        CHECKSUM       = 0x6ffffff8 => "Checksum for DSO content",
+        SUNW_MOVE      = 0x6ffffffa => "Partially initialized data",
+        SUNW_COMDAT    = 0x6ffffffb => "COMDAT section",
+        SUNW_SYMINFO   = 0x6ffffffc => "Additional symbol information",
+        GNU_VERDEF     = 0x6ffffffd => "Version definitions",
+        GNU_VERNEED    = 0x6ffffffe => "Version needs",
+        GNU_VERSYM     = 0x6fffffff => "Symbol versions",
+        LOPROC         = 0x70000000 => "First processor specific type",
+        HIPROC         = 0x7fffffff => "Last processor specific type",
+        LOUSER         = 0x80000000 => "First user specific type",
+        HIUSER         = 0x8fffffff => "Last user specific type",
+    }
+    ranges {
+        os: LOOS..=HIOS,
+        processor: LOPROC..=HIPROC,
+        user: LOUSER..=HIUSER,
+    }
+    machines {
+        ARM {
+            ARM_EXIDX          = 0x70000001 => "Exception unwind index table",
+            ARM_PREEMPTMAP     = 0x70000002 => "BPABI DLL dynamic linking pre-emption map",
+            ARM_ATTRIBUTES     = 0x70000003 => "Build attributes",
+            ARM_DEBUGOVERLAY   = 0x70000004 => "Debug overlay",
+            ARM_OVERLAYSECTION = 0x70000005 => "Overlay section",
+        }
+        AARCH64 {
+            AARCH64_ATTRIBUTES = 0x70000003 => "Build attributes",
+            AARCH64_AUTH_RELR  = 0x70000004 => "Relative relocations for signed pointers",
+            AARCH64_MEMTAG_GLOBALS_STATIC = 0x70000007 => "Tagged globals in a relocatable object",
+            AARCH64_MEMTAG_GLOBALS_DYNAMIC = 0x70000008 => "Tagged globals in a linked object",
+        }
+        X86_64 {
+            X86_64_UNWIND      = 0x70000001 => "Unwind information",
+        }
+        MIPS {
+            MIPS_LIBLIST       = 0x70000000 => "Shared objects used in link",
+            MIPS_MSYM          = 0x70000001 => "Additional symbol information",
+            MIPS_CONFLICT      = 0x70000002 => "Conflicting symbols",
+            MIPS_GPTAB         = 0x70000003 => "Global pointer table",
+            MIPS_UCODE         = 0x70000004 => "Reserved",
+            MIPS_DEBUG         = 0x70000005 => "Debugging information",
+            MIPS_REGINFO       = 0x70000006 => "Register usage information",
+            MIPS_OPTIONS       = 0x7000000d => "Options",
+            MIPS_DWARF         = 0x7000001e => "DWARF debugging information",
+            MIPS_ABIFLAGS      = 0x7000002a => "ABI flags",
+            MIPS_XHASH         = 0x7000002b => "Symbol hash table with version indexes",
+        }
+        RISCV {
+            RISCV_ATTRIBUTES   = 0x70000003 => "RISC-V attributes",
+        }
+    }
+    osabis {
+        SYSV {
+            ANDROID_REL        = 0x60000001 => "Android packed relocations",
+            ANDROID_RELA       = 0x60000002 => "Android packed relocations with addends",
+            ANDROID_RELR       = 0x6fffff00 => "Android relative relocations",
+        }
     }
     );
+    impl SHT {
+        /// Last operating system specific type (shared with [`SHT::GNU_VERSYM`])
+        pub const HIOS: SHT = SHT(0x6fffffff);
+    }
 
     flag_struct!(
     /// Section flags (32-bit)
     pub struct SHF32(u32) {
-        NONE = 0 => "No flags",
+        NONE             = 0          => "No flags",
+        WRITE            = 0x1        => "Writable",
+        ALLOC            = 0x2        => "Occupies memory during execution",
+        EXECINSTR        = 0x4        => "Executable",
+        MERGE            = 0x10       => "Might be merged",
+        STRINGS          = 0x20       => "Contains nul-terminated strings",
+        INFO_LINK        = 0x40       => "`sh_info` contains section index",
+        LINK_ORDER       = 0x80       => "Preserve order after combining",
+        OS_NONCONFORMING = 0x100      => "Non-standard OS specific handling required",
+        GROUP            = 0x200      => "Section is member of a group",
+        TLS              = 0x400      => "Section holds thread-local data",
+        COMPRESSED       = 0x800      => "Section with compressed data",
+        GNU_RETAIN       = 0x200000   => "Not to be garbage collected by the linker",
+        EXCLUDE          = 0x80000000 => "Excluded from executable and shared library",
+    }
+    machines {
+        ARM {
+            ARM_PURECODE     = 0x20000000 => "Contains only instructions, no data",
+        }
+        AARCH64 {
+            AARCH64_PURECODE = 0x20000000 => "Contains only instructions, no data",
+        }
+        X86_64 {
+            X86_64_LARGE     = 0x10000000 => "Beyond the reach of 32-bit offsets",
+        }
+        MIPS {
+            MIPS_NODUPES     = 0x1000000  => "Identical definitions are merged",
+            MIPS_NAMES       = 0x2000000  => "Linker must generate implicit hidden weak names",
+            MIPS_LOCAL       = 0x4000000  => "Local to the process",
+            MIPS_NOSTRIP     = 0x8000000  => "Not to be stripped",
+            MIPS_GPREL       = 0x10000000 => "Must be part of the global data area",
+            MIPS_MERGE       = 0x20000000 => "Can be merged",
+            MIPS_ADDR        = 0x40000000 => "Contains address data",
+        }
     }
     );
 
     flag_struct!(
     /// Section flags (64-bit)
     pub struct SHF64(u64) {
-        NONE = 0 => "No flags",
+        NONE             = 0          => "No flags",
+        WRITE            = 0x1        => "Writable",
+        ALLOC            = 0x2        => "Occupies memory during execution",
+        EXECINSTR        = 0x4        => "Executable",
+        MERGE            = 0x10       => "Might be merged",
+        STRINGS          = 0x20       => "Contains nul-terminated strings",
+        INFO_LINK        = 0x40       => "`sh_info` contains section index",
+        LINK_ORDER       = 0x80       => "Preserve order after combining",
+        OS_NONCONFORMING = 0x100      => "Non-standard OS specific handling required",
+        GROUP            = 0x200      => "Section is member of a group",
+        TLS              = 0x400      => "Section holds thread-local data",
+        COMPRESSED       = 0x800      => "Section with compressed data",
+        GNU_RETAIN       = 0x200000   => "Not to be garbage collected by the linker",
+        EXCLUDE          = 0x80000000 => "Excluded from executable and shared library",
+    }
+    machines {
+        ARM {
+            ARM_PURECODE     = 0x20000000 => "Contains only instructions, no data",
+        }
+        AARCH64 {
+            AARCH64_PURECODE = 0x20000000 => "Contains only instructions, no data",
+        }
+        X86_64 {
+            X86_64_LARGE     = 0x10000000 => "Beyond the reach of 32-bit offsets",
+        }
+        MIPS {
+            MIPS_NODUPES     = 0x1000000  => "Identical definitions are merged",
+            MIPS_NAMES       = 0x2000000  => "Linker must generate implicit hidden weak names",
+            MIPS_LOCAL       = 0x4000000  => "Local to the process",
+            MIPS_NOSTRIP     = 0x8000000  => "Not to be stripped",
+            MIPS_GPREL       = 0x10000000 => "Must be part of the global data area",
+            MIPS_MERGE       = 0x20000000 => "Can be merged",
+            MIPS_ADDR        = 0x40000000 => "Contains address data",
+        }
     }
     );
+    impl SHF32 {
+        /// MIPS section contains strings (shares its bit with [`SHF32::EXCLUDE`], which names it)
+        pub const MIPS_STRING: SHF32 = SHF32(0x80000000);
+    }
+    impl SHF64 {
+        /// MIPS section contains strings (shares its bit with [`SHF64::EXCLUDE`], which names it)
+        pub const MIPS_STRING: SHF64 = SHF64(0x80000000);
+    }
     impl From<SHF32> for SHF64 {
         fn from(other: SHF32) -> SHF64 {
             SHF64(other.0 as u64)
         }
     }
+
+    enum_struct!(
+    /// Special section indexes
+    pub struct SHN(u16) {
+        UNDEF  = 0      => "Undefined section",
+        LOPROC = 0xff00 => "First processor specific index",
+        HIPROC = 0xff1f => "Last processor specific index",
+        LOOS   = 0xff20 => "First operating system specific index",
+        HIOS   = 0xff3f => "Last operating system specific index",
+        ABS    = 0xfff1 => "Absolute values",
+        COMMON = 0xfff2 => "Common symbols",
+        XINDEX = 0xffff => "Index is in extra table",
+    }
+    ranges {
+        os: LOOS..=HIOS,
+        processor: LOPROC..=HIPROC,
+    }
+    );
+    impl SHN {
+        /// First reserved index, sections at or above this index can't be referenced directly
+        pub const LORESERVE: SHN = SHN(0xff00);
+    }
 }
 
 // Trait for Shdr32 and Shdr64
@@ -434,6 +983,407 @@ impl From<Shdr32> for Shdr64 {
     }
 }
 
+/// Symbol table types
+pub mod sym {
+    enum_struct!(
+    /// Symbol binding
+    pub struct STB(u8) {
+        LOCAL  = 0  => "Local",
+        GLOBAL = 1  => "Global",
+        WEAK   = 2  => "Weak",
+        LOOS   = 10 => "First operating system specific binding",
+        HIOS   = 12 => "Last operating system specific binding",
+        LOPROC = 13 => "First processor specific binding",
+        HIPROC = 15 => "Last processor specific binding",
+    }
+    ranges {
+        os: LOOS..=HIOS,
+        processor: LOPROC..=HIPROC,
+    }
+    );
+
+    enum_struct!(
+    /// Symbol type
+    pub struct STT(u8) {
+        NOTYPE  = 0  => "Unspecified",
+        OBJECT  = 1  => "Data object",
+        FUNC    = 2  => "Function",
+        SECTION = 3  => "Section",
+        FILE    = 4  => "Source file",
+        COMMON  = 5  => "Common data object",
+        TLS     = 6  => "Thread-local data object",
+        LOOS    = 10 => "First operating system specific type",
+        HIOS    = 12 => "Last operating system specific type",
+        LOPROC  = 13 => "First processor specific type",
+        HIPROC  = 15 => "Last processor specific type",
+    }
+    ranges {
+        os: LOOS..=HIOS,
+        processor: LOPROC..=HIPROC,
+    }
+    osabis {
+        SYSV | LINUX | FREEBSD {
+            GNU_IFUNC = 10 => "Indirect function",
+        }
+    }
+    );
+
+    enum_struct!(
+    /// Symbol visibility
+    pub struct STV(u8) {
+        DEFAULT   = 0 => "Default",
+        INTERNAL  = 1 => "Internal",
+        HIDDEN    = 2 => "Hidden",
+        PROTECTED = 3 => "Protected",
+    }
+    );
+}
+
+/// 32-bit symbol table entry
+#[derive(Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Sym32 {
+    /// Symbol name (string table index)
+    pub st_name: u32,
+    /// Symbol value
+    pub st_value: u32,
+    /// Size of object
+    pub st_size: u32,
+    /// Symbol binding and type
+    pub st_info: u8,
+    /// Symbol visibility
+    pub st_other: u8,
+    /// Index of section the symbol is defined in
+    pub st_shndx: shdr::SHN,
+}
+
+/// 64-bit symbol table entry
+#[derive(Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Sym64 {
+    /// Symbol name (string table index)
+    pub st_name: u32,
+    /// Symbol binding and type
+    pub st_info: u8,
+    /// Symbol visibility
+    pub st_other: u8,
+    /// Index of section the symbol is defined in
+    pub st_shndx: shdr::SHN,
+    /// Symbol value
+    pub st_value: u64,
+    /// Size of object
+    pub st_size: u64,
+}
+impl Sym64 {
+    pub const SIZE: usize = size_of::<Self>();
+    /// Symbol binding
+    pub fn st_bind(&self) -> sym::STB {
+        sym::STB::from(self.st_info >> 4)
+    }
+    /// Symbol type
+    pub fn st_type(&self) -> sym::STT {
+        sym::STT::from(self.st_info & 0xf)
+    }
+    /// Symbol visibility
+    pub fn st_visibility(&self) -> sym::STV {
+        sym::STV::from(self.st_other & 0x3)
+    }
+}
+impl Sym32 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl From<Sym32> for Sym64 {
+    fn from(o: Sym32) -> Sym64 {
+        Sym64 {
+            st_name: o.st_name,
+            st_info: o.st_info,
+            st_other: o.st_other,
+            st_shndx: o.st_shndx,
+            st_value: o.st_value as u64,
+            st_size: o.st_size as u64,
+        }
+    }
+}
+
+/// 32-bit relocation entry
+#[derive(Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Rel32 {
+    /// Location to apply the relocation
+    pub r_offset: u32,
+    /// Symbol index and relocation type
+    pub r_info: u32,
+}
+
+/// 32-bit relocation entry with addend
+#[derive(Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Rela32 {
+    /// Location to apply the relocation
+    pub r_offset: u32,
+    /// Symbol index and relocation type
+    pub r_info: u32,
+    /// Constant addend
+    pub r_addend: i32,
+}
+
+/// 64-bit relocation entry
+#[derive(Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Rel64 {
+    /// Location to apply the relocation
+    pub r_offset: u64,
+    /// Symbol index and relocation type
+    pub r_info: u64,
+}
+
+/// 64-bit relocation entry with addend
+#[derive(Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Rela64 {
+    /// Location to apply the relocation
+    pub r_offset: u64,
+    /// Symbol index and relocation type
+    pub r_info: u64,
+    /// Constant addend
+    pub r_addend: i64,
+}
+impl Rel32 {
+    pub const SIZE: usize = size_of::<Self>();
+    /// Symbol table index
+    pub fn r_sym(&self) -> u32 {
+        self.r_info >> 8
+    }
+    /// Relocation type
+    pub fn r_type(&self) -> u32 {
+        self.r_info & 0xff
+    }
+}
+impl Rela32 {
+    pub const SIZE: usize = size_of::<Self>();
+    /// Symbol table index
+    pub fn r_sym(&self) -> u32 {
+        self.r_info >> 8
+    }
+    /// Relocation type
+    pub fn r_type(&self) -> u32 {
+        self.r_info & 0xff
+    }
+}
+impl Rel64 {
+    pub const SIZE: usize = size_of::<Self>();
+    /// Symbol table index
+    pub fn r_sym(&self) -> u32 {
+        (self.r_info >> 32) as u32
+    }
+    /// Relocation type
+    pub fn r_type(&self) -> u32 {
+        self.r_info as u32
+    }
+}
+impl Rela64 {
+    pub const SIZE: usize = size_of::<Self>();
+    /// Symbol table index
+    pub fn r_sym(&self) -> u32 {
+        (self.r_info >> 32) as u32
+    }
+    /// Relocation type
+    pub fn r_type(&self) -> u32 {
+        self.r_info as u32
+    }
+}
+impl From<Rel32> for Rel64 {
+    fn from(o: Rel32) -> Rel64 {
+        Rel64 {
+            r_offset: o.r_offset as u64,
+            r_info: (o.r_sym() as u64) << 32 | o.r_type() as u64,
+        }
+    }
+}
+impl From<Rela32> for Rela64 {
+    fn from(o: Rela32) -> Rela64 {
+        Rela64 {
+            r_offset: o.r_offset as u64,
+            r_info: (o.r_sym() as u64) << 32 | o.r_type() as u64,
+            r_addend: o.r_addend as i64,
+        }
+    }
+}
+
+/// Dynamic section types
+pub mod dynamic {
+    enum_struct!(
+    /// Dynamic entry type
+    pub struct DT(i64) {
+        NULL            = 0          => "End of dynamic section",
+        NEEDED          = 1          => "Name of needed library",
+        PLTRELSZ        = 2          => "Size of PLT relocations",
+        PLTGOT          = 3          => "Address of PLT and/or GOT",
+        HASH            = 4          => "Address of symbol hash table",
+        STRTAB          = 5          => "Address of string table",
+        SYMTAB          = 6          => "Address of symbol table",
+        RELA            = 7          => "Address of relocations with addends",
+        RELASZ          = 8          => "Size of relocations with addends",
+        RELAENT         = 9          => "Size of relocation with addend entry",
+        STRSZ           = 10         => "Size of string table",
+        SYMENT          = 11         => "Size of symbol table entry",
+        INIT            = 12         => "Address of initialization function",
+        FINI            = 13         => "Address of termination function",
+        SONAME          = 14         => "Name of shared object",
+        RPATH           = 15         => "Library search path (deprecated)",
+        SYMBOLIC        = 16         => "Start symbol search within shared object",
+        REL             = 17         => "Address of relocations",
+        RELSZ           = 18         => "Size of relocations",
+        RELENT          = 19         => "Size of relocation entry",
+        PLTREL          = 20         => "Type of PLT relocations",
+        DEBUG           = 21         => "Debugging",
+        TEXTREL         = 22         => "Relocations might modify non-writable segments",
+        JMPREL          = 23         => "Address of PLT relocations",
+        BIND_NOW        = 24         => "Process all relocations before execution",
+        INIT_ARRAY      = 25         => "Address of constructors",
+        FINI_ARRAY      = 26         => "Address of destructors",
+        INIT_ARRAYSZ    = 27         => "Size of constructors",
+        FINI_ARRAYSZ    = 28         => "Size of destructors",
+        RUNPATH         = 29         => "Library search path",
+        FLAGS           = 30         => "Flags",
+        PREINIT_ARRAY   = 32         => "Address of pre-constructors",
+        PREINIT_ARRAYSZ = 33         => "Size of pre-constructors",
+        SYMTAB_SHNDX    = 34         => "Address of extended section indexes",
+        RELRSZ          = 35         => "Size of relative relocations",
+        RELR            = 36         => "Address of relative relocations",
+        RELRENT         = 37         => "Size of relative relocation entry",
+        LOOS            = 0x6000000d => "First operating system specific type",
+        GNU_HASH        = 0x6ffffef5 => "Address of GNU symbol hash table",
+        HIOS            = 0x6ffff000 => "Last operating system specific type",
+        VERSYM          = 0x6ffffff0 => "Address of symbol versions",
+        RELACOUNT       = 0x6ffffff9 => "Number of relative relocations with addends",
+        RELCOUNT        = 0x6ffffffa => "Number of relative relocations",
+        FLAGS_1         = 0x6ffffffb => "Extended flags",
+        VERDEF          = 0x6ffffffc => "Address of version definitions",
+        VERDEFNUM       = 0x6ffffffd => "Number of version definitions",
+        VERNEED         = 0x6ffffffe => "Address of needed versions",
+        VERNEEDNUM      = 0x6fffffff => "Number of needed versions",
+        LOPROC          = 0x70000000 => "First processor specific type",
+        HIPROC          = 0x7fffffff => "Last processor specific type",
+    }
+    ranges {
+        os: LOOS..=HIOS,
+        processor: LOPROC..=HIPROC,
+    }
+    machines {
+        MIPS {
+            MIPS_RLD_VERSION  = 0x70000001 => "Runtime linker interface version",
+            MIPS_TIME_STAMP   = 0x70000002 => "Timestamp",
+            MIPS_ICHECKSUM    = 0x70000003 => "Checksum",
+            MIPS_IVERSION     = 0x70000004 => "Version string",
+            MIPS_FLAGS        = 0x70000005 => "Flags",
+            MIPS_BASE_ADDRESS = 0x70000006 => "Base address",
+            MIPS_MSYM         = 0x70000007 => "Address of additional symbol information",
+            MIPS_CONFLICT     = 0x70000008 => "Address of conflicting symbols",
+            MIPS_LIBLIST      = 0x70000009 => "Address of shared object list",
+            MIPS_LOCAL_GOTNO  = 0x7000000a => "Number of local GOT entries",
+            MIPS_CONFLICTNO   = 0x7000000b => "Number of conflicting symbols",
+            MIPS_LIBLISTNO    = 0x70000010 => "Number of shared objects",
+            MIPS_SYMTABNO     = 0x70000011 => "Number of dynamic symbols",
+            MIPS_UNREFEXTNO   = 0x70000012 => "First external unreferenced symbol",
+            MIPS_GOTSYM       = 0x70000013 => "First dynamic symbol with a GOT entry",
+            MIPS_HIPAGENO     = 0x70000014 => "Number of page table entries in GOT",
+            MIPS_RLD_MAP      = 0x70000016 => "Address of debug map",
+            MIPS_PLTGOT       = 0x70000032 => "Address of PLT GOT",
+            MIPS_RWPLT        = 0x70000034 => "Address of writable PLT",
+            MIPS_RLD_MAP_REL  = 0x70000035 => "Relative address of debug map",
+            MIPS_XHASH        = 0x70000036 => "Address of symbol hash table with version indexes",
+        }
+        PPC32 {
+            PPC_GOT           = 0x70000000 => "Address of GOT",
+            PPC_OPT           = 0x70000001 => "Optimization flags",
+        }
+        PPC64 {
+            PPC64_GLINK       = 0x70000000 => "Address of PLT call stubs",
+            PPC64_OPD         = 0x70000001 => "Address of function descriptors",
+            PPC64_OPDSZ       = 0x70000002 => "Size of function descriptors",
+            PPC64_OPT         = 0x70000003 => "Optimization flags",
+        }
+        AARCH64 {
+            AARCH64_BTI_PLT   = 0x70000001 => "PLT uses branch target identification",
+            AARCH64_PAC_PLT   = 0x70000003 => "PLT uses pointer authentication",
+            AARCH64_VARIANT_PCS = 0x70000005 => "Symbols use variant procedure call standards",
+        }
+        X86_64 {
+            X86_64_PLT        = 0x70000000 => "Address of PLT",
+            X86_64_PLTSZ      = 0x70000001 => "Size of PLT",
+            X86_64_PLTENT     = 0x70000003 => "Size of PLT entry",
+        }
+        RISCV {
+            RISCV_VARIANT_CC  = 0x70000001 => "Symbols use variant calling conventions",
+        }
+        SPARC | SPARCV9 {
+            SPARC_REGISTER    = 0x70000001 => "Index of register symbol",
+        }
+    }
+    osabis {
+        SYSV {
+            ANDROID_REL       = 0x6000000f => "Address of Android packed relocations",
+            ANDROID_RELSZ     = 0x60000010 => "Size of Android packed relocations",
+            ANDROID_RELA      = 0x60000011 => "Address of Android packed relocations with addends",
+            ANDROID_RELASZ    = 0x60000012 => "Size of Android packed relocations with addends",
+            ANDROID_RELR      = 0x6fffe000 => "Address of Android relative relocations",
+            ANDROID_RELRSZ    = 0x6fffe001 => "Size of Android relative relocations",
+            ANDROID_RELRENT   = 0x6fffe003 => "Size of Android relative relocation entry",
+        }
+        SOLARIS {
+            SUNW_AUXILIARY    = 0x6000000d => "Name of auxiliary filtee",
+            SUNW_RTLDINF      = 0x6000000e => "Reserved",
+            SUNW_FILTER       = 0x6000000f => "Name of filtee",
+            SUNW_CAP          = 0x60000010 => "Address of hardware capabilities",
+            SUNW_SYMTAB       = 0x60000011 => "Address of symbol table including local symbols",
+            SUNW_SYMSZ        = 0x60000012 => "Size of local and dynamic symbol tables",
+            SUNW_SORTENT      = 0x60000013 => "Size of symbol sort entry",
+            SUNW_SYMSORT      = 0x60000014 => "Address of symbol sort table",
+            SUNW_SYMSORTSZ    = 0x60000015 => "Size of symbol sort table",
+        }
+    }
+    );
+}
+
+/// 32-bit dynamic section entry
+#[derive(Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Dyn32 {
+    /// Entry type
+    ///
+    /// This is a raw `i32` because [`dynamic::DT`] holds the 64-bit `Elf64_Sxword`, so it
+    /// can't be deserialized from the 4-byte `Elf32_Sword`. Converting to [`Dyn64`]
+    /// sign-extends it into a [`dynamic::DT`].
+    pub d_tag: i32,
+    /// Integer value or address
+    pub d_val: u32,
+}
+
+/// 64-bit dynamic section entry
+#[derive(Clone, Copy, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Dyn64 {
+    /// Entry type
+    pub d_tag: dynamic::DT,
+    /// Integer value or address
+    pub d_val: u64,
+}
+impl Dyn32 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl Dyn64 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl From<Dyn32> for Dyn64 {
+    fn from(o: Dyn32) -> Dyn64 {
+        Dyn64 {
+            d_tag: dynamic::DT::from(o.d_tag as i64),
+            d_val: o.d_val as u64,
+        }
+    }
+}
+
 /// Common section names
 pub mod section {
     /// Zeroed data
@@ -450,12 +1400,18 @@ pub mod section {
     pub const DYNSYM: &str = ".dynsym";
     /// Destructors
     pub const FINI: &str = ".fini";
+    /// Destructor pointers
+    pub const FINI_ARRAY: &str = ".fini_array";
     /// Global offsets
     pub const GOT: &str = ".got";
     /// Symbol hashes
     pub const HASH: &str = ".hash";
     /// Constructors
     pub const INIT: &str = ".init";
+    /// Constructor pointers
+    pub const INIT_ARRAY: &str = ".init_array";
+    /// Pre-constructor pointers
+    pub const PREINIT_ARRAY: &str = ".preinit_array";
     /// Relocation data
     pub const REL_DATA: &str = ".rel.data";
     /// Relocation destructors
@@ -476,6 +1432,10 @@ pub mod section {
     pub const STRTAB: &str = ".strtab";
     /// Symbols
     pub const SYMTAB: &str = ".symtab";
+    /// Zeroed thread-local data
+    pub const TBSS: &str = ".tbss";
+    /// Thread-local data from file
+    pub const TDATA: &str = ".tdata";
     /// Executable code
     pub const TEXT: &str = ".text";
 }
@@ -496,6 +1456,96 @@ mod test {
         assert_eq!(Ehdr64::SIZE, serialized_size(&Ehdr64::default()));
     }
 
+    #[test]
+    fn phdr_type() {
+        use phdr::PT;
+        extern crate std;
+        use std::format;
+        assert_eq!(format!("{:?}", PT::GNU_STACK), "GNU_STACK");
+        assert_eq!(format!("{}", PT::GNU_RELRO), "Read-only after relocation");
+        assert_eq!(format!("{:?}", PT::from(0x6474e554)), "LOOS+0x474e554");
+        assert_eq!(format!("{:?}", PT::ARM_EXIDX), "LOPROC+0x1");
+        assert_eq!(format!("{}", PT::from(8)), "unknown(8)");
+        assert!(PT::GNU_STACK.is_os_specific());
+        assert!(PT::RISCV_ATTRIBUTES.is_processor_specific());
+        assert!(!PT::LOAD.is_os_specific() && !PT::LOAD.is_processor_specific());
+    }
+
+    #[test]
+    fn reserved_ranges() {
+        use shdr::SHT;
+        extern crate std;
+        use std::format;
+        assert_eq!(format!("{:?}", ehdr::ET::from(0xff01)), "LOPROC+0x1");
+        assert_eq!(format!("{:?}", SHT::from(0x70000001)), "LOPROC+0x1");
+        assert_eq!(format!("{:?}", SHT::from(0x80000010)), "LOUSER+0x10");
+        assert!(SHT::from(0x80000010).is_user_specific());
+        assert_eq!(format!("{:?}", dynamic::DT::from(0x6000000f)), "LOOS+0x2");
+        assert!(!dynamic::DT::NEEDED.is_os_specific());
+    }
+
+    #[test]
+    fn targeted_names() {
+        use ehdr::{EF, EIOSABI, EM};
+        use shdr::{SHF64, SHT};
+        extern crate std;
+        use std::format;
+        let sht = SHT::from(0x70000001);
+        assert_eq!(sht.name_for(EM::ARM, EIOSABI::SYSV), Some("ARM_EXIDX"));
+        assert_eq!(
+            sht.name_for(EM::X86_64, EIOSABI::SYSV),
+            Some("X86_64_UNWIND")
+        );
+        assert_eq!(sht.name_for(EM::MIPS, EIOSABI::SYSV), Some("MIPS_MSYM"));
+        assert_eq!(sht.name_for(EM::X86, EIOSABI::SYSV), None);
+        assert_eq!(sht.name(), None);
+        let riscv = sht.for_target(EM::RISCV, EIOSABI::SYSV);
+        assert_eq!(format!("{:?}", riscv), "LOPROC+0x1");
+        let riscv = SHT::from(0x70000003).for_target(EM::RISCV, EIOSABI::SYSV);
+        assert_eq!(format!("{:?}", riscv), "RISCV_ATTRIBUTES");
+        assert_eq!(format!("{}", riscv), "RISC-V attributes");
+        let program = SHT::PROGBITS.for_target(EM::ARM, EIOSABI::SYSV);
+        assert_eq!(format!("{:?}", program), "PROGBITS");
+
+        let pt = phdr::PT::from(0x70000003);
+        assert_eq!(pt.name_for(EM::MIPS, EIOSABI::LINUX), Some("MIPS_ABIFLAGS"));
+        assert_eq!(
+            pt.name_for(EM::RISCV, EIOSABI::LINUX),
+            Some("RISCV_ATTRIBUTES")
+        );
+
+        let dt = dynamic::DT::from(0x6000000f);
+        assert_eq!(
+            dt.name_for(EM::SPARCV9, EIOSABI::SOLARIS),
+            Some("SUNW_FILTER")
+        );
+        assert_eq!(dt.name_for(EM::X86_64, EIOSABI::LINUX), None);
+        assert_eq!(dt.name_for(EM::AARCH64, EIOSABI::SYSV), Some("ANDROID_REL"));
+        let dt = dynamic::DT::from(0x70000001);
+        assert_eq!(
+            dt.name_for(EM::SPARCV9, EIOSABI::SOLARIS),
+            Some("SPARC_REGISTER")
+        );
+        assert_eq!(
+            dt.name_for(EM::SPARC, EIOSABI::SYSV),
+            Some("SPARC_REGISTER")
+        );
+        assert_eq!(dt.name_for(EM::PPC64, EIOSABI::SYSV), Some("PPC64_OPD"));
+
+        let flags = SHF64::ALLOC | SHF64::EXECINSTR | SHF64::ARM_PURECODE;
+        let flags = flags.for_target(EM::ARM, EIOSABI::SYSV);
+        assert_eq!(format!("{:?}", flags), "ALLOC | EXECINSTR | ARM_PURECODE");
+        let flags = SHF64::X86_64_LARGE.for_target(EM::ARM, EIOSABI::SYSV);
+        assert_eq!(format!("{:?}", flags), "bit28");
+        let flags = (SHF64::ALLOC | SHF64::EXCLUDE).for_target(EM::MIPS, EIOSABI::SYSV);
+        assert_eq!(format!("{:?}", flags), "ALLOC | EXCLUDE");
+
+        let flags = EF::from(0x5).for_target(EM::RISCV, EIOSABI::SYSV);
+        assert_eq!(format!("{:?}", flags), "RISCV_RVC | RISCV_FLOAT_ABI_DOUBLE");
+        let flags = EF::from(0x5).for_target(EM::MIPS, EIOSABI::SYSV);
+        assert_eq!(format!("{:?}", flags), "MIPS_NOREORDER | MIPS_CPIC");
+    }
+
     #[test]
     fn phdr_size() {
         assert_eq!(Phdr32::SIZE, 0x20);
@@ -510,4 +1560,40 @@ mod test {
         assert_eq!(Shdr64::SIZE, 0x40);
         assert_eq!(Shdr64::SIZE, serialized_size(&Shdr64::default()));
     }
+
+    #[test]
+    fn sym_size() {
+        assert_eq!(Sym32::SIZE, 0x10);
+        assert_eq!(Sym32::SIZE, serialized_size(&Sym32::default()));
+        assert_eq!(Sym64::SIZE, 0x18);
+        assert_eq!(Sym64::SIZE, serialized_size(&Sym64::default()));
+    }
+
+    #[test]
+    fn rel_size() {
+        assert_eq!(Rel32::SIZE, 0x8);
+        assert_eq!(Rel32::SIZE, serialized_size(&Rel32::default()));
+        assert_eq!(Rela32::SIZE, 0xc);
+        assert_eq!(Rela32::SIZE, serialized_size(&Rela32::default()));
+        assert_eq!(Rel64::SIZE, 0x10);
+        assert_eq!(Rel64::SIZE, serialized_size(&Rel64::default()));
+        assert_eq!(Rela64::SIZE, 0x18);
+        assert_eq!(Rela64::SIZE, serialized_size(&Rela64::default()));
+        let rela = Rela64::from(Rela32 {
+            r_offset: 0x1000,
+            r_info: 0x0012_3405,
+            r_addend: -4,
+        });
+        assert_eq!(rela.r_sym(), 0x1234);
+        assert_eq!(rela.r_type(), 5);
+        assert_eq!(rela.r_addend, -4);
+    }
+
+    #[test]
+    fn dyn_size() {
+        assert_eq!(Dyn32::SIZE, 0x8);
+        assert_eq!(Dyn32::SIZE, serialized_size(&Dyn32::default()));
+        assert_eq!(Dyn64::SIZE, 0x10);
+        assert_eq!(Dyn64::SIZE, serialized_size(&Dyn64::default()));
+    }
 }
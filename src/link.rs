@@ -0,0 +1,1456 @@
+//! Linking relocatable objects (`ET::REL`) into memory
+//!
+//! Loading an object file is done in three steps, all without an allocator:
+//!
+//! 1. [`ElfFile::object_layout()`] assigns an address to every `SHF_ALLOC` section, then
+//!    reserves space after them for `SHN::COMMON` symbols, a GOT for relocations that
+//!    reference one, and a stub for each branch that may need a veneer.
+//! 2. [`ElfFile::load_object()`] maps and zeroes that memory and copies the section data.
+//! 3. [`ElfFile::link_object()`] applies every relocation section targeting an allocated
+//!    section, resolving undefined symbols through a [`Resolver`], then applies the sections'
+//!    permissions.
+//!
+//! Values that don't fit their field fail with [`Error::Overflow`], except for branches on
+//! AArch64, ARM and RISC-V, which go through a veneer that loads the full target address.
+//! ARM branches also use one to switch between ARM and Thumb states when the instruction
+//! can't. Relocations for x86-64, AArch64, ARM and RISC-V are supported, TLS and
+//! relaxation are not, and only little-endian objects are.
+
+use crate::load::Memory;
+use crate::phdr::PF;
+use crate::reloc::{AARCH64, ARM, RISCV, X86_64};
+use crate::relocate::{read, write, Resolver};
+use crate::shdr::{SHF64, SHN, SHT};
+use crate::sym::{STB, STT};
+use crate::{ehdr::EM, ehdr::ET, ElfFile, Error, Relocation, Shdr64, SymbolTable};
+use core::ops::Range;
+
+/// Addresses chosen by [`ElfFile::object_layout()`]
+pub struct ObjectLayout<'s> {
+    /// Address of each section, then of each symbol, `UNALLOCATED` for sections not loaded
+    /// and for symbols other than common ones
+    addresses: &'s [u64],
+    /// Number of sections
+    sections: usize,
+    /// First address of the object
+    pub base: u64,
+    /// Size of the object in memory, including common symbols, GOT and veneers
+    pub size: u64,
+    /// Start of the common symbols
+    common: u64,
+    /// Start of the GOT
+    got: u64,
+    /// Start of the veneers
+    stubs: u64,
+}
+
+/// Address of sections not loaded and of symbols that aren't common
+const UNALLOCATED: u64 = u64::MAX;
+
+impl<'s> ObjectLayout<'s> {
+    /// Address of section `index`, or `None` if it isn't loaded
+    pub fn section_address(&self, index: usize) -> Option<u64> {
+        self.addresses[..self.sections]
+            .get(index)
+            .copied()
+            .filter(|&address| address != UNALLOCATED)
+    }
+
+    /// Address of the common symbol `index`, or `None` if it isn't common
+    fn common_address(&self, index: usize) -> Option<u64> {
+        self.addresses[self.sections..]
+            .get(index)
+            .copied()
+            .filter(|&address| address != UNALLOCATED)
+    }
+
+    /// Memory after the sections, holding common symbols and the GOT
+    fn data(&self) -> Range<u64> {
+        self.common..self.stubs
+    }
+
+    /// Memory holding the veneers
+    fn stubs(&self) -> Range<u64> {
+        self.stubs..self.base + self.size
+    }
+}
+
+/// Size of a veneer, or 0 if the machine doesn't use them
+fn stub_size(machine: EM, elf32: bool) -> u64 {
+    match machine {
+        EM::AARCH64 => 16,
+        EM::ARM => 8,
+        EM::RISCV if elf32 => 16,
+        EM::RISCV => 24,
+        _ => 0,
+    }
+}
+
+/// Whether a relocation is a branch that may need a veneer
+fn needs_stub(machine: EM, r_type: u32) -> bool {
+    match machine {
+        EM::AARCH64 => matches!(
+            AARCH64::from(r_type),
+            AARCH64::R_AARCH64_JUMP26 | AARCH64::R_AARCH64_CALL26
+        ),
+        EM::ARM => matches!(
+            ARM::from(r_type),
+            ARM::R_ARM_PC24
+                | ARM::R_ARM_CALL
+                | ARM::R_ARM_JUMP24
+                | ARM::R_ARM_THM_CALL
+                | ARM::R_ARM_THM_JUMP24
+        ),
+        EM::RISCV => matches!(
+            RISCV::from(r_type),
+            RISCV::R_RISCV_JAL | RISCV::R_RISCV_CALL | RISCV::R_RISCV_CALL_PLT
+        ),
+        _ => false,
+    }
+}
+
+/// Whether a relocation references the GOT entry of its symbol
+fn uses_got(machine: EM, r_type: u32) -> bool {
+    match machine {
+        EM::X86_64 => matches!(
+            X86_64::from(r_type),
+            X86_64::R_X86_64_GOTPCREL | X86_64::R_X86_64_GOTPCRELX | X86_64::R_X86_64_REX_GOTPCRELX
+        ),
+        EM::AARCH64 => matches!(
+            AARCH64::from(r_type),
+            AARCH64::R_AARCH64_ADR_GOT_PAGE | AARCH64::R_AARCH64_LD64_GOT_LO12_NC
+        ),
+        EM::ARM => ARM::from(r_type) == ARM::R_ARM_GOT_PREL,
+        EM::RISCV => RISCV::from(r_type) == RISCV::R_RISCV_GOT_HI20,
+        _ => false,
+    }
+}
+
+/// Whether a section occupies memory
+fn allocated(shdr: &Shdr64) -> bool {
+    shdr.sh_flags & SHF64::ALLOC == SHF64::ALLOC
+}
+
+/// Permissions of a section
+fn section_flags(shdr: &Shdr64) -> PF {
+    let mut flags = PF::R;
+    if shdr.sh_flags & SHF64::WRITE == SHF64::WRITE {
+        flags |= PF::W;
+    }
+    if shdr.sh_flags & SHF64::EXECINSTR == SHF64::EXECINSTR {
+        flags |= PF::X;
+    }
+    flags
+}
+
+/// Round `address` up to a multiple of `align`, 0 meaning no alignment
+fn align_up(address: u64, align: u64) -> Result<u64, Error> {
+    match align {
+        0 | 1 => Ok(address),
+        align if align.is_power_of_two() => address
+            .checked_add(align - 1)
+            .map(|end| end & !(align - 1))
+            .ok_or(Error::Overflow),
+        _ => Err(Error::Malformed),
+    }
+}
+
+/// Whether `value` fits in a `bits` wide signed field
+fn fits(value: u64, bits: u32) -> bool {
+    let shift = 64 - bits;
+    ((value << shift) as i64 >> shift) as u64 == value
+}
+
+/// Whether `value` fits in a `bits` wide field, either signed or unsigned
+fn fits_either(value: u64, bits: u32) -> bool {
+    fits(value, bits) || value >> bits == 0
+}
+
+/// Sign-extend the low `bits` bits of `value`
+fn sign_extend(value: u64, bits: u32) -> u64 {
+    let shift = 64 - bits;
+    ((value << shift) as i64 >> shift) as u64
+}
+
+/// Start of the 4KiB page holding `address`
+fn page(address: u64) -> u64 {
+    address & !0xfff
+}
+
+impl<'a> ElfFile<'a> {
+    /// Relocation sections to apply when linking, with the section they modify
+    fn link_relocations(&self) -> Result<impl Iterator<Item = (Shdr64, usize)> + 'a, Error> {
+        let file = *self;
+        Ok(self.shdrs()?.filter_map(move |shdr| {
+            if shdr.sh_type != SHT::REL && shdr.sh_type != SHT::RELA {
+                return None;
+            }
+            let target = shdr.sh_info as usize;
+            match file.shdr(target) {
+                Ok(section) if allocated(&section) => Some((shdr, target)),
+                _ => None,
+            }
+        }))
+    }
+
+    /// Number of addresses [`ElfFile::object_layout()`] needs, one per section and one per
+    /// symbol
+    pub fn object_layout_len(&self) -> Result<usize, Error> {
+        let symbols = self.symbol_table()?.map_or(0, |table| table.len());
+        self.shnum()?.checked_add(symbols).ok_or(Error::Overflow)
+    }
+
+    /// Choose addresses from `base` for the sections of a relocatable object, using
+    /// `addresses` to store them
+    ///
+    /// `addresses` needs [`ElfFile::object_layout_len()`] entries, or the layout fails with
+    /// [`Error::Truncated`]. Sections are placed in order, aligned to their `sh_addralign`,
+    /// followed by the common symbols, the GOT and the veneers.
+    pub fn object_layout<'s>(
+        &self,
+        base: u64,
+        addresses: &'s mut [u64],
+    ) -> Result<ObjectLayout<'s>, Error> {
+        if self.ehdr().e_type != ET::REL {
+            return Err(Error::Unsupported);
+        }
+        let sections = self.shnum()?;
+        let addresses = addresses
+            .get_mut(..self.object_layout_len()?)
+            .ok_or(Error::Truncated)?;
+        let mut end = base;
+        for (address, shdr) in addresses.iter_mut().zip(self.shdrs()?) {
+            *address = UNALLOCATED;
+            if allocated(&shdr) {
+                *address = align_up(end, shdr.sh_addralign)?;
+                end = address.checked_add(shdr.sh_size).ok_or(Error::Overflow)?;
+            }
+        }
+
+        let common = end;
+        let mut symbols = 0;
+        if let Some(table) = self.symbol_table()? {
+            symbols = table.len() as u64;
+            for (address, sym) in addresses[sections..].iter_mut().zip(table.iter()) {
+                *address = UNALLOCATED;
+                if sym.st_shndx == SHN::COMMON {
+                    *address = align_up(end, sym.st_value)?;
+                    end = address.checked_add(sym.st_size).ok_or(Error::Overflow)?;
+                }
+            }
+        }
+
+        let machine = self.ehdr().e_machine;
+        let (mut got, mut stubs) = (false, 0);
+        for (shdr, _) in self.link_relocations()? {
+            for relocation in self.relocations(&shdr)? {
+                got |= uses_got(machine, relocation.r_type);
+                stubs += needs_stub(machine, relocation.r_type) as u64;
+            }
+        }
+        let word = self.word_size() as u64;
+        let got_address = align_up(end, word)?;
+        end = symbols
+            .checked_mul(if got { word } else { 0 })
+            .and_then(|size| got_address.checked_add(size))
+            .ok_or(Error::Overflow)?;
+        let stubs_address = align_up(end, 8)?;
+        end = stubs
+            .checked_mul(stub_size(machine, self.is_elf32()))
+            .and_then(|size| stubs_address.checked_add(size))
+            .ok_or(Error::Overflow)?;
+
+        Ok(ObjectLayout {
+            addresses,
+            sections,
+            base,
+            size: end - base,
+            common,
+            got: got_address,
+            stubs: stubs_address,
+        })
+    }
+
+    /// Map and zero the memory of `layout`, then copy the data of its sections
+    pub fn load_object(
+        &self,
+        memory: &mut impl Memory,
+        layout: &ObjectLayout,
+    ) -> Result<(), Error> {
+        for (i, shdr) in self.shdrs()?.enumerate() {
+            if let Some(address) = layout.section_address(i) {
+                let range = address..address + shdr.sh_size;
+                memory.map(range.clone(), section_flags(&shdr))?;
+                memory.zero(range)?;
+            }
+        }
+        for range in [layout.data(), layout.stubs()] {
+            if !range.is_empty() {
+                memory.map(range.clone(), PF::R | PF::W)?;
+                memory.zero(range)?;
+            }
+        }
+        for (i, shdr) in self.shdrs()?.enumerate() {
+            if let Some(address) = layout.section_address(i) {
+                if shdr.sh_type != SHT::NOBITS {
+                    memory.write(address, self.section_data(&shdr)?)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply the relocations of an object loaded with [`ElfFile::load_object()`], then the
+    /// permissions of its sections
+    ///
+    /// Relocations without explicit addends, used by ARM, read them from `memory`.
+    pub fn link_object(
+        &self,
+        memory: &mut impl Memory,
+        layout: &ObjectLayout,
+        resolver: &mut impl Resolver,
+    ) -> Result<(), Error> {
+        if self.is_big_endian() {
+            return Err(Error::Unsupported);
+        }
+        let symbols = self.symbol_table()?.ok_or(Error::NotFound)?;
+        let mut linker = Linker {
+            file: self,
+            layout,
+            memory: &mut *memory,
+            resolver,
+            symbols,
+            stub: layout.stubs,
+            section: 0..0,
+            hi20: [None; HI20_CACHE],
+        };
+        for (shdr, target) in self.link_relocations()? {
+            let section = self.shdr(target)?;
+            let address = layout.section_address(target).ok_or(Error::Malformed)?;
+            linker.section = address..address + section.sh_size;
+            linker.hi20 = [None; HI20_CACHE];
+            for (i, relocation) in self.relocations(&shdr)?.enumerate() {
+                linker.apply(&shdr, address, i, &relocation)?;
+            }
+        }
+
+        for (i, shdr) in self.shdrs()?.enumerate() {
+            if let Some(address) = layout.section_address(i) {
+                memory.protect(address..address + shdr.sh_size, section_flags(&shdr))?;
+            }
+        }
+        for (range, flags) in [
+            (layout.data(), PF::R | PF::W),
+            (layout.stubs(), PF::R | PF::X),
+        ] {
+            if !range.is_empty() {
+                memory.protect(range, flags)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Address of the symbol `name` defined by an object placed with `layout`
+    pub fn object_symbol(&self, layout: &ObjectLayout, name: &str) -> Result<Option<u64>, Error> {
+        let symbols = match self.symbol_table()? {
+            Some(symbols) => symbols,
+            None => return Ok(None),
+        };
+        let (index, sym) = match symbols.by_name(name) {
+            Some(found) => found,
+            None => return Ok(None),
+        };
+        Ok(match sym.st_shndx {
+            SHN::UNDEF => None,
+            SHN::ABS => Some(sym.st_value),
+            SHN::COMMON => Some(layout.common_address(index).ok_or(Error::Malformed)?),
+            shndx => layout
+                .section_address(u16::from(shndx) as usize)
+                .map(|address| address.wrapping_add(sym.st_value)),
+        })
+    }
+}
+
+/// Number of `PCREL_HI20` and `GOT_HI20` values [`Linker`] remembers for the `PCREL_LO12`
+/// relocations that follow them
+const HI20_CACHE: usize = 16;
+
+/// State of [`ElfFile::link_object()`]
+struct Linker<'l, 'a, 's, M, R> {
+    file: &'l ElfFile<'a>,
+    layout: &'l ObjectLayout<'s>,
+    memory: &'l mut M,
+    resolver: &'l mut R,
+    symbols: SymbolTable<'a>,
+    /// Veneer of the next branch relocation
+    stub: u64,
+    /// Memory of the section being relocated
+    section: Range<u64>,
+    /// Label and PC-relative value of recent RISC-V `HI20` relocations, by relocation index
+    /// modulo [`HI20_CACHE`]
+    hi20: [Option<(u64, u64)>; HI20_CACHE],
+}
+
+impl<'l, 'a, 's, M: Memory, R: Resolver> Linker<'l, 'a, 's, M, R> {
+    /// Check that the `size` bytes at `address` are in the section being relocated
+    fn check(&self, address: u64, size: usize) -> Result<(), Error> {
+        let end = address.checked_add(size as u64).ok_or(Error::Malformed)?;
+        if address < self.section.start || end > self.section.end {
+            return Err(Error::Malformed);
+        }
+        Ok(())
+    }
+
+    /// Read the `size` byte field at `address` in the section being relocated
+    fn read(&mut self, address: u64, size: usize) -> Result<u64, Error> {
+        self.check(address, size)?;
+        read(self.memory, address, size, false)
+    }
+
+    /// Write the `size` byte field at `address` in the section being relocated
+    fn write(&mut self, address: u64, size: usize, value: u64) -> Result<(), Error> {
+        self.check(address, size)?;
+        write(self.memory, address, size, value, false)
+    }
+
+    /// Write `value` to a `size` byte field if `fits`
+    fn field(&mut self, address: u64, size: usize, value: u64, fits: bool) -> Result<(), Error> {
+        if !fits {
+            return Err(Error::Overflow);
+        }
+        self.write(address, size, value)
+    }
+
+    /// Replace the bits `mask` of the instruction at `address` with those of `value` if `fits`
+    fn patch(&mut self, address: u64, mask: u32, value: u64, fits: bool) -> Result<(), Error> {
+        if !fits {
+            return Err(Error::Overflow);
+        }
+        let insn = self.read(address, 4)? as u32;
+        self.write(address, 4, u64::from(insn & !mask | value as u32 & mask))
+    }
+
+    /// Difference between `target` and `place`, wrapping to 32 bits for 32-bit objects
+    fn pcrel(&self, target: u64, place: u64) -> u64 {
+        let value = target.wrapping_sub(place);
+        if self.file.is_elf32() {
+            sign_extend(value, 32)
+        } else {
+            value
+        }
+    }
+
+    /// Address of symbol `index`
+    fn symbol(&mut self, index: u32) -> Result<u64, Error> {
+        if index == 0 {
+            return Ok(0);
+        }
+        let sym = self.symbols.get(index as usize)?;
+        let address = match sym.st_shndx {
+            SHN::UNDEF => {
+                let name = self.symbols.name(&sym)?;
+                return match self.resolver.symbol(name, &sym) {
+                    Some(address) => Ok(address),
+                    None if sym.st_bind() == STB::WEAK => Ok(0),
+                    None => Err(Error::NotFound),
+                };
+            }
+            SHN::ABS => sym.st_value,
+            SHN::COMMON => self
+                .layout
+                .common_address(index as usize)
+                .ok_or(Error::Malformed)?,
+            shndx => self
+                .layout
+                .section_address(u16::from(shndx) as usize)
+                .ok_or(Error::Malformed)?
+                .wrapping_add(sym.st_value),
+        };
+        if sym.st_type() == STT::GNU_IFUNC {
+            self.resolver.ifunc(address)
+        } else {
+            Ok(address)
+        }
+    }
+
+    /// Address of the GOT entry of symbol `index`, filled with `value`
+    fn got(&mut self, index: u32, value: u64) -> Result<u64, Error> {
+        let word = self.file.word_size();
+        let address = self.layout.got + u64::from(index) * word as u64;
+        if address >= self.layout.stubs {
+            return Err(Error::Malformed);
+        }
+        write(self.memory, address, word, value, false)?;
+        Ok(address)
+    }
+
+    /// Address of the veneer reserved for the current branch relocation
+    fn stub(&mut self) -> u64 {
+        let address = self.stub;
+        self.stub += stub_size(self.file.ehdr().e_machine, self.file.is_elf32());
+        address
+    }
+
+    /// Write a veneer at `address` made of `code` followed by the word `target`
+    fn veneer(&mut self, address: u64, code: &[u32], target: u64) -> Result<(), Error> {
+        let mut offset = address;
+        for &insn in code {
+            write(self.memory, offset, 4, u64::from(insn), false)?;
+            offset += 4;
+        }
+        write(self.memory, offset, self.file.word_size(), target, false)
+    }
+
+    /// Apply `relocation`, entry `index` of the section `shdr`, which modifies the section
+    /// at `address`
+    fn apply(
+        &mut self,
+        shdr: &Shdr64,
+        address: u64,
+        index: usize,
+        relocation: &Relocation,
+    ) -> Result<(), Error> {
+        let place = address.wrapping_add(relocation.r_offset);
+        match self.file.ehdr().e_machine {
+            EM::X86_64 => self.x86_64(place, relocation),
+            EM::AARCH64 => self.aarch64(place, relocation),
+            EM::ARM => self.arm(place, relocation),
+            EM::RISCV => self.riscv(shdr, address, place, index, relocation),
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    fn x86_64(&mut self, place: u64, relocation: &Relocation) -> Result<(), Error> {
+        let addend = relocation.r_addend.ok_or(Error::Unsupported)? as u64;
+        let symbol = self.symbol(relocation.r_sym)?;
+        let value = symbol.wrapping_add(addend);
+        let pcrel = value.wrapping_sub(place);
+        match X86_64::from(relocation.r_type) {
+            X86_64::R_X86_64_NONE => Ok(()),
+            X86_64::R_X86_64_64 => self.write(place, 8, value),
+            X86_64::R_X86_64_PC64 => self.write(place, 8, pcrel),
+            X86_64::R_X86_64_32 => self.field(place, 4, value, value >> 32 == 0),
+            X86_64::R_X86_64_32S => self.field(place, 4, value, fits(value, 32)),
+            X86_64::R_X86_64_PC32 | X86_64::R_X86_64_PLT32 => {
+                self.field(place, 4, pcrel, fits(pcrel, 32))
+            }
+            X86_64::R_X86_64_16 => self.field(place, 2, value, value >> 16 == 0),
+            X86_64::R_X86_64_PC16 => self.field(place, 2, pcrel, fits(pcrel, 16)),
+            X86_64::R_X86_64_8 => self.field(place, 1, value, value >> 8 == 0),
+            X86_64::R_X86_64_PC8 => self.field(place, 1, pcrel, fits(pcrel, 8)),
+            X86_64::R_X86_64_GOTPCREL
+            | X86_64::R_X86_64_GOTPCRELX
+            | X86_64::R_X86_64_REX_GOTPCRELX => {
+                let got = self.got(relocation.r_sym, symbol)?;
+                let pcrel = got.wrapping_add(addend).wrapping_sub(place);
+                self.field(place, 4, pcrel, fits(pcrel, 32))
+            }
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    fn aarch64(&mut self, place: u64, relocation: &Relocation) -> Result<(), Error> {
+        let addend = relocation.r_addend.ok_or(Error::Unsupported)? as u64;
+        let symbol = self.symbol(relocation.r_sym)?;
+        let value = symbol.wrapping_add(addend);
+        let pcrel = value.wrapping_sub(place);
+        // Immediate of MOVZ/MOVK, ADD and LDR/STR, and the split immediate of ADR/ADRP
+        let movw = |shift: u32| (value >> shift & 0xffff) << 5;
+        let lo12 = |value: u64, scale: u32| (value & 0xfff) >> scale << 10;
+        let adr = |imm: u64| (imm & 3) << 29 | (imm >> 2 & 0x7ffff) << 5;
+        const MOVW: u32 = 0xffff << 5;
+        const LO12: u32 = 0xfff << 10;
+        const ADR: u32 = 3 << 29 | 0x7ffff << 5;
+        match AARCH64::from(relocation.r_type) {
+            AARCH64::R_AARCH64_NONE => Ok(()),
+            AARCH64::R_AARCH64_ABS64 => self.write(place, 8, value),
+            AARCH64::R_AARCH64_ABS32 => self.field(place, 4, value, fits_either(value, 32)),
+            AARCH64::R_AARCH64_ABS16 => self.field(place, 2, value, fits_either(value, 16)),
+            AARCH64::R_AARCH64_PREL64 => self.write(place, 8, pcrel),
+            AARCH64::R_AARCH64_PREL32 => self.field(place, 4, pcrel, fits_either(pcrel, 32)),
+            AARCH64::R_AARCH64_PREL16 => self.field(place, 2, pcrel, fits_either(pcrel, 16)),
+            AARCH64::R_AARCH64_MOVW_UABS_G0 => self.patch(place, MOVW, movw(0), value >> 16 == 0),
+            AARCH64::R_AARCH64_MOVW_UABS_G0_NC => self.patch(place, MOVW, movw(0), true),
+            AARCH64::R_AARCH64_MOVW_UABS_G1 => self.patch(place, MOVW, movw(16), value >> 32 == 0),
+            AARCH64::R_AARCH64_MOVW_UABS_G1_NC => self.patch(place, MOVW, movw(16), true),
+            AARCH64::R_AARCH64_MOVW_UABS_G2 => self.patch(place, MOVW, movw(32), value >> 48 == 0),
+            AARCH64::R_AARCH64_MOVW_UABS_G2_NC => self.patch(place, MOVW, movw(32), true),
+            AARCH64::R_AARCH64_MOVW_UABS_G3 => self.patch(place, MOVW, movw(48), true),
+            AARCH64::R_AARCH64_LD_PREL_LO19 | AARCH64::R_AARCH64_CONDBR19 => self.patch(
+                place,
+                0x7ffff << 5,
+                pcrel >> 2 << 5,
+                fits(pcrel, 21) && pcrel % 4 == 0,
+            ),
+            AARCH64::R_AARCH64_TSTBR14 => self.patch(
+                place,
+                0x3fff << 5,
+                pcrel >> 2 << 5,
+                fits(pcrel, 16) && pcrel % 4 == 0,
+            ),
+            AARCH64::R_AARCH64_ADR_PREL_LO21 => self.patch(place, ADR, adr(pcrel), fits(pcrel, 21)),
+            AARCH64::R_AARCH64_ADR_PREL_PG_HI21 => {
+                let pages = page(value).wrapping_sub(page(place));
+                self.patch(place, ADR, adr(pages >> 12), fits(pages, 33))
+            }
+            AARCH64::R_AARCH64_ADR_PREL_PG_HI21_NC => {
+                let pages = page(value).wrapping_sub(page(place));
+                self.patch(place, ADR, adr(pages >> 12), true)
+            }
+            AARCH64::R_AARCH64_ADD_ABS_LO12_NC | AARCH64::R_AARCH64_LDST8_ABS_LO12_NC => {
+                self.patch(place, LO12, lo12(value, 0), true)
+            }
+            AARCH64::R_AARCH64_LDST16_ABS_LO12_NC => self.patch(place, LO12, lo12(value, 1), true),
+            AARCH64::R_AARCH64_LDST32_ABS_LO12_NC => self.patch(place, LO12, lo12(value, 2), true),
+            AARCH64::R_AARCH64_LDST64_ABS_LO12_NC => self.patch(place, LO12, lo12(value, 3), true),
+            AARCH64::R_AARCH64_LDST128_ABS_LO12_NC => self.patch(place, LO12, lo12(value, 4), true),
+            AARCH64::R_AARCH64_JUMP26 | AARCH64::R_AARCH64_CALL26 => {
+                let stub = self.stub();
+                let mut offset = pcrel;
+                if !fits(offset, 28) {
+                    // ldr x16, #8; br x16; .quad target
+                    self.veneer(stub, &[0x5800_0050, 0xd61f_0200], value)?;
+                    offset = stub.wrapping_sub(place);
+                }
+                self.patch(place, 0x3ff_ffff, offset >> 2, fits(offset, 28))
+            }
+            AARCH64::R_AARCH64_ADR_GOT_PAGE => {
+                let got = self.got(relocation.r_sym, symbol)?;
+                let pages = page(got).wrapping_sub(page(place));
+                self.patch(place, ADR, adr(pages >> 12), fits(pages, 33))
+            }
+            AARCH64::R_AARCH64_LD64_GOT_LO12_NC => {
+                let got = self.got(relocation.r_sym, symbol)?;
+                self.patch(place, LO12, lo12(got, 3), true)
+            }
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    fn arm(&mut self, place: u64, relocation: &Relocation) -> Result<(), Error> {
+        let symbol = self.symbol(relocation.r_sym)?;
+        let r_type = ARM::from(relocation.r_type);
+        let insn = self.read(place, 4)?;
+        // Addends of Thumb instructions are split across both halfwords
+        let (upper, lower) = (insn & 0xffff, insn >> 16);
+        let thumb_movw = |value: u64| {
+            let upper = upper & 0xfbf0 | (value >> 12 & 0xf) | (value >> 11 & 1) << 10;
+            let lower = lower & 0x8f00 | (value >> 8 & 7) << 12 | (value & 0xff);
+            upper | lower << 16
+        };
+        let addend = relocation
+            .r_addend
+            .map(|addend| addend as u64)
+            .unwrap_or_else(|| match r_type {
+                ARM::R_ARM_PREL31 => sign_extend(insn, 31),
+                ARM::R_ARM_PC24 | ARM::R_ARM_CALL | ARM::R_ARM_JUMP24 => sign_extend(insn << 2, 26),
+                ARM::R_ARM_THM_CALL | ARM::R_ARM_THM_JUMP24 => {
+                    let sign = upper >> 10 & 1;
+                    let i1 = !(lower >> 13 ^ sign) & 1;
+                    let i2 = !(lower >> 11 ^ sign) & 1;
+                    let offset = sign << 24
+                        | i1 << 23
+                        | i2 << 22
+                        | (upper & 0x3ff) << 12
+                        | (lower & 0x7ff) << 1;
+                    sign_extend(offset, 25)
+                }
+                ARM::R_ARM_MOVW_ABS_NC
+                | ARM::R_ARM_MOVT_ABS
+                | ARM::R_ARM_MOVW_PREL_NC
+                | ARM::R_ARM_MOVT_PREL => sign_extend(insn >> 4 & 0xf000 | insn & 0xfff, 16),
+                ARM::R_ARM_THM_MOVW_ABS_NC
+                | ARM::R_ARM_THM_MOVT_ABS
+                | ARM::R_ARM_THM_MOVW_PREL_NC
+                | ARM::R_ARM_THM_MOVT_PREL => {
+                    let imm = (upper & 0xf) << 12
+                        | (upper >> 10 & 1) << 11
+                        | (lower >> 12 & 7) << 8
+                        | lower & 0xff;
+                    sign_extend(imm, 16)
+                }
+                _ => sign_extend(insn, 32),
+            });
+        let value = symbol.wrapping_add(addend) & 0xffff_ffff;
+        let pcrel = self.pcrel(value, place);
+        match r_type {
+            ARM::R_ARM_NONE | ARM::R_ARM_V4BX => Ok(()),
+            ARM::R_ARM_ABS32 | ARM::R_ARM_TARGET1 => self.write(place, 4, value),
+            ARM::R_ARM_REL32 | ARM::R_ARM_TARGET2 => self.write(place, 4, pcrel),
+            ARM::R_ARM_GOT_PREL => {
+                let got = self.got(relocation.r_sym, symbol)?;
+                let pcrel = self.pcrel(got.wrapping_add(addend), place);
+                self.write(place, 4, pcrel)
+            }
+            ARM::R_ARM_PREL31 => self.patch(place, 0x7fff_ffff, pcrel, fits(pcrel, 31)),
+            ARM::R_ARM_PC24 | ARM::R_ARM_CALL | ARM::R_ARM_JUMP24 => {
+                self.arm_branch(place, r_type, insn as u32, value)
+            }
+            ARM::R_ARM_THM_CALL | ARM::R_ARM_THM_JUMP24 => {
+                self.thumb_branch(place, r_type, lower, value)
+            }
+            ARM::R_ARM_MOVW_ABS_NC => {
+                self.patch(place, 0xf_0fff, (value & 0xf000) << 4 | value & 0xfff, true)
+            }
+            ARM::R_ARM_MOVT_ABS => {
+                let value = value >> 16;
+                self.patch(place, 0xf_0fff, (value & 0xf000) << 4 | value & 0xfff, true)
+            }
+            ARM::R_ARM_MOVW_PREL_NC => {
+                self.patch(place, 0xf_0fff, (pcrel & 0xf000) << 4 | pcrel & 0xfff, true)
+            }
+            ARM::R_ARM_MOVT_PREL => {
+                let pcrel = pcrel >> 16;
+                self.patch(place, 0xf_0fff, (pcrel & 0xf000) << 4 | pcrel & 0xfff, true)
+            }
+            ARM::R_ARM_THM_MOVW_ABS_NC => self.write(place, 4, thumb_movw(value)),
+            ARM::R_ARM_THM_MOVT_ABS => self.write(place, 4, thumb_movw(value >> 16)),
+            ARM::R_ARM_THM_MOVW_PREL_NC => self.write(place, 4, thumb_movw(pcrel)),
+            ARM::R_ARM_THM_MOVT_PREL => self.write(place, 4, thumb_movw(pcrel >> 16)),
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// Relocate the ARM branch `insn` to `target`, whose low bit selects Thumb state
+    fn arm_branch(&mut self, place: u64, r_type: ARM, insn: u32, target: u64) -> Result<(), Error> {
+        const BL: u32 = 0xeb00_0000;
+        let stub = self.stub();
+        let thumb = target & 1 != 0;
+        let pcrel = self.pcrel(target & !1, place);
+        let blx = insn >> 25 == 0x7d;
+        let unconditional = insn >> 28 == 0xe;
+        let insn = if thumb && r_type == ARM::R_ARM_CALL && unconditional && fits(pcrel, 26) {
+            // BLX switches to Thumb, with bit 1 of the offset in the H bit
+            0xfa00_0000 | (pcrel as u32 >> 1 & 1) << 24 | pcrel as u32 >> 2 & 0xff_ffff
+        } else {
+            let mut pcrel = pcrel;
+            if thumb || !fits(pcrel, 26) {
+                // ldr pc, [pc, #-4]; .word target
+                // The addend includes the PC reading 8 bytes ahead
+                self.veneer(stub, &[0xe51f_f004], target.wrapping_add(8) & 0xffff_ffff)?;
+                pcrel = self.pcrel(stub, place + 8);
+                if !fits(pcrel, 26) {
+                    return Err(Error::Overflow);
+                }
+            }
+            let insn = if blx { BL } else { insn };
+            insn & 0xff00_0000 | pcrel as u32 >> 2 & 0xff_ffff
+        };
+        self.write(place, 4, u64::from(insn))
+    }
+
+    /// Relocate the Thumb branch whose second halfword is `lower` to `target`, whose low
+    /// bit selects Thumb state
+    fn thumb_branch(
+        &mut self,
+        place: u64,
+        r_type: ARM,
+        lower: u64,
+        target: u64,
+    ) -> Result<(), Error> {
+        let stub = self.stub();
+        let thumb = target & 1 != 0;
+        let (mut pcrel, mut blx) = if thumb {
+            (self.pcrel(target & !1, place), false)
+        } else {
+            // BLX computes the target from the word-aligned PC
+            (self.pcrel(target, place & !3), true)
+        };
+        if !fits(pcrel, 25) || (!thumb && r_type != ARM::R_ARM_THM_CALL) {
+            // ldr.w pc, [pc, #0]; .word target
+            // The addend includes the PC reading 4 bytes ahead
+            self.veneer(stub, &[0xf000_f8df], target.wrapping_add(4) & 0xffff_ffff)?;
+            pcrel = self.pcrel(stub, place + 4);
+            blx = false;
+            if !fits(pcrel, 25) {
+                return Err(Error::Overflow);
+            }
+        }
+        let sign = pcrel >> 24 & 1;
+        let j1 = (pcrel >> 23 & 1 ^ 1) ^ sign;
+        let j2 = (pcrel >> 22 & 1 ^ 1) ^ sign;
+        let upper = 0xf000 | sign << 10 | pcrel >> 12 & 0x3ff;
+        let mut lower = lower & 0xc000 | j1 << 13 | j2 << 11 | pcrel >> 1 & 0x7ff;
+        if blx {
+            lower &= !1;
+        } else {
+            lower |= 0x1000;
+        }
+        self.write(place, 4, upper | lower << 16)
+    }
+
+    fn riscv(
+        &mut self,
+        shdr: &Shdr64,
+        address: u64,
+        place: u64,
+        index: usize,
+        relocation: &Relocation,
+    ) -> Result<(), Error> {
+        let addend = relocation.r_addend.ok_or(Error::Unsupported)? as u64;
+        let symbol = self.symbol(relocation.r_sym)?;
+        let value = symbol.wrapping_add(addend);
+        let pcrel = self.pcrel(value, place);
+        // The upper 20 bits of 32-bit values only overflow on RV64
+        let elf32 = self.file.is_elf32();
+        let hi20 = |value: u64| elf32 || fits(value.wrapping_add(0x800), 32);
+        match RISCV::from(relocation.r_type) {
+            RISCV::R_RISCV_NONE | RISCV::R_RISCV_RELAX | RISCV::R_RISCV_ALIGN => Ok(()),
+            RISCV::R_RISCV_32 => self.field(place, 4, value, fits_either(value, 32)),
+            RISCV::R_RISCV_64 => self.write(place, 8, value),
+            RISCV::R_RISCV_32_PCREL => self.field(place, 4, pcrel, fits(pcrel, 32)),
+            RISCV::R_RISCV_ADD8 => self.add(place, 1, value),
+            RISCV::R_RISCV_ADD16 => self.add(place, 2, value),
+            RISCV::R_RISCV_ADD32 => self.add(place, 4, value),
+            RISCV::R_RISCV_ADD64 => self.add(place, 8, value),
+            RISCV::R_RISCV_SUB8 => self.add(place, 1, value.wrapping_neg()),
+            RISCV::R_RISCV_SUB16 => self.add(place, 2, value.wrapping_neg()),
+            RISCV::R_RISCV_SUB32 => self.add(place, 4, value.wrapping_neg()),
+            RISCV::R_RISCV_SUB64 => self.add(place, 8, value.wrapping_neg()),
+            RISCV::R_RISCV_SUB6 => {
+                let byte = self.read(place, 1)?;
+                self.write(place, 1, byte & 0xc0 | byte.wrapping_sub(value) & 0x3f)
+            }
+            RISCV::R_RISCV_SET6 => {
+                let byte = self.read(place, 1)?;
+                self.write(place, 1, byte & 0xc0 | value & 0x3f)
+            }
+            RISCV::R_RISCV_SET8 => self.write(place, 1, value),
+            RISCV::R_RISCV_SET16 => self.write(place, 2, value),
+            RISCV::R_RISCV_SET32 => self.write(place, 4, value),
+            RISCV::R_RISCV_BRANCH => self.patch(place, 0xfe00_0f80, b_type(pcrel), fits(pcrel, 13)),
+            RISCV::R_RISCV_JAL => {
+                let stub = self.stub();
+                let mut offset = pcrel;
+                if !fits(offset, 21) {
+                    self.riscv_veneer(stub, value)?;
+                    offset = self.pcrel(stub, place);
+                }
+                self.patch(place, 0xffff_f000, j_type(offset), fits(offset, 21))
+            }
+            RISCV::R_RISCV_CALL | RISCV::R_RISCV_CALL_PLT => {
+                // auipc and jalr pair
+                let stub = self.stub();
+                let mut offset = pcrel;
+                if !hi20(offset) {
+                    self.riscv_veneer(stub, value)?;
+                    offset = self.pcrel(stub, place);
+                }
+                self.patch(place, 0xffff_f000, u_type(offset), hi20(offset))?;
+                self.patch(place + 4, 0xfff0_0000, i_type(offset), true)
+            }
+            RISCV::R_RISCV_PCREL_HI20 => {
+                self.hi20[index % HI20_CACHE] = Some((place, pcrel));
+                self.patch(place, 0xffff_f000, u_type(pcrel), hi20(pcrel))
+            }
+            RISCV::R_RISCV_GOT_HI20 => {
+                let got = self.got(relocation.r_sym, symbol)?;
+                let pcrel = self.pcrel(got.wrapping_add(addend), place);
+                self.hi20[index % HI20_CACHE] = Some((place, pcrel));
+                self.patch(place, 0xffff_f000, u_type(pcrel), hi20(pcrel))
+            }
+            RISCV::R_RISCV_PCREL_LO12_I => {
+                let pcrel = self.riscv_hi20(shdr, address, symbol)?;
+                self.patch(place, 0xfff0_0000, i_type(pcrel), true)
+            }
+            RISCV::R_RISCV_PCREL_LO12_S => {
+                let pcrel = self.riscv_hi20(shdr, address, symbol)?;
+                self.patch(place, 0xfe00_0f80, s_type(pcrel), true)
+            }
+            RISCV::R_RISCV_HI20 => self.patch(place, 0xffff_f000, u_type(value), hi20(value)),
+            RISCV::R_RISCV_LO12_I => self.patch(place, 0xfff0_0000, i_type(value), true),
+            RISCV::R_RISCV_LO12_S => self.patch(place, 0xfe00_0f80, s_type(value), true),
+            RISCV::R_RISCV_RVC_BRANCH => {
+                let insn = self.read(place, 2)?;
+                let offset = (pcrel >> 8 & 1) << 12
+                    | (pcrel >> 3 & 3) << 10
+                    | (pcrel >> 6 & 3) << 5
+                    | (pcrel >> 1 & 3) << 3
+                    | (pcrel >> 5 & 1) << 2;
+                self.field(place, 2, insn & 0xe383 | offset, fits(pcrel, 9))
+            }
+            RISCV::R_RISCV_RVC_JUMP => {
+                let insn = self.read(place, 2)?;
+                let offset = (pcrel >> 11 & 1) << 12
+                    | (pcrel >> 4 & 1) << 11
+                    | (pcrel >> 8 & 3) << 9
+                    | (pcrel >> 10 & 1) << 8
+                    | (pcrel >> 6 & 1) << 7
+                    | (pcrel >> 7 & 1) << 6
+                    | (pcrel >> 1 & 7) << 3
+                    | (pcrel >> 5 & 1) << 2;
+                self.field(place, 2, insn & 0xe003 | offset, fits(pcrel, 12))
+            }
+            _ => Err(Error::Unsupported),
+        }
+    }
+
+    /// Add `value` to the `size` byte field at `address`
+    fn add(&mut self, address: u64, size: usize, value: u64) -> Result<(), Error> {
+        let old = self.read(address, size)?;
+        self.write(address, size, old.wrapping_add(value))
+    }
+
+    /// Write a RISC-V veneer jumping to `target` at `address`, using `t1`
+    fn riscv_veneer(&mut self, address: u64, target: u64) -> Result<(), Error> {
+        if self.file.is_elf32() {
+            // auipc t1, 0; lw t1, 12(t1); jr t1; .word target
+            self.veneer(address, &[0x0000_0317, 0x00c3_2303, 0x0003_0067], target)
+        } else {
+            // auipc t1, 0; ld t1, 16(t1); jr t1; nop; .quad target
+            let code = [0x0000_0317, 0x0103_3303, 0x0003_0067, 0x0000_0013];
+            self.veneer(address, &code, target)
+        }
+    }
+
+    /// PC-relative value of the `PCREL_HI20` or `GOT_HI20` relocation at `label`, from the
+    /// relocation section `shdr` modifying the section at `address`
+    ///
+    /// The value is normally remembered from a recent relocation, otherwise the section is
+    /// searched again.
+    fn riscv_hi20(&mut self, shdr: &Shdr64, address: u64, label: u64) -> Result<u64, Error> {
+        let cached = self
+            .hi20
+            .iter()
+            .flatten()
+            .find(|&&(place, _)| place == label);
+        if let Some(&(_, pcrel)) = cached {
+            return Ok(pcrel);
+        }
+        for relocation in self.file.relocations(shdr)? {
+            if address.wrapping_add(relocation.r_offset) != label {
+                continue;
+            }
+            let addend = relocation.r_addend.ok_or(Error::Unsupported)? as u64;
+            let symbol = self.symbol(relocation.r_sym)?;
+            let target = match RISCV::from(relocation.r_type) {
+                RISCV::R_RISCV_PCREL_HI20 => symbol,
+                RISCV::R_RISCV_GOT_HI20 => self.got(relocation.r_sym, symbol)?,
+                _ => continue,
+            };
+            return Ok(self.pcrel(target.wrapping_add(addend), label));
+        }
+        Err(Error::Malformed)
+    }
+}
+
+/// Upper immediate of `auipc`/`lui`, rounded for the sign of the lower 12 bits
+fn u_type(value: u64) -> u64 {
+    value.wrapping_add(0x800) & 0xffff_f000
+}
+
+/// Immediate of I-type instructions
+fn i_type(value: u64) -> u64 {
+    (value & 0xfff) << 20
+}
+
+/// Immediate of S-type instructions
+fn s_type(value: u64) -> u64 {
+    (value >> 5 & 0x7f) << 25 | (value & 0x1f) << 7
+}
+
+/// Offset of conditional branches
+fn b_type(offset: u64) -> u64 {
+    (offset >> 12 & 1) << 31
+        | (offset >> 5 & 0x3f) << 25
+        | (offset >> 1 & 0xf) << 8
+        | (offset >> 11 & 1) << 7
+}
+
+/// Offset of `jal`
+fn j_type(offset: u64) -> u64 {
+    (offset >> 20 & 1) << 31
+        | (offset >> 1 & 0x3ff) << 21
+        | (offset >> 11 & 1) << 20
+        | (offset >> 12 & 0xff) << 12
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::load::Arena;
+    use crate::testutil::Builder;
+    use crate::Sym64;
+    use core::convert::TryInto;
+    use std::vec;
+    use std::vec::Vec;
+
+    const NEAR: u32 = 3;
+    const FAR: u32 = 4;
+
+    struct Imports {
+        near: u64,
+        far: u64,
+    }
+
+    impl Resolver for Imports {
+        fn symbol(&mut self, name: &str, _sym: &Sym64) -> Option<u64> {
+            match name {
+                "near" => Some(self.near),
+                "far" => Some(self.far),
+                _ => None,
+            }
+        }
+    }
+
+    /// Object with `text`, 16 bytes of `.data`, and symbols local "entry" at the start of
+    /// `.text`, common "counter" of 8 bytes, and imports "near" and "far"
+    fn object(
+        elf32: bool,
+        machine: EM,
+        text: Vec<u8>,
+        relocations: &[(u64, u32, u32, i64)],
+    ) -> Vec<u8> {
+        let mut builder = Builder::new(elf32, false, ET::REL, machine);
+        let text = builder.section(".text", SHT::PROGBITS, 0, text);
+        builder.shdr_mut(text).sh_flags = SHF64::ALLOC | SHF64::EXECINSTR;
+        builder.shdr_mut(text).sh_addralign = 4;
+        let data = builder.section(".data", SHT::PROGBITS, 0, vec![0; 16]);
+        builder.shdr_mut(data).sh_flags = SHF64::ALLOC | SHF64::WRITE;
+        builder.shdr_mut(data).sh_addralign = 8;
+        let (strtab, names) = Builder::strings(&["entry", "counter", "near", "far"]);
+        let strtab = builder.section(".strtab", SHT::STRTAB, 0, strtab);
+        let mut symtab = builder.sym(0, 0, 0, 0, 0);
+        symtab.extend(builder.sym(names[0], 0, 0, 0x00, text as u16));
+        symtab.extend(builder.sym(names[1], 8, 8, 0x11, 0xfff2));
+        symtab.extend(builder.sym(names[2], 0, 0, 0x10, 0));
+        symtab.extend(builder.sym(names[3], 0, 0, 0x10, 0));
+        let symtab = builder.section(".symtab", SHT::SYMTAB, 0, symtab);
+        builder.shdr_mut(symtab).sh_link = strtab as u32;
+        // ARM uses implicit addends
+        let rela = machine != EM::ARM;
+        let mut data = Vec::new();
+        for &(r_offset, r_sym, r_type, r_addend) in relocations {
+            data.extend(if rela {
+                builder.rela(r_offset, r_sym, r_type, r_addend)
+            } else {
+                builder.rel(r_offset, r_sym, r_type)
+            });
+        }
+        let sh_type = if rela { SHT::RELA } else { SHT::REL };
+        let rel = builder.section(".rel.text", sh_type, 0, data);
+        builder.shdr_mut(rel).sh_link = symtab as u32;
+        builder.shdr_mut(rel).sh_info = text as u32;
+        builder.build()
+    }
+
+    fn words(words: &[u32]) -> Vec<u8> {
+        words.iter().flat_map(|word| word.to_le_bytes()).collect()
+    }
+
+    fn word(buffer: &[u8], offset: usize) -> u32 {
+        u32::from_le_bytes(buffer[offset..offset + 4].try_into().unwrap())
+    }
+
+    /// Lay out, load and link `data` at `base` in `buffer`
+    fn link(
+        data: &[u8],
+        base: u64,
+        buffer: &mut [u8],
+        imports: &mut Imports,
+    ) -> Result<u64, Error> {
+        let file = ElfFile::new(data)?;
+        let mut addresses = vec![0; file.object_layout_len()?];
+        let layout = file.object_layout(base, &mut addresses)?;
+        let mut arena = Arena::new(buffer, base);
+        file.load_object(&mut arena, &layout)?;
+        file.link_object(&mut arena, &layout, imports)?;
+        Ok(layout.size)
+    }
+
+    #[test]
+    fn aarch64() {
+        let mut text = words(&[0x9400_0000, 0x9400_0000, 0x9000_0000, 0x9100_0000]);
+        text.extend(&[0; 8]);
+        let data = object(
+            false,
+            EM::AARCH64,
+            text,
+            &[
+                (0, NEAR, AARCH64::R_AARCH64_CALL26.into(), 0),
+                (4, FAR, AARCH64::R_AARCH64_CALL26.into(), 0),
+                (8, FAR, AARCH64::R_AARCH64_ADR_PREL_PG_HI21_NC.into(), 0),
+                (12, FAR, AARCH64::R_AARCH64_ADD_ABS_LO12_NC.into(), 0),
+                (16, 2, AARCH64::R_AARCH64_ABS64.into(), 4),
+            ],
+        );
+        let file = ElfFile::new(&data).unwrap();
+        assert_eq!(file.object_layout_len(), Ok(12));
+        let mut addresses = [0; 12];
+        let layout = file.object_layout(0x10000, &mut addresses).unwrap();
+        // .text, .data, common symbol, then a veneer per branch
+        assert_eq!(layout.section_address(1), Some(0x10000));
+        assert_eq!(layout.section_address(2), Some(0x10018));
+        assert_eq!(layout.section_address(3), None);
+        assert_eq!(layout.size, 0x50);
+        assert_eq!(file.object_symbol(&layout, "counter"), Ok(Some(0x10028)));
+        assert_eq!(file.object_symbol(&layout, "entry"), Ok(Some(0x10000)));
+        assert_eq!(file.object_symbol(&layout, "near"), Ok(None));
+
+        let mut buffer = vec![0xff; 0x50];
+        let mut imports = Imports {
+            near: 0x11000,
+            far: 0x1234_5678_9abc,
+        };
+        assert_eq!(link(&data, 0x10000, &mut buffer, &mut imports), Ok(0x50));
+        assert_eq!(word(&buffer, 0), 0x9400_0400);
+        // Branch to the second veneer
+        assert_eq!(word(&buffer, 4), 0x9400_000f);
+        assert_eq!(word(&buffer, 0x40), 0x5800_0050);
+        assert_eq!(word(&buffer, 0x44), 0xd61f_0200);
+        assert_eq!(&buffer[0x48..0x50], &0x1234_5678_9abc_u64.to_le_bytes()[..]);
+        // adrp x0, far; add x0, x0, :lo12:far
+        let pages = (0x1234_5678_9000_u64 - 0x10000) >> 12;
+        let adrp = 0x9000_0000 | (pages as u32 & 3) << 29 | (pages as u32 >> 2 & 0x7ffff) << 5;
+        assert_eq!(word(&buffer, 8), adrp);
+        assert_eq!(word(&buffer, 12), 0x9100_0000 | 0xabc << 10);
+        assert_eq!(&buffer[16..24], &0x1002c_u64.to_le_bytes()[..]);
+        // The common symbol is zeroed
+        assert_eq!(&buffer[0x28..0x30], &[0; 8][..]);
+
+        // ADRP reaches 4GiB only when checked
+        let data = object(
+            false,
+            EM::AARCH64,
+            words(&[0x9000_0000]),
+            &[(0, FAR, AARCH64::R_AARCH64_ADR_PREL_PG_HI21.into(), 0)],
+        );
+        let result = link(&data, 0x10000, &mut buffer, &mut imports);
+        assert_eq!(result, Err(Error::Overflow));
+    }
+
+    #[test]
+    fn aarch64_fields() {
+        // movz x0; movk x0, lsl 16; movk x0, lsl 32; movk x0, lsl 48; .word; adrp; ldr
+        let mut text = words(&[0xd280_0000, 0xf2a0_0000, 0xf2c0_0000, 0xf2e0_0000]);
+        text.extend(words(&[0, 0, 0x9000_0000, 0xf940_0000]));
+        let data = object(
+            false,
+            EM::AARCH64,
+            text,
+            &[
+                (0, FAR, AARCH64::R_AARCH64_MOVW_UABS_G0_NC.into(), 0),
+                (4, FAR, AARCH64::R_AARCH64_MOVW_UABS_G1_NC.into(), 0),
+                (8, FAR, AARCH64::R_AARCH64_MOVW_UABS_G2_NC.into(), 0),
+                (12, FAR, AARCH64::R_AARCH64_MOVW_UABS_G3.into(), 0),
+                (16, 0, AARCH64::R_AARCH64_ABS32.into(), -4),
+                (24, FAR, AARCH64::R_AARCH64_ADR_GOT_PAGE.into(), 0),
+                (28, FAR, AARCH64::R_AARCH64_LD64_GOT_LO12_NC.into(), 0),
+            ],
+        );
+        let mut buffer = vec![0; 0x60];
+        let mut imports = Imports {
+            near: 0,
+            far: 0x1234_5678_9abc,
+        };
+        // .text, .data, counter, then a GOT of 5 entries at 0x10038
+        assert_eq!(link(&data, 0x10000, &mut buffer, &mut imports), Ok(0x60));
+        assert_eq!(word(&buffer, 0), 0xd293_5780);
+        assert_eq!(word(&buffer, 4), 0xf2aa_cf00);
+        assert_eq!(word(&buffer, 8), 0xf2c2_4680);
+        assert_eq!(word(&buffer, 12), 0xf2e0_0000);
+        // Negative values fit a 32-bit absolute relocation
+        assert_eq!(word(&buffer, 16), 0xffff_fffc);
+        // adrp x0, :got:far; ldr x0, [x0, :got_lo12:far]
+        assert_eq!(word(&buffer, 24), 0x9000_0000);
+        assert_eq!(word(&buffer, 28), 0xf940_2c00);
+        assert_eq!(&buffer[0x58..0x60], &0x1234_5678_9abc_u64.to_le_bytes()[..]);
+
+        // Conditional branches must be to instructions in range
+        let data = object(
+            false,
+            EM::AARCH64,
+            words(&[0x5400_0000]),
+            &[(0, 1, AARCH64::R_AARCH64_CONDBR19.into(), 6)],
+        );
+        let result = link(&data, 0x10000, &mut buffer, &mut imports);
+        assert_eq!(result, Err(Error::Overflow));
+        let data = object(
+            false,
+            EM::AARCH64,
+            words(&[0x5400_0000]),
+            &[(0, FAR, AARCH64::R_AARCH64_CONDBR19.into(), 0)],
+        );
+        let result = link(&data, 0x10000, &mut buffer, &mut imports);
+        assert_eq!(result, Err(Error::Overflow));
+    }
+
+    #[test]
+    fn arm() {
+        // bl, b.w and blx with the addends for the PC offset
+        let text = words(&[0xfffe_f7ff, 0xbffe_f7ff, 0xebff_fffe, 0]);
+        let data = object(
+            true,
+            EM::ARM,
+            text,
+            &[
+                (0, NEAR, ARM::R_ARM_THM_CALL.into(), 0),
+                (4, NEAR, ARM::R_ARM_THM_JUMP24.into(), 0),
+                (8, FAR, ARM::R_ARM_CALL.into(), 0),
+            ],
+        );
+        let mut buffer = vec![0; 0x40];
+        let mut imports = Imports {
+            near: 0x8000,
+            far: 0x9001,
+        };
+        assert_eq!(link(&data, 0x1000, &mut buffer, &mut imports), Ok(0x40));
+        // Thumb call to ARM code becomes blx
+        assert_eq!(word(&buffer, 0), 0xeffe_f006);
+        // Thumb jump to ARM code goes through the veneer at 0x1030
+        assert_eq!(word(&buffer, 4), 0xb814_f000);
+        assert_eq!(word(&buffer, 0x30), 0xf000_f8df);
+        assert_eq!(word(&buffer, 0x34), 0x8000);
+        // ARM call to Thumb code becomes blx
+        assert_eq!(word(&buffer, 8), 0xfa00_1ffc);
+    }
+
+    #[test]
+    fn arm_fields() {
+        // .word 0x10; .word 0; movw r0, #0; movt r0, #0
+        let text = words(&[0x10, 0, 0xe300_0000, 0xe340_0000]);
+        let data = object(
+            true,
+            EM::ARM,
+            text,
+            &[
+                (0, 2, ARM::R_ARM_ABS32.into(), 0),
+                (4, 1, ARM::R_ARM_REL32.into(), 0),
+                (8, FAR, ARM::R_ARM_MOVW_ABS_NC.into(), 0),
+                (12, FAR, ARM::R_ARM_MOVT_ABS.into(), 0),
+            ],
+        );
+        let mut buffer = vec![0; 0x28];
+        let mut imports = Imports {
+            near: 0,
+            far: 0x1234_5678,
+        };
+        // .text at 0x1000, .data at 0x1010, counter at 0x1020
+        assert_eq!(link(&data, 0x1000, &mut buffer, &mut imports), Ok(0x28));
+        assert_eq!(word(&buffer, 0), 0x1030);
+        assert_eq!(word(&buffer, 4), 0xffff_fffc);
+        assert_eq!(word(&buffer, 8), 0xe305_0678);
+        assert_eq!(word(&buffer, 12), 0xe341_0234);
+    }
+
+    #[test]
+    fn riscv() {
+        let text = words(&[
+            0x0000_0517,
+            0x0005_0513,
+            0x0000_00ef,
+            0x0000_0097,
+            0x0000_80e7,
+        ]);
+        let data = object(
+            false,
+            EM::RISCV,
+            text,
+            &[
+                (0, 2, RISCV::R_RISCV_PCREL_HI20.into(), 0),
+                (0, 0, RISCV::R_RISCV_RELAX.into(), 0),
+                (4, 1, RISCV::R_RISCV_PCREL_LO12_I.into(), 0),
+                (8, FAR, RISCV::R_RISCV_JAL.into(), 0),
+                (12, NEAR, RISCV::R_RISCV_CALL_PLT.into(), 0),
+            ],
+        );
+        let mut buffer = vec![0; 0x60];
+        let mut imports = Imports {
+            near: 0x20000,
+            far: 0x1_0000_0000,
+        };
+        assert_eq!(link(&data, 0x10000, &mut buffer, &mut imports), Ok(0x60));
+        // auipc a0, %pcrel_hi(counter); addi a0, a0, %pcrel_lo(counter)
+        assert_eq!(word(&buffer, 0), 0x0000_0517);
+        assert_eq!(word(&buffer, 4), 0x0285_0513);
+        // jal to the veneer at 0x10030
+        assert_eq!(word(&buffer, 8), 0x0280_00ef);
+        assert_eq!(word(&buffer, 0x30), 0x0000_0317);
+        assert_eq!(word(&buffer, 0x34), 0x0103_3303);
+        assert_eq!(&buffer[0x40..0x48], &0x1_0000_0000_u64.to_le_bytes()[..]);
+        // call near
+        assert_eq!(word(&buffer, 12), 0x0001_0097);
+        assert_eq!(word(&buffer, 16), 0xff40_80e7);
+    }
+
+    #[test]
+    fn riscv_fields() {
+        let text = words(&[
+            0x0000_0517,
+            0x0005_3503,
+            0x0000_0537,
+            0x0005_0513,
+            0x00b5_3023,
+            0x0000_0063,
+            100,
+            0xc5,
+        ]);
+        let mut relocations = vec![
+            (0, FAR, RISCV::R_RISCV_GOT_HI20.into(), 0),
+            (4, 1, RISCV::R_RISCV_PCREL_LO12_I.into(), 0),
+            (8, FAR, RISCV::R_RISCV_HI20.into(), 0),
+            (12, FAR, RISCV::R_RISCV_LO12_I.into(), 0),
+            (16, FAR, RISCV::R_RISCV_LO12_S.into(), 0),
+            (20, 1, RISCV::R_RISCV_BRANCH.into(), 0),
+            (24, 0, RISCV::R_RISCV_ADD32.into(), 5),
+            (24, 0, RISCV::R_RISCV_SUB32.into(), 3),
+            (28, 0, RISCV::R_RISCV_SET6.into(), 0x2a),
+        ];
+        let mut imports = Imports {
+            near: 0,
+            far: 0x1234_5678,
+        };
+        let mut expected = None;
+        // The PCREL_LO12 relocation is also found before its GOT_HI20
+        for _ in 0..2 {
+            let data = object(false, EM::RISCV, text.clone(), &relocations);
+            let mut buffer = vec![0; 0x60];
+            // .text, .data, counter, then a GOT of 5 entries at 0x10038
+            assert_eq!(link(&data, 0x10000, &mut buffer, &mut imports), Ok(0x60));
+            // auipc a0, %got_pcrel_hi(far); ld a0, %pcrel_lo(entry)(a0)
+            assert_eq!(word(&buffer, 0), 0x0000_0517);
+            assert_eq!(word(&buffer, 4), 0x0585_3503);
+            assert_eq!(&buffer[0x58..0x60], &0x1234_5678_u64.to_le_bytes()[..]);
+            // lui a0, %hi(far); addi a0, a0, %lo(far); sd a1, %lo(far)(a0)
+            assert_eq!(word(&buffer, 8), 0x1234_5537);
+            assert_eq!(word(&buffer, 12), 0x6785_0513);
+            assert_eq!(word(&buffer, 16), 0x66b5_3c23);
+            // beq zero, zero, entry
+            assert_eq!(word(&buffer, 20), 0xfe00_06e3);
+            assert_eq!(word(&buffer, 24), 102);
+            assert_eq!(buffer[28], 0xea);
+            if let Some(expected) = &expected {
+                assert_eq!(&buffer, expected);
+            }
+            expected = Some(buffer);
+            relocations.reverse();
+        }
+
+        // PCREL_LO12 must refer to a HI20 relocation
+        let data = object(
+            false,
+            EM::RISCV,
+            text,
+            &[(4, 1, RISCV::R_RISCV_PCREL_LO12_I.into(), 0)],
+        );
+        let mut buffer = vec![0; 0x60];
+        let result = link(&data, 0x10000, &mut buffer, &mut imports);
+        assert_eq!(result, Err(Error::Malformed));
+    }
+
+    #[test]
+    fn x86_64() {
+        let data = object(
+            false,
+            EM::X86_64,
+            vec![0; 12],
+            &[
+                (0, FAR, X86_64::R_X86_64_REX_GOTPCRELX.into(), -4),
+                (4, 2, X86_64::R_X86_64_32S.into(), 0),
+                (8, 1, X86_64::R_X86_64_PC32.into(), 0),
+            ],
+        );
+        let file = ElfFile::new(&data).unwrap();
+        let mut addresses = [0; 3];
+        let layout = file.object_layout(0x40_0000, &mut addresses);
+        assert!(matches!(layout, Err(Error::Truncated)));
+
+        // .text at 0x400000, .data at 0x400010, counter at 0x400020, 5 GOT entries
+        let mut buffer = vec![0; 0x50];
+        let mut imports = Imports {
+            near: 0,
+            far: 0x7000_0000_1000,
+        };
+        assert_eq!(link(&data, 0x40_0000, &mut buffer, &mut imports), Ok(0x50));
+        // GOT entry of "far" at 0x400048
+        assert_eq!(word(&buffer, 0), 0x48 - 4);
+        assert_eq!(&buffer[0x48..0x50], &0x7000_0000_1000_u64.to_le_bytes()[..]);
+        assert_eq!(word(&buffer, 4), 0x40_0020);
+        assert_eq!(word(&buffer, 8), -8i32 as u32);
+
+        // Direct references must be within 2GiB
+        let data = object(
+            false,
+            EM::X86_64,
+            vec![0; 4],
+            &[(0, FAR, X86_64::R_X86_64_PC32.into(), -4)],
+        );
+        let result = link(&data, 0x40_0000, &mut buffer, &mut imports);
+        assert_eq!(result, Err(Error::Overflow));
+        imports.far = 0x40_1000;
+        assert_eq!(link(&data, 0x40_0000, &mut buffer, &mut imports), Ok(0x20));
+        assert_eq!(word(&buffer, 0), 0xffc);
+    }
+
+    #[test]
+    fn x86_64_fields() {
+        let data = object(
+            false,
+            EM::X86_64,
+            vec![0; 32],
+            &[
+                (0, NEAR, X86_64::R_X86_64_64.into(), 8),
+                (8, NEAR, X86_64::R_X86_64_PC64.into(), 0),
+                (16, 0, X86_64::R_X86_64_16.into(), 0x1234),
+                (18, 1, X86_64::R_X86_64_PC16.into(), 0),
+                (20, 0, X86_64::R_X86_64_8.into(), 0x7f),
+                (21, 1, X86_64::R_X86_64_PC8.into(), 0x30),
+                (24, 2, X86_64::R_X86_64_32.into(), 0),
+            ],
+        );
+        let mut buffer = vec![0; 0x38];
+        let mut imports = Imports {
+            near: 0x40_1000,
+            far: 0x7000_0000_1000,
+        };
+        // .text at 0x400000, .data at 0x400020, counter at 0x400030
+        assert_eq!(link(&data, 0x40_0000, &mut buffer, &mut imports), Ok(0x38));
+        assert_eq!(&buffer[0..8], &0x40_1008_u64.to_le_bytes()[..]);
+        assert_eq!(&buffer[8..16], &0xff8_u64.to_le_bytes()[..]);
+        assert_eq!(&buffer[16..18], &[0x34, 0x12][..]);
+        assert_eq!(&buffer[18..20], &(-0x12i16).to_le_bytes()[..]);
+        assert_eq!(&buffer[20..22], &[0x7f, 0x1b][..]);
+        assert_eq!(word(&buffer, 24), 0x40_0030);
+
+        for &(r_type, addend) in &[(X86_64::R_X86_64_32, 0), (X86_64::R_X86_64_8, 0x100)] {
+            let data = object(
+                false,
+                EM::X86_64,
+                vec![0; 4],
+                &[(0, FAR, r_type.into(), addend)],
+            );
+            let result = link(&data, 0x40_0000, &mut buffer, &mut imports);
+            assert_eq!(result, Err(Error::Overflow));
+        }
+    }
+
+    #[test]
+    fn bounds() {
+        let mut buffer = vec![0; 0x40];
+        let mut imports = Imports {
+            near: 0x40_1000,
+            far: 0,
+        };
+        // Fields must be within the relocated section
+        for &(r_offset, r_type) in &[(2, X86_64::R_X86_64_32), (0, X86_64::R_X86_64_64)] {
+            let data = object(
+                false,
+                EM::X86_64,
+                vec![0; 4],
+                &[(r_offset, NEAR, r_type.into(), 0)],
+            );
+            let result = link(&data, 0x40_0000, &mut buffer, &mut imports);
+            assert_eq!(result, Err(Error::Malformed));
+        }
+        // Including the second instruction of a call
+        let data = object(
+            false,
+            EM::RISCV,
+            words(&[0x0000_0097]),
+            &[(0, NEAR, RISCV::R_RISCV_CALL_PLT.into(), 0)],
+        );
+        let result = link(&data, 0x40_0000, &mut buffer, &mut imports);
+        assert_eq!(result, Err(Error::Malformed));
+    }
+}
@@ -0,0 +1,333 @@
+//! Loading `PT::LOAD` segments into memory
+//!
+//! [`ElfFile::load()`] copies each loadable segment to its virtual address plus a bias,
+//! zero-fills the rest of its memory image (`.bss` and the padding up to the page size
+//! given by the caller), then applies the segment's permissions. Memory is provided by the caller through the
+//! [`Memory`] trait, so the loader works without an allocator or operating system; [`Arena`]
+//! implements it for a plain byte buffer.
+//!
+//! Position independent images (`ET::DYN`) still need relocating after loading, see
+//! [`crate::relocate`].
+
+use crate::phdr::{PF, PT};
+use crate::{ElfFile, Error, Phdr64};
+use core::convert::TryFrom;
+use core::ops::Range;
+
+/// Destination memory for [`ElfFile::load()`]
+///
+/// Addresses are virtual addresses plus the load bias. Segments are loaded in three passes:
+/// every segment is mapped and zeroed, then file data is written, then permissions are
+/// applied, so segments sharing a page are never written after being protected.
+pub trait Memory {
+    /// Make `range` available for writing
+    ///
+    /// The range covers one segment, widened to whole pages, so ranges of segments sharing a
+    /// page overlap.
+    fn map(&mut self, range: Range<u64>, flags: PF) -> Result<(), Error>;
+
+    /// Copy `data` to `address`
+    fn write(&mut self, address: u64, data: &[u8]) -> Result<(), Error>;
+
+    /// Copy the memory at `address` to `data`
+    ///
+    /// This is only needed to apply relocations without explicit addends, see
+    /// [`ElfFile::relocate()`].
+    fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+        let _ = (address, data);
+        Err(Error::Unsupported)
+    }
+
+    /// Fill `range` with zeros
+    fn zero(&mut self, range: Range<u64>) -> Result<(), Error> {
+        let zeros = [0; 256];
+        let mut address = range.start;
+        while address < range.end {
+            let len = (range.end - address).min(zeros.len() as u64);
+            self.write(address, &zeros[..len as usize])?;
+            address += len;
+        }
+        Ok(())
+    }
+
+    /// Apply the permissions `flags` to `range`, once it has been written
+    ///
+    /// Return an error to refuse a segment, for example one both writable and executable.
+    fn protect(&mut self, range: Range<u64>, flags: PF) -> Result<(), Error> {
+        let _ = (range, flags);
+        Ok(())
+    }
+}
+
+/// Memory backed by a byte buffer holding addresses `base..base + memory.len()`
+pub struct Arena<'m> {
+    /// Contents of the memory
+    pub memory: &'m mut [u8],
+    /// Address of the first byte of `memory`
+    pub base: u64,
+}
+
+impl<'m> Arena<'m> {
+    /// Arena holding `memory` at `base`
+    pub fn new(memory: &'m mut [u8], base: u64) -> Self {
+        Arena { memory, base }
+    }
+
+    /// Part of the buffer for `range`
+    fn slice(&mut self, range: Range<u64>) -> Result<&mut [u8], Error> {
+        let start = range.start.checked_sub(self.base).ok_or(Error::NotFound)?;
+        let end = range.end.checked_sub(self.base).ok_or(Error::NotFound)?;
+        let start = usize::try_from(start).map_err(|_| Error::NotFound)?;
+        let end = usize::try_from(end).map_err(|_| Error::NotFound)?;
+        self.memory.get_mut(start..end).ok_or(Error::NotFound)
+    }
+}
+
+impl<'m> Memory for Arena<'m> {
+    fn map(&mut self, range: Range<u64>, _flags: PF) -> Result<(), Error> {
+        self.slice(range).map(|_| ())
+    }
+
+    fn write(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
+        let end = address
+            .checked_add(data.len() as u64)
+            .ok_or(Error::NotFound)?;
+        self.slice(address..end)?.copy_from_slice(data);
+        Ok(())
+    }
+
+    fn read(&mut self, address: u64, data: &mut [u8]) -> Result<(), Error> {
+        let end = address
+            .checked_add(data.len() as u64)
+            .ok_or(Error::NotFound)?;
+        data.copy_from_slice(self.slice(address..end)?);
+        Ok(())
+    }
+
+    fn zero(&mut self, range: Range<u64>) -> Result<(), Error> {
+        self.slice(range)?.fill(0);
+        Ok(())
+    }
+}
+
+/// Alignment of a segment, `p_align` of 0 or 1 meaning none
+fn alignment(phdr: &Phdr64) -> Result<u64, Error> {
+    match phdr.p_align {
+        0 => Ok(1),
+        align if align.is_power_of_two() => Ok(align),
+        _ => Err(Error::Malformed),
+    }
+}
+
+/// Memory image of a segment widened to pages of `page_size` bytes, before adding the bias
+fn page_range(phdr: &Phdr64, page_size: u64) -> Result<Range<u64>, Error> {
+    let align = alignment(phdr)?;
+    if phdr.p_filesz > phdr.p_memsz || phdr.p_vaddr % align != phdr.p_offset % align {
+        return Err(Error::Malformed);
+    }
+    if !page_size.is_power_of_two() {
+        return Err(Error::Malformed);
+    }
+    let end = phdr
+        .p_vaddr
+        .checked_add(phdr.p_memsz)
+        .and_then(|end| end.checked_add(page_size - 1))
+        .ok_or(Error::Malformed)?;
+    Ok(phdr.p_vaddr & !(page_size - 1)..end & !(page_size - 1))
+}
+
+/// Add `bias` to both ends of `range`
+fn biased(range: Range<u64>, bias: u64) -> Range<u64> {
+    range.start.wrapping_add(bias)..range.end.wrapping_add(bias)
+}
+
+impl<'a> ElfFile<'a> {
+    /// Iterate over the `PT::LOAD` segments that occupy memory
+    fn load_segments(&self) -> Result<impl Iterator<Item = Phdr64> + 'a, Error> {
+        Ok(self
+            .phdrs()?
+            .filter(|phdr| phdr.p_type == PT::LOAD && phdr.p_memsz != 0))
+    }
+
+    /// Largest `p_align` of the `PT::LOAD` segments, which a load bias must be a multiple of
+    pub fn load_align(&self) -> Result<u64, Error> {
+        let mut align = 1;
+        for phdr in self.load_segments()? {
+            align = align.max(alignment(&phdr)?);
+        }
+        Ok(align)
+    }
+
+    /// Addresses covered by the `PT::LOAD` segments, widened to pages of `page_size` bytes,
+    /// or `None` if there are none
+    ///
+    /// An arena for a position independent image needs the size of this range.
+    pub fn load_range(&self, page_size: u64) -> Result<Option<Range<u64>>, Error> {
+        let mut result: Option<Range<u64>> = None;
+        for phdr in self.load_segments()? {
+            let range = page_range(&phdr, page_size)?;
+            result = Some(match result {
+                Some(r) => r.start.min(range.start)..r.end.max(range.end),
+                None => range,
+            });
+        }
+        Ok(result)
+    }
+
+    /// Load the `PT::LOAD` segments into `memory` at their addresses plus `bias`, and
+    /// return the entry point
+    ///
+    /// Segments are mapped in whole pages of `page_size` bytes, a power of two. `bias` is
+    /// normally 0 for executables, and the chosen base address minus the start of
+    /// [`ElfFile::load_range()`] for position independent images. It must be a multiple of
+    /// both `page_size` and [`ElfFile::load_align()`].
+    pub fn load(&self, memory: &mut impl Memory, bias: u64, page_size: u64) -> Result<u64, Error> {
+        if !page_size.is_power_of_two() || bias % page_size != 0 || bias % self.load_align()? != 0 {
+            return Err(Error::Malformed);
+        }
+        for phdr in self.load_segments()? {
+            let range = biased(page_range(&phdr, page_size)?, bias);
+            memory.map(range.clone(), phdr.p_flags)?;
+            memory.zero(range)?;
+        }
+        for phdr in self.load_segments()? {
+            let data = self.bytes(phdr.p_offset, phdr.p_filesz)?;
+            memory.write(phdr.p_vaddr.wrapping_add(bias), data)?;
+        }
+        for phdr in self.load_segments()? {
+            memory.protect(biased(page_range(&phdr, page_size)?, bias), phdr.p_flags)?;
+        }
+        Ok(self.ehdr().e_entry.wrapping_add(bias))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::{EM, ET};
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    /// Memory that records permissions and refuses writable executable segments
+    struct Checked<'m> {
+        arena: Arena<'m>,
+        protected: Vec<(Range<u64>, PF)>,
+    }
+
+    impl<'m> Memory for Checked<'m> {
+        fn map(&mut self, range: Range<u64>, flags: PF) -> Result<(), Error> {
+            self.arena.map(range, flags)
+        }
+        fn write(&mut self, address: u64, data: &[u8]) -> Result<(), Error> {
+            self.arena.write(address, data)
+        }
+        fn protect(&mut self, range: Range<u64>, flags: PF) -> Result<(), Error> {
+            if flags & (PF::W | PF::X) == PF::W | PF::X {
+                return Err(Error::Unsupported);
+            }
+            self.protected.push((range, flags));
+            Ok(())
+        }
+    }
+
+    /// Text and data segments, aligned to 8 bytes like the builder's file offsets
+    fn image(e_type: ET, data_flags: PF) -> Vec<u8> {
+        let mut builder = Builder::new(false, false, e_type, EM::X86_64);
+        builder.ehdr.e_entry = 0x1004;
+        let text = builder.section(".text", SHT::PROGBITS, 0x1000, vec![0xc3; 8]);
+        let data = builder.section(".data", SHT::PROGBITS, 0x2000, vec![0xaa; 4]);
+        let bss = builder.section(".bss", SHT::NOBITS, 0x2004, vec![0; 0x1c]);
+        let text = builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+        let data = builder.segment(PT::LOAD, data_flags, &[data, bss]);
+        builder.phdr_mut(text).p_align = 8;
+        builder.phdr_mut(data).p_align = 8;
+        builder.build()
+    }
+
+    #[test]
+    fn arena() {
+        let data = image(ET::EXEC, PF::R | PF::W);
+        let file = ElfFile::new(&data).unwrap();
+        let range = file.load_range(8).unwrap().unwrap();
+        assert_eq!(range, 0x1000..0x2020);
+        assert_eq!(file.load_align(), Ok(8));
+
+        let mut buffer = vec![0x55; (range.end - range.start) as usize];
+        let mut memory = Checked {
+            arena: Arena::new(&mut buffer, range.start),
+            protected: Vec::new(),
+        };
+        let entry = file.load(&mut memory, 0, 8).unwrap();
+        assert_eq!(entry, file.ehdr().e_entry);
+        assert_eq!(memory.protected.len(), 2);
+        assert_eq!(memory.protected[1].1, PF::R | PF::W);
+        assert_eq!(&buffer[..8], &[0xc3; 8][..]);
+        assert_eq!(buffer[8], 0x55);
+        assert_eq!(&buffer[0x1000..0x1004], &[0xaa; 4][..]);
+        // .bss and the padding to the segment's alignment are zeroed
+        assert!(buffer[0x1004..0x1020].iter().all(|&b| b == 0));
+
+        // Position independent image at a new base
+        let data = image(ET::DYN, PF::R | PF::W);
+        let file = ElfFile::new(&data).unwrap();
+        let mut buffer = vec![0; 0x1020];
+        let bias = 0x40_0000 - 0x1000;
+        let mut arena = Arena::new(&mut buffer, 0x40_0000);
+        assert_eq!(
+            file.load(&mut arena, bias, 8),
+            Ok(file.ehdr().e_entry + bias)
+        );
+        assert_eq!(file.load(&mut arena, bias + 4, 8), Err(Error::Malformed));
+        // Too small
+        let mut arena = Arena::new(&mut buffer[..0x101c], 0x40_0000);
+        assert_eq!(file.load(&mut arena, bias, 8), Err(Error::NotFound));
+
+        // Permissions are refused by the callback
+        let data = image(ET::EXEC, PF::R | PF::W | PF::X);
+        let file = ElfFile::new(&data).unwrap();
+        let mut buffer = vec![0; 0x1020];
+        let mut memory = Checked {
+            arena: Arena::new(&mut buffer, 0x1000),
+            protected: Vec::new(),
+        };
+        assert_eq!(file.load(&mut memory, 0, 8), Err(Error::Unsupported));
+    }
+
+    #[test]
+    fn pages() {
+        // Segments are widened to the caller's pages rather than to their alignment, with
+        // addresses congruent to the offsets found by a first build
+        let build = |text_addr: u64, data_addr: u64| {
+            let mut builder = Builder::new(false, false, ET::DYN, EM::AARCH64);
+            let text = builder.section(".text", SHT::PROGBITS, text_addr, vec![0xc3; 8]);
+            let data = builder.section(".data", SHT::PROGBITS, data_addr, vec![0xaa; 4]);
+            let text = builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+            let data = builder.segment(PT::LOAD, PF::R | PF::W, &[data]);
+            builder.phdr_mut(text).p_align = 0x10000;
+            builder.phdr_mut(data).p_align = 0x10000;
+            builder.build()
+        };
+        let data = build(0, 0);
+        let file = ElfFile::new(&data).unwrap();
+        let offsets: Vec<u64> = file.phdrs().unwrap().map(|phdr| phdr.p_offset).collect();
+        let data = build(0x10000 + offsets[0], 0x20000 + offsets[1]);
+        let file = ElfFile::new(&data).unwrap();
+        assert_eq!(file.load_range(0x1000), Ok(Some(0x10000..0x21000)));
+        assert_eq!(file.load_range(0x10000), Ok(Some(0x10000..0x30000)));
+        assert_eq!(file.load_range(0x1800), Err(Error::Malformed));
+
+        let mut buffer = vec![0; 0x11000];
+        let mut arena = Arena::new(&mut buffer, 0x10000);
+        assert_eq!(file.load(&mut arena, 0, 0x1000), Ok(0));
+        assert_eq!(buffer[0x10000 + offsets[1] as usize], 0xaa);
+        let mut arena = Arena::new(&mut buffer, 0x10000);
+        assert_eq!(file.load(&mut arena, 0x1000, 0x1000), Err(Error::Malformed));
+        assert_eq!(file.load(&mut arena, 0, 0), Err(Error::Malformed));
+        // Addresses outside the arena, or beyond what a pointer can hold, aren't found
+        assert_eq!(arena.write(u64::MAX - 1, &[0]), Err(Error::NotFound));
+        assert_eq!(arena.write(0x10000 + (1 << 40), &[0]), Err(Error::NotFound));
+    }
+}
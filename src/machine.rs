@@ -0,0 +1,116 @@
+//! Machine properties
+//!
+//! Properties of the architectures in [`EM`] that can be used to sanity check an ELF header:
+//! the byte order and class a machine normally uses, and whether the crate can name its
+//! relocation types.
+
+use crate::ehdr::{EIC, EID, EM};
+
+/// Properties of a machine architecture
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Info {
+    /// Default byte order, [`EID::NONE`] if unknown
+    pub endianness: EID,
+    /// Machine is also used with the other byte order
+    pub bi_endian: bool,
+    /// Native class, [`EIC::NONE`] if unknown
+    pub class: EIC,
+    /// Machine is also used with the other class, such as x32 on x86-64
+    pub multi_class: bool,
+    /// [`crate::reloc::name()`] can name relocation types for the machine
+    pub relocation_names: bool,
+}
+
+impl Info {
+    /// Returns true if a file with `class` and `data` encoding is plausible for the machine
+    ///
+    /// Unknown properties aren't checked.
+    pub fn accepts(&self, class: EIC, data: EID) -> bool {
+        let class_ok = self.class == EIC::NONE || self.multi_class || class == self.class;
+        let data_ok = self.endianness == EID::NONE || self.bi_endian || data == self.endianness;
+        class_ok && data_ok
+    }
+}
+
+impl EM {
+    /// Properties of the machine
+    pub fn info(self) -> Info {
+        const LE: EID = EID::LSB;
+        const BE: EID = EID::MSB;
+        const UNKNOWN: EID = EID::NONE;
+        const C32: EIC = EIC::ELF32;
+        const C64: EIC = EIC::ELF64;
+        const ANY: EIC = EIC::NONE;
+        // (endianness, bi-endian, class, multi-class)
+        let (endianness, bi_endian, class, multi_class) = match self {
+            EM::X86 | EM::IAMCU => (LE, false, C32, false),
+            EM::X86_64 => (LE, false, C64, true),
+            EM::IA_64 => (LE, true, C64, true),
+            EM::L10M | EM::K10M => (LE, false, C64, false),
+            EM::ARM => (LE, true, C32, false),
+            EM::AARCH64 => (LE, true, C64, true),
+            EM::MIPS => (BE, true, C32, true),
+            EM::MIPS_RS3_LE => (LE, false, C32, false),
+            EM::PPC32 => (BE, true, C32, false),
+            EM::PPC64 => (BE, true, C64, false),
+            EM::SPARC | EM::SPARC32PLUS => (BE, false, C32, false),
+            EM::SPARCV9 => (BE, false, C64, false),
+            EM::S370 => (BE, false, C32, false),
+            EM::S390 => (BE, false, C64, true),
+            EM::M68K | EM::COLDFIRE | EM::M88K => (BE, false, C32, false),
+            EM::M68HC11 | EM::M68HC12 | EM::M68HC16 => (BE, false, C32, false),
+            EM::PARISC => (BE, false, C32, true),
+            EM::SH => (LE, true, C32, false),
+            EM::ALPHA | EM::OLD_ALPHA => (LE, false, C64, false),
+            EM::RISCV => (LE, true, C64, true),
+            EM::LOONGARCH => (LE, false, C64, true),
+            EM::BPF => (LE, true, C64, false),
+            EM::AVR | EM::MSP430 | EM::Z80 | EM::STM8 => (LE, false, C32, false),
+            EM::XTENSA | EM::ARC | EM::ARC_COMPACT | EM::ARCV2 => (LE, true, C32, false),
+            EM::ARC_COMPACT3 => (LE, false, C32, false),
+            EM::ARC_COMPACT3_64 => (LE, false, C64, false),
+            EM::HEXAGON | EM::NIOS2 | EM::CSKY | EM::TRICORE | EM::V850 => (LE, false, C32, false),
+            EM::MICROBLAZE => (BE, true, C32, true),
+            EM::OPENRISC | EM::M32R | EM::H8_300 | EM::H8_300H | EM::H8S => (BE, false, C32, false),
+            EM::MMIX => (BE, false, C64, false),
+            EM::CRIS | EM::VAX | EM::BLACKFIN | EM::RX | EM::RL78 => (LE, false, C32, false),
+            EM::TILEPRO => (LE, false, C32, false),
+            EM::TILEGX => (LE, true, C64, true),
+            EM::CUDA | EM::AMDGPU | EM::VE | EM::KVX => (LE, false, C64, false),
+            _ => (UNKNOWN, false, ANY, false),
+        };
+        Info {
+            endianness,
+            bi_endian,
+            class,
+            multi_class,
+            relocation_names: crate::reloc::has_names(self),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn accepts() {
+        let info = EM::X86_64.info();
+        assert_eq!(info.endianness, EID::LSB);
+        assert_eq!(info.class, EIC::ELF64);
+        assert!(info.relocation_names);
+        assert!(info.accepts(EIC::ELF64, EID::LSB));
+        // x32
+        assert!(info.accepts(EIC::ELF32, EID::LSB));
+        assert!(!info.accepts(EIC::ELF64, EID::MSB));
+
+        let info = EM::SPARCV9.info();
+        assert!(!info.relocation_names);
+        assert!(!info.accepts(EIC::ELF32, EID::MSB));
+        assert!(!info.accepts(EIC::ELF64, EID::LSB));
+
+        // Unknown properties aren't checked
+        let info = EM::from(0x1234).info();
+        assert!(info.accepts(EIC::ELF32, EID::MSB));
+    }
+}
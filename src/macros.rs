@@ -8,6 +8,32 @@ macro_rules! value_struct {
                 $(=> $description:literal)?
             ),* $(,)?
         }
+        $(
+            machines {
+                $(
+                    $($machine:ident)|+ {
+                        $(
+                            $(#[$mattrs:meta])*
+                            $mvariant:ident = $mvalue:literal
+                            $(=> $mdescription:literal)?
+                        ),* $(,)?
+                    }
+                )*
+            }
+        )?
+        $(
+            osabis {
+                $(
+                    $($osabi:ident)|+ {
+                        $(
+                            $(#[$oattrs:meta])*
+                            $ovariant:ident = $ovalue:literal
+                            $(=> $odescription:literal)?
+                        ),* $(,)?
+                    }
+                )*
+            }
+        )?
     ) => {
         $(#[$attrs])* // Include attributes
         #[derive(Clone, Copy, Default, Eq, Ord, PartialEq, PartialOrd, serde::Deserialize, serde::Serialize)]
@@ -21,6 +47,17 @@ macro_rules! value_struct {
                 $(#[$vattrs])* // Include attributes
                 pub const $variant: $name = $name($value);
             )*
+            // Define machine and operating system specific values
+            $($($(
+                $(#[doc=$mdescription])?
+                $(#[$mattrs])*
+                pub const $mvariant: $name = $name($mvalue);
+            )*)*)?
+            $($($(
+                $(#[doc=$odescription])?
+                $(#[$oattrs])*
+                pub const $ovariant: $name = $name($ovalue);
+            )*)*)?
             /// Return the name of the value, or `None` if unknown
             pub fn name(self) -> Option<&'static str> {
                 match self {
@@ -39,14 +76,72 @@ macro_rules! value_struct {
                     _ => None,
                 }
             }
-        }
-        /// Convert from inner type
-        impl Into<$inner> for $name {
-            fn into(self) -> $inner {
-                self.0
+            /// Return the name of the value in a file for `machine` and `osabi`, or `None` if unknown
+            pub fn name_for(self, machine: $crate::ehdr::EM, osabi: $crate::ehdr::EIOSABI) -> Option<&'static str> {
+                let _ = (machine, osabi);
+                $($(
+                    if $(machine == $crate::ehdr::EM::$machine)||+ {
+                        match self {
+                            $(
+                                Self::$mvariant => return Some(stringify!($mvariant)),
+                            )*
+                            _ => {}
+                        }
+                    }
+                )*)?
+                $($(
+                    if $(osabi == $crate::ehdr::EIOSABI::$osabi)||+ {
+                        match self {
+                            $(
+                                Self::$ovariant => return Some(stringify!($ovariant)),
+                            )*
+                            _ => {}
+                        }
+                    }
+                )*)?
+                self.name()
+            }
+            /// Return description for the value in a file for `machine` and `osabi`, or `None` if no description
+            pub fn description_for(self, machine: $crate::ehdr::EM, osabi: $crate::ehdr::EIOSABI) -> Option<&'static str> {
+                let _ = (machine, osabi);
+                $($(
+                    if $(machine == $crate::ehdr::EM::$machine)||+ {
+                        match self {
+                            $(
+                                Self::$mvariant => return None $(.or(Some($mdescription)))?,
+                            )*
+                            _ => {}
+                        }
+                    }
+                )*)?
+                $($(
+                    if $(osabi == $crate::ehdr::EIOSABI::$osabi)||+ {
+                        match self {
+                            $(
+                                Self::$ovariant => return None $(.or(Some($odescription)))?,
+                            )*
+                            _ => {}
+                        }
+                    }
+                )*)?
+                self.description()
+            }
+            /// Wrap the value to format it for a file for `machine` and `osabi`
+            pub fn for_target(self, machine: $crate::ehdr::EM, osabi: $crate::ehdr::EIOSABI) -> $crate::Targeted<Self> {
+                $crate::Targeted {
+                    value: self,
+                    machine,
+                    osabi,
+                }
             }
         }
         /// Convert to inner type
+        impl From<$name> for $inner {
+            fn from(other: $name) -> Self {
+                other.0
+            }
+        }
+        /// Convert from inner type
         impl From<$inner> for $name {
             fn from(other: $inner) -> Self {
                 Self(other)
@@ -56,6 +151,17 @@ macro_rules! value_struct {
 }
 
 /// Macro for defining helpful enum-like new-type structs
+///
+/// An optional `ranges { os: LO..=HI, processor: LO..=HI, user: LO..=HI, }` block after the
+/// values declares reserved ranges (each is optional, but they must be in that order). Unknown
+/// values within a range are formatted relative to the start of the range (`LOPROC+0x1`), and
+/// `is_os_specific()`, `is_processor_specific()` and `is_user_specific()` predicates are
+/// generated for the declared ranges.
+///
+/// Optional `machines { ARM { ... } MIPS | MIPS_RS3_LE { ... } }` and `osabis { ... }` blocks
+/// declare values that only have a meaning for files for a particular machine or operating
+/// system ABI, which often share values with each other. These are only named by `name_for()`,
+/// `description_for()` and the formatting of `for_target()`.
 macro_rules! enum_struct {
     (
         $(#[$attrs:meta])*
@@ -66,6 +172,15 @@ macro_rules! enum_struct {
                 $(=> $description:literal)?
             ),* $(,)?
         }
+        $(
+            ranges {
+                $(os: $os_low:ident ..= $os_high:ident,)?
+                $(processor: $proc_low:ident ..= $proc_high:ident,)?
+                $(user: $user_low:ident ..= $user_high:ident,)?
+            }
+        )?
+        $(machines $machines:tt)?
+        $(osabis $osabis:tt)?
     ) => {
         // Implement values using values! macro
         value_struct!(
@@ -75,13 +190,55 @@ macro_rules! enum_struct {
                 $(#[$vattrs])*
                 $variant = $value $(=> $description)?,
             )*
-        });
+        }
+        $(machines $machines)?
+        $(osabis $osabis)?
+        );
+        impl $name {
+            $($(
+                /// Whether the value is in the operating system specific range
+                pub fn is_os_specific(self) -> bool {
+                    self >= Self::$os_low && self <= Self::$os_high
+                }
+            )?)?
+            $($(
+                /// Whether the value is in the processor specific range
+                pub fn is_processor_specific(self) -> bool {
+                    self >= Self::$proc_low && self <= Self::$proc_high
+                }
+            )?)?
+            $($(
+                /// Whether the value is in the user specific range
+                pub fn is_user_specific(self) -> bool {
+                    self >= Self::$user_low && self <= Self::$user_high
+                }
+            )?)?
+            /// Format a value without a name
+            fn fmt_unknown(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                $($(
+                    if self.is_os_specific() {
+                        return write!(f, "{}+{:#x}", stringify!($os_low), self.0 - Self::$os_low.0);
+                    }
+                )?)?
+                $($(
+                    if self.is_processor_specific() {
+                        return write!(f, "{}+{:#x}", stringify!($proc_low), self.0 - Self::$proc_low.0);
+                    }
+                )?)?
+                $($(
+                    if self.is_user_specific() {
+                        return write!(f, "{}+{:#x}", stringify!($user_low), self.0 - Self::$user_low.0);
+                    }
+                )?)?
+                write!(f, "unknown({})", self.0)
+            }
+        }
         impl core::fmt::Debug for $name {
             fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
                 if let Some(name) = self.name() {
                     write!(f, "{}", name)
                 } else {
-                    write!(f, "unknown({})", self.0)
+                    self.fmt_unknown(f)
                 }
             }
         }
@@ -90,7 +247,26 @@ macro_rules! enum_struct {
                 if let Some(name) = self.description().or_else(|| self.name()) {
                     write!(f, "{}", name)
                 } else {
-                    write!(f, "unknown({})", self.0)
+                    self.fmt_unknown(f)
+                }
+            }
+        }
+        impl core::fmt::Debug for $crate::Targeted<$name> {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                if let Some(name) = self.value.name_for(self.machine, self.osabi) {
+                    write!(f, "{}", name)
+                } else {
+                    self.value.fmt_unknown(f)
+                }
+            }
+        }
+        impl core::fmt::Display for $crate::Targeted<$name> {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                let (machine, osabi) = (self.machine, self.osabi);
+                if let Some(name) = self.value.description_for(machine, osabi).or_else(|| self.value.name_for(machine, osabi)) {
+                    write!(f, "{}", name)
+                } else {
+                    self.value.fmt_unknown(f)
                 }
             }
         }
@@ -98,6 +274,8 @@ macro_rules! enum_struct {
 }
 
 /// Macro for defining helpful flags new-type structs that can be combined and checked
+///
+/// Takes the same optional `machines` and `osabis` blocks as `enum_struct!`.
 macro_rules! flag_struct {
     (
         $(#[$attrs:meta])*
@@ -108,6 +286,8 @@ macro_rules! flag_struct {
                 $(=> $description:literal)?
             ),* $(,)?
         }
+        $(machines $machines:tt)?
+        $(osabis $osabis:tt)?
     ) => {
         // Implement values using values! macro
         value_struct!(
@@ -117,7 +297,10 @@ macro_rules! flag_struct {
                 $(#[$vattrs])*
                 $variant = $value $(=> $description)?,
             )*
-        });
+        }
+        $(machines $machines)?
+        $(osabis $osabis)?
+        );
         impl core::ops::BitOr for $name {
             type Output = Self;
             fn bitor(self, other: Self) -> Self {
@@ -140,23 +323,24 @@ macro_rules! flag_struct {
                 self.0 &= other.0;
             }
         }
-        impl core::fmt::Debug for $name {
-            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+        impl $name {
+            /// Format each set bit using `name`
+            fn fmt_bits(&self, f: &mut core::fmt::Formatter, name: impl Fn(Self) -> Option<&'static str>) -> core::fmt::Result {
                 use core::mem::size_of_val;
-                use core::convert::TryInto;
                 if self.0 == 0 {
                     // Special case empty flags
-                    return write!(f, "{}", self.name().unwrap_or("none"));
+                    return write!(f, "{}", name(*self).unwrap_or("none"));
                 }
 
                 let mut seperate = false;
                 for bit in 0..size_of_val(&self.0) * 8 {
-                    let value = Self((1<<bit).try_into().unwrap());
+                    let one: $inner = 1;
+                    let value = Self(one << bit);
                     if *self & value == value {
                         if seperate {
                             write!(f, " | ")?;
                         }
-                        if let Some(name) = value.name() {
+                        if let Some(name) = name(value) {
                             write!(f, "{}", name)?;
                         } else {
                             write!(f, "bit{}", bit)?;
@@ -167,10 +351,22 @@ macro_rules! flag_struct {
                 Ok(())
             }
         }
+        impl core::fmt::Debug for $name {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                self.fmt_bits(f, Self::name)
+            }
+        }
+        impl core::fmt::Debug for $crate::Targeted<$name> {
+            fn fmt(&self, f: &mut core::fmt::Formatter) -> core::fmt::Result {
+                let (machine, osabi) = (self.machine, self.osabi);
+                self.value.fmt_bits(f, |value| value.name_for(machine, osabi))
+            }
+        }
     };
 }
 
 #[cfg(test)]
+#[allow(clippy::upper_case_acronyms)]
 mod test {
     extern crate std;
     use std::format;
@@ -229,6 +425,34 @@ mod test {
         assert_eq!(format!("{:?}", ABCdescription::from(9)), "unknown(9)");
     }
 
+    #[test]
+    fn enum_struct_ranges() {
+        enum_struct!(pub struct Ranges(u16) {
+            NONE   = 0,
+            LOOS   = 0x100 => "First OS value",
+            NAMED  = 0x110 => "Named OS value",
+            HIOS   = 0x1ff => "Last OS value",
+            LOPROC = 0x200,
+            HIPROC = 0x2ff,
+        }
+        ranges {
+            os: LOOS..=HIOS,
+            processor: LOPROC..=HIPROC,
+        });
+        assert_eq!(format!("{:?}", Ranges::NAMED), "NAMED");
+        assert_eq!(format!("{:?}", Ranges::from(0x101)), "LOOS+0x1");
+        assert_eq!(format!("{}", Ranges::from(0x1fe)), "LOOS+0xfe");
+        assert_eq!(format!("{}", Ranges::HIOS), "Last OS value");
+        assert_eq!(format!("{:?}", Ranges::from(0x201)), "LOPROC+0x1");
+        assert_eq!(format!("{}", Ranges::from(0x201)), "LOPROC+0x1");
+        assert_eq!(format!("{:?}", Ranges::from(0x300)), "unknown(768)");
+        assert!(Ranges::NAMED.is_os_specific());
+        assert!(!Ranges::NAMED.is_processor_specific());
+        assert!(Ranges::from(0x2ff).is_processor_specific());
+        assert!(!Ranges::NONE.is_os_specific());
+        assert!(!Ranges::NONE.is_processor_specific());
+    }
+
     #[test]
     fn flag_struct() {
         flag_struct!(pub struct ABC(u8) {
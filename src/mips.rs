@@ -0,0 +1,635 @@
+//! MIPS specific data
+//!
+//! ABI flags (`.MIPS.abiflags`), register usage (`.reginfo`), option descriptors
+//! (`.MIPS.options`), the layout of the global offset table and the packed relocation types
+//! of MIPS64.
+
+use crate::de::from_bytes;
+use crate::dynamic::DT;
+use crate::ehdr::EM;
+use crate::phdr::PT;
+use crate::reloc::MIPS;
+use crate::shdr::SHT;
+use crate::{ElfFile, Entry, Error};
+use core::fmt;
+use core::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+enum_struct!(
+/// Register size in [`AbiFlags`]
+pub struct AflReg(u8) {
+    NONE = 0 => "none",
+    R32  = 1 => "32",
+    R64  = 2 => "64",
+    R128 = 3 => "128",
+}
+);
+
+enum_struct!(
+/// Floating point ABI in [`AbiFlags`], also used by `Tag_GNU_MIPS_ABI_FP`
+pub struct FpAbi(u8) {
+    ANY    = 0 => "Hard or soft float",
+    DOUBLE = 1 => "Hard float (double precision)",
+    SINGLE = 2 => "Hard float (single precision)",
+    SOFT   = 3 => "Soft float",
+    OLD_64 = 4 => "Hard float (MIPS32r2 64-bit FPU 12 callee-saved)",
+    XX     = 5 => "Hard float (32-bit CPU, Any FPU)",
+    FP64   = 6 => "Hard float (32-bit CPU, 64-bit FPU)",
+    FP64A  = 7 => "Hard float compat (32-bit CPU, 64-bit FPU)",
+}
+);
+
+enum_struct!(
+/// Processor specific extension in [`AbiFlags`]
+pub struct IsaExt(u32) {
+    NONE           = 0  => "None",
+    XLR            = 1  => "RMI XLR",
+    OCTEON2        = 2  => "Cavium Networks Octeon2",
+    OCTEONP        = 3  => "Cavium Networks OcteonP",
+    LOONGSON_3A    = 4  => "Loongson 3A",
+    OCTEON         = 5  => "Cavium Networks Octeon",
+    R5900          = 6  => "Toshiba R5900",
+    R4650          = 7  => "MIPS R4650",
+    R4010          = 8  => "LSI R4010",
+    R4100          = 9  => "NEC VR4100",
+    R3900          = 10 => "Toshiba R3900",
+    R10000         = 11 => "MIPS R10000",
+    SB1            = 12 => "Broadcom SB-1",
+    R4111          = 13 => "NEC VR4111/VR4181",
+    R4120          = 14 => "NEC VR4120",
+    R5400          = 15 => "NEC VR5400",
+    R5500          = 16 => "NEC VR5500",
+    LOONGSON_2E    = 17 => "ST Microelectronics Loongson 2E",
+    LOONGSON_2F    = 18 => "ST Microelectronics Loongson 2F",
+    OCTEON3        = 19 => "Cavium Networks Octeon3",
+    INTERAPTIV_MR2 = 20 => "Imagination interAptiv MR2",
+}
+);
+
+flag_struct!(
+/// Application specific extensions in [`AbiFlags`]
+pub struct Ase(u32) {
+    NONE          = 0        => "None",
+    DSP           = 0x1      => "DSP",
+    DSPR2         = 0x2      => "DSPR2",
+    EVA           = 0x4      => "Enhanced VA Scheme",
+    MCU           = 0x8      => "MCU (MicroController)",
+    MDMX          = 0x10     => "MDMX",
+    MIPS3D        = 0x20     => "MIPS-3D",
+    MT            = 0x40     => "MT",
+    SMARTMIPS     = 0x80     => "SmartMIPS",
+    VIRT          = 0x100    => "VZ",
+    MSA           = 0x200    => "MSA",
+    MIPS16        = 0x400    => "MIPS16",
+    MICROMIPS     = 0x800    => "microMIPS",
+    XPA           = 0x1000   => "XPA",
+    DSPR3         = 0x2000   => "DSPR3",
+    MIPS16E2      = 0x4000   => "MIPS16e2",
+    CRC           = 0x8000   => "CRC",
+    GINV          = 0x20000  => "GINV",
+    LOONGSON_MMI  = 0x40000  => "Loongson MMI",
+    LOONGSON_CAM  = 0x80000  => "Loongson CAM",
+    LOONGSON_EXT  = 0x100000 => "Loongson EXT",
+    LOONGSON_EXT2 = 0x200000 => "Loongson EXT2",
+}
+);
+
+flag_struct!(
+/// Flags in [`AbiFlags::flags1`]
+pub struct AflFlags1(u32) {
+    NONE     = 0 => "None",
+    ODDSPREG = 1 => "Uses odd single-precision registers",
+}
+);
+
+/// ABI flags (`SHT::MIPS_ABIFLAGS` section or `PT::MIPS_ABIFLAGS` segment)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct AbiFlags {
+    /// Version of the structure, 0
+    pub version: u16,
+    /// ISA level, such as 32 or 64
+    pub isa_level: u8,
+    /// ISA revision, such as 2 or 6
+    pub isa_rev: u8,
+    /// General purpose register size
+    pub gpr_size: AflReg,
+    /// Coprocessor 1 (floating point) register size
+    pub cpr1_size: AflReg,
+    /// Coprocessor 2 register size
+    pub cpr2_size: AflReg,
+    /// Floating point ABI
+    pub fp_abi: FpAbi,
+    /// Processor specific extension
+    pub isa_ext: IsaExt,
+    /// Application specific extensions
+    pub ases: Ase,
+    /// Flags
+    pub flags1: AflFlags1,
+    /// Reserved flags
+    pub flags2: u32,
+}
+impl AbiFlags {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+/// 32-bit register usage information (`.reginfo`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct RegInfo32 {
+    /// General purpose registers used, bit `n` for register `n`
+    pub ri_gprmask: u32,
+    /// Coprocessor registers used, one mask per coprocessor
+    pub ri_cprmask: [u32; 4],
+    /// Initial value of `$gp`
+    pub ri_gp_value: i32,
+}
+
+/// 64-bit register usage information (`ODK::REGINFO` option)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct RegInfo64 {
+    /// General purpose registers used, bit `n` for register `n`
+    pub ri_gprmask: u32,
+    /// Padding
+    pub ri_pad: u32,
+    /// Coprocessor registers used, one mask per coprocessor
+    pub ri_cprmask: [u32; 4],
+    /// Initial value of `$gp`
+    pub ri_gp_value: i64,
+}
+impl RegInfo32 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl RegInfo64 {
+    pub const SIZE: usize = size_of::<Self>();
+}
+impl From<RegInfo32> for RegInfo64 {
+    fn from(o: RegInfo32) -> RegInfo64 {
+        RegInfo64 {
+            ri_gprmask: o.ri_gprmask,
+            ri_pad: 0,
+            ri_cprmask: o.ri_cprmask,
+            ri_gp_value: o.ri_gp_value as i64,
+        }
+    }
+}
+impl Entry for RegInfo64 {
+    type Elf32 = RegInfo32;
+}
+
+enum_struct!(
+/// Kind of an option descriptor
+pub struct ODK(u8) {
+    NULL       = 0  => "Undefined",
+    REGINFO    = 1  => "Register usage information",
+    EXCEPTIONS = 2  => "Exception processing options",
+    PAD        = 3  => "Section padding options",
+    HWPATCH    = 4  => "Hardware workarounds performed",
+    FILL       = 5  => "Fill value used by the linker",
+    TAGS       = 6  => "Space reserved for desktop tools",
+    HWAND      = 7  => "Hardware workarounds, AND when merging",
+    HWOR       = 8  => "Hardware workarounds, OR when merging",
+    GP_GROUP   = 9  => "GP group to use for text/data sections",
+    IDENT      = 10 => "ID information",
+    PAGESIZE   = 11 => "Page size information",
+}
+);
+
+/// Option descriptor header (`Elf_Options`)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct OptionHeader {
+    /// Kind of option
+    pub kind: ODK,
+    /// Size of the descriptor, including the header
+    pub size: u8,
+    /// Section index the option applies to, 0 for the whole file
+    pub section: u16,
+    /// Kind specific information
+    pub info: u32,
+}
+impl OptionHeader {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+/// Option descriptor of a `.MIPS.options` section
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Descriptor<'a> {
+    /// Header
+    pub header: OptionHeader,
+    /// Data following the header
+    pub data: &'a [u8],
+}
+
+/// Iterator over the descriptors of a `.MIPS.options` section
+#[derive(Clone)]
+pub struct Options<'a> {
+    data: &'a [u8],
+    big_endian: bool,
+}
+
+impl<'a> Options<'a> {
+    /// Option descriptors in section `data`
+    pub fn new(data: &'a [u8], big_endian: bool) -> Self {
+        Options { data, big_endian }
+    }
+
+    fn read(&mut self) -> Result<Descriptor<'a>, Error> {
+        let header: OptionHeader = from_bytes(self.data, self.big_endian)?;
+        let size = header.size as usize;
+        if size < OptionHeader::SIZE {
+            return Err(Error::Malformed);
+        }
+        let data = self
+            .data
+            .get(OptionHeader::SIZE..size)
+            .ok_or(Error::Truncated)?;
+        self.data = &self.data[size..];
+        Ok(Descriptor { header, data })
+    }
+}
+
+impl<'a> Iterator for Options<'a> {
+    type Item = Result<Descriptor<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let result = self.read();
+        if result.is_err() {
+            self.data = &[];
+        }
+        Some(result)
+    }
+}
+
+enum_struct!(
+/// Special symbol of a MIPS64 relocation
+pub struct RSS(u8) {
+    UNDEF = 0 => "None",
+    GP    = 1 => "Value of gp",
+    GP0   = 2 => "Value of gp used to create the object",
+    LOC   = 3 => "Address of the location being relocated",
+}
+);
+
+/// Relocation types of a MIPS64 relocation
+///
+/// MIPS64 relocations apply up to three operations in turn, the result of each being the
+/// addend of the next, with `r_ssym` replacing the symbol of the second and third. The
+/// types are packed into [`crate::Relocation::r_type`] in the big-endian `r_info` layout:
+/// `r_type` in the low byte, then `r_type2`, `r_type3` and `r_ssym`. The little-endian
+/// layout is converted when reading relocations.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct RelocationTypes {
+    /// First operation
+    pub r_type: MIPS,
+    /// Second operation
+    pub r_type2: MIPS,
+    /// Third operation
+    pub r_type3: MIPS,
+    /// Special symbol used by the second and third operations
+    pub r_ssym: RSS,
+}
+
+impl RelocationTypes {
+    /// Iterate over the operations, up to the first `R_MIPS_NONE`
+    pub fn types(&self) -> impl Iterator<Item = MIPS> {
+        let once = core::iter::once;
+        once(self.r_type)
+            .chain(once(self.r_type2))
+            .chain(once(self.r_type3))
+            .take_while(|&r_type| r_type != MIPS::R_MIPS_NONE)
+    }
+}
+
+impl From<u32> for RelocationTypes {
+    fn from(r_type: u32) -> Self {
+        RelocationTypes {
+            r_type: MIPS::from(r_type & 0xff),
+            r_type2: MIPS::from((r_type >> 8) & 0xff),
+            r_type3: MIPS::from((r_type >> 16) & 0xff),
+            r_ssym: RSS::from((r_type >> 24) as u8),
+        }
+    }
+}
+
+impl From<RelocationTypes> for u32 {
+    fn from(types: RelocationTypes) -> u32 {
+        (u32::from(types.r_type) & 0xff)
+            | (u32::from(types.r_type2) & 0xff) << 8
+            | (u32::from(types.r_type3) & 0xff) << 16
+            | u32::from(u8::from(types.r_ssym)) << 24
+    }
+}
+
+impl fmt::Display for RelocationTypes {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let mut separator = "";
+        for r_type in self.types() {
+            write!(f, "{}{}", separator, r_type)?;
+            separator = "/";
+        }
+        if separator.is_empty() {
+            write!(f, "{}", self.r_type)?;
+        }
+        Ok(())
+    }
+}
+
+/// Layout of the primary global offset table, from the dynamic section
+///
+/// The GOT starts with `local_gotno` local entries, the first of which is reserved for the
+/// lazy resolver (and the second for the module pointer with GNU tools), followed by one
+/// global entry per dynamic symbol from `gotsym` up to `symtabno`. When the static linker
+/// needs several GOTs, the secondary GOTs are only described by relocations.
+#[derive(Clone, Copy)]
+pub struct Got<'a> {
+    file: ElfFile<'a>,
+    /// Address of the GOT (`DT::PLTGOT`)
+    pub address: u64,
+    /// Number of local entries (`DT::MIPS_LOCAL_GOTNO`)
+    pub local_gotno: u64,
+    /// Index of the first dynamic symbol with a GOT entry (`DT::MIPS_GOTSYM`)
+    pub gotsym: u64,
+    /// Number of dynamic symbols (`DT::MIPS_SYMTABNO`)
+    pub symtabno: u64,
+}
+
+impl<'a> Got<'a> {
+    /// Number of entries
+    pub fn len(&self) -> u64 {
+        self.local_gotno
+            .saturating_add(self.symtabno.saturating_sub(self.gotsym))
+    }
+
+    /// Returns true if there are no entries
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Address of entry `index`, wrapping around the address space like the dynamic linker
+    pub fn entry_address(&self, index: u64) -> u64 {
+        self.address
+            .wrapping_add(index.wrapping_mul(self.file.word_size() as u64))
+    }
+
+    /// Value of entry `index`
+    pub fn get(&self, index: u64) -> Result<u64, Error> {
+        if index >= self.len() {
+            return Err(Error::NotFound);
+        }
+        let size = self.file.word_size() as u64;
+        let offset = self
+            .file
+            .vaddr_to_offset(self.entry_address(index), size)
+            .ok_or(Error::Malformed)?;
+        self.file.read_word(offset)
+    }
+
+    /// Dynamic symbol index of global entry `index`, or `None` for a local entry
+    pub fn symbol(&self, index: u64) -> Option<u64> {
+        if index < self.len() {
+            let global = index.checked_sub(self.local_gotno)?;
+            self.gotsym.checked_add(global)
+        } else {
+            None
+        }
+    }
+
+    /// Index of the global entry of dynamic symbol `symbol`, or `None` if it has none
+    pub fn index_of_symbol(&self, symbol: u64) -> Option<u64> {
+        if symbol < self.symtabno {
+            let global = symbol.checked_sub(self.gotsym)?;
+            self.local_gotno.checked_add(global)
+        } else {
+            None
+        }
+    }
+}
+
+/// Returns true if `file` is for a MIPS machine
+fn is_mips(file: &ElfFile) -> bool {
+    matches!(file.ehdr().e_machine, EM::MIPS | EM::MIPS_RS3_LE)
+}
+
+impl<'a> ElfFile<'a> {
+    /// Contents of the MIPS section of type `sh_type`, or else of the segment of type `p_type`
+    fn mips_data(&self, sh_type: SHT, p_type: PT) -> Result<Option<&'a [u8]>, Error> {
+        if !is_mips(self) {
+            return Ok(None);
+        }
+        if let Some((_, shdr)) = self.section_by_type(sh_type)? {
+            return self.section_data(&shdr).map(Some);
+        }
+        match self.phdrs()?.find(|phdr| phdr.p_type == p_type) {
+            Some(phdr) => self.segment_data(&phdr).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// MIPS ABI flags, or `None` if there are none
+    pub fn mips_abiflags(&self) -> Result<Option<AbiFlags>, Error> {
+        match self.mips_data(SHT::MIPS_ABIFLAGS, PT::MIPS_ABIFLAGS)? {
+            Some(data) => from_bytes(data, self.is_big_endian()).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// MIPS option descriptors, or `None` if there are none
+    pub fn mips_options(&self) -> Result<Option<Options<'a>>, Error> {
+        Ok(self
+            .mips_data(SHT::MIPS_OPTIONS, PT::MIPS_OPTIONS)?
+            .map(|data| Options::new(data, self.is_big_endian())))
+    }
+
+    /// MIPS register usage information, or `None` if there is none
+    ///
+    /// This is the `.reginfo` section or `PT::MIPS_REGINFO` segment for 32-bit files, and the
+    /// `ODK::REGINFO` option for 64-bit files.
+    pub fn mips_reginfo(&self) -> Result<Option<RegInfo64>, Error> {
+        let big_endian = self.is_big_endian();
+        if let Some(data) = self.mips_data(SHT::MIPS_REGINFO, PT::MIPS_REGINFO)? {
+            return from_bytes::<RegInfo32>(data, big_endian).map(|r| Some(r.into()));
+        }
+        for descriptor in self.mips_options()?.into_iter().flatten() {
+            let descriptor = descriptor?;
+            if descriptor.header.kind == ODK::REGINFO {
+                return Ok(Some(if self.is_elf32() {
+                    from_bytes::<RegInfo32>(descriptor.data, big_endian)?.into()
+                } else {
+                    from_bytes(descriptor.data, big_endian)?
+                }));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Layout of the MIPS global offset table, or `None` if there is no GOT
+    pub fn mips_got(&self) -> Result<Option<Got<'a>>, Error> {
+        if !is_mips(self) {
+            return Ok(None);
+        }
+        let (mut address, mut local_gotno, mut gotsym, mut symtabno) = (None, 0, 0, 0);
+        for d in self.dynamic()? {
+            match d.d_tag {
+                DT::PLTGOT => address = Some(d.d_val),
+                DT::MIPS_LOCAL_GOTNO => local_gotno = d.d_val,
+                DT::MIPS_GOTSYM => gotsym = d.d_val,
+                DT::MIPS_SYMTABNO => symtabno = d.d_val,
+                _ => {}
+            }
+        }
+        Ok(address.map(|address| Got {
+            file: *self,
+            address,
+            local_gotno,
+            gotsym,
+            symtabno,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::ET;
+    use crate::testutil::Builder;
+    use crate::Rela64;
+    use std::vec::Vec;
+    use std::{format, vec};
+
+    #[test]
+    fn sizes() {
+        assert_eq!(AbiFlags::SIZE, 24);
+        assert_eq!(RegInfo32::SIZE, 24);
+        assert_eq!(RegInfo64::SIZE, 32);
+        assert_eq!(OptionHeader::SIZE, 8);
+    }
+
+    #[test]
+    fn structures() {
+        let mut builder = Builder::new(true, true, ET::EXEC, EM::MIPS);
+        // MIPS32r2, fpxx, with odd single precision registers
+        let mut abiflags = vec![0, 0, 32, 2, 1, 1, 0, 5];
+        abiflags.extend([0, 0, 0, 0, 0, 0, 0, 0x02, 0, 0, 0, 1, 0, 0, 0, 0]);
+        builder.section(".MIPS.abiflags", SHT::MIPS_ABIFLAGS, 0x100, abiflags);
+        let mut reginfo = 0xf000_0001u32.to_be_bytes().to_vec();
+        reginfo.extend([0; 16]);
+        reginfo.extend(0x8100_7ff0u32.to_be_bytes());
+        builder.section(".reginfo", SHT::MIPS_REGINFO, 0x118, reginfo);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+
+        let abiflags = file.mips_abiflags().unwrap().unwrap();
+        assert_eq!((abiflags.isa_level, abiflags.isa_rev), (32, 2));
+        assert_eq!(abiflags.gpr_size, AflReg::R32);
+        assert_eq!(abiflags.fp_abi, FpAbi::XX);
+        assert_eq!(abiflags.ases, Ase::DSPR2);
+        assert_eq!(abiflags.flags1, AflFlags1::ODDSPREG);
+        let reginfo = file.mips_reginfo().unwrap().unwrap();
+        assert_eq!(reginfo.ri_gprmask, 0xf000_0001);
+        assert_eq!(reginfo.ri_gp_value, 0x8100_7ff0u32 as i32 as i64);
+        assert!(file.mips_options().unwrap().is_none());
+
+        // 64-bit files keep the register information in the options
+        let mut builder = Builder::new(false, false, ET::EXEC, EM::MIPS);
+        let mut options = vec![ODK::PAD.into(), 8, 0, 0, 0, 0, 0, 0];
+        options.extend([ODK::REGINFO.into(), 40, 0, 0, 0, 0, 0, 0]);
+        options.extend(0x3u32.to_le_bytes());
+        options.extend([0; 20]);
+        options.extend(0x1_0000_8000i64.to_le_bytes());
+        builder.section(".MIPS.options", SHT::MIPS_OPTIONS, 0x100, options);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+        let kinds: Vec<_> = file
+            .mips_options()
+            .unwrap()
+            .unwrap()
+            .map(|d| d.unwrap().header.kind)
+            .collect();
+        assert_eq!(kinds, [ODK::PAD, ODK::REGINFO]);
+        let reginfo = file.mips_reginfo().unwrap().unwrap();
+        assert_eq!(reginfo.ri_gprmask, 3);
+        assert_eq!(reginfo.ri_gp_value, 0x1_0000_8000);
+
+        let mut options = Options::new(&[1, 4, 0, 0, 0, 0, 0, 0], false);
+        assert_eq!(options.next(), Some(Err(Error::Malformed)));
+        assert_eq!(options.next(), None);
+    }
+
+    #[test]
+    fn got() {
+        let mut builder = Builder::new(true, false, ET::DYN, EM::MIPS);
+        let got: Vec<u8> = [0u32, 0x8000_0000, 0x1000, 0x2000, 0x3000]
+            .iter()
+            .flat_map(|w| w.to_le_bytes())
+            .collect();
+        let got = builder.section(".got", SHT::PROGBITS, 0x10000, got);
+        let mut dynamic = Vec::new();
+        for &(tag, value) in &[
+            (DT::PLTGOT, 0x10000),
+            (DT::MIPS_LOCAL_GOTNO, 3),
+            (DT::MIPS_GOTSYM, 4),
+            (DT::MIPS_SYMTABNO, 6),
+            (DT::NULL, 0),
+        ] {
+            dynamic.extend((i64::from(tag) as u32).to_le_bytes());
+            dynamic.extend((value as u32).to_le_bytes());
+        }
+        let dynamic = builder.section(".dynamic", SHT::DYNAMIC, 0x10100, dynamic);
+        builder.segment(PT::LOAD, crate::phdr::PF::R, &[got, dynamic]);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+
+        let got = file.mips_got().unwrap().unwrap();
+        assert_eq!(got.len(), 5);
+        assert_eq!(got.get(1), Ok(0x8000_0000));
+        assert_eq!(got.get(4), Ok(0x3000));
+        assert_eq!(got.get(5), Err(Error::NotFound));
+        assert_eq!(got.entry_address(3), 0x1000c);
+        assert_eq!(got.entry_address(u64::MAX), 0xfffc);
+        assert_eq!(got.symbol(2), None);
+        assert_eq!(got.symbol(3), Some(4));
+        assert_eq!(got.index_of_symbol(5), Some(4));
+        assert_eq!(got.index_of_symbol(3), None);
+
+        let mut huge = got;
+        huge.local_gotno = u64::MAX;
+        huge.symtabno = u64::MAX;
+        assert_eq!(huge.index_of_symbol(5), None);
+    }
+
+    #[test]
+    fn relocation_types() {
+        // R_MIPS_REL32/R_MIPS_64/R_MIPS_NONE against symbol 7, in both byte orders
+        for &big_endian in &[false, true] {
+            let mut builder = Builder::new(false, big_endian, ET::DYN, EM::MIPS);
+            let mut rela = vec![0; 8];
+            let info = if big_endian {
+                [0, 0, 0, 7, 0, 0, 18, 3]
+            } else {
+                [7, 0, 0, 0, 0, 0, 18, 3]
+            };
+            rela.extend(info);
+            rela.extend([0; 8]);
+            assert_eq!(rela.len(), Rela64::SIZE);
+            let rela = builder.section(".rela.dyn", SHT::RELA, 0, rela);
+            let data = builder.build();
+            let file = ElfFile::new(&data).unwrap();
+            let shdr = file.shdr(rela).unwrap();
+            let relocation = file.relocations(&shdr).unwrap().next().unwrap();
+            assert_eq!(relocation.r_sym, 7);
+            let types = RelocationTypes::from(relocation.r_type);
+            assert_eq!(types.r_type, MIPS::R_MIPS_REL32);
+            assert_eq!(types.r_type2, MIPS::R_MIPS_64);
+            assert_eq!(types.r_type3, MIPS::R_MIPS_NONE);
+            assert_eq!(types.r_ssym, RSS::UNDEF);
+            assert_eq!(types.types().count(), 2);
+            assert_eq!(format!("{}", types), "R_MIPS_REL32/R_MIPS_64");
+            assert_eq!(u32::from(types), relocation.r_type);
+        }
+    }
+}
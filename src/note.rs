@@ -0,0 +1,199 @@
+//! Notes in `PT::NOTE` segments and `SHT::NOTE` sections
+//!
+//! Each note is a [`Nhdr`] followed by the owner name and the descriptor, each padded to the
+//! alignment of the segment or section. This is 4 bytes except for notes that ask for 8,
+//! such as GNU property notes in 64-bit files. The meaning of the note type depends on the
+//! owner name, [`NT`] has the types of notes named `"CORE"` and `"LINUX"` in core dumps and
+//! [`NTGNU`] those of notes named `"GNU"`.
+
+use crate::de::from_bytes;
+use crate::{ElfFile, Error, Phdr64, Shdr64};
+use core::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+enum_struct!(
+/// Core dump note types, for notes named `"CORE"` or `"LINUX"`
+pub struct NT(u32) {
+    PRSTATUS        = 1          => "Process status and general purpose registers",
+    FPREGSET        = 2          => "Floating point registers",
+    PRPSINFO        = 3          => "Process information",
+    TASKSTRUCT      = 4          => "Task structure",
+    AUXV            = 6          => "Auxiliary vector",
+    X86_XSTATE      = 0x202      => "x86 extended state",
+    ARM_VFP         = 0x400      => "ARM VFP registers",
+    ARM_TLS         = 0x401      => "ARM TLS register",
+    ARM_HW_BREAK    = 0x402      => "AArch64 hardware breakpoint registers",
+    ARM_HW_WATCH    = 0x403      => "AArch64 hardware watchpoint registers",
+    ARM_SYSTEM_CALL = 0x404      => "AArch64 system call number",
+    ARM_SVE         = 0x405      => "AArch64 SVE registers",
+    ARM_PAC_MASK    = 0x406      => "AArch64 pointer authentication code masks",
+    RISCV_CSR       = 0x900      => "RISC-V control and status registers",
+    RISCV_VECTOR    = 0x901      => "RISC-V vector registers",
+    PRXFPREG        = 0x46e62b7f => "x86 extended floating point registers",
+    FILE            = 0x46494c45 => "Mapped files",
+    SIGINFO         = 0x53494749 => "Signal information",
+}
+);
+
+enum_struct!(
+/// GNU note types, for notes named `"GNU"`
+pub struct NTGNU(u32) {
+    ABI_TAG         = 1 => "ABI tag",
+    HWCAP           = 2 => "Hardware capabilities",
+    BUILD_ID        = 3 => "Build ID",
+    GOLD_VERSION    = 4 => "Version of gold",
+    PROPERTY_TYPE_0 = 5 => "Program properties",
+}
+);
+
+/// Note header
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Nhdr {
+    /// Size of the owner name, including the terminating nul
+    pub n_namesz: u32,
+    /// Size of the descriptor
+    pub n_descsz: u32,
+    /// Type of the note, its meaning depends on the owner name
+    pub n_type: u32,
+}
+impl Nhdr {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+/// Note
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Note<'a> {
+    /// Owner name, without the terminating nul
+    pub name: &'a str,
+    /// Type of the note
+    pub n_type: u32,
+    /// Descriptor
+    pub desc: &'a [u8],
+}
+
+impl<'a> Note<'a> {
+    /// Whether this is a core dump note of type `n_type`
+    pub fn is_core(&self, n_type: NT) -> bool {
+        (self.name == "CORE" || self.name == "LINUX") && self.n_type == u32::from(n_type)
+    }
+
+    /// Whether this is a GNU note of type `n_type`
+    pub fn is_gnu(&self, n_type: NTGNU) -> bool {
+        self.name == "GNU" && self.n_type == u32::from(n_type)
+    }
+}
+
+/// Iterator over the notes in a segment or section
+#[derive(Clone, Debug)]
+pub struct Notes<'a> {
+    data: &'a [u8],
+    align: usize,
+    big_endian: bool,
+}
+
+impl<'a> Notes<'a> {
+    /// Notes in `data` aligned to `align` bytes, which is 4 if it isn't 8
+    pub fn new(data: &'a [u8], align: u64, big_endian: bool) -> Self {
+        Notes {
+            data,
+            align: if align == 8 { 8 } else { 4 },
+            big_endian,
+        }
+    }
+
+    fn parse(&self) -> Result<(Note<'a>, usize), Error> {
+        let nhdr: Nhdr = from_bytes(self.data, self.big_endian)?;
+        let align = |offset: usize| {
+            offset
+                .checked_add(self.align - 1)
+                .map(|o| o & !(self.align - 1))
+        };
+        let name_end = Nhdr::SIZE + nhdr.n_namesz as usize;
+        let desc_start = align(name_end).ok_or(Error::Malformed)?;
+        let desc_end = desc_start
+            .checked_add(nhdr.n_descsz as usize)
+            .ok_or(Error::Malformed)?;
+        if desc_end > self.data.len() {
+            return Err(Error::Truncated);
+        }
+        let name = &self.data[Nhdr::SIZE..name_end];
+        let name = name.split(|&c| c == 0).next().unwrap_or(name);
+        let note = Note {
+            name: core::str::from_utf8(name).map_err(|_| Error::Malformed)?,
+            n_type: nhdr.n_type,
+            desc: &self.data[desc_start..desc_end],
+        };
+        // The padding of the last note may be missing
+        let next = align(desc_end).map_or(desc_end, |next| next.min(self.data.len()));
+        Ok((note, next))
+    }
+}
+
+impl<'a> Iterator for Notes<'a> {
+    type Item = Result<Note<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        match self.parse() {
+            Ok((note, next)) => {
+                self.data = &self.data[next..];
+                Some(Ok(note))
+            }
+            Err(err) => {
+                self.data = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// Notes in the `PT::NOTE` segment `phdr`
+    pub fn segment_notes(&self, phdr: &Phdr64) -> Result<Notes<'a>, Error> {
+        let data = self.segment_data(phdr)?;
+        Ok(Notes::new(data, phdr.p_align, self.is_big_endian()))
+    }
+
+    /// Notes in the `SHT::NOTE` section `shdr`
+    pub fn section_notes(&self, shdr: &Shdr64) -> Result<Notes<'a>, Error> {
+        let data = self.section_data(shdr)?;
+        Ok(Notes::new(data, shdr.sh_addralign, self.is_big_endian()))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn notes() {
+        let data = [
+            4, 0, 0, 0, 3, 0, 0, 0, 3, 0, 0, 0, b'G', b'N', b'U', 0, 1, 2, 3, 0, // build ID
+            5, 0, 0, 0, 0, 0, 0, 0, 1, 0, 0, 0, b'C', b'O', b'R', b'E', 0, 0, 0, 0, // empty
+            6, 0, 0, 0, 4, 0, 0, 0, 6, 0, 0, 0, b'L', b'I', b'N', b'U', b'X', 0, // truncated
+        ];
+        let mut notes = Notes::new(&data, 4, false);
+        let note = notes.next().unwrap().unwrap();
+        assert_eq!(note.name, "GNU");
+        assert_eq!(note.n_type, 3);
+        assert_eq!(note.desc, &[1, 2, 3]);
+        let note = notes.next().unwrap().unwrap();
+        assert!(note.is_core(NT::PRSTATUS));
+        assert!(note.desc.is_empty());
+        assert_eq!(notes.next(), Some(Err(Error::Truncated)));
+        assert_eq!(notes.next(), None);
+
+        // With 8-byte alignment the descriptor is padded to 8 bytes
+        let data = [
+            4, 0, 0, 0, 4, 0, 0, 0, 5, 0, 0, 0, b'G', b'N', b'U', 0, 1, 2, 3, 4, 0, 0, 0,
+            0, //
+            4, 0, 0, 0, 0, 0, 0, 0, 5, 0, 0, 0, b'G', b'N', b'U', 0,
+        ];
+        let notes = Notes::new(&data, 8, false);
+        assert_eq!(notes.clone().count(), 2);
+        assert!(notes.map(Result::unwrap).all(|note| note.n_type == 5));
+    }
+}
@@ -0,0 +1,461 @@
+//! Packed relocation formats
+//!
+//! Two formats shrink the relocation tables of position independent executables and shared
+//! libraries:
+//!
+//! * Android's `APS2` format (`SHT::ANDROID_REL` and `SHT::ANDROID_RELA`) stores relocations
+//!   as groups of SLEB128 deltas, sharing the offset delta, info or addend within a group.
+//! * `SHT::RELR` stores relative relocations without addends as a list of addresses, where
+//!   each address may be followed by bitmaps of the next words to relocate.
+//!
+//! Both are decoded into ordinary [`Relocation`]s by
+//! [`ElfFile::relocations()`](crate::ElfFile::relocations) and
+//! [`ElfFile::dynamic_relocations()`](crate::ElfFile::dynamic_relocations).
+//! [`encode_relr()`] does the reverse for RELR.
+
+use crate::de::from_bytes;
+use crate::leb128::read_sleb128;
+use crate::{Error, Relocation};
+
+/// Magic number at the start of Android packed relocations
+pub const APS2: &[u8] = b"APS2";
+
+/// Group members share `r_info`
+pub const GROUPED_BY_INFO: u64 = 1;
+/// Group members share the offset delta
+pub const GROUPED_BY_OFFSET_DELTA: u64 = 2;
+/// Group members share the addend delta
+pub const GROUPED_BY_ADDEND: u64 = 4;
+/// Group members have addends
+pub const GROUP_HAS_ADDEND: u64 = 8;
+
+/// Iterator over Android packed relocations
+///
+/// Iteration stops at the first malformed group.
+#[derive(Clone, Debug)]
+pub struct AndroidRelocations<'a> {
+    data: &'a [u8],
+    elf32: bool,
+    rela: bool,
+    /// Relocations left in total
+    count: u64,
+    /// Relocations left in the current group
+    group_size: u64,
+    group_flags: u64,
+    group_offset_delta: u64,
+    r_offset: u64,
+    r_info: u64,
+    r_addend: i64,
+}
+
+impl<'a> AndroidRelocations<'a> {
+    /// Relocations packed in `data`, with addends if `rela`
+    pub fn new(data: &'a [u8], elf32: bool, rela: bool) -> Result<Self, Error> {
+        let mut data = data.strip_prefix(APS2).ok_or(Error::BadMagic)?;
+        let count = read_sleb128(&mut data)? as u64;
+        let r_offset = read_sleb128(&mut data)? as u64;
+        Ok(AndroidRelocations {
+            data,
+            elf32,
+            rela,
+            count,
+            group_size: 0,
+            group_flags: 0,
+            group_offset_delta: 0,
+            r_offset,
+            r_info: 0,
+            r_addend: 0,
+        })
+    }
+
+    /// Number of relocations left
+    pub fn remaining(&self) -> u64 {
+        self.count
+    }
+
+    fn start_group(&mut self) -> Result<(), Error> {
+        self.group_size = read_sleb128(&mut self.data)? as u64;
+        self.group_flags = read_sleb128(&mut self.data)? as u64;
+        if self.group_size == 0 || (!self.rela && self.group_flags & GROUP_HAS_ADDEND != 0) {
+            return Err(Error::Malformed);
+        }
+        if self.group_flags & GROUPED_BY_OFFSET_DELTA != 0 {
+            self.group_offset_delta = read_sleb128(&mut self.data)? as u64;
+        }
+        if self.group_flags & GROUPED_BY_INFO != 0 {
+            self.r_info = read_sleb128(&mut self.data)? as u64;
+        }
+        if self.group_flags & GROUP_HAS_ADDEND == 0 {
+            self.r_addend = 0;
+        } else if self.group_flags & GROUPED_BY_ADDEND != 0 {
+            self.r_addend = self.r_addend.wrapping_add(read_sleb128(&mut self.data)?);
+        }
+        Ok(())
+    }
+
+    fn read(&mut self) -> Result<Relocation, Error> {
+        if self.group_size == 0 {
+            self.start_group()?;
+        }
+        let flags = self.group_flags;
+        let delta = if flags & GROUPED_BY_OFFSET_DELTA != 0 {
+            self.group_offset_delta
+        } else {
+            read_sleb128(&mut self.data)? as u64
+        };
+        self.r_offset = self.r_offset.wrapping_add(delta);
+        if flags & GROUPED_BY_INFO == 0 {
+            self.r_info = read_sleb128(&mut self.data)? as u64;
+        }
+        if flags & GROUP_HAS_ADDEND != 0 && flags & GROUPED_BY_ADDEND == 0 {
+            self.r_addend = self.r_addend.wrapping_add(read_sleb128(&mut self.data)?);
+        }
+        self.group_size -= 1;
+        let (r_sym, r_type) = if self.elf32 {
+            ((self.r_info >> 8) as u32, self.r_info as u32 & 0xff)
+        } else {
+            ((self.r_info >> 32) as u32, self.r_info as u32)
+        };
+        Ok(Relocation {
+            r_offset: self.r_offset,
+            r_sym,
+            r_type,
+            r_addend: if self.rela { Some(self.r_addend) } else { None },
+        })
+    }
+}
+
+impl<'a> Iterator for AndroidRelocations<'a> {
+    type Item = Relocation;
+
+    fn next(&mut self) -> Option<Relocation> {
+        if self.count == 0 {
+            return None;
+        }
+        self.count -= 1;
+        match self.read() {
+            Ok(relocation) => Some(relocation),
+            Err(_) => {
+                self.count = 0;
+                None
+            }
+        }
+    }
+}
+
+/// Iterator over the offsets relocated by a RELR table
+#[derive(Clone, Debug)]
+pub struct Relr<'a> {
+    data: &'a [u8],
+    word_size: usize,
+    big_endian: bool,
+    /// Offset relocated by bit 0 of `bitmap`
+    base: u64,
+    /// Bits of the current bitmap that are left
+    bitmap: u64,
+    /// Offset relocated by bit 1 of the next bitmap entry
+    next_base: u64,
+}
+
+impl<'a> Relr<'a> {
+    /// Offsets encoded in the RELR table `data`
+    pub fn new(data: &'a [u8], word_size: usize, big_endian: bool) -> Self {
+        Relr {
+            data,
+            word_size,
+            big_endian,
+            base: 0,
+            bitmap: 0,
+            next_base: 0,
+        }
+    }
+
+    fn read_word(&mut self) -> Option<u64> {
+        let word = self.data.get(..self.word_size)?;
+        self.data = &self.data[self.word_size..];
+        if self.word_size == 8 {
+            from_bytes(word, self.big_endian).ok()
+        } else {
+            from_bytes::<u32>(word, self.big_endian).ok().map(u64::from)
+        }
+    }
+}
+
+impl<'a> Iterator for Relr<'a> {
+    type Item = u64;
+
+    fn next(&mut self) -> Option<u64> {
+        let word_size = self.word_size as u64;
+        while self.bitmap == 0 {
+            let entry = self.read_word()?;
+            if entry & 1 == 0 {
+                self.next_base = entry.wrapping_add(word_size);
+                return Some(entry);
+            }
+            // Bit n relocates the word at next_base + (n - 1) * word_size
+            self.base = self.next_base;
+            self.bitmap = entry >> 1;
+            self.next_base = self.next_base.wrapping_add((8 * word_size - 1) * word_size);
+        }
+        let bit = u64::from(self.bitmap.trailing_zeros());
+        self.bitmap &= self.bitmap - 1;
+        Some(self.base.wrapping_add(bit * word_size))
+    }
+}
+
+/// Encode sorted, word aligned `offsets` as a RELR table, calling `f` with each word
+///
+/// Returns [`Error::Unsupported`] if `word_size` isn't 4 or 8, and [`Error::Malformed`] if
+/// the offsets aren't sorted, are duplicated or aren't aligned to `word_size`.
+pub fn encode_relr(
+    offsets: impl IntoIterator<Item = u64>,
+    word_size: usize,
+    mut f: impl FnMut(u64),
+) -> Result<(), Error> {
+    if word_size != 4 && word_size != 8 {
+        return Err(Error::Unsupported);
+    }
+    let word_size = word_size as u64;
+    let bits = 8 * word_size - 1;
+    let mut offsets = offsets.into_iter().peekable();
+    let mut last = None;
+    let mut check = |offset: u64| {
+        if offset % word_size != 0 || last.is_some_and(|last| offset <= last) {
+            return Err(Error::Malformed);
+        }
+        last = Some(offset);
+        Ok(offset)
+    };
+    while let Some(offset) = offsets.next() {
+        let offset = check(offset)?;
+        f(offset);
+        // At the end of the address space any further offset is out of order
+        let mut base = match offset.checked_add(word_size) {
+            Some(base) => base,
+            None => continue,
+        };
+        loop {
+            let mut bitmap = 0u64;
+            while let Some(&next) = offsets.peek() {
+                let delta = next.wrapping_sub(base);
+                if next < base || delta >= bits * word_size || delta % word_size != 0 {
+                    break;
+                }
+                check(next)?;
+                offsets.next();
+                bitmap |= 1 << (delta / word_size);
+            }
+            if bitmap == 0 {
+                break;
+            }
+            f(bitmap << 1 | 1);
+            base = match base.checked_add(bits * word_size) {
+                Some(base) => base,
+                None => break,
+            };
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::dynamic::DT;
+    use crate::ehdr::{EIOSABI, EM, ET};
+    use crate::phdr::{PF, PT};
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use crate::ElfFile;
+    use std::vec::Vec;
+
+    fn sleb128(data: &mut Vec<u8>, mut value: i64) {
+        loop {
+            let byte = value as u8 & 0x7f;
+            value >>= 7;
+            if (value == 0 && byte & 0x40 == 0) || (value == -1 && byte & 0x40 != 0) {
+                data.push(byte);
+                return;
+            }
+            data.push(byte | 0x80);
+        }
+    }
+
+    #[test]
+    fn android() {
+        let mut data = APS2.to_vec();
+        for &value in &[
+            // Count and initial offset
+            3,
+            0x1000,
+            // Two relative relocations 8 bytes apart
+            2,
+            (GROUPED_BY_INFO | GROUPED_BY_OFFSET_DELTA | GROUP_HAS_ADDEND) as i64,
+            8,
+            8,
+            0x10,
+            0x8,
+            // One GLOB_DAT relocation without addend
+            1,
+            0,
+            0x10,
+            1 << 32 | 6,
+        ] {
+            sleb128(&mut data, value);
+        }
+        let relocations: Vec<_> = AndroidRelocations::new(&data, false, true)
+            .unwrap()
+            .collect();
+        assert_eq!(
+            relocations,
+            [
+                Relocation {
+                    r_offset: 0x1008,
+                    r_sym: 0,
+                    r_type: 8,
+                    r_addend: Some(0x10)
+                },
+                Relocation {
+                    r_offset: 0x1010,
+                    r_sym: 0,
+                    r_type: 8,
+                    r_addend: Some(0x18)
+                },
+                Relocation {
+                    r_offset: 0x1020,
+                    r_sym: 1,
+                    r_type: 6,
+                    r_addend: Some(0)
+                },
+            ]
+        );
+        // Addends aren't allowed without RELA, and a truncated stream ends early
+        assert_eq!(
+            AndroidRelocations::new(&data, false, false)
+                .unwrap()
+                .remaining(),
+            3
+        );
+        assert_eq!(
+            AndroidRelocations::new(&data, false, false).unwrap().next(),
+            None
+        );
+        assert_eq!(
+            AndroidRelocations::new(&data[..data.len() - 2], false, true)
+                .unwrap()
+                .count(),
+            2
+        );
+        assert_eq!(
+            AndroidRelocations::new(&data[..data.len() - 2], false, true)
+                .unwrap()
+                .last()
+                .map(|r| r.r_offset),
+            Some(0x1010)
+        );
+        assert_eq!(
+            AndroidRelocations::new(b"APS1", false, true).err(),
+            Some(Error::BadMagic)
+        );
+    }
+
+    #[test]
+    fn relr() {
+        let offsets = [0x1000, 0x1008, 0x1010, 0x1020, 0x11f8, 0x1200, 0x2000];
+        let mut words = Vec::new();
+        encode_relr(offsets.iter().copied(), 8, |word| words.push(word)).unwrap();
+        assert_eq!(
+            words,
+            [0x1000, (1 << 62 | 0b1011) << 1 | 1, 1 << 1 | 1, 0x2000]
+        );
+        let data: Vec<u8> = words.iter().flat_map(|w| w.to_be_bytes()).collect();
+        assert_eq!(Relr::new(&data, 8, true).collect::<Vec<_>>(), offsets);
+
+        // 32-bit bitmaps cover 31 words
+        let offsets = [0x100, 0x104, 0x17c, 0x180];
+        let mut words = Vec::new();
+        encode_relr(offsets.iter().copied(), 4, |word| words.push(word)).unwrap();
+        assert_eq!(words, [0x100, (1 << 30 | 1) << 1 | 1, 1 << 1 | 1]);
+        let data: Vec<u8> = words
+            .iter()
+            .flat_map(|&w| (w as u32).to_le_bytes())
+            .collect();
+        assert_eq!(Relr::new(&data, 4, false).collect::<Vec<_>>(), offsets);
+
+        let mut words = Vec::new();
+        assert_eq!(
+            encode_relr([0x10, 0x8].iter().copied(), 8, |word| words.push(word)),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            encode_relr([0x10, 0x14].iter().copied(), 8, |word| words.push(word)),
+            Err(Error::Malformed)
+        );
+        assert_eq!(
+            encode_relr([0x10].iter().copied(), 0, |word| words.push(word)),
+            Err(Error::Unsupported)
+        );
+
+        // Offsets at the end of the address space
+        let offsets = [u64::MAX - 0x17, u64::MAX - 7];
+        let mut words = Vec::new();
+        encode_relr(offsets.iter().copied(), 8, |word| words.push(word)).unwrap();
+        assert_eq!(words, [u64::MAX - 0x17, 0b10 << 1 | 1]);
+        let data: Vec<u8> = words.iter().flat_map(|w| w.to_le_bytes()).collect();
+        assert_eq!(Relr::new(&data, 8, false).collect::<Vec<_>>(), offsets);
+        let mut words = Vec::new();
+        encode_relr([u64::MAX - 3].iter().copied(), 4, |word| words.push(word)).unwrap();
+        assert_eq!(words, [u64::MAX - 3]);
+    }
+
+    #[test]
+    fn file() {
+        let mut builder = Builder::new(false, false, ET::DYN, EM::AARCH64);
+        let mut relr = Vec::new();
+        encode_relr([0x3000, 0x3008].iter().copied(), 8, |word| {
+            relr.extend(builder.word(word))
+        })
+        .unwrap();
+        let relr = builder.section(".relr.dyn", SHT::RELR, 0x2000, relr);
+        let dynamic = builder.dynamic(&[(DT::RELR, 0x2000), (DT::RELRSZ, 16), (DT::RELRENT, 8)]);
+        let dynamic = builder.section(".dynamic", SHT::DYNAMIC, 0x2100, dynamic);
+        let data = builder.section(".data", SHT::PROGBITS, 0x3000, std::vec![0; 16]);
+        builder.segment(PT::LOAD, PF::R | PF::W, &[relr, dynamic, data]);
+        builder.segment(PT::DYNAMIC, PF::R | PF::W, &[dynamic]);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+
+        let expected = [
+            Relocation {
+                r_offset: 0x3000,
+                r_sym: 0,
+                r_type: 1027,
+                r_addend: None,
+            },
+            Relocation {
+                r_offset: 0x3008,
+                r_sym: 0,
+                r_type: 1027,
+                r_addend: None,
+            },
+        ];
+        let shdr = file.shdr(relr).unwrap();
+        let relocations: Vec<_> = file.relocations(&shdr).unwrap().collect();
+        assert_eq!(relocations, expected);
+        let relocations: Vec<_> = file.dynamic_relocations().unwrap().collect();
+        assert_eq!(relocations, expected);
+
+        // DT::SUNW_SYMTAB has the value of DT::ANDROID_RELA
+        let mut builder = Builder::new(false, false, ET::DYN, EM::X86_64);
+        builder.ehdr.e_ident.osabi = EIOSABI::SOLARIS;
+        let symtab = builder.section(".SUNW_ldynsym", SHT::PROGBITS, 0x2000, std::vec![0; 24]);
+        let dynamic = builder.dynamic(&[(DT::SUNW_SYMTAB, 0x2000), (DT::SUNW_SYMSZ, 24)]);
+        let dynamic = builder.section(".dynamic", SHT::DYNAMIC, 0x2100, dynamic);
+        builder.segment(PT::LOAD, PF::R, &[symtab, dynamic]);
+        builder.segment(PT::DYNAMIC, PF::R, &[dynamic]);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+        assert_eq!(file.dynamic_relocations().unwrap().count(), 0);
+    }
+}
@@ -0,0 +1,290 @@
+//! PowerPC 64-bit specific data
+//!
+//! In the ELFv1 ABI a function symbol points at a function descriptor in the `.opd` section
+//! rather than at the code. The descriptor holds the entry point, the TOC pointer (`r2`) and
+//! an environment pointer. The ELFv2 ABI dropped descriptors, so symbols point at the code.
+
+use crate::de::from_bytes;
+use crate::dynamic::DT;
+use crate::ehdr::EM;
+use crate::flags::{Flags, Ppc64Abi};
+use crate::shdr::SHN;
+use crate::sym::STT;
+use crate::{ElfFile, Error, Sym64, SymbolTable};
+use core::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+/// ELFv1 function descriptor
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct Descriptor {
+    /// Address of the code
+    pub entry: u64,
+    /// TOC pointer, loaded into `r2`
+    pub toc: u64,
+    /// Environment pointer, loaded into `r11`
+    pub environment: u64,
+}
+impl Descriptor {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+/// Function descriptor table (`.opd` section)
+///
+/// In relocatable objects the descriptors are filled in by relocations, so they read as 0.
+#[derive(Clone, Copy, Debug)]
+pub struct Opd<'a> {
+    data: &'a [u8],
+    address: u64,
+    big_endian: bool,
+}
+
+impl<'a> Opd<'a> {
+    /// Descriptor table with contents `data` loaded at `address`
+    pub fn new(data: &'a [u8], address: u64, big_endian: bool) -> Self {
+        Opd {
+            data,
+            address,
+            big_endian,
+        }
+    }
+
+    /// Returns true if `address` is inside the table
+    pub fn contains(&self, address: u64) -> bool {
+        address >= self.address && address - self.address < self.data.len() as u64
+    }
+
+    /// Descriptor at `address`
+    pub fn get(&self, address: u64) -> Result<Descriptor, Error> {
+        if !self.contains(address) {
+            return Err(Error::NotFound);
+        }
+        from_bytes(
+            &self.data[(address - self.address) as usize..],
+            self.big_endian,
+        )
+    }
+
+    /// Code address and TOC pointer of `sym`
+    ///
+    /// Function symbols pointing into the table are dereferenced, other symbols are returned
+    /// unchanged without a TOC pointer.
+    pub fn resolve(&self, sym: &Sym64) -> Result<Function, Error> {
+        if sym.st_type() == STT::FUNC && self.contains(sym.st_value) {
+            let descriptor = self.get(sym.st_value)?;
+            Ok(Function {
+                sym: *sym,
+                entry: descriptor.entry,
+                toc: Some(descriptor.toc),
+            })
+        } else {
+            Ok(Function {
+                sym: *sym,
+                entry: sym.st_value,
+                toc: None,
+            })
+        }
+    }
+}
+
+/// Function symbol with its code address
+#[derive(Clone, Copy, Eq, PartialEq)]
+pub struct Function {
+    /// Symbol
+    pub sym: Sym64,
+    /// Address of the code
+    pub entry: u64,
+    /// TOC pointer from the function descriptor, or `None` if there is no descriptor
+    pub toc: Option<u64>,
+}
+
+impl<'a> SymbolTable<'a> {
+    /// Symbol whose code contains `address`, dereferencing ELFv1 function descriptors
+    ///
+    /// Without `opd` this is [`SymbolTable::by_address()`]. With it, function symbols are
+    /// matched by the entry point of their descriptor and `st_size` bytes of code, so the
+    /// result is the function rather than the descriptor.
+    pub fn function_by_address(&self, address: u64, opd: Option<&Opd>) -> Option<Function> {
+        let opd = match opd {
+            Some(opd) => opd,
+            None => {
+                return self.by_address(address).map(|sym| Function {
+                    sym,
+                    entry: sym.st_value,
+                    toc: None,
+                })
+            }
+        };
+        let mut best: Option<Function> = None;
+        let candidates = self.iter().filter(|sym| {
+            let sym_type = sym.st_type();
+            (sym_type == STT::FUNC || sym_type == STT::NOTYPE)
+                && sym.st_shndx != SHN::UNDEF
+                && sym.st_name != 0
+        });
+        for function in candidates.filter_map(|sym| opd.resolve(&sym).ok()) {
+            if opd.contains(function.entry) {
+                continue;
+            }
+            let offset = address.wrapping_sub(function.entry);
+            if function.entry > address || (offset != 0 && offset >= function.sym.st_size) {
+                continue;
+            }
+            // Prefer the nearest start, so nested functions win over their parents, and
+            // descriptors over plain symbols at the same entry
+            let better = best.map_or(true, |b| {
+                function.entry > b.entry || (function.entry == b.entry && b.toc.is_none())
+            });
+            if better {
+                best = Some(function);
+            }
+        }
+        best
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// PowerPC 64-bit ABI version, or `None` for other machines
+    ///
+    /// Files that don't specify the version in `e_flags` are ELFv1 if big-endian and ELFv2
+    /// if little-endian, as little-endian ELFv1 was never used.
+    pub fn ppc64_abi(&self) -> Option<Ppc64Abi> {
+        let ehdr = self.ehdr();
+        match ehdr.e_flags.decode(ehdr.e_machine) {
+            Flags::Ppc64(Ppc64Abi::UNSPECIFIED) if self.is_big_endian() => Some(Ppc64Abi::ELFV1),
+            Flags::Ppc64(Ppc64Abi::UNSPECIFIED) => Some(Ppc64Abi::ELFV2),
+            Flags::Ppc64(abi) => Some(abi),
+            _ => None,
+        }
+    }
+
+    /// ELFv1 function descriptor table, or `None` if the file doesn't use descriptors
+    ///
+    /// This is the `.opd` section, or the table given by `DT::PPC64_OPD` and
+    /// `DT::PPC64_OPDSZ` if there are no section headers.
+    pub fn ppc64_opd(&self) -> Result<Option<Opd<'a>>, Error> {
+        if self.ehdr().e_machine != EM::PPC64 || self.ppc64_abi() != Some(Ppc64Abi::ELFV1) {
+            return Ok(None);
+        }
+        let big_endian = self.is_big_endian();
+        if let Some((_, shdr)) = self.section_by_name(".opd")? {
+            let data = self.section_data(&shdr)?;
+            return Ok(Some(Opd::new(data, shdr.sh_addr, big_endian)));
+        }
+        let address = self.dynamic_value(DT::PPC64_OPD)?;
+        let size = self.dynamic_value(DT::PPC64_OPDSZ)?;
+        match (address, size) {
+            (Some(address), Some(size)) => {
+                let offset = self
+                    .vaddr_to_offset(address, size)
+                    .ok_or(Error::Malformed)?;
+                Ok(Some(Opd::new(
+                    self.bytes(offset, size)?,
+                    address,
+                    big_endian,
+                )))
+            }
+            _ => Ok(None),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::{EF, ET};
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn opd() {
+        let mut builder = Builder::new(false, true, ET::EXEC, EM::PPC64);
+        let text = builder.section(".text", SHT::PROGBITS, 0x10000, vec![0; 0x40]);
+        let mut opd = builder.serialize(&Descriptor {
+            entry: 0x10010,
+            toc: 0x28000,
+            environment: 0,
+        });
+        opd.extend(builder.serialize(&Descriptor {
+            entry: 0x10030,
+            toc: 0x28000,
+            environment: 0,
+        }));
+        let opd = builder.section(".opd", SHT::PROGBITS, 0x20000, opd);
+        let (strtab, names) = Builder::strings(&["foo", "bar", ".bar"]);
+        let strtab = builder.section(".strtab", SHT::STRTAB, 0, strtab);
+        let mut symtab = builder.sym(0, 0, 0, 0, 0);
+        symtab.extend(builder.sym(names[0], 0x20000, 0x20, 0x12, opd as u16));
+        symtab.extend(builder.sym(names[1], 0x20018, 0x10, 0x12, opd as u16));
+        // Dot symbol at the code of "bar", as emitted by older toolchains
+        symtab.extend(builder.sym(names[2], 0x10030, 0x10, 0x10, text as u16));
+        let symtab = builder.section(".symtab", SHT::SYMTAB, 0, symtab);
+        builder.shdr_mut(symtab).sh_link = strtab as u32;
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+
+        assert_eq!(file.ppc64_abi(), Some(Ppc64Abi::ELFV1));
+        let opd = file.ppc64_opd().unwrap().unwrap();
+        let symbols = file.symbol_table().unwrap().unwrap();
+        let foo = symbols.function_by_address(0x10018, Some(&opd)).unwrap();
+        assert_eq!(symbols.name(&foo.sym), Ok("foo"));
+        assert_eq!(foo.entry, 0x10010);
+        assert_eq!(foo.toc, Some(0x28000));
+        let bar = symbols.function_by_address(0x10030, Some(&opd)).unwrap();
+        assert_eq!(symbols.name(&bar.sym), Ok("bar"));
+        assert!(symbols.function_by_address(0x10008, Some(&opd)).is_none());
+        // Without dereferencing, the descriptor is found
+        let foo = symbols.function_by_address(0x20008, None).unwrap();
+        assert_eq!(symbols.name(&foo.sym), Ok("foo"));
+        assert_eq!(foo.entry, 0x20000);
+
+        let mut builder = Builder::new(false, false, ET::EXEC, EM::PPC64);
+        builder.ehdr.e_flags = EF::from(2);
+        builder.section(".text", SHT::PROGBITS, 0x10000, vec![0; 0x40]);
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+        assert_eq!(file.ppc64_abi(), Some(Ppc64Abi::ELFV2));
+        assert!(file.ppc64_opd().unwrap().is_none());
+        assert_eq!(Descriptor::SIZE, 24);
+    }
+
+    #[test]
+    fn nested() {
+        let mut builder = Builder::new(false, true, ET::EXEC, EM::PPC64);
+        let text = builder.section(".text", SHT::PROGBITS, 0x10000, vec![0; 0x40]);
+        let mut opd = Vec::new();
+        for &entry in &[0x10000, 0x10010] {
+            opd.extend(builder.serialize(&Descriptor {
+                entry,
+                toc: 0x28000,
+                environment: 0,
+            }));
+        }
+        let opd = builder.section(".opd", SHT::PROGBITS, 0x20000, opd);
+        let (strtab, names) = Builder::strings(&["outer", "inner", ".inner"]);
+        let strtab = builder.section(".strtab", SHT::STRTAB, 0, strtab);
+        let mut symtab = builder.sym(0, 0, 0, 0, 0);
+        symtab.extend(builder.sym(names[0], 0x20000, 0x40, 0x12, opd as u16));
+        symtab.extend(builder.sym(names[1], 0x20018, 0x10, 0x12, opd as u16));
+        symtab.extend(builder.sym(names[2], 0x10010, 0x10, 0x12, text as u16));
+        let symtab = builder.section(".symtab", SHT::SYMTAB, 0, symtab);
+        builder.shdr_mut(symtab).sh_link = strtab as u32;
+        let data = builder.build();
+        let file = ElfFile::new(&data).unwrap();
+
+        let opd = file.ppc64_opd().unwrap().unwrap();
+        let symbols = file.symbol_table().unwrap().unwrap();
+        let name = |address| {
+            let function = symbols.function_by_address(address, Some(&opd)).unwrap();
+            symbols.name(&function.sym).unwrap()
+        };
+        assert_eq!(name(0x10008), "outer");
+        assert_eq!(name(0x10010), "inner");
+        assert_eq!(name(0x10018), "inner");
+        assert_eq!(name(0x10020), "outer");
+        assert!(symbols.function_by_address(0x10040, Some(&opd)).is_none());
+    }
+}
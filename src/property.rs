@@ -0,0 +1,321 @@
+//! GNU program properties
+//!
+//! Properties are recorded in a `NTGNU::PROPERTY_TYPE_0` note in the `.note.gnu.property`
+//! section, which the `PT::GNU_PROPERTY` segment covers in executables and shared objects.
+//! The descriptor is an array of properties, each a type and a data size followed by the
+//! data, padded to 8 bytes in 64-bit files and 4 bytes in 32-bit files. The note itself is
+//! 8-byte aligned in 64-bit files.
+//!
+//! The linker combines the properties of the input files, so the `FEATURE_1_AND` properties
+//! tell whether every object was built for x86 CET ([`X86Feature1::IBT`],
+//! [`X86Feature1::SHSTK`]) or AArch64 BTI and PAC ([`AArch64Feature1`]).
+
+use crate::de::from_bytes;
+use crate::ehdr::EM;
+use crate::note::{Notes, NTGNU};
+use crate::phdr::PT;
+use crate::shdr::SHT;
+use crate::{ElfFile, Error};
+
+enum_struct!(
+/// Program property types
+pub struct PropertyType(u32) {
+    STACK_SIZE            = 1          => "Stack size",
+    NO_COPY_ON_PROTECTED  = 2          => "No copy relocations on protected symbols",
+    MEMORY_SEAL           = 3          => "Seal memory mappings",
+    NEEDED_1              = 0xb0008000 => "Features needed at run time",
+    LOPROC                = 0xc0000000 => "First processor specific type",
+    HIPROC                = 0xdfffffff => "Last processor specific type",
+    LOUSER                = 0xe0000000 => "First application specific type",
+    HIUSER                = 0xffffffff => "Last application specific type",
+}
+ranges {
+    processor: LOPROC..=HIPROC,
+    user: LOUSER..=HIUSER,
+}
+machines {
+    X86 | X86_64 {
+        X86_FEATURE_1_AND     = 0xc0000002 => "x86 features supported by all objects",
+        X86_FEATURE_2_NEEDED  = 0xc0008001 => "x86 features needed by any object",
+        X86_ISA_1_NEEDED      = 0xc0008002 => "x86 ISA level needed by any object",
+        X86_FEATURE_2_USED    = 0xc0010001 => "x86 features used by any object",
+        X86_ISA_1_USED        = 0xc0010002 => "x86 ISA level used by any object",
+    }
+    AARCH64 {
+        AARCH64_FEATURE_1_AND = 0xc0000000 => "AArch64 features supported by all objects",
+        AARCH64_FEATURE_PAUTH = 0xc0000001 => "AArch64 pointer authentication ABI",
+    }
+}
+);
+
+flag_struct!(
+/// x86 features in `PropertyType::X86_FEATURE_1_AND`
+pub struct X86Feature1(u32) {
+    NONE    = 0 => "No features",
+    IBT     = 1 => "Indirect branch tracking",
+    SHSTK   = 2 => "Shadow stack",
+    LAM_U48 = 4 => "Linear address masking, 48-bit user space",
+    LAM_U57 = 8 => "Linear address masking, 57-bit user space",
+}
+);
+
+flag_struct!(
+/// x86 ISA levels in `PropertyType::X86_ISA_1_NEEDED` and `PropertyType::X86_ISA_1_USED`
+pub struct X86Isa1(u32) {
+    NONE     = 0 => "No ISA level",
+    BASELINE = 1 => "x86-64 baseline",
+    V2       = 2 => "x86-64-v2",
+    V3       = 4 => "x86-64-v3",
+    V4       = 8 => "x86-64-v4",
+}
+);
+
+flag_struct!(
+/// AArch64 features in `PropertyType::AARCH64_FEATURE_1_AND`
+pub struct AArch64Feature1(u32) {
+    NONE = 0 => "No features",
+    BTI  = 1 => "Branch target identification",
+    PAC  = 2 => "Pointer authentication of return addresses",
+    GCS  = 4 => "Guarded control stack",
+}
+);
+
+/// Program property
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Property<'a> {
+    /// Stack size the program needs
+    StackSize(u64),
+    /// Protected symbols may not be the target of copy relocations
+    NoCopyOnProtected,
+    /// x86 features supported by all objects
+    X86Feature1And(X86Feature1),
+    /// x86 ISA levels needed by any object
+    X86Isa1Needed(X86Isa1),
+    /// x86 ISA levels used by any object
+    X86Isa1Used(X86Isa1),
+    /// AArch64 features supported by all objects
+    AArch64Feature1And(AArch64Feature1),
+    /// Any other property, with its data
+    Other(PropertyType, &'a [u8]),
+}
+
+/// Iterator over the properties in a `NTGNU::PROPERTY_TYPE_0` note descriptor
+#[derive(Clone, Debug)]
+pub struct Properties<'a> {
+    data: &'a [u8],
+    machine: EM,
+    elf32: bool,
+    big_endian: bool,
+}
+
+impl<'a> Properties<'a> {
+    /// Properties in the descriptor `data` of a note in a file for `machine`
+    pub fn new(data: &'a [u8], machine: EM, elf32: bool, big_endian: bool) -> Self {
+        Properties {
+            data,
+            machine,
+            elf32,
+            big_endian,
+        }
+    }
+
+    /// x86 features supported by all objects, none if the property is missing
+    pub fn x86_feature_1_and(&self) -> Result<X86Feature1, Error> {
+        for property in self.clone() {
+            if let Property::X86Feature1And(features) = property? {
+                return Ok(features);
+            }
+        }
+        Ok(X86Feature1::NONE)
+    }
+
+    /// AArch64 features supported by all objects, none if the property is missing
+    pub fn aarch64_feature_1_and(&self) -> Result<AArch64Feature1, Error> {
+        for property in self.clone() {
+            if let Property::AArch64Feature1And(features) = property? {
+                return Ok(features);
+            }
+        }
+        Ok(AArch64Feature1::NONE)
+    }
+
+    fn parse(&self) -> Result<(Property<'a>, usize), Error> {
+        let (pr_type, pr_datasz): (u32, u32) = from_bytes(self.data, self.big_endian)?;
+        let end = 8usize
+            .checked_add(pr_datasz as usize)
+            .ok_or(Error::Malformed)?;
+        if end > self.data.len() {
+            return Err(Error::Truncated);
+        }
+        let data = &self.data[8..end];
+        let pr_type = PropertyType::from(pr_type);
+        let u32 = || -> Result<u32, Error> {
+            match data.len() {
+                4 => from_bytes(data, self.big_endian),
+                _ => Err(Error::Malformed),
+            }
+        };
+        let x86 = self.machine == EM::X86 || self.machine == EM::X86_64;
+        let property = match pr_type {
+            PropertyType::STACK_SIZE => match (data.len(), self.elf32) {
+                (4, true) => {
+                    Property::StackSize(u64::from(from_bytes::<u32>(data, self.big_endian)?))
+                }
+                (8, false) => Property::StackSize(from_bytes(data, self.big_endian)?),
+                _ => return Err(Error::Malformed),
+            },
+            PropertyType::NO_COPY_ON_PROTECTED if data.is_empty() => Property::NoCopyOnProtected,
+            PropertyType::NO_COPY_ON_PROTECTED => return Err(Error::Malformed),
+            PropertyType::X86_FEATURE_1_AND if x86 => Property::X86Feature1And(u32()?.into()),
+            PropertyType::X86_ISA_1_NEEDED if x86 => Property::X86Isa1Needed(u32()?.into()),
+            PropertyType::X86_ISA_1_USED if x86 => Property::X86Isa1Used(u32()?.into()),
+            PropertyType::AARCH64_FEATURE_1_AND if self.machine == EM::AARCH64 => {
+                Property::AArch64Feature1And(u32()?.into())
+            }
+            _ => Property::Other(pr_type, data),
+        };
+        // Data is padded to the word size, except maybe for the last property
+        let align = if self.elf32 { 4 } else { 8 };
+        let next = end
+            .checked_add(align - 1)
+            .map_or(end, |next| (next & !(align - 1)).min(self.data.len()));
+        Ok((property, next))
+    }
+}
+
+impl<'a> Iterator for Properties<'a> {
+    type Item = Result<Property<'a>, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        match self.parse() {
+            Ok((property, next)) => {
+                self.data = &self.data[next..];
+                Some(Ok(property))
+            }
+            Err(err) => {
+                self.data = &[];
+                Some(Err(err))
+            }
+        }
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// GNU program properties, or `None` if there aren't any
+    ///
+    /// These are in the `.note.gnu.property` section, or the `PT::GNU_PROPERTY` segment if
+    /// there are no section headers.
+    pub fn gnu_properties(&self) -> Result<Option<Properties<'a>>, Error> {
+        let data = match self.section_by_name(".note.gnu.property")? {
+            Some((_, shdr)) if shdr.sh_type == SHT::NOTE => Some(self.section_data(&shdr)?),
+            _ => match self.phdrs()?.find(|phdr| phdr.p_type == PT::GNU_PROPERTY) {
+                Some(phdr) => Some(self.segment_data(&phdr)?),
+                None => None,
+            },
+        };
+        let data = match data {
+            Some(data) => data,
+            None => return Ok(None),
+        };
+        // The recorded alignment isn't always right, notes are 8-byte aligned in 64-bit files
+        let align = if self.is_elf32() { 4 } else { 8 };
+        for note in Notes::new(data, align, self.is_big_endian()) {
+            let note = note?;
+            if note.is_gnu(NTGNU::PROPERTY_TYPE_0) {
+                return Ok(Some(Properties::new(
+                    note.desc,
+                    self.ehdr().e_machine,
+                    self.is_elf32(),
+                    self.is_big_endian(),
+                )));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::ET;
+    use crate::phdr::PF;
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn property(pr_type: PropertyType, data: &[u8], align: usize) -> Vec<u8> {
+        let mut property = Vec::new();
+        property.extend_from_slice(&u32::from(pr_type).to_le_bytes());
+        property.extend_from_slice(&(data.len() as u32).to_le_bytes());
+        property.extend_from_slice(data);
+        property.resize((property.len() + align - 1) & !(align - 1), 0);
+        property
+    }
+
+    #[test]
+    fn properties() {
+        let mut desc = property(PropertyType::X86_ISA_1_NEEDED, &3u32.to_le_bytes(), 8);
+        desc.extend(property(
+            PropertyType::X86_FEATURE_1_AND,
+            &3u32.to_le_bytes(),
+            8,
+        ));
+        desc.extend(property(
+            PropertyType::STACK_SIZE,
+            &0x80_0000u64.to_le_bytes(),
+            8,
+        ));
+        let mut note = vec![4, 0, 0, 0, desc.len() as u8, 0, 0, 0, 5, 0, 0, 0];
+        note.extend_from_slice(b"GNU\0");
+        note.extend(desc);
+
+        let mut builder = Builder::new(false, false, ET::DYN, EM::X86_64);
+        let section = builder.section(".note.gnu.property", SHT::NOTE, 0x2a8, note);
+        builder.segment(PT::GNU_PROPERTY, PF::R, &[section]);
+        let elf = builder.build();
+        let file = ElfFile::new(&elf).unwrap();
+        let properties = file.gnu_properties().unwrap().unwrap();
+        let mut iter = properties.clone();
+        assert_eq!(
+            iter.next(),
+            Some(Ok(Property::X86Isa1Needed(X86Isa1::BASELINE | X86Isa1::V2)))
+        );
+        assert_eq!(
+            iter.next(),
+            Some(Ok(Property::X86Feature1And(
+                X86Feature1::IBT | X86Feature1::SHSTK
+            )))
+        );
+        assert_eq!(iter.next(), Some(Ok(Property::StackSize(0x80_0000))));
+        assert_eq!(iter.next(), None);
+        assert_eq!(
+            properties.x86_feature_1_and(),
+            Ok(X86Feature1::IBT | X86Feature1::SHSTK)
+        );
+        assert_eq!(
+            properties.aarch64_feature_1_and(),
+            Ok(AArch64Feature1::NONE)
+        );
+
+        // 32-bit properties are padded to 4 bytes, and the type depends on the machine
+        let mut desc = property(PropertyType::AARCH64_FEATURE_1_AND, &[1, 0, 0, 0], 4);
+        desc.extend(property(PropertyType::STACK_SIZE, &[0, 0, 1, 0], 4));
+        let mut iter = Properties::new(&desc, EM::ARM, true, false);
+        assert_eq!(
+            iter.next(),
+            Some(Ok(Property::Other(
+                PropertyType::AARCH64_FEATURE_1_AND,
+                &[1, 0, 0, 0]
+            )))
+        );
+        assert_eq!(iter.next(), Some(Ok(Property::StackSize(0x1_0000))));
+        let desc = property(PropertyType::AARCH64_FEATURE_1_AND, &[3, 0], 4);
+        let mut iter = Properties::new(&desc, EM::AARCH64, false, false);
+        assert_eq!(iter.next(), Some(Err(Error::Malformed)));
+        assert_eq!(iter.next(), None);
+    }
+}
@@ -0,0 +1,557 @@
+//! Relocation types
+//!
+//! Relocation type numbers are specific to each machine, so each machine with named
+//! relocations has its own type. [`name()`] looks up the name for any machine.
+
+use crate::ehdr::EM;
+
+enum_struct!(
+/// Intel 80386 relocation types ([`EM::X86`])
+pub struct X86(u32) {
+    R_386_NONE          = 0,
+    R_386_32            = 1,
+    R_386_PC32          = 2,
+    R_386_GOT32         = 3,
+    R_386_PLT32         = 4,
+    R_386_COPY          = 5,
+    R_386_GLOB_DAT      = 6,
+    R_386_JMP_SLOT      = 7,
+    R_386_RELATIVE      = 8,
+    R_386_GOTOFF        = 9,
+    R_386_GOTPC         = 10,
+    R_386_32PLT         = 11,
+    R_386_TLS_TPOFF     = 14,
+    R_386_TLS_IE        = 15,
+    R_386_TLS_GOTIE     = 16,
+    R_386_TLS_LE        = 17,
+    R_386_TLS_GD        = 18,
+    R_386_TLS_LDM       = 19,
+    R_386_16            = 20,
+    R_386_PC16          = 21,
+    R_386_8             = 22,
+    R_386_PC8           = 23,
+    R_386_TLS_GD_32     = 24,
+    R_386_TLS_GD_PUSH   = 25,
+    R_386_TLS_GD_CALL   = 26,
+    R_386_TLS_GD_POP    = 27,
+    R_386_TLS_LDM_32    = 28,
+    R_386_TLS_LDM_PUSH  = 29,
+    R_386_TLS_LDM_CALL  = 30,
+    R_386_TLS_LDM_POP   = 31,
+    R_386_TLS_LDO_32    = 32,
+    R_386_TLS_IE_32     = 33,
+    R_386_TLS_LE_32     = 34,
+    R_386_TLS_DTPMOD32  = 35,
+    R_386_TLS_DTPOFF32  = 36,
+    R_386_TLS_TPOFF32   = 37,
+    R_386_SIZE32        = 38,
+    R_386_TLS_GOTDESC   = 39,
+    R_386_TLS_DESC_CALL = 40,
+    R_386_TLS_DESC      = 41,
+    R_386_IRELATIVE     = 42,
+    R_386_GOT32X        = 43,
+}
+);
+
+enum_struct!(
+/// AMD x86-64 relocation types ([`EM::X86_64`])
+pub struct X86_64(u32) {
+    R_X86_64_NONE            = 0,
+    R_X86_64_64              = 1,
+    R_X86_64_PC32            = 2,
+    R_X86_64_GOT32           = 3,
+    R_X86_64_PLT32           = 4,
+    R_X86_64_COPY            = 5,
+    R_X86_64_GLOB_DAT        = 6,
+    R_X86_64_JUMP_SLOT       = 7,
+    R_X86_64_RELATIVE        = 8,
+    R_X86_64_GOTPCREL        = 9,
+    R_X86_64_32              = 10,
+    R_X86_64_32S             = 11,
+    R_X86_64_16              = 12,
+    R_X86_64_PC16            = 13,
+    R_X86_64_8               = 14,
+    R_X86_64_PC8             = 15,
+    R_X86_64_DTPMOD64        = 16,
+    R_X86_64_DTPOFF64        = 17,
+    R_X86_64_TPOFF64         = 18,
+    R_X86_64_TLSGD           = 19,
+    R_X86_64_TLSLD           = 20,
+    R_X86_64_DTPOFF32        = 21,
+    R_X86_64_GOTTPOFF        = 22,
+    R_X86_64_TPOFF32         = 23,
+    R_X86_64_PC64            = 24,
+    R_X86_64_GOTOFF64        = 25,
+    R_X86_64_GOTPC32         = 26,
+    R_X86_64_GOT64           = 27,
+    R_X86_64_GOTPCREL64      = 28,
+    R_X86_64_GOTPC64         = 29,
+    R_X86_64_GOTPLT64        = 30,
+    R_X86_64_PLTOFF64        = 31,
+    R_X86_64_SIZE32          = 32,
+    R_X86_64_SIZE64          = 33,
+    R_X86_64_GOTPC32_TLSDESC = 34,
+    R_X86_64_TLSDESC_CALL    = 35,
+    R_X86_64_TLSDESC         = 36,
+    R_X86_64_IRELATIVE       = 37,
+    R_X86_64_RELATIVE64      = 38,
+    R_X86_64_GOTPCRELX       = 41,
+    R_X86_64_REX_GOTPCRELX   = 42,
+}
+);
+
+enum_struct!(
+/// ARM relocation types ([`EM::ARM`])
+pub struct ARM(u32) {
+    R_ARM_NONE              = 0,
+    R_ARM_PC24              = 1,
+    R_ARM_ABS32             = 2,
+    R_ARM_REL32             = 3,
+    R_ARM_LDR_PC_G0         = 4,
+    R_ARM_ABS16             = 5,
+    R_ARM_ABS12             = 6,
+    R_ARM_THM_ABS5          = 7,
+    R_ARM_ABS8              = 8,
+    R_ARM_SBREL32           = 9,
+    R_ARM_THM_CALL          = 10,
+    R_ARM_THM_PC8           = 11,
+    R_ARM_BREL_ADJ          = 12,
+    R_ARM_TLS_DESC          = 13,
+    R_ARM_THM_SWI8          = 14,
+    R_ARM_XPC25             = 15,
+    R_ARM_THM_XPC22         = 16,
+    R_ARM_TLS_DTPMOD32      = 17,
+    R_ARM_TLS_DTPOFF32      = 18,
+    R_ARM_TLS_TPOFF32       = 19,
+    R_ARM_COPY              = 20,
+    R_ARM_GLOB_DAT          = 21,
+    R_ARM_JUMP_SLOT         = 22,
+    R_ARM_RELATIVE          = 23,
+    R_ARM_GOTOFF32          = 24,
+    R_ARM_BASE_PREL         = 25,
+    R_ARM_GOT_BREL          = 26,
+    R_ARM_PLT32             = 27,
+    R_ARM_CALL              = 28,
+    R_ARM_JUMP24            = 29,
+    R_ARM_THM_JUMP24        = 30,
+    R_ARM_BASE_ABS          = 31,
+    R_ARM_ALU_PCREL_7_0     = 32,
+    R_ARM_ALU_PCREL_15_8    = 33,
+    R_ARM_ALU_PCREL_23_15   = 34,
+    R_ARM_LDR_SBREL_11_0    = 35,
+    R_ARM_ALU_SBREL_19_12   = 36,
+    R_ARM_ALU_SBREL_27_20   = 37,
+    R_ARM_TARGET1           = 38,
+    R_ARM_SBREL31           = 39,
+    R_ARM_V4BX              = 40,
+    R_ARM_TARGET2           = 41,
+    R_ARM_PREL31            = 42,
+    R_ARM_MOVW_ABS_NC       = 43,
+    R_ARM_MOVT_ABS          = 44,
+    R_ARM_MOVW_PREL_NC      = 45,
+    R_ARM_MOVT_PREL         = 46,
+    R_ARM_THM_MOVW_ABS_NC   = 47,
+    R_ARM_THM_MOVT_ABS      = 48,
+    R_ARM_THM_MOVW_PREL_NC  = 49,
+    R_ARM_THM_MOVT_PREL     = 50,
+    R_ARM_THM_JUMP19        = 51,
+    R_ARM_THM_JUMP6         = 52,
+    R_ARM_THM_ALU_PREL_11_0 = 53,
+    R_ARM_THM_PC12          = 54,
+    R_ARM_ABS32_NOI         = 55,
+    R_ARM_REL32_NOI         = 56,
+    R_ARM_ALU_PC_G0_NC      = 57,
+    R_ARM_ALU_PC_G0         = 58,
+    R_ARM_ALU_PC_G1_NC      = 59,
+    R_ARM_ALU_PC_G1         = 60,
+    R_ARM_ALU_PC_G2         = 61,
+    R_ARM_LDR_PC_G1         = 62,
+    R_ARM_LDR_PC_G2         = 63,
+    R_ARM_LDRS_PC_G0        = 64,
+    R_ARM_LDRS_PC_G1        = 65,
+    R_ARM_LDRS_PC_G2        = 66,
+    R_ARM_LDC_PC_G0         = 67,
+    R_ARM_LDC_PC_G1         = 68,
+    R_ARM_LDC_PC_G2         = 69,
+    R_ARM_ALU_SB_G0_NC      = 70,
+    R_ARM_ALU_SB_G0         = 71,
+    R_ARM_ALU_SB_G1_NC      = 72,
+    R_ARM_ALU_SB_G1         = 73,
+    R_ARM_ALU_SB_G2         = 74,
+    R_ARM_LDR_SB_G0         = 75,
+    R_ARM_LDR_SB_G1         = 76,
+    R_ARM_LDR_SB_G2         = 77,
+    R_ARM_LDRS_SB_G0        = 78,
+    R_ARM_LDRS_SB_G1        = 79,
+    R_ARM_LDRS_SB_G2        = 80,
+    R_ARM_LDC_SB_G0         = 81,
+    R_ARM_LDC_SB_G1         = 82,
+    R_ARM_LDC_SB_G2         = 83,
+    R_ARM_MOVW_BREL_NC      = 84,
+    R_ARM_MOVT_BREL         = 85,
+    R_ARM_MOVW_BREL         = 86,
+    R_ARM_THM_MOVW_BREL_NC  = 87,
+    R_ARM_THM_MOVT_BREL     = 88,
+    R_ARM_THM_MOVW_BREL     = 89,
+    R_ARM_TLS_GOTDESC       = 90,
+    R_ARM_TLS_CALL          = 91,
+    R_ARM_TLS_DESCSEQ       = 92,
+    R_ARM_THM_TLS_CALL      = 93,
+    R_ARM_PLT32_ABS         = 94,
+    R_ARM_GOT_ABS           = 95,
+    R_ARM_GOT_PREL          = 96,
+    R_ARM_GOT_BREL12        = 97,
+    R_ARM_GOTOFF12          = 98,
+    R_ARM_GOTRELAX          = 99,
+    R_ARM_GNU_VTENTRY       = 100,
+    R_ARM_GNU_VTINHERIT     = 101,
+    R_ARM_THM_JUMP11        = 102,
+    R_ARM_THM_JUMP8         = 103,
+    R_ARM_TLS_GD32          = 104,
+    R_ARM_TLS_LDM32         = 105,
+    R_ARM_TLS_LDO32         = 106,
+    R_ARM_TLS_IE32          = 107,
+    R_ARM_TLS_LE32          = 108,
+    R_ARM_TLS_LDO12         = 109,
+    R_ARM_TLS_LE12          = 110,
+    R_ARM_TLS_IE12GP        = 111,
+    R_ARM_ME_TOO            = 128,
+    R_ARM_THM_TLS_DESCSEQ16 = 129,
+    R_ARM_THM_TLS_DESCSEQ32 = 130,
+    R_ARM_THM_GOT_BREL12    = 131,
+    R_ARM_IRELATIVE         = 160,
+}
+);
+
+enum_struct!(
+/// ARM 64-bit relocation types ([`EM::AARCH64`])
+pub struct AARCH64(u32) {
+    R_AARCH64_NONE                         = 0,
+    R_AARCH64_P32_ABS32                    = 1,
+    R_AARCH64_P32_COPY                     = 180,
+    R_AARCH64_P32_GLOB_DAT                 = 181,
+    R_AARCH64_P32_JUMP_SLOT                = 182,
+    R_AARCH64_P32_RELATIVE                 = 183,
+    R_AARCH64_P32_TLS_DTPMOD               = 184,
+    R_AARCH64_P32_TLS_DTPREL               = 185,
+    R_AARCH64_P32_TLS_TPREL                = 186,
+    R_AARCH64_P32_TLSDESC                  = 187,
+    R_AARCH64_P32_IRELATIVE                = 188,
+    R_AARCH64_ABS64                        = 257,
+    R_AARCH64_ABS32                        = 258,
+    R_AARCH64_ABS16                        = 259,
+    R_AARCH64_PREL64                       = 260,
+    R_AARCH64_PREL32                       = 261,
+    R_AARCH64_PREL16                       = 262,
+    R_AARCH64_MOVW_UABS_G0                 = 263,
+    R_AARCH64_MOVW_UABS_G0_NC              = 264,
+    R_AARCH64_MOVW_UABS_G1                 = 265,
+    R_AARCH64_MOVW_UABS_G1_NC              = 266,
+    R_AARCH64_MOVW_UABS_G2                 = 267,
+    R_AARCH64_MOVW_UABS_G2_NC              = 268,
+    R_AARCH64_MOVW_UABS_G3                 = 269,
+    R_AARCH64_MOVW_SABS_G0                 = 270,
+    R_AARCH64_MOVW_SABS_G1                 = 271,
+    R_AARCH64_MOVW_SABS_G2                 = 272,
+    R_AARCH64_LD_PREL_LO19                 = 273,
+    R_AARCH64_ADR_PREL_LO21                = 274,
+    R_AARCH64_ADR_PREL_PG_HI21             = 275,
+    R_AARCH64_ADR_PREL_PG_HI21_NC          = 276,
+    R_AARCH64_ADD_ABS_LO12_NC              = 277,
+    R_AARCH64_LDST8_ABS_LO12_NC            = 278,
+    R_AARCH64_TSTBR14                      = 279,
+    R_AARCH64_CONDBR19                     = 280,
+    R_AARCH64_JUMP26                       = 282,
+    R_AARCH64_CALL26                       = 283,
+    R_AARCH64_LDST16_ABS_LO12_NC           = 284,
+    R_AARCH64_LDST32_ABS_LO12_NC           = 285,
+    R_AARCH64_LDST64_ABS_LO12_NC           = 286,
+    R_AARCH64_MOVW_PREL_G0                 = 287,
+    R_AARCH64_MOVW_PREL_G0_NC              = 288,
+    R_AARCH64_MOVW_PREL_G1                 = 289,
+    R_AARCH64_MOVW_PREL_G1_NC              = 290,
+    R_AARCH64_MOVW_PREL_G2                 = 291,
+    R_AARCH64_MOVW_PREL_G2_NC              = 292,
+    R_AARCH64_MOVW_PREL_G3                 = 293,
+    R_AARCH64_LDST128_ABS_LO12_NC          = 299,
+    R_AARCH64_MOVW_GOTOFF_G0               = 300,
+    R_AARCH64_MOVW_GOTOFF_G0_NC            = 301,
+    R_AARCH64_MOVW_GOTOFF_G1               = 302,
+    R_AARCH64_MOVW_GOTOFF_G1_NC            = 303,
+    R_AARCH64_MOVW_GOTOFF_G2               = 304,
+    R_AARCH64_MOVW_GOTOFF_G2_NC            = 305,
+    R_AARCH64_MOVW_GOTOFF_G3               = 306,
+    R_AARCH64_GOTREL64                     = 307,
+    R_AARCH64_GOTREL32                     = 308,
+    R_AARCH64_GOT_LD_PREL19                = 309,
+    R_AARCH64_LD64_GOTOFF_LO15             = 310,
+    R_AARCH64_ADR_GOT_PAGE                 = 311,
+    R_AARCH64_LD64_GOT_LO12_NC             = 312,
+    R_AARCH64_LD64_GOTPAGE_LO15            = 313,
+    R_AARCH64_TLSGD_ADR_PREL21             = 512,
+    R_AARCH64_TLSGD_ADR_PAGE21             = 513,
+    R_AARCH64_TLSGD_ADD_LO12_NC            = 514,
+    R_AARCH64_TLSGD_MOVW_G1                = 515,
+    R_AARCH64_TLSGD_MOVW_G0_NC             = 516,
+    R_AARCH64_TLSLD_ADR_PREL21             = 517,
+    R_AARCH64_TLSLD_ADR_PAGE21             = 518,
+    R_AARCH64_TLSLD_ADD_LO12_NC            = 519,
+    R_AARCH64_TLSLD_MOVW_G1                = 520,
+    R_AARCH64_TLSLD_MOVW_G0_NC             = 521,
+    R_AARCH64_TLSLD_LD_PREL19              = 522,
+    R_AARCH64_TLSLD_MOVW_DTPREL_G2         = 523,
+    R_AARCH64_TLSLD_MOVW_DTPREL_G1         = 524,
+    R_AARCH64_TLSLD_MOVW_DTPREL_G1_NC      = 525,
+    R_AARCH64_TLSLD_MOVW_DTPREL_G0         = 526,
+    R_AARCH64_TLSLD_MOVW_DTPREL_G0_NC      = 527,
+    R_AARCH64_TLSLD_ADD_DTPREL_HI12        = 528,
+    R_AARCH64_TLSLD_ADD_DTPREL_LO12        = 529,
+    R_AARCH64_TLSLD_ADD_DTPREL_LO12_NC     = 530,
+    R_AARCH64_TLSLD_LDST8_DTPREL_LO12      = 531,
+    R_AARCH64_TLSLD_LDST8_DTPREL_LO12_NC   = 532,
+    R_AARCH64_TLSLD_LDST16_DTPREL_LO12     = 533,
+    R_AARCH64_TLSLD_LDST16_DTPREL_LO12_NC  = 534,
+    R_AARCH64_TLSLD_LDST32_DTPREL_LO12     = 535,
+    R_AARCH64_TLSLD_LDST32_DTPREL_LO12_NC  = 536,
+    R_AARCH64_TLSLD_LDST64_DTPREL_LO12     = 537,
+    R_AARCH64_TLSLD_LDST64_DTPREL_LO12_NC  = 538,
+    R_AARCH64_TLSIE_MOVW_GOTTPREL_G1       = 539,
+    R_AARCH64_TLSIE_MOVW_GOTTPREL_G0_NC    = 540,
+    R_AARCH64_TLSIE_ADR_GOTTPREL_PAGE21    = 541,
+    R_AARCH64_TLSIE_LD64_GOTTPREL_LO12_NC  = 542,
+    R_AARCH64_TLSIE_LD_GOTTPREL_PREL19     = 543,
+    R_AARCH64_TLSLE_MOVW_TPREL_G2          = 544,
+    R_AARCH64_TLSLE_MOVW_TPREL_G1          = 545,
+    R_AARCH64_TLSLE_MOVW_TPREL_G1_NC       = 546,
+    R_AARCH64_TLSLE_MOVW_TPREL_G0          = 547,
+    R_AARCH64_TLSLE_MOVW_TPREL_G0_NC       = 548,
+    R_AARCH64_TLSLE_ADD_TPREL_HI12         = 549,
+    R_AARCH64_TLSLE_ADD_TPREL_LO12         = 550,
+    R_AARCH64_TLSLE_ADD_TPREL_LO12_NC      = 551,
+    R_AARCH64_TLSLE_LDST8_TPREL_LO12       = 552,
+    R_AARCH64_TLSLE_LDST8_TPREL_LO12_NC    = 553,
+    R_AARCH64_TLSLE_LDST16_TPREL_LO12      = 554,
+    R_AARCH64_TLSLE_LDST16_TPREL_LO12_NC   = 555,
+    R_AARCH64_TLSLE_LDST32_TPREL_LO12      = 556,
+    R_AARCH64_TLSLE_LDST32_TPREL_LO12_NC   = 557,
+    R_AARCH64_TLSLE_LDST64_TPREL_LO12      = 558,
+    R_AARCH64_TLSLE_LDST64_TPREL_LO12_NC   = 559,
+    R_AARCH64_TLSDESC_LD_PREL19            = 560,
+    R_AARCH64_TLSDESC_ADR_PREL21           = 561,
+    R_AARCH64_TLSDESC_ADR_PAGE21           = 562,
+    R_AARCH64_TLSDESC_LD64_LO12            = 563,
+    R_AARCH64_TLSDESC_ADD_LO12             = 564,
+    R_AARCH64_TLSDESC_OFF_G1               = 565,
+    R_AARCH64_TLSDESC_OFF_G0_NC            = 566,
+    R_AARCH64_TLSDESC_LDR                  = 567,
+    R_AARCH64_TLSDESC_ADD                  = 568,
+    R_AARCH64_TLSDESC_CALL                 = 569,
+    R_AARCH64_TLSLE_LDST128_TPREL_LO12     = 570,
+    R_AARCH64_TLSLE_LDST128_TPREL_LO12_NC  = 571,
+    R_AARCH64_TLSLD_LDST128_DTPREL_LO12    = 572,
+    R_AARCH64_TLSLD_LDST128_DTPREL_LO12_NC = 573,
+    R_AARCH64_COPY                         = 1024,
+    R_AARCH64_GLOB_DAT                     = 1025,
+    R_AARCH64_JUMP_SLOT                    = 1026,
+    R_AARCH64_RELATIVE                     = 1027,
+    R_AARCH64_TLS_DTPMOD                   = 1028,
+    R_AARCH64_TLS_DTPREL                   = 1029,
+    R_AARCH64_TLS_TPREL                    = 1030,
+    R_AARCH64_TLSDESC                      = 1031,
+    R_AARCH64_IRELATIVE                    = 1032,
+}
+);
+
+enum_struct!(
+/// MIPS relocation types ([`EM::MIPS`])
+///
+/// MIPS64 relocations hold up to three of these, see [`crate::mips::RelocationTypes`].
+pub struct MIPS(u32) {
+    R_MIPS_NONE            = 0,
+    R_MIPS_16              = 1,
+    R_MIPS_32              = 2,
+    R_MIPS_REL32           = 3,
+    R_MIPS_26              = 4,
+    R_MIPS_HI16            = 5,
+    R_MIPS_LO16            = 6,
+    R_MIPS_GPREL16         = 7,
+    R_MIPS_LITERAL         = 8,
+    R_MIPS_GOT16           = 9,
+    R_MIPS_PC16            = 10,
+    R_MIPS_CALL16          = 11,
+    R_MIPS_GPREL32         = 12,
+    R_MIPS_SHIFT5          = 16,
+    R_MIPS_SHIFT6          = 17,
+    R_MIPS_64              = 18,
+    R_MIPS_GOT_DISP        = 19,
+    R_MIPS_GOT_PAGE        = 20,
+    R_MIPS_GOT_OFST        = 21,
+    R_MIPS_GOT_HI16        = 22,
+    R_MIPS_GOT_LO16        = 23,
+    R_MIPS_SUB             = 24,
+    R_MIPS_INSERT_A        = 25,
+    R_MIPS_INSERT_B        = 26,
+    R_MIPS_DELETE          = 27,
+    R_MIPS_HIGHER          = 28,
+    R_MIPS_HIGHEST         = 29,
+    R_MIPS_CALL_HI16       = 30,
+    R_MIPS_CALL_LO16       = 31,
+    R_MIPS_SCN_DISP        = 32,
+    R_MIPS_REL16           = 33,
+    R_MIPS_ADD_IMMEDIATE   = 34,
+    R_MIPS_PJUMP           = 35,
+    R_MIPS_RELGOT          = 36,
+    R_MIPS_JALR            = 37,
+    R_MIPS_TLS_DTPMOD32    = 38,
+    R_MIPS_TLS_DTPREL32    = 39,
+    R_MIPS_TLS_DTPMOD64    = 40,
+    R_MIPS_TLS_DTPREL64    = 41,
+    R_MIPS_TLS_GD          = 42,
+    R_MIPS_TLS_LDM         = 43,
+    R_MIPS_TLS_DTPREL_HI16 = 44,
+    R_MIPS_TLS_DTPREL_LO16 = 45,
+    R_MIPS_TLS_GOTTPREL    = 46,
+    R_MIPS_TLS_TPREL32     = 47,
+    R_MIPS_TLS_TPREL64     = 48,
+    R_MIPS_TLS_TPREL_HI16  = 49,
+    R_MIPS_TLS_TPREL_LO16  = 50,
+    R_MIPS_GLOB_DAT        = 51,
+    R_MIPS_COPY            = 126,
+    R_MIPS_JUMP_SLOT       = 127,
+}
+);
+
+enum_struct!(
+/// RISC-V relocation types ([`EM::RISCV`])
+pub struct RISCV(u32) {
+    R_RISCV_NONE              = 0,
+    R_RISCV_32                = 1,
+    R_RISCV_64                = 2,
+    R_RISCV_RELATIVE          = 3,
+    R_RISCV_COPY              = 4,
+    R_RISCV_JUMP_SLOT         = 5,
+    R_RISCV_TLS_DTPMOD32      = 6,
+    R_RISCV_TLS_DTPMOD64      = 7,
+    R_RISCV_TLS_DTPREL32      = 8,
+    R_RISCV_TLS_DTPREL64      = 9,
+    R_RISCV_TLS_TPREL32       = 10,
+    R_RISCV_TLS_TPREL64       = 11,
+    R_RISCV_TLSDESC           = 12,
+    R_RISCV_BRANCH            = 16,
+    R_RISCV_JAL               = 17,
+    R_RISCV_CALL              = 18,
+    R_RISCV_CALL_PLT          = 19,
+    R_RISCV_GOT_HI20          = 20,
+    R_RISCV_TLS_GOT_HI20      = 21,
+    R_RISCV_TLS_GD_HI20       = 22,
+    R_RISCV_PCREL_HI20        = 23,
+    R_RISCV_PCREL_LO12_I      = 24,
+    R_RISCV_PCREL_LO12_S      = 25,
+    R_RISCV_HI20              = 26,
+    R_RISCV_LO12_I            = 27,
+    R_RISCV_LO12_S            = 28,
+    R_RISCV_TPREL_HI20        = 29,
+    R_RISCV_TPREL_LO12_I      = 30,
+    R_RISCV_TPREL_LO12_S      = 31,
+    R_RISCV_TPREL_ADD         = 32,
+    R_RISCV_ADD8              = 33,
+    R_RISCV_ADD16             = 34,
+    R_RISCV_ADD32             = 35,
+    R_RISCV_ADD64             = 36,
+    R_RISCV_SUB8              = 37,
+    R_RISCV_SUB16             = 38,
+    R_RISCV_SUB32             = 39,
+    R_RISCV_SUB64             = 40,
+    R_RISCV_GNU_VTINHERIT     = 41,
+    R_RISCV_GNU_VTENTRY       = 42,
+    R_RISCV_ALIGN             = 43,
+    R_RISCV_RVC_BRANCH        = 44,
+    R_RISCV_RVC_JUMP          = 45,
+    R_RISCV_RVC_LUI           = 46,
+    R_RISCV_GPREL_I           = 47,
+    R_RISCV_GPREL_S           = 48,
+    R_RISCV_TPREL_I           = 49,
+    R_RISCV_TPREL_S           = 50,
+    R_RISCV_RELAX             = 51,
+    R_RISCV_SUB6              = 52,
+    R_RISCV_SET6              = 53,
+    R_RISCV_SET8              = 54,
+    R_RISCV_SET16             = 55,
+    R_RISCV_SET32             = 56,
+    R_RISCV_32_PCREL          = 57,
+    R_RISCV_IRELATIVE         = 58,
+    R_RISCV_PLT32             = 59,
+    R_RISCV_SET_ULEB128       = 60,
+    R_RISCV_SUB_ULEB128       = 61,
+    R_RISCV_TLSDESC_HI20      = 62,
+    R_RISCV_TLSDESC_LOAD_LO12 = 63,
+    R_RISCV_TLSDESC_ADD_LO12  = 64,
+    R_RISCV_TLSDESC_CALL      = 65,
+}
+);
+
+enum_struct!(
+/// Linux BPF relocation types ([`EM::BPF`])
+pub struct BPF(u32) {
+    R_BPF_NONE        = 0,
+    R_BPF_64_64       = 1,
+    R_BPF_64_ABS64    = 2,
+    R_BPF_64_ABS32    = 3,
+    R_BPF_64_NODYLD32 = 4,
+    R_BPF_64_32       = 10,
+}
+);
+
+/// Name of relocation type `r_type` for `machine`, or `None` if unknown
+pub fn name(machine: EM, r_type: u32) -> Option<&'static str> {
+    match machine {
+        EM::X86 => X86::from(r_type).name(),
+        EM::X86_64 => X86_64::from(r_type).name(),
+        EM::ARM => ARM::from(r_type).name(),
+        EM::AARCH64 => AARCH64::from(r_type).name(),
+        EM::MIPS => MIPS::from(r_type).name(),
+        EM::RISCV => RISCV::from(r_type).name(),
+        EM::BPF => BPF::from(r_type).name(),
+        _ => None,
+    }
+}
+
+/// Relative relocation type for `machine`, as implied by packed relative relocations
+pub fn relative(machine: EM) -> Option<u32> {
+    match machine {
+        EM::X86 => Some(X86::R_386_RELATIVE.into()),
+        EM::X86_64 => Some(X86_64::R_X86_64_RELATIVE.into()),
+        EM::ARM => Some(ARM::R_ARM_RELATIVE.into()),
+        EM::AARCH64 => Some(AARCH64::R_AARCH64_RELATIVE.into()),
+        EM::RISCV => Some(RISCV::R_RISCV_RELATIVE.into()),
+        _ => None,
+    }
+}
+
+/// Returns true if [`name()`] knows the relocation types of `machine`
+pub fn has_names(machine: EM) -> bool {
+    matches!(
+        machine,
+        EM::X86 | EM::X86_64 | EM::ARM | EM::AARCH64 | EM::MIPS | EM::RISCV | EM::BPF
+    )
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn names() {
+        assert_eq!(name(EM::X86_64, 8), Some("R_X86_64_RELATIVE"));
+        assert_eq!(name(EM::X86, 7), Some("R_386_JMP_SLOT"));
+        assert_eq!(name(EM::ARM, 10), Some("R_ARM_THM_CALL"));
+        assert_eq!(name(EM::AARCH64, 1027), Some("R_AARCH64_RELATIVE"));
+        assert_eq!(name(EM::RISCV, 3), Some("R_RISCV_RELATIVE"));
+        assert_eq!(name(EM::MIPS, 3), Some("R_MIPS_REL32"));
+        assert_eq!(name(EM::RISCV, 200), None);
+        assert_eq!(name(EM::SPARC, 1), None);
+        assert_eq!(X86_64::R_X86_64_GLOB_DAT, X86_64::from(6));
+        assert_eq!(relative(EM::AARCH64), Some(1027));
+        assert_eq!(relative(EM::SPARC), None);
+    }
+}
@@ -0,0 +1,552 @@
+//! Applying dynamic relocations to a loaded image
+//!
+//! [`ElfFile::relocate()`] processes the relocations from [`ElfFile::dynamic_relocations()`]
+//! for an image loaded with [`ElfFile::load()`], acting as a minimal dynamic linker with
+//! immediate binding. Imports are looked up through a [`Resolver`], and symbols defined by
+//! the image bind to the image itself.
+//!
+//! The supported relocations are the relative, absolute, GOT and PLT relocations and
+//! `IRELATIVE` for x86-64, AArch64, ARM and RISC-V. Anything else, such as TLS or copy
+//! relocations, is reported as [`Error::Unsupported`], and a value that doesn't fit a 32-bit
+//! field as [`Error::Overflow`].
+
+use crate::ehdr::EM;
+use crate::load::Memory;
+use crate::reloc::{AARCH64, ARM, RISCV, X86_64};
+use crate::shdr::SHN;
+use crate::sym::{STB, STT};
+use crate::{ElfFile, Error, Relocation, Sym64};
+
+/// Symbol and indirect function resolution for [`ElfFile::relocate()`]
+pub trait Resolver {
+    /// Address of `sym`, named `name`, which the image imports
+    ///
+    /// Return `None` if the symbol isn't found. Weak symbols then resolve to 0, other
+    /// symbols fail with [`Error::NotFound`].
+    fn symbol(&mut self, name: &str, sym: &Sym64) -> Option<u64>;
+
+    /// Call the indirect function resolver at `address`, returning the address of the
+    /// implementation it selects
+    fn ifunc(&mut self, address: u64) -> Result<u64, Error> {
+        let _ = address;
+        Err(Error::Unsupported)
+    }
+}
+
+/// Operation of a relocation type
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Action {
+    /// Nothing to do
+    None,
+    /// Load bias plus addend (`B + A`)
+    Relative,
+    /// Address of the result of the indirect function at `B + A`
+    IRelative,
+    /// Symbol plus addend (`S + A`) in a word, or 4 bytes if `wide` is false
+    Absolute {
+        /// Relocate a 64-bit word rather than 32 bits
+        wide: bool,
+        /// Values accepted in 32 bits
+        range: Range32,
+    },
+    /// GOT entry of a symbol (`S + A`), ignoring implicit addends
+    GlobDat,
+    /// PLT entry of a symbol (`S + A`), ignoring implicit addends
+    JumpSlot,
+}
+
+/// Values accepted by a 32-bit [`Action::Absolute`] relocation
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Range32 {
+    /// 0 to `u32::MAX`, zero-extended
+    Unsigned,
+    /// `i32::MIN` to `i32::MAX`, sign-extended
+    Signed,
+    /// `i32::MIN` to `u32::MAX`, zero-extended
+    Either,
+}
+
+impl Action {
+    /// Operation of relocation type `r_type` for `machine`, or `None` if unsupported
+    pub fn new(machine: EM, r_type: u32) -> Option<Action> {
+        let absolute = |wide, range| Some(Action::Absolute { wide, range });
+        match machine {
+            EM::X86_64 => match X86_64::from(r_type) {
+                X86_64::R_X86_64_NONE => Some(Action::None),
+                X86_64::R_X86_64_64 => absolute(true, Range32::Unsigned),
+                X86_64::R_X86_64_32 => absolute(false, Range32::Unsigned),
+                X86_64::R_X86_64_32S => absolute(false, Range32::Signed),
+                X86_64::R_X86_64_GLOB_DAT => Some(Action::GlobDat),
+                X86_64::R_X86_64_JUMP_SLOT => Some(Action::JumpSlot),
+                X86_64::R_X86_64_RELATIVE | X86_64::R_X86_64_RELATIVE64 => Some(Action::Relative),
+                X86_64::R_X86_64_IRELATIVE => Some(Action::IRelative),
+                _ => None,
+            },
+            EM::AARCH64 => match AARCH64::from(r_type) {
+                AARCH64::R_AARCH64_NONE => Some(Action::None),
+                AARCH64::R_AARCH64_ABS64 => absolute(true, Range32::Unsigned),
+                AARCH64::R_AARCH64_ABS32 => absolute(false, Range32::Either),
+                AARCH64::R_AARCH64_GLOB_DAT => Some(Action::GlobDat),
+                AARCH64::R_AARCH64_JUMP_SLOT => Some(Action::JumpSlot),
+                AARCH64::R_AARCH64_RELATIVE => Some(Action::Relative),
+                AARCH64::R_AARCH64_IRELATIVE => Some(Action::IRelative),
+                _ => None,
+            },
+            EM::ARM => match ARM::from(r_type) {
+                ARM::R_ARM_NONE => Some(Action::None),
+                ARM::R_ARM_ABS32 => absolute(false, Range32::Unsigned),
+                ARM::R_ARM_GLOB_DAT => Some(Action::GlobDat),
+                ARM::R_ARM_JUMP_SLOT => Some(Action::JumpSlot),
+                ARM::R_ARM_RELATIVE => Some(Action::Relative),
+                ARM::R_ARM_IRELATIVE => Some(Action::IRelative),
+                _ => None,
+            },
+            EM::RISCV => match RISCV::from(r_type) {
+                RISCV::R_RISCV_NONE => Some(Action::None),
+                RISCV::R_RISCV_32 => absolute(false, Range32::Unsigned),
+                RISCV::R_RISCV_64 => absolute(true, Range32::Unsigned),
+                RISCV::R_RISCV_JUMP_SLOT => Some(Action::JumpSlot),
+                RISCV::R_RISCV_RELATIVE => Some(Action::Relative),
+                RISCV::R_RISCV_IRELATIVE => Some(Action::IRelative),
+                _ => None,
+            },
+            _ => None,
+        }
+    }
+}
+
+/// Read the `size` byte value at `address`
+pub(crate) fn read(
+    memory: &mut impl Memory,
+    address: u64,
+    size: usize,
+    big_endian: bool,
+) -> Result<u64, Error> {
+    let mut bytes = [0; 8];
+    let bytes = &mut bytes[..size];
+    memory.read(address, bytes)?;
+    if big_endian {
+        bytes.reverse();
+    }
+    Ok(bytes
+        .iter()
+        .rev()
+        .fold(0, |value, &byte| value << 8 | u64::from(byte)))
+}
+
+/// Write the low `size` bytes of `value` at `address`
+pub(crate) fn write(
+    memory: &mut impl Memory,
+    address: u64,
+    size: usize,
+    value: u64,
+    big_endian: bool,
+) -> Result<(), Error> {
+    let bytes = if big_endian {
+        value.to_be_bytes()
+    } else {
+        value.to_le_bytes()
+    };
+    if big_endian {
+        memory.write(address, &bytes[8 - size..])
+    } else {
+        memory.write(address, &bytes[..size])
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// Apply the dynamic relocations of an image loaded at `bias` in `memory`
+    ///
+    /// Relocations without explicit addends read them from `memory`, so the image must have
+    /// been loaded with [`ElfFile::load()`] using the same `bias`. `IRELATIVE` relocations
+    /// are applied last, once the data their resolvers may use is relocated.
+    pub fn relocate(
+        &self,
+        memory: &mut impl Memory,
+        bias: u64,
+        resolver: &mut impl Resolver,
+    ) -> Result<(), Error> {
+        let machine = self.ehdr().e_machine;
+        for &irelative in &[false, true] {
+            for relocation in self.dynamic_relocations()? {
+                let action = Action::new(machine, relocation.r_type);
+                if (action == Some(Action::IRelative)) == irelative {
+                    self.apply(memory, bias, resolver, &relocation)?;
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Apply one dynamic relocation, see [`ElfFile::relocate()`]
+    pub fn apply(
+        &self,
+        memory: &mut impl Memory,
+        bias: u64,
+        resolver: &mut impl Resolver,
+        relocation: &Relocation,
+    ) -> Result<(), Error> {
+        let machine = self.ehdr().e_machine;
+        let action = Action::new(machine, relocation.r_type).ok_or(Error::Unsupported)?;
+        let big_endian = self.is_big_endian();
+        let word = self.word_size();
+        let address = relocation.r_offset.wrapping_add(bias);
+        let size = match action {
+            Action::None => return Ok(()),
+            Action::Absolute { wide: false, .. } => 4,
+            Action::Absolute { wide: true, .. } if word == 4 => return Err(Error::Unsupported),
+            _ => word,
+        };
+        let addend = match relocation.r_addend {
+            Some(addend) => addend as u64,
+            None if action == Action::GlobDat || action == Action::JumpSlot => 0,
+            None => {
+                let value = read(memory, address, size, big_endian)?;
+                match action {
+                    Action::Absolute {
+                        wide: false,
+                        range: Range32::Signed,
+                    } => value as i32 as u64,
+                    _ => value,
+                }
+            }
+        };
+        let value = match action {
+            Action::Relative => bias.wrapping_add(addend),
+            Action::IRelative => resolver.ifunc(bias.wrapping_add(addend))?,
+            _ => self
+                .symbol_address(bias, resolver, relocation.r_sym)?
+                .wrapping_add(addend),
+        };
+        let value = if word == 4 {
+            value & 0xffff_ffff
+        } else {
+            value
+        };
+        if let Action::Absolute { wide: false, range } = action {
+            let signed = value as i64 == i64::from(value as i32);
+            let unsigned = value >> 32 == 0 || word == 4;
+            let fits = match range {
+                Range32::Unsigned => unsigned,
+                Range32::Signed => signed,
+                Range32::Either => signed || unsigned,
+            };
+            if !fits {
+                return Err(Error::Overflow);
+            }
+        }
+        write(memory, address, size, value, big_endian)
+    }
+
+    /// Address of dynamic symbol `index`, 0 for the null symbol
+    fn symbol_address(
+        &self,
+        bias: u64,
+        resolver: &mut impl Resolver,
+        index: u32,
+    ) -> Result<u64, Error> {
+        if index == 0 {
+            return Ok(0);
+        }
+        let sym = self.dynamic_symbol(index as usize)?;
+        if sym.st_shndx == SHN::UNDEF {
+            let name = self.dynamic_string(sym.st_name)?;
+            return match resolver.symbol(name, &sym) {
+                Some(address) => Ok(address),
+                None if sym.st_bind() == STB::WEAK => Ok(0),
+                None => Err(Error::NotFound),
+            };
+        }
+        let address = if sym.st_shndx == SHN::ABS {
+            sym.st_value
+        } else {
+            sym.st_value.wrapping_add(bias)
+        };
+        if sym.st_type() == STT::GNU_IFUNC {
+            resolver.ifunc(address)
+        } else {
+            Ok(address)
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::dynamic::DT;
+    use crate::ehdr::ET;
+    use crate::load::Arena;
+    use crate::packed::encode_relr;
+    use crate::phdr::{PF, PT};
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    struct Imports;
+
+    impl Resolver for Imports {
+        fn symbol(&mut self, name: &str, _sym: &Sym64) -> Option<u64> {
+            match name {
+                "puts" => Some(0x7000_1000),
+                _ => None,
+            }
+        }
+
+        fn ifunc(&mut self, address: u64) -> Result<u64, Error> {
+            Ok(address + 0x100)
+        }
+    }
+
+    /// Resolver for "puts" whose indirect functions fail until it has been looked up
+    #[derive(Default)]
+    struct Ordered {
+        resolved: bool,
+    }
+
+    impl Resolver for Ordered {
+        fn symbol(&mut self, name: &str, sym: &Sym64) -> Option<u64> {
+            self.resolved = true;
+            Imports.symbol(name, sym)
+        }
+
+        fn ifunc(&mut self, address: u64) -> Result<u64, Error> {
+            if self.resolved {
+                Imports.ifunc(address)
+            } else {
+                Err(Error::Unsupported)
+            }
+        }
+    }
+
+    /// Image importing "puts", weak "optional" and "missing", with `rela` and `relr`
+    /// relocations against a data section at 0x2000 filled with 0xff, except for a pointer
+    /// to 0x2010 at 0x2040, 32-bit for ARM and 64-bit otherwise
+    fn image(machine: EM, rela: &[(u64, u32, u32, i64)], relr: &[u64]) -> Vec<u8> {
+        let elf32 = machine == EM::ARM;
+        let mut builder = Builder::new(elf32, false, ET::DYN, machine);
+        // Sizes are multiples of 8, so the file offsets and addresses stay congruent
+        let mut address = 0x1000;
+        let mut place = |data: &Vec<u8>| {
+            assert_eq!(data.len() % 8, 0);
+            address += data.len() as u64;
+            address - data.len() as u64
+        };
+        let (mut dynstr, names) = Builder::strings(&["puts", "optional", "missing", "local"]);
+        dynstr.resize(32, 0);
+        let mut dynsym = builder.sym(0, 0, 0, 0, 0);
+        dynsym.extend(builder.sym(names[0], 0, 0, 0x12, 0));
+        dynsym.extend(builder.sym(names[1], 0, 0, 0x22, 0));
+        dynsym.extend(builder.sym(names[2], 0, 0, 0x12, 0));
+        dynsym.extend(builder.sym(names[3], 0x2040, 8, 0x11, 5));
+        let mut rela_data = Vec::new();
+        for &(r_offset, r_sym, r_type, r_addend) in rela {
+            rela_data.extend(builder.rela(r_offset, r_sym, r_type, r_addend));
+        }
+        let rela_size = rela_data.len() as u64;
+        rela_data.resize((rela_data.len() + 7) & !7, 0);
+        let mut relr_data = Vec::new();
+        let word_size = if elf32 { 4 } else { 8 };
+        encode_relr(relr.iter().copied(), word_size, |word| {
+            relr_data.extend(builder.word(word))
+        })
+        .unwrap();
+        let relr_size = relr_data.len() as u64;
+        relr_data.resize((relr_data.len() + 7) & !7, 0);
+        let (dynstr_addr, dynsym_addr) = (place(&dynstr), place(&dynsym));
+        let (rela_addr, relr_addr) = (place(&rela_data), place(&relr_data));
+        let dynamic = builder.dynamic(&[
+            (DT::STRTAB, dynstr_addr),
+            (DT::STRSZ, 32),
+            (DT::SYMTAB, dynsym_addr),
+            (DT::RELA, rela_addr),
+            (DT::RELASZ, rela_size),
+            (DT::RELR, relr_addr),
+            (DT::RELRSZ, relr_size),
+        ]);
+        let dynamic_addr = place(&dynamic);
+        let dynstr = builder.section(".dynstr", SHT::STRTAB, dynstr_addr, dynstr);
+        let dynsym = builder.section(".dynsym", SHT::DYNSYM, dynsym_addr, dynsym);
+        builder.shdr_mut(dynsym).sh_link = dynstr as u32;
+        let rela = builder.section(".rela.dyn", SHT::RELA, rela_addr, rela_data);
+        let relr = builder.section(".relr.dyn", SHT::RELR, relr_addr, relr_data);
+        let dynamic = builder.section(".dynamic", SHT::DYNAMIC, dynamic_addr, dynamic);
+        let mut data = vec![0xff; 0x48];
+        let pointer = builder.word(0x2010);
+        data[0x40..0x40 + pointer.len()].copy_from_slice(&pointer);
+        let data = builder.section(".data", SHT::PROGBITS, 0x2000, data);
+        assert_eq!(data, 6);
+        builder.segment(PT::LOAD, PF::R, &[dynstr, dynsym, rela, relr, dynamic]);
+        builder.segment(PT::LOAD, PF::R | PF::W, &[data]);
+        builder.segment(PT::DYNAMIC, PF::R | PF::W, &[dynamic]);
+        builder.build()
+    }
+
+    /// Load and relocate `data` at `base`, returning the words of the relocated data section
+    fn run(data: &[u8], base: u64) -> Result<Vec<u64>, Error> {
+        run_with(data, base, &mut Imports)
+    }
+
+    /// [`run()`] with `resolver`
+    fn run_with(data: &[u8], base: u64, resolver: &mut impl Resolver) -> Result<Vec<u64>, Error> {
+        let file = ElfFile::new(data).unwrap();
+        let range = file.load_range(8)?.unwrap();
+        let mut buffer = vec![0; (range.end - range.start) as usize];
+        let bias = base - range.start;
+        let mut arena = Arena::new(&mut buffer, base);
+        file.load(&mut arena, bias, 8)?;
+        file.relocate(&mut arena, bias, resolver)?;
+        let data = (0x2000 - range.start) as usize;
+        Ok(buffer[data..data + 0x48]
+            .chunks_exact(file.word_size())
+            .map(|word| {
+                let mut bytes = [0; 8];
+                bytes[..word.len()].copy_from_slice(word);
+                u64::from_le_bytes(bytes)
+            })
+            .collect())
+    }
+
+    #[test]
+    fn x86_64() {
+        let data = image(
+            EM::X86_64,
+            &[
+                (0x2000, 0, 8, 0x2040),
+                (0x2008, 1, 6, 0),
+                (0x2010, 1, 7, 0),
+                (0x2018, 1, 1, 0x10),
+                (0x2020, 2, 6, 0),
+                (0x2028, 4, 1, 0),
+                (0x2030, 0, 37, 0x1500),
+            ],
+            &[0x2040],
+        );
+        let base = 0x5555_0000_0000;
+        let bias = base - 0x1000;
+        assert_eq!(
+            run(&data, base),
+            Ok(vec![
+                bias + 0x2040,
+                0x7000_1000,
+                0x7000_1000,
+                0x7000_1010,
+                0,
+                bias + 0x2040,
+                bias + 0x1600,
+                u64::MAX,
+                bias + 0x2010,
+            ])
+        );
+
+        // R_X86_64_32 only fits at a low base
+        let data = image(EM::X86_64, &[(0x2038, 4, 10, 0)], &[]);
+        assert_eq!(run(&data, 0x10000).unwrap()[7], 0xffff_ffff_0001_1040);
+        assert_eq!(run(&data, base), Err(Error::Overflow));
+
+        let data = image(EM::X86_64, &[(0x2000, 3, 6, 0)], &[]);
+        assert_eq!(run(&data, base), Err(Error::NotFound));
+        let data = image(EM::X86_64, &[(0x2000, 1, 5, 0)], &[]);
+        assert_eq!(run(&data, base), Err(Error::Unsupported));
+    }
+
+    #[test]
+    fn aarch64() {
+        let data = image(
+            EM::AARCH64,
+            &[
+                (0x2000, 1, 1025, 8),
+                (0x2008, 0, 1027, 0x10),
+                (0x2010, 1, 258, 0),
+            ],
+            &[],
+        );
+        let result = run(&data, 0x4000_0000).unwrap();
+        assert_eq!(result[..2], [0x7000_1008, 0x4000_0000 - 0x1000 + 0x10]);
+        assert_eq!(result[2], 0xffff_ffff_7000_1000);
+
+        // R_AARCH64_ABS32 takes signed and unsigned values
+        let data = image(
+            EM::AARCH64,
+            &[(0x2000, 0, 258, -8), (0x2008, 0, 258, 0xffff_fff8)],
+            &[],
+        );
+        let result = run(&data, 0x4000_0000).unwrap();
+        assert_eq!(result[..2], [0xffff_ffff_ffff_fff8, 0xffff_ffff_ffff_fff8]);
+        let data = image(EM::AARCH64, &[(0x2000, 0, 258, 0x1_0000_0000)], &[]);
+        assert_eq!(run(&data, 0x4000_0000), Err(Error::Overflow));
+        let data = image(EM::AARCH64, &[(0x2000, 0, 258, -0x8000_0001)], &[]);
+        assert_eq!(run(&data, 0x4000_0000), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn arm() {
+        let data = image(
+            EM::ARM,
+            &[
+                (0x2000, 1, 2, 8),
+                (0x2004, 0, 23, 0x10),
+                (0x2008, 1, 22, 0),
+                (0x200c, 0, 160, 0x500),
+                (0x2010, 2, 21, 0),
+            ],
+            &[0x2040],
+        );
+        let base = 0x4000_0000;
+        let bias = base - 0x1000;
+        let result = run(&data, base).unwrap();
+        assert_eq!(
+            result[..5],
+            [0x7000_1008, bias + 0x10, 0x7000_1000, bias + 0x600, 0]
+        );
+        assert_eq!(result[5], 0xffff_ffff);
+        assert_eq!(result[16], bias + 0x2010);
+    }
+
+    #[test]
+    fn riscv() {
+        // The indirect function is resolved after the imports, despite coming first
+        let data = image(
+            EM::RISCV,
+            &[
+                (0x2000, 0, 58, 0x500),
+                (0x2008, 1, 5, 0),
+                (0x2010, 1, 2, 8),
+                (0x2018, 0, 3, 0x20),
+                (0x2020, 4, 1, 0),
+            ],
+            &[],
+        );
+        let base = 0x10000;
+        let bias = base - 0x1000;
+        let result = run_with(&data, base, &mut Ordered::default()).unwrap();
+        assert_eq!(
+            result[..5],
+            [
+                bias + 0x600,
+                0x7000_1000,
+                0x7000_1008,
+                bias + 0x20,
+                0xffff_ffff_0000_0000 | (bias + 0x2040)
+            ]
+        );
+        assert_eq!(run(&data, 0x5555_0000_0000), Err(Error::Overflow));
+    }
+
+    #[test]
+    fn actions() {
+        assert_eq!(Action::new(EM::ARM, 23), Some(Action::Relative));
+        assert_eq!(Action::new(EM::ARM, 160), Some(Action::IRelative));
+        assert_eq!(Action::new(EM::RISCV, 5), Some(Action::JumpSlot));
+        assert_eq!(
+            Action::new(EM::RISCV, 2),
+            Some(Action::Absolute {
+                wide: true,
+                range: Range32::Unsigned
+            })
+        );
+        assert_eq!(Action::new(EM::RISCV, 4), None);
+        assert_eq!(Action::new(EM::SPARC, 1), None);
+    }
+}
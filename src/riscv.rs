@@ -0,0 +1,492 @@
+//! RISC-V specific data
+//!
+//! Build attributes of the `riscv` vendor, as defined by the RISC-V ELF psABI, and the ISA
+//! strings recorded in `Tag_RISCV_arch`.
+
+use crate::attributes::{Group, Value};
+use crate::Error;
+use core::fmt;
+
+enum_struct!(
+/// `riscv` attribute tags
+pub struct Tag(u64) {
+    FILE               = 1  => "Tag_File",
+    STACK_ALIGN        = 4  => "Tag_RISCV_stack_align",
+    ARCH               = 5  => "Tag_RISCV_arch",
+    UNALIGNED_ACCESS   = 6  => "Tag_RISCV_unaligned_access",
+    PRIV_SPEC          = 8  => "Tag_RISCV_priv_spec",
+    PRIV_SPEC_MINOR    = 10 => "Tag_RISCV_priv_spec_minor",
+    PRIV_SPEC_REVISION = 12 => "Tag_RISCV_priv_spec_revision",
+    ATOMIC_ABI         = 14 => "Tag_RISCV_atomic_abi",
+    X3_REG_USAGE       = 16 => "Tag_RISCV_x3_reg_usage",
+}
+);
+
+enum_struct!(
+/// `Tag_RISCV_atomic_abi` values
+pub struct AtomicAbi(u64) {
+    UNKNOWN = 0 => "UNKNOWN",
+    A6C     = 1 => "A6C",
+    A6S     = 2 => "A6S",
+    A7      = 3 => "A7",
+}
+);
+
+enum_struct!(
+/// `Tag_RISCV_x3_reg_usage` values
+pub struct X3RegUsage(u64) {
+    UNKNOWN = 0 => "unknown",
+    GP      = 1 => "global pointer",
+    SCS     = 2 => "shadow stack pointer",
+    TP      = 3 => "reserved for platform",
+}
+);
+
+/// Decoded `riscv` attributes of a file
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct Riscv<'a> {
+    /// Stack alignment in bytes
+    pub stack_align: Option<u64>,
+    /// Target ISA
+    pub arch: Option<Isa<'a>>,
+    /// Unaligned memory accesses are used
+    pub unaligned_access: Option<bool>,
+    /// Privileged specification version as (major, minor, revision)
+    pub priv_spec: Option<(u64, u64, u64)>,
+    /// Mapping of atomic operations to instructions
+    pub atomic_abi: Option<AtomicAbi>,
+    /// Use of register `x3`
+    pub x3_reg_usage: Option<X3RegUsage>,
+}
+
+impl<'a> Riscv<'a> {
+    /// Decode the attributes of `group`, usually [`crate::attributes::Subsection::file()`]
+    ///
+    /// Returns [`Error::Malformed`] if `Tag_RISCV_arch` isn't a valid ISA string.
+    pub fn new(group: &Group<'a>) -> Result<Self, Error> {
+        let mut riscv = Riscv::default();
+        let mut priv_spec = [None; 3];
+        for attribute in group.attributes() {
+            let attribute = attribute?;
+            let integer = attribute.value.integer();
+            match Tag::from(attribute.tag) {
+                Tag::STACK_ALIGN => riscv.stack_align = integer,
+                Tag::ARCH => {
+                    if let Some(arch) = attribute.value.string() {
+                        riscv.arch = Some(Isa::parse(arch)?);
+                    }
+                }
+                Tag::UNALIGNED_ACCESS => riscv.unaligned_access = integer.map(|v| v != 0),
+                Tag::PRIV_SPEC => priv_spec[0] = integer,
+                Tag::PRIV_SPEC_MINOR => priv_spec[1] = integer,
+                Tag::PRIV_SPEC_REVISION => priv_spec[2] = integer,
+                Tag::ATOMIC_ABI => riscv.atomic_abi = integer.map(AtomicAbi::from),
+                Tag::X3_REG_USAGE => riscv.x3_reg_usage = integer.map(X3RegUsage::from),
+                _ => {}
+            }
+        }
+        if let Some(major) = priv_spec[0] {
+            riscv.priv_spec = Some((major, priv_spec[1].unwrap_or(0), priv_spec[2].unwrap_or(0)));
+        }
+        Ok(riscv)
+    }
+
+    /// Check that code built with these attributes can be linked with `other`
+    ///
+    /// Returns the tag of the first incompatible attribute. This follows the checks the GNU
+    /// linker makes: the ISAs must be compatible (see [`Isa::check_compatible()`]), the
+    /// stack alignment must match and the atomic mappings must not mix `A6C` with `A7`.
+    pub fn check_compatible(&self, other: &Riscv) -> Result<(), Tag> {
+        if let (Some(a), Some(b)) = (&self.arch, &other.arch) {
+            a.check_compatible(b).map_err(|_| Tag::ARCH)?;
+        }
+        if let (Some(a), Some(b)) = (self.stack_align, other.stack_align) {
+            if a != b {
+                return Err(Tag::STACK_ALIGN);
+            }
+        }
+        if let (Some(a), Some(b)) = (self.atomic_abi, other.atomic_abi) {
+            let mixed = |x, y| a == x && b == y || a == y && b == x;
+            if mixed(AtomicAbi::A6C, AtomicAbi::A7) {
+                return Err(Tag::ATOMIC_ABI);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Description of the typed `value` of `tag`, or `None` if the tag has no typed values
+pub fn describe(tag: Tag, value: &Value) -> Option<&'static str> {
+    let integer = value.integer()?;
+    match tag {
+        Tag::UNALIGNED_ACCESS => Some(match integer {
+            0 => "No unaligned access",
+            _ => "Unaligned access",
+        }),
+        Tag::ATOMIC_ABI => AtomicAbi::from(integer).description(),
+        Tag::X3_REG_USAGE => X3RegUsage::from(integer).description(),
+        _ => None,
+    }
+}
+
+/// Extension version, such as `2p1`
+#[derive(Clone, Copy, Debug, Eq, Hash, Ord, PartialEq, PartialOrd)]
+pub struct Version {
+    /// Major version
+    pub major: u32,
+    /// Minor version, 0 if not given
+    pub minor: u32,
+}
+
+impl fmt::Display for Version {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}p{}", self.major, self.minor)
+    }
+}
+
+/// Extension of an ISA string
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Extension<'a> {
+    /// Name, a single letter such as `m` or a multi-letter name such as `zicsr`
+    pub name: &'a str,
+    /// Version, or `None` for the default version
+    pub version: Option<Version>,
+}
+
+impl<'a> fmt::Display for Extension<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.name)?;
+        match self.version {
+            Some(version) => write!(f, "{}", version),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Why two ISAs can't be linked together
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum Incompatible<'a> {
+    /// Register widths differ
+    Xlen,
+    /// One base ISA is RV32E/RV64E and the other isn't
+    Base,
+    /// Both use an extension but with different major versions
+    Version(&'a str),
+}
+
+/// Multi-letter extensions start with one of these prefixes
+const MULTI_LETTER: &[u8] = b"zsxh";
+
+/// Extensions implied by the `g` base
+const G: &[&str] = &["i", "m", "a", "f", "d", "zicsr", "zifencei"];
+
+/// ISA string, such as `rv32imac_zicsr2p0`
+///
+/// The string is validated on parsing, and the extensions are read from it on demand.
+/// `g` is kept as written; [`Isa::has()`] treats it as `imafd_zicsr_zifencei`.
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Isa<'a> {
+    /// Register width in bits, 32, 64 or 128
+    pub xlen: u32,
+    /// Base integer ISA, `i`, `e` or `g`
+    pub base: Extension<'a>,
+    extensions: &'a str,
+}
+
+impl<'a> Isa<'a> {
+    /// Parse ISA string `s`
+    ///
+    /// Both the short form (`rv64gc`) and the canonical form emitted by toolchains
+    /// (`rv64i2p1_m2p0_a2p1_f2p2_d2p2_c2p0_zicsr2p0`) are accepted.
+    pub fn parse(s: &'a str) -> Result<Self, Error> {
+        let rest = s.strip_prefix("rv").ok_or(Error::Malformed)?;
+        let digits = rest.bytes().take_while(u8::is_ascii_digit).count();
+        let xlen = match &rest[..digits] {
+            "32" => 32,
+            "64" => 64,
+            "128" => 128,
+            _ => return Err(Error::Malformed),
+        };
+        let mut extensions = Extensions {
+            data: &rest[digits..],
+        };
+        let base = match extensions.read()? {
+            Some(base) if matches!(base.name, "i" | "e" | "g") => base,
+            _ => return Err(Error::Malformed),
+        };
+        let isa = Isa {
+            xlen,
+            base,
+            extensions: extensions.data,
+        };
+        // Validate the rest so that the iterator can't fail
+        let mut extensions = isa.extensions();
+        while extensions.read()?.is_some() {}
+        Ok(isa)
+    }
+
+    /// Iterate over the extensions after the base ISA
+    pub fn extensions(&self) -> Extensions<'a> {
+        Extensions {
+            data: self.extensions,
+        }
+    }
+
+    /// Extension `name` or the base ISA called `name`, or `None` if not present
+    ///
+    /// Extensions implied by `g` are returned without a version.
+    pub fn get(&self, name: &str) -> Option<Extension<'a>> {
+        if self.base.name == name {
+            return Some(self.base);
+        }
+        if let Some(extension) = self.extensions().find(|e| e.name == name) {
+            return Some(extension);
+        }
+        if self.base.name == "g" {
+            if let Some(&name) = G.iter().find(|&&g| g == name) {
+                return Some(Extension {
+                    name,
+                    version: None,
+                });
+            }
+        }
+        None
+    }
+
+    /// Returns true if extension `name` is present
+    pub fn has(&self, name: &str) -> bool {
+        self.get(name).is_some()
+    }
+
+    /// Returns true if every extension of `other` is present
+    ///
+    /// Use this to check that code built for `other` can run on this ISA.
+    pub fn supports(&self, other: &Isa) -> bool {
+        self.xlen == other.xlen
+            && (self.base.name == "e") == (other.base.name == "e")
+            && other.has_all(|name| self.has(name))
+    }
+
+    fn has_all(&self, mut f: impl FnMut(&str) -> bool) -> bool {
+        let implied: &[&str] = if self.base.name == "g" { &G[1..] } else { &[] };
+        implied.iter().all(|name| f(name)) && self.extensions().all(|e| f(e.name))
+    }
+
+    /// Check that objects built for this ISA can be linked with objects built for `other`
+    ///
+    /// The register width and base ISA must match, and extensions present in both with an
+    /// explicit version must have the same major version. The linked object uses the union
+    /// of the extensions.
+    pub fn check_compatible(&self, other: &Isa<'a>) -> Result<(), Incompatible<'a>> {
+        if self.xlen != other.xlen {
+            return Err(Incompatible::Xlen);
+        }
+        if (self.base.name == "e") != (other.base.name == "e") {
+            return Err(Incompatible::Base);
+        }
+        for a in core::iter::once(self.base).chain(self.extensions()) {
+            if let (Some(va), Some(vb)) = (a.version, other.get(a.name).and_then(|b| b.version)) {
+                if va.major != vb.major {
+                    return Err(Incompatible::Version(a.name));
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl<'a> fmt::Display for Isa<'a> {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "rv{}{}", self.xlen, self.base)?;
+        for extension in self.extensions() {
+            write!(f, "_{}", extension)?;
+        }
+        Ok(())
+    }
+}
+
+/// Iterator over the extensions of an ISA string
+#[derive(Clone)]
+pub struct Extensions<'a> {
+    data: &'a str,
+}
+
+impl<'a> Extensions<'a> {
+    fn read(&mut self) -> Result<Option<Extension<'a>>, Error> {
+        let data = self.data.trim_start_matches('_');
+        let first = match data.bytes().next() {
+            Some(first) if first.is_ascii_lowercase() => first,
+            Some(_) => return Err(Error::Malformed),
+            None => return Ok(None),
+        };
+        let (name, version) = if MULTI_LETTER.contains(&first) {
+            // Up to the next '_', with an optional version at the end
+            let end = data.find('_').unwrap_or(data.len());
+            let token = &data[..end];
+            let (name, version) = split_version(token)?;
+            if name.len() < 2
+                || !name
+                    .bytes()
+                    .all(|b| b.is_ascii_lowercase() || b.is_ascii_digit())
+            {
+                return Err(Error::Malformed);
+            }
+            self.data = &data[end..];
+            (name, version)
+        } else {
+            let mut rest = &data[1..];
+            let version = read_version(&mut rest)?;
+            self.data = rest;
+            (&data[..1], version)
+        };
+        Ok(Some(Extension { name, version }))
+    }
+}
+
+impl<'a> Iterator for Extensions<'a> {
+    type Item = Extension<'a>;
+    fn next(&mut self) -> Option<Self::Item> {
+        // The ISA string was validated by Isa::parse()
+        self.read().ok().flatten()
+    }
+}
+
+/// Read a decimal number from the start of `data`
+fn read_number(data: &mut &str) -> Result<Option<u32>, Error> {
+    let digits = data.bytes().take_while(u8::is_ascii_digit).count();
+    if digits == 0 {
+        return Ok(None);
+    }
+    let number = data[..digits].parse().map_err(|_| Error::Malformed)?;
+    *data = &data[digits..];
+    Ok(Some(number))
+}
+
+/// Read an optional `<major>[p<minor>]` version from the start of `data`
+fn read_version(data: &mut &str) -> Result<Option<Version>, Error> {
+    let major = match read_number(data)? {
+        Some(major) => major,
+        None => return Ok(None),
+    };
+    let mut minor = 0;
+    if let Some(mut rest) = data.strip_prefix('p') {
+        if let Some(number) = read_number(&mut rest)? {
+            minor = number;
+            *data = rest;
+        }
+    }
+    Ok(Some(Version { major, minor }))
+}
+
+/// Split a multi-letter extension into its name and trailing version
+fn split_version(token: &str) -> Result<(&str, Option<Version>), Error> {
+    // The name ends with a letter, so the version starts after the last letter other than
+    // a 'p' between digits
+    let bytes = token.as_bytes();
+    let mut start = bytes.len();
+    while start > 0 && bytes[start - 1].is_ascii_digit() {
+        start -= 1;
+    }
+    if start > 1
+        && start < bytes.len()
+        && bytes[start - 1] == b'p'
+        && bytes[start - 2].is_ascii_digit()
+    {
+        start -= 1;
+        while start > 0 && bytes[start - 1].is_ascii_digit() {
+            start -= 1;
+        }
+    }
+    if start == 0 {
+        return Err(Error::Malformed);
+    }
+    let mut rest = &token[start..];
+    let version = read_version(&mut rest)?;
+    Ok((&token[..start], version))
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::attributes::test::section;
+    use crate::attributes::Attributes;
+    use std::format;
+
+    #[test]
+    fn isa() {
+        let isa = Isa::parse("rv32imac_zicsr2p0_zba").unwrap();
+        assert_eq!(isa.xlen, 32);
+        assert_eq!(isa.base.name, "i");
+        assert!(isa.has("m") && isa.has("c") && isa.has("zba"));
+        assert!(!isa.has("f"));
+        let zicsr = isa.get("zicsr").unwrap();
+        assert_eq!(zicsr.version, Some(Version { major: 2, minor: 0 }));
+        assert_eq!(format!("{}", isa), "rv32i_m_a_c_zicsr2p0_zba");
+
+        let canonical = "rv64i2p1_m2p0_a2p1_f2p2_d2p2_c2p0_zicsr2p0_zifencei2p0_zve32x1p0";
+        let isa = Isa::parse(canonical).unwrap();
+        assert_eq!(format!("{}", isa), canonical);
+        assert_eq!(isa.extensions().count(), 8);
+        assert_eq!(isa.get("zve32x").unwrap().version.unwrap().major, 1);
+
+        let g = Isa::parse("rv64gc").unwrap();
+        assert!(g.has("d") && g.has("zifencei"));
+        assert!(Isa::parse(canonical).unwrap().supports(&g));
+        assert!(!g.supports(&Isa::parse("rv64gcv").unwrap()));
+
+        assert_eq!(Isa::parse("rv64"), Err(Error::Malformed));
+        assert_eq!(Isa::parse("rv16i"), Err(Error::Malformed));
+        assert_eq!(Isa::parse("rv32m"), Err(Error::Malformed));
+        assert_eq!(Isa::parse("rv32i_M"), Err(Error::Malformed));
+        assert_eq!(Isa::parse("rv32i_z2p0"), Err(Error::Malformed));
+    }
+
+    #[test]
+    fn compatible() {
+        let a = Isa::parse("rv32i2p1_m2p0_c2p0").unwrap();
+        let b = Isa::parse("rv32i2p0_f2p2").unwrap();
+        assert_eq!(a.check_compatible(&b), Ok(()));
+        let c = Isa::parse("rv32i2p1_m3p0").unwrap();
+        assert_eq!(a.check_compatible(&c), Err(Incompatible::Version("m")));
+        let d = Isa::parse("rv64imac").unwrap();
+        assert_eq!(a.check_compatible(&d), Err(Incompatible::Xlen));
+        let e = Isa::parse("rv32e_c").unwrap();
+        assert_eq!(a.check_compatible(&e), Err(Incompatible::Base));
+    }
+
+    #[test]
+    fn attributes() {
+        // As emitted by GCC for -march=rv32imac_zicsr -mabi=ilp32
+        let file =
+            b"\x04\x10\x05rv32i2p1_m2p0_a2p1_c2p0_zicsr2p0\0\x06\x00\x08\x01\x0a\x0b\x0e\x03";
+        let data = section("riscv", &[(1, file)]);
+        let attributes = Attributes::new(&data, false).unwrap();
+        let subsection = attributes.subsection("riscv").unwrap().unwrap();
+        let riscv = Riscv::new(&subsection.file().unwrap().unwrap()).unwrap();
+        assert_eq!(riscv.stack_align, Some(16));
+        assert!(riscv.arch.unwrap().has("zicsr"));
+        assert_eq!(riscv.unaligned_access, Some(false));
+        assert_eq!(riscv.priv_spec, Some((1, 11, 0)));
+        assert_eq!(riscv.atomic_abi, Some(AtomicAbi::A7));
+
+        let other = Riscv {
+            stack_align: Some(8),
+            ..riscv
+        };
+        assert_eq!(riscv.check_compatible(&other), Err(Tag::STACK_ALIGN));
+        let other = Riscv {
+            arch: Some(Isa::parse("rv64imac").unwrap()),
+            ..riscv
+        };
+        assert_eq!(riscv.check_compatible(&other), Err(Tag::ARCH));
+        let other = Riscv {
+            atomic_abi: Some(AtomicAbi::A6C),
+            ..riscv
+        };
+        assert_eq!(riscv.check_compatible(&other), Err(Tag::ATOMIC_ABI));
+        assert_eq!(riscv.check_compatible(&Riscv::default()), Ok(()));
+
+        assert_eq!(describe(Tag::ATOMIC_ABI, &Value::Integer(2)), Some("A6S"));
+    }
+}
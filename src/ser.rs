@@ -0,0 +1,251 @@
+//! Minimal `no_std` serde serializer for fixed-size ELF structures
+//!
+//! The counterpart of the deserializer in `de.rs`: fields are written in order as
+//! fixed-width integers without padding, into a caller-provided buffer.
+
+use crate::ehdr::{EIC, EID};
+use crate::{Ehdr32, Ehdr64, Error, Phdr, Phdr32, Phdr64};
+use core::convert::TryFrom;
+use serde::ser::{Impossible, Serialize, SerializeStruct, SerializeTuple, SerializeTupleStruct};
+
+/// Write `value` to the start of `data`, returning the number of bytes written
+pub(crate) fn to_bytes<T: Serialize>(
+    value: &T,
+    big_endian: bool,
+    data: &mut [u8],
+) -> Result<usize, Error> {
+    let mut serializer = Serializer {
+        data,
+        len: 0,
+        big_endian,
+    };
+    value.serialize(&mut serializer)?;
+    Ok(serializer.len)
+}
+
+/// Write `ehdr` to `f` in the class and byte order of its identification
+pub(crate) fn write_ehdr(
+    ehdr: &Ehdr64,
+    f: &mut impl FnMut(&[u8]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut data = [0; Ehdr64::SIZE];
+    let big_endian = ehdr.e_ident.data == EID::MSB;
+    let len = match ehdr.e_ident.class {
+        EIC::ELF32 => to_bytes(&Ehdr32::try_from(*ehdr)?, big_endian, &mut data)?,
+        EIC::ELF64 => to_bytes(ehdr, big_endian, &mut data)?,
+        _ => return Err(Error::Unsupported),
+    };
+    f(&data[..len])
+}
+
+/// Write `phdr` to `f` in the class and byte order of the file header `ehdr`
+pub(crate) fn write_phdr(
+    ehdr: &Ehdr64,
+    phdr: &Phdr64,
+    f: &mut impl FnMut(&[u8]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let mut data = [0; Phdr64::SIZE];
+    let big_endian = ehdr.e_ident.data == EID::MSB;
+    let len = match ehdr.e_ident.class {
+        EIC::ELF32 => to_bytes(&Phdr32::try_from(*phdr)?, big_endian, &mut data)?,
+        EIC::ELF64 => to_bytes(phdr, big_endian, &mut data)?,
+        _ => return Err(Error::Unsupported),
+    };
+    f(&data[..len])
+}
+
+struct Serializer<'a> {
+    data: &'a mut [u8],
+    len: usize,
+    big_endian: bool,
+}
+
+impl<'a> Serializer<'a> {
+    fn put(&mut self, bytes: &[u8]) -> Result<(), Error> {
+        let end = self.len + bytes.len();
+        self.data
+            .get_mut(self.len..end)
+            .ok_or(Error::Truncated)?
+            .copy_from_slice(bytes);
+        self.len = end;
+        Ok(())
+    }
+}
+
+macro_rules! serialize_int {
+    ($($method:ident($ty:ty),)*) => {
+        $(
+            fn $method(self, value: $ty) -> Result<(), Error> {
+                if self.big_endian {
+                    self.put(&value.to_be_bytes())
+                } else {
+                    self.put(&value.to_le_bytes())
+                }
+            }
+        )*
+    };
+}
+
+macro_rules! unsupported {
+    ($($method:ident($($arg:ty),*) -> $ok:ty,)*) => {
+        $(
+            fn $method(self, $(_: $arg),*) -> Result<$ok, Error> {
+                Err(Error::Unsupported)
+            }
+        )*
+    };
+}
+
+impl<'s, 'a> serde::Serializer for &'s mut Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+    type SerializeSeq = Impossible<(), Error>;
+    type SerializeTuple = Self;
+    type SerializeTupleStruct = Self;
+    type SerializeTupleVariant = Impossible<(), Error>;
+    type SerializeMap = Impossible<(), Error>;
+    type SerializeStruct = Self;
+    type SerializeStructVariant = Impossible<(), Error>;
+
+    serialize_int! {
+        serialize_u8(u8),
+        serialize_u16(u16),
+        serialize_u32(u32),
+        serialize_u64(u64),
+        serialize_i8(i8),
+        serialize_i16(i16),
+        serialize_i32(i32),
+        serialize_i64(i64),
+    }
+
+    unsupported! {
+        serialize_bool(bool) -> (),
+        serialize_f32(f32) -> (),
+        serialize_f64(f64) -> (),
+        serialize_char(char) -> (),
+        serialize_str(&str) -> (),
+        serialize_bytes(&[u8]) -> (),
+        serialize_none() -> (),
+        serialize_unit() -> (),
+        serialize_unit_struct(&'static str) -> (),
+        serialize_unit_variant(&'static str, u32, &'static str) -> (),
+        serialize_seq(Option<usize>) -> Self::SerializeSeq,
+        serialize_tuple_variant(&'static str, u32, &'static str, usize) -> Self::SerializeTupleVariant,
+        serialize_map(Option<usize>) -> Self::SerializeMap,
+        serialize_struct_variant(&'static str, u32, &'static str, usize) -> Self::SerializeStructVariant,
+    }
+
+    fn serialize_some<T: ?Sized + Serialize>(self, _value: &T) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn serialize_newtype_struct<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: ?Sized + Serialize>(
+        self,
+        _name: &'static str,
+        _index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<(), Error> {
+        Err(Error::Unsupported)
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_tuple_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+
+    fn serialize_struct(self, _name: &'static str, _len: usize) -> Result<Self, Error> {
+        Ok(self)
+    }
+}
+
+impl<'s, 'a> SerializeTuple for &'s mut Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_element<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'s, 'a> SerializeTupleStruct for &'s mut Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(&mut self, value: &T) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl<'s, 'a> SerializeStruct for &'s mut Serializer<'a> {
+    type Ok = ();
+    type Error = Error;
+
+    fn serialize_field<T: ?Sized + Serialize>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        value.serialize(&mut **self)
+    }
+
+    fn end(self) -> Result<(), Error> {
+        Ok(())
+    }
+}
+
+impl serde::ser::Error for Error {
+    fn custom<T: core::fmt::Display>(_msg: T) -> Self {
+        Error::Unsupported
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::{Ehdr32, Phdr, Phdr64};
+
+    #[test]
+    fn matches_bincode() {
+        use bincode::Options;
+        let phdr = Phdr64 {
+            p_vaddr: 0x1122334455667788,
+            p_align: 0x1000,
+            ..Default::default()
+        };
+        let options = bincode::DefaultOptions::new().with_fixint_encoding();
+        let mut data = [0; Phdr64::SIZE];
+        assert_eq!(to_bytes(&phdr, false, &mut data), Ok(Phdr64::SIZE));
+        assert_eq!(options.with_little_endian().serialize(&phdr).unwrap(), data);
+        assert_eq!(to_bytes(&phdr, true, &mut data), Ok(Phdr64::SIZE));
+        assert_eq!(options.with_big_endian().serialize(&phdr).unwrap(), data);
+        let mut data = [0; Ehdr32::SIZE];
+        assert_eq!(
+            to_bytes(&Ehdr32::default(), false, &mut data),
+            Ok(Ehdr32::SIZE)
+        );
+        assert_eq!(
+            to_bytes(&phdr, false, &mut data[1..]),
+            Err(Error::Truncated)
+        );
+    }
+}
@@ -0,0 +1,306 @@
+//! Helpers for building small ELF images in unit tests
+
+extern crate std;
+
+use crate::ehdr::*;
+use crate::phdr::{PF, PT};
+use crate::shdr::{SHF32, SHF64, SHN, SHT};
+use crate::*;
+use std::string::String;
+use std::vec;
+use std::vec::Vec;
+
+pub(crate) struct Builder {
+    pub elf32: bool,
+    pub big_endian: bool,
+    pub ehdr: Ehdr64,
+    sections: Vec<(String, Shdr64, Vec<u8>)>,
+    segments: Vec<(Phdr64, Vec<usize>)>,
+}
+
+impl Builder {
+    pub fn new(elf32: bool, big_endian: bool, e_type: ET, e_machine: EM) -> Self {
+        let mut ehdr = Ehdr64::default();
+        ehdr.e_ident.magic = Eident::MAGIC;
+        ehdr.e_ident.class = if elf32 { EIC::ELF32 } else { EIC::ELF64 };
+        ehdr.e_ident.data = if big_endian { EID::MSB } else { EID::LSB };
+        ehdr.e_ident.version = EIV::CURRENT;
+        ehdr.e_type = e_type;
+        ehdr.e_machine = e_machine;
+        ehdr.e_version = EV::CURRENT;
+        Builder {
+            elf32,
+            big_endian,
+            ehdr,
+            sections: vec![(String::new(), Shdr64::default(), Vec::new())],
+            segments: Vec::new(),
+        }
+    }
+
+    /// Add a section, sections with an address are marked as allocated
+    pub fn section(&mut self, name: &str, sh_type: SHT, addr: u64, data: Vec<u8>) -> usize {
+        let shdr = Shdr64 {
+            sh_type,
+            sh_addr: addr,
+            sh_size: data.len() as u64,
+            sh_addralign: 1,
+            sh_flags: if addr != 0 { SHF64::ALLOC } else { SHF64::NONE },
+            ..Default::default()
+        };
+        self.sections.push((name.into(), shdr, data));
+        self.sections.len() - 1
+    }
+
+    pub fn shdr_mut(&mut self, index: usize) -> &mut Shdr64 {
+        &mut self.sections[index].1
+    }
+
+    /// Add a segment covering `sections`, which must be consecutive
+    pub fn segment(&mut self, p_type: PT, p_flags: PF, sections: &[usize]) -> usize {
+        let phdr = Phdr64 {
+            p_type,
+            p_flags,
+            p_align: 1,
+            ..Default::default()
+        };
+        self.segments.push((phdr, sections.into()));
+        self.segments.len() - 1
+    }
+
+    pub fn phdr_mut(&mut self, index: usize) -> &mut Phdr64 {
+        &mut self.segments[index].0
+    }
+
+    /// String table containing `strings`, and the offset of each string
+    pub fn strings(strings: &[&str]) -> (Vec<u8>, Vec<u32>) {
+        let mut data = vec![0];
+        let mut offsets = Vec::new();
+        for s in strings {
+            offsets.push(data.len() as u32);
+            data.extend(s.as_bytes());
+            data.push(0);
+        }
+        (data, offsets)
+    }
+
+    pub fn serialize<T: serde::Serialize>(&self, t: &T) -> Vec<u8> {
+        use bincode::Options;
+        let options = bincode::DefaultOptions::new().with_fixint_encoding();
+        if self.big_endian {
+            options.with_big_endian().serialize(t).unwrap()
+        } else {
+            options.with_little_endian().serialize(t).unwrap()
+        }
+    }
+
+    /// Address sized value
+    pub fn word(&self, value: u64) -> Vec<u8> {
+        if self.elf32 {
+            self.serialize(&(value as u32))
+        } else {
+            self.serialize(&value)
+        }
+    }
+
+    pub fn sym(
+        &self,
+        st_name: u32,
+        st_value: u64,
+        st_size: u64,
+        st_info: u8,
+        st_shndx: u16,
+    ) -> Vec<u8> {
+        if self.elf32 {
+            self.serialize(&Sym32 {
+                st_name,
+                st_value: st_value as u32,
+                st_size: st_size as u32,
+                st_info,
+                st_other: 0,
+                st_shndx: SHN::from(st_shndx),
+            })
+        } else {
+            self.serialize(&Sym64 {
+                st_name,
+                st_info,
+                st_other: 0,
+                st_shndx: SHN::from(st_shndx),
+                st_value,
+                st_size,
+            })
+        }
+    }
+
+    pub fn rela(&self, r_offset: u64, r_sym: u32, r_type: u32, r_addend: i64) -> Vec<u8> {
+        if self.elf32 {
+            self.serialize(&Rela32 {
+                r_offset: r_offset as u32,
+                r_info: r_sym << 8 | r_type,
+                r_addend: r_addend as i32,
+            })
+        } else {
+            self.serialize(&Rela64 {
+                r_offset,
+                r_info: (r_sym as u64) << 32 | r_type as u64,
+                r_addend,
+            })
+        }
+    }
+
+    pub fn rel(&self, r_offset: u64, r_sym: u32, r_type: u32) -> Vec<u8> {
+        if self.elf32 {
+            self.serialize(&Rel32 {
+                r_offset: r_offset as u32,
+                r_info: r_sym << 8 | r_type,
+            })
+        } else {
+            self.serialize(&Rel64 {
+                r_offset,
+                r_info: (r_sym as u64) << 32 | r_type as u64,
+            })
+        }
+    }
+
+    pub fn dynamic(&self, entries: &[(dynamic::DT, u64)]) -> Vec<u8> {
+        let mut data = Vec::new();
+        for &(tag, value) in entries.iter().chain(&[(dynamic::DT::NULL, 0)]) {
+            data.extend(self.word(i64::from(tag) as u64));
+            data.extend(self.word(value));
+        }
+        data
+    }
+
+    pub fn build(mut self) -> Vec<u8> {
+        let (ehsize, phentsize, shentsize) = if self.elf32 {
+            (Ehdr32::SIZE, Phdr32::SIZE, Shdr32::SIZE)
+        } else {
+            (Ehdr64::SIZE, Phdr64::SIZE, Shdr64::SIZE)
+        };
+
+        // Section header string table
+        let names: Vec<&str> = self.sections.iter().skip(1).map(|s| s.0.as_str()).collect();
+        let (mut shstrtab, mut offsets) = Self::strings(&names);
+        offsets.insert(0, 0);
+        offsets.push(shstrtab.len() as u32);
+        shstrtab.extend(b".shstrtab\0");
+        self.section(".shstrtab", SHT::STRTAB, 0, shstrtab);
+        for (section, offset) in self.sections.iter_mut().zip(offsets).skip(1) {
+            section.1.sh_name = offset;
+        }
+
+        // Lay out section data after the headers
+        let mut offset = ehsize + phentsize * self.segments.len();
+        for section in self.sections.iter_mut().skip(1) {
+            offset = (offset + 7) & !7;
+            section.1.sh_offset = offset as u64;
+            if section.1.sh_type != SHT::NOBITS {
+                section.1.sh_size = section.2.len() as u64;
+                offset += section.2.len();
+            }
+        }
+        let shoff = (offset + 7) & !7;
+
+        // Segments cover their sections
+        let all_sections = &self.sections;
+        for (phdr, sections) in self.segments.iter_mut() {
+            if let (Some(&first), Some(&last)) = (sections.first(), sections.last()) {
+                let end = sections
+                    .iter()
+                    .map(|&i| &all_sections[i].1)
+                    .filter(|s| s.sh_type != SHT::NOBITS)
+                    .map(|s| s.sh_offset + s.sh_size)
+                    .max()
+                    .unwrap_or(all_sections[first].1.sh_offset);
+                let first = &all_sections[first].1;
+                let last = &all_sections[last].1;
+                phdr.p_offset = first.sh_offset;
+                phdr.p_vaddr = first.sh_addr;
+                if phdr.p_paddr == 0 {
+                    phdr.p_paddr = first.sh_addr;
+                }
+                phdr.p_filesz = end - first.sh_offset;
+                phdr.p_memsz = last
+                    .sh_addr
+                    .wrapping_add(last.sh_size)
+                    .wrapping_sub(first.sh_addr);
+            }
+        }
+
+        let mut ehdr = self.ehdr;
+        ehdr.e_phoff = if self.segments.is_empty() {
+            0
+        } else {
+            ehsize as u64
+        };
+        ehdr.e_shoff = shoff as u64;
+        ehdr.e_ehsize = ehsize as u16;
+        ehdr.e_phentsize = phentsize as u16;
+        ehdr.e_phnum = self.segments.len() as u16;
+        ehdr.e_shentsize = shentsize as u16;
+        ehdr.e_shnum = self.sections.len() as u16;
+        ehdr.e_shstrndx = (self.sections.len() - 1) as u16;
+
+        let mut data = if self.elf32 {
+            self.serialize(&Ehdr32 {
+                e_ident: ehdr.e_ident,
+                e_type: ehdr.e_type,
+                e_machine: ehdr.e_machine,
+                e_version: ehdr.e_version,
+                e_entry: ehdr.e_entry as u32,
+                e_phoff: ehdr.e_phoff as u32,
+                e_shoff: ehdr.e_shoff as u32,
+                e_flags: ehdr.e_flags,
+                e_ehsize: ehdr.e_ehsize,
+                e_phentsize: ehdr.e_phentsize,
+                e_phnum: ehdr.e_phnum,
+                e_shentsize: ehdr.e_shentsize,
+                e_shnum: ehdr.e_shnum,
+                e_shstrndx: ehdr.e_shstrndx,
+            })
+        } else {
+            self.serialize(&ehdr)
+        };
+        for (p, _) in &self.segments {
+            data.extend(if self.elf32 {
+                self.serialize(&Phdr32 {
+                    p_type: p.p_type,
+                    p_offset: p.p_offset as u32,
+                    p_vaddr: p.p_vaddr as u32,
+                    p_paddr: p.p_paddr as u32,
+                    p_filesz: p.p_filesz as u32,
+                    p_memsz: p.p_memsz as u32,
+                    p_flags: p.p_flags,
+                    p_align: p.p_align as u32,
+                })
+            } else {
+                self.serialize(p)
+            });
+        }
+        for (_, shdr, contents) in &self.sections {
+            if shdr.sh_type != SHT::NOBITS && shdr.sh_type != SHT::NULL {
+                data.resize(shdr.sh_offset as usize, 0);
+                data.extend(contents);
+            }
+        }
+        data.resize(shoff, 0);
+        for (_, s, _) in &self.sections {
+            data.extend(if self.elf32 {
+                self.serialize(&Shdr32 {
+                    sh_name: s.sh_name,
+                    sh_type: s.sh_type,
+                    sh_flags: SHF32::from(u64::from(s.sh_flags) as u32),
+                    sh_addr: s.sh_addr as u32,
+                    sh_offset: s.sh_offset as u32,
+                    sh_size: s.sh_size as u32,
+                    sh_link: s.sh_link,
+                    sh_info: s.sh_info,
+                    sh_addralign: s.sh_addralign as u32,
+                    sh_entsize: s.sh_entsize as u32,
+                })
+            } else {
+                self.serialize(s)
+            });
+        }
+        data
+    }
+}
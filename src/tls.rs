@@ -0,0 +1,243 @@
+//! Thread-local storage
+//!
+//! The `PT::TLS` segment describes the TLS template: an initialization image copied into
+//! each thread's TLS block, followed by zeroed memory. Symbols of type `STT::TLS` have values
+//! that are offsets into this template. Where the block is placed relative to the thread
+//! pointer depends on the machine's TLS variant:
+//!
+//! * Variant I (ARM, AArch64, RISC-V, PowerPC, MIPS): the thread pointer points at a thread
+//!   control block, and the TLS block follows it
+//! * Variant II (x86, x86-64, SPARC): the TLS block ends at the thread pointer
+
+use crate::ehdr::EM;
+use crate::phdr::PT;
+use crate::sym::STT;
+use crate::{ElfFile, Error, Sym64, SymbolTable};
+
+enum_struct!(
+/// TLS data structure variant
+pub struct Variant(u8) {
+    I  = 1 => "Variant I (TLS block follows thread pointer)",
+    II = 2 => "Variant II (TLS block precedes thread pointer)",
+}
+);
+
+/// Position of the executable's TLS block relative to the thread pointer
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Layout {
+    /// TLS variant
+    pub variant: Variant,
+    /// Size of the thread control block between the thread pointer and the TLS block (variant I)
+    pub tcb_size: u64,
+    /// Distance the thread pointer is moved past the start of the TCB (variant I)
+    pub tp_bias: u64,
+}
+
+impl Layout {
+    /// Variant II layout
+    pub const VARIANT_II: Layout = Layout {
+        variant: Variant::II,
+        tcb_size: 0,
+        tp_bias: 0,
+    };
+
+    /// Variant I layout with a TCB of `tcb_size` bytes and thread pointer biased by `tp_bias`
+    pub const fn variant_i(tcb_size: u64, tp_bias: u64) -> Layout {
+        Layout {
+            variant: Variant::I,
+            tcb_size,
+            tp_bias,
+        }
+    }
+
+    /// Layout used by `machine`, or `None` if unknown
+    pub fn for_machine(machine: EM) -> Option<Layout> {
+        match machine {
+            EM::X86 | EM::X86_64 | EM::SPARC | EM::SPARCV9 => Some(Layout::VARIANT_II),
+            EM::ARM => Some(Layout::variant_i(8, 0)),
+            EM::AARCH64 => Some(Layout::variant_i(16, 0)),
+            EM::RISCV => Some(Layout::variant_i(0, 0)),
+            EM::PPC32 | EM::PPC64 | EM::MIPS | EM::M68K => Some(Layout::variant_i(0, 0x7000)),
+            _ => None,
+        }
+    }
+}
+
+/// TLS template from the `PT::TLS` segment
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Template<'a> {
+    /// Initialization image (`.tdata`)
+    pub image: &'a [u8],
+    /// Virtual address of the template
+    pub vaddr: u64,
+    /// Size of the TLS block, including zeroed data (`.tbss`)
+    pub size: u64,
+    /// Alignment of the TLS block
+    pub align: u64,
+}
+
+impl<'a> Template<'a> {
+    /// Offset from the thread pointer to the start of the executable's TLS block
+    pub fn block_offset(&self, layout: &Layout) -> i64 {
+        let mask = self.align.max(1) - 1;
+        match layout.variant {
+            Variant::II => {
+                let padding = self.vaddr.wrapping_neg().wrapping_sub(self.size) & mask;
+                (self.size + padding).wrapping_neg() as i64
+            }
+            _ => {
+                let padding = self.vaddr.wrapping_sub(layout.tcb_size) & mask;
+                (layout.tcb_size + padding).wrapping_sub(layout.tp_bias) as i64
+            }
+        }
+    }
+
+    /// Offset from the thread pointer to TLS symbol value `st_value` in the executable
+    pub fn tp_offset(&self, layout: &Layout, st_value: u64) -> i64 {
+        self.block_offset(layout).wrapping_add(st_value as i64)
+    }
+}
+
+/// TLS symbol and its location
+#[derive(Clone, Copy)]
+pub struct Symbol<'a> {
+    /// Symbol name
+    pub name: &'a str,
+    /// Symbol table entry, `st_value` is the offset into the TLS block
+    pub sym: Sym64,
+    /// Offset from the thread pointer, if the symbol is in the executable's TLS block
+    pub tp_offset: i64,
+}
+
+/// Iterator over TLS symbols
+pub struct Symbols<'a> {
+    symbols: Option<SymbolTable<'a>>,
+    table: crate::Table<'a, Sym64>,
+    template: Template<'a>,
+    layout: Layout,
+}
+
+impl<'a> Iterator for Symbols<'a> {
+    type Item = Symbol<'a>;
+    fn next(&mut self) -> Option<Symbol<'a>> {
+        let symbols = self.symbols.as_ref()?;
+        for sym in &mut self.table {
+            if sym.st_type() != STT::TLS || sym.st_shndx == crate::shdr::SHN::UNDEF {
+                continue;
+            }
+            return Some(Symbol {
+                name: symbols.name(&sym).unwrap_or(""),
+                sym,
+                tp_offset: self.template.tp_offset(&self.layout, sym.st_value),
+            });
+        }
+        None
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// TLS template, or `None` if there is no `PT::TLS` segment
+    pub fn tls_template(&self) -> Result<Option<Template<'a>>, Error> {
+        let phdr = match self.phdrs()?.find(|p| p.p_type == PT::TLS) {
+            Some(phdr) => phdr,
+            None => return Ok(None),
+        };
+        if phdr.p_filesz > phdr.p_memsz {
+            return Err(Error::Malformed);
+        }
+        Ok(Some(Template {
+            image: self.segment_data(&phdr)?,
+            vaddr: phdr.p_vaddr,
+            size: phdr.p_memsz,
+            align: phdr.p_align,
+        }))
+    }
+
+    /// Defined TLS symbols and their offsets from the thread pointer using `layout`
+    ///
+    /// Returns `None` if there is no TLS template.
+    pub fn tls_symbols(&self, layout: Layout) -> Result<Option<Symbols<'a>>, Error> {
+        let template = match self.tls_template()? {
+            Some(template) => template,
+            None => return Ok(None),
+        };
+        let symbols = self.symbol_table()?;
+        let table = match &symbols {
+            Some(symbols) => symbols.iter(),
+            None => self.table(0, 0, 0)?,
+        };
+        Ok(Some(Symbols {
+            symbols,
+            table,
+            template,
+            layout,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::ET;
+    use crate::phdr::PF;
+    use crate::shdr::{SHF64, SHT};
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    fn build(machine: EM) -> Vec<u8> {
+        let mut builder = Builder::new(false, false, ET::EXEC, machine);
+        let tdata = builder.section(".tdata", SHT::PROGBITS, 0x10008, vec![1; 12]);
+        let tbss = builder.section(".tbss", SHT::NOBITS, 0x10014, vec![]);
+        builder.shdr_mut(tdata).sh_flags |= SHF64::TLS;
+        builder.shdr_mut(tbss).sh_flags |= SHF64::TLS;
+        builder.shdr_mut(tbss).sh_size = 20;
+        let (strtab, names) = Builder::strings(&["first", "last"]);
+        let strtab = builder.section(".strtab", SHT::STRTAB, 0, strtab);
+        let mut symtab = builder.sym(0, 0, 0, 0, 0);
+        symtab.extend(builder.sym(names[0], 0, 4, 0x16, tdata as u16));
+        symtab.extend(builder.sym(names[1], 28, 4, 0x16, tbss as u16));
+        let symtab = builder.section(".symtab", SHT::SYMTAB, 0, symtab);
+        builder.shdr_mut(symtab).sh_link = strtab as u32;
+        let tls = builder.segment(PT::TLS, PF::R, &[tdata, tbss]);
+        builder.phdr_mut(tls).p_align = 16;
+        builder.build()
+    }
+
+    #[test]
+    fn template() {
+        let data = build(EM::X86_64);
+        let file = ElfFile::new(&data).unwrap();
+        let template = file.tls_template().unwrap().unwrap();
+        assert_eq!(template.image, &[1; 12][..]);
+        assert_eq!(template.size, 32);
+        assert_eq!(template.align, 16);
+    }
+
+    #[test]
+    fn offsets() {
+        // Variant II: the block ends at the thread pointer, block start is aligned
+        let data = build(EM::X86_64);
+        let file = ElfFile::new(&data).unwrap();
+        let layout = Layout::for_machine(EM::X86_64).unwrap();
+        let symbols: Vec<_> = file.tls_symbols(layout).unwrap().unwrap().collect();
+        assert_eq!(symbols.len(), 2);
+        assert_eq!(symbols[0].name, "first");
+        assert_eq!(symbols[0].tp_offset, -40);
+        assert_eq!(symbols[1].tp_offset, -12);
+
+        // Variant I: the block follows a 16 byte TCB, padded to keep the template alignment
+        let data = build(EM::AARCH64);
+        let file = ElfFile::new(&data).unwrap();
+        let layout = Layout::for_machine(EM::AARCH64).unwrap();
+        let symbols: Vec<_> = file.tls_symbols(layout).unwrap().unwrap().collect();
+        assert_eq!(symbols[0].tp_offset, 24);
+        assert_eq!(symbols[1].tp_offset, 52);
+        let template = file.tls_template().unwrap().unwrap();
+        assert_eq!(
+            template.tp_offset(&Layout::variant_i(0, 0x7000), 0),
+            -0x7000 + 8
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! UF2 firmware images
+//!
+//! Boards with a UF2 bootloader appear as a USB drive, and program the 512-byte blocks of
+//! a `.uf2` file copied to it. Each block carries 256 bytes of payload with its flash
+//! address, its position in the file and optionally the family ID of the chip it's for.
+//!
+//! [`ElfFile::uf2()`] writes the regions selected like [`ElfFile::binary()`], normally at
+//! their physical addresses, as blocks of whole 256-byte pages, zero-filling partial pages.
+//! [`Blocks`] parses a file, and [`uf2_to_elf()`] converts one into a minimal ELF file.
+
+use crate::binary::{write_elf, BinaryOptions};
+use crate::de::from_bytes;
+use crate::ser::to_bytes;
+use crate::{Ehdr64, ElfFile, Error};
+use core::convert::TryFrom;
+use core::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+/// First magic number, `"UF2\n"`
+pub const MAGIC_START0: u32 = 0x0a32_4655;
+/// Second magic number
+pub const MAGIC_START1: u32 = 0x9e5d_5157;
+/// Magic number in the last word of a block
+pub const MAGIC_END: u32 = 0x0ab1_6f30;
+/// Size of a block
+pub const BLOCK_SIZE: usize = 512;
+/// Payload bytes per block written
+pub const PAYLOAD_SIZE: usize = 256;
+/// Largest payload of a block
+const MAX_PAYLOAD_SIZE: usize = 476;
+
+flag_struct!(
+/// Block flags
+pub struct Flags(u32) {
+    NONE                   = 0      => "No flags",
+    NOT_MAIN_FLASH         = 0x1    => "Not written to main flash",
+    FILE_CONTAINER         = 0x1000 => "Part of a file, `family_id` is the file size",
+    FAMILY_ID_PRESENT      = 0x2000 => "`family_id` is set",
+    MD5_CHECKSUM_PRESENT   = 0x4000 => "MD5 checksum after the payload",
+    EXTENSION_TAGS_PRESENT = 0x8000 => "Extension tags after the payload",
+}
+);
+
+enum_struct!(
+/// Chip family ID
+pub struct Family(u32) {
+    SAMD21       = 0x68ed_2b88 => "Microchip SAMD21",
+    SAMD51       = 0x5511_4460 => "Microchip SAMD51",
+    NRF52        = 0x1b57_745f => "Nordic nRF52",
+    NRF52840     = 0xada5_2840 => "Nordic nRF52840",
+    STM32F1      = 0x5ee2_1072 => "ST STM32F1",
+    STM32F4      = 0x5775_5a57 => "ST STM32F4",
+    ESP32S2      = 0xbfdd_4eee => "Espressif ESP32-S2",
+    RP2040       = 0xe48b_ff56 => "Raspberry Pi RP2040",
+    RP2350_ARM_S = 0xe48b_ff59 => "Raspberry Pi RP2350, Arm secure",
+    RP2350_RISCV = 0xe48b_ff5a => "Raspberry Pi RP2350, RISC-V",
+}
+);
+
+/// Header at the start of each block
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq, Deserialize, Serialize)]
+#[repr(C)]
+pub struct BlockHeader {
+    /// Always [`MAGIC_START0`]
+    pub magic_start0: u32,
+    /// Always [`MAGIC_START1`]
+    pub magic_start1: u32,
+    /// Block flags
+    pub flags: Flags,
+    /// Address to write the payload to
+    pub target_addr: u32,
+    /// Number of payload bytes
+    pub payload_size: u32,
+    /// Position of the block in the file, from 0
+    pub block_no: u32,
+    /// Number of blocks in the file
+    pub num_blocks: u32,
+    /// Family ID if [`Flags::FAMILY_ID_PRESENT`], or file size if [`Flags::FILE_CONTAINER`]
+    pub family_id: u32,
+}
+impl BlockHeader {
+    pub const SIZE: usize = size_of::<Self>();
+}
+
+/// Block of a UF2 file
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct Block<'a> {
+    /// Header
+    pub header: BlockHeader,
+    /// Payload
+    pub data: &'a [u8],
+}
+
+impl<'a> Block<'a> {
+    /// Parse the block at the start of `data`
+    pub fn parse(data: &'a [u8]) -> Result<Self, Error> {
+        let block = data.get(..BLOCK_SIZE).ok_or(Error::Truncated)?;
+        let header: BlockHeader = from_bytes(block, false)?;
+        let magic_end: u32 = from_bytes(&block[BLOCK_SIZE - 4..], false)?;
+        if header.magic_start0 != MAGIC_START0
+            || header.magic_start1 != MAGIC_START1
+            || magic_end != MAGIC_END
+        {
+            return Err(Error::BadMagic);
+        }
+        let size = header.payload_size as usize;
+        if size > MAX_PAYLOAD_SIZE {
+            return Err(Error::Malformed);
+        }
+        Ok(Block {
+            header,
+            data: &block[BlockHeader::SIZE..BlockHeader::SIZE + size],
+        })
+    }
+
+    /// Whether the block is meant for main flash of the chip `family`, or of any chip if
+    /// `family` is `None`
+    pub fn is_flash(&self, family: Option<Family>) -> bool {
+        let flags = self.header.flags;
+        let family_matches = match family {
+            Some(family) => {
+                flags & Flags::FAMILY_ID_PRESENT != Flags::FAMILY_ID_PRESENT
+                    || Family::from(self.header.family_id) == family
+            }
+            None => true,
+        };
+        flags & (Flags::NOT_MAIN_FLASH | Flags::FILE_CONTAINER) == Flags::NONE && family_matches
+    }
+}
+
+/// Blocks of a UF2 file
+#[derive(Clone, Debug)]
+pub struct Blocks<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Blocks<'a> {
+    /// Blocks of the file `data`
+    pub fn new(data: &'a [u8]) -> Self {
+        Blocks { data }
+    }
+}
+
+impl<'a> Iterator for Blocks<'a> {
+    type Item = Result<Block<'a>, Error>;
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.data.is_empty() {
+            return None;
+        }
+        let result = Block::parse(self.data);
+        self.data = match result {
+            Ok(_) => &self.data[BLOCK_SIZE..],
+            Err(_) => &[],
+        };
+        Some(result)
+    }
+}
+
+impl<'a> ElfFile<'a> {
+    /// Call `f` with the address and payload of each page holding data of the regions
+    /// selected by `options`, in order of address
+    fn pages(
+        &self,
+        options: &BinaryOptions,
+        mut f: impl FnMut(u32, &[u8; PAYLOAD_SIZE]) -> Result<(), Error>,
+    ) -> Result<(), Error> {
+        let mut page: Option<u32> = None;
+        let mut payload = [0; PAYLOAD_SIZE];
+        self.sorted_regions(options, |region| {
+            if region.data.is_empty() {
+                return Ok(());
+            }
+            // Pages are 32-bit, so the page after the last doesn't overflow
+            let last = region.address + (region.data.len() as u64 - 1);
+            u32::try_from(last).map_err(|_| Error::Unsupported)?;
+            let (mut address, mut data) = (region.address as u32, region.data);
+            while !data.is_empty() {
+                let start = address & !(PAYLOAD_SIZE as u32 - 1);
+                if page != Some(start) {
+                    if let Some(page) = page {
+                        f(page, &payload)?;
+                    }
+                    page = Some(start);
+                    payload = [0; PAYLOAD_SIZE];
+                }
+                let offset = (address - start) as usize;
+                let len = data.len().min(PAYLOAD_SIZE - offset);
+                payload[offset..offset + len].copy_from_slice(&data[..len]);
+                address = address.wrapping_add(len as u32);
+                data = &data[len..];
+            }
+            Ok(())
+        })?;
+        match page {
+            Some(page) => f(page, &payload),
+            None => Ok(()),
+        }
+    }
+
+    /// Write the regions selected by `options` as a UF2 file to `f`, a block at a time, and
+    /// return the number of blocks
+    ///
+    /// Flash is programmed at the load address, so `options.physical` is normally set. Each
+    /// block holds a 256-byte page, with the parts not covered by a region zeroed. Blocks
+    /// are marked with `family` if it's given. Overlapping regions are
+    /// [`Error::Malformed`], and regions above 4GiB are [`Error::Unsupported`].
+    pub fn uf2(
+        &self,
+        options: &BinaryOptions,
+        family: Option<Family>,
+        mut f: impl FnMut(&[u8]) -> Result<(), Error>,
+    ) -> Result<u32, Error> {
+        let mut num_blocks = 0u32;
+        self.pages(options, |_, _| {
+            num_blocks = num_blocks.checked_add(1).ok_or(Error::Unsupported)?;
+            Ok(())
+        })?;
+
+        let mut header = BlockHeader {
+            magic_start0: MAGIC_START0,
+            magic_start1: MAGIC_START1,
+            flags: Flags::NONE,
+            payload_size: PAYLOAD_SIZE as u32,
+            num_blocks,
+            ..Default::default()
+        };
+        if let Some(family) = family {
+            header.flags = Flags::FAMILY_ID_PRESENT;
+            header.family_id = family.into();
+        }
+        self.pages(options, |page, payload| {
+            let mut block = [0; BLOCK_SIZE];
+            header.target_addr = page;
+            to_bytes(&header, false, &mut block)?;
+            block[BLOCK_SIZE - 4..].copy_from_slice(&MAGIC_END.to_le_bytes());
+            block[BlockHeader::SIZE..BlockHeader::SIZE + PAYLOAD_SIZE].copy_from_slice(payload);
+            f(&block)?;
+            header.block_no += 1;
+            Ok(())
+        })?;
+        Ok(num_blocks)
+    }
+}
+
+/// Convert the main flash blocks of the UF2 file `data` for `family`, or any family if
+/// `None`, into a minimal ELF file with a `PT::LOAD` segment per contiguous region, see
+/// [`write_elf()`]
+pub fn uf2_to_elf(
+    data: &[u8],
+    family: Option<Family>,
+    ehdr: &Ehdr64,
+    f: impl FnMut(&[u8]) -> Result<(), Error>,
+) -> Result<(), Error> {
+    let chunks = Blocks::new(data).filter_map(move |block| match block {
+        Ok(block) if block.is_flash(family) => {
+            Some(Ok((u64::from(block.header.target_addr), block.data)))
+        }
+        Ok(_) => None,
+        Err(err) => Some(Err(err)),
+    });
+    write_elf(ehdr, chunks, f)
+}
+
+#[cfg(test)]
+mod test {
+    extern crate std;
+    use super::*;
+    use crate::ehdr::{EIC, EID, EM, ET};
+    use crate::phdr::{PF, PT};
+    use crate::shdr::SHT;
+    use crate::testutil::Builder;
+    use std::vec;
+    use std::vec::Vec;
+
+    #[test]
+    fn uf2() {
+        // Text loaded at 0x10000000, with data at 0x20000000 loaded right after it
+        let mut builder = Builder::new(true, false, ET::EXEC, EM::ARM);
+        let text = builder.section(".text", SHT::PROGBITS, 0x1000_0000, vec![1; 0x180]);
+        let data = builder.section(".data", SHT::PROGBITS, 0x2000_0000, vec![2; 0x100]);
+        builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+        let data = builder.segment(PT::LOAD, PF::R | PF::W, &[data]);
+        builder.phdr_mut(data).p_paddr = 0x1000_0180;
+        let elf = builder.build();
+        let file = ElfFile::new(&elf).unwrap();
+
+        let options = BinaryOptions {
+            physical: true,
+            ..Default::default()
+        };
+        let mut uf2 = Vec::new();
+        let result = file.uf2(&options, Some(Family::RP2040), |block| {
+            uf2.extend(block);
+            Ok(())
+        });
+        assert_eq!(result, Ok(3));
+        assert_eq!(uf2.len(), 3 * BLOCK_SIZE);
+        let blocks: Vec<_> = Blocks::new(&uf2).map(Result::unwrap).collect();
+        assert_eq!(blocks[1].header.block_no, 1);
+        assert_eq!(blocks[1].header.num_blocks, 3);
+        assert_eq!(blocks[1].header.target_addr, 0x1000_0100);
+        assert_eq!(blocks[1].header.family_id, u32::from(Family::RP2040));
+        // The page holding the end of the text and start of the data
+        assert_eq!(&blocks[1].data[..0x80], &[1; 0x80][..]);
+        assert_eq!(&blocks[1].data[0x80..], &[2; 0x80][..]);
+        // The last page is zero-filled
+        assert_eq!(&blocks[2].data[..0x80], &[2; 0x80][..]);
+        assert_eq!(&blocks[2].data[0x80..], &[0; 0x80][..]);
+
+        let ehdr = Ehdr64::new(EIC::ELF32, EID::LSB, ET::EXEC, EM::ARM);
+        let mut converted = Vec::new();
+        let result = uf2_to_elf(&uf2, Some(Family::RP2040), &ehdr, |data| {
+            converted.extend(data);
+            Ok(())
+        });
+        assert_eq!(result, Ok(()));
+        let converted = ElfFile::new(&converted).unwrap();
+        let regions: Vec<_> = converted
+            .regions(&BinaryOptions::default())
+            .map(Result::unwrap)
+            .collect();
+        assert_eq!(regions.len(), 1);
+        assert_eq!(regions[0].address, 0x1000_0000);
+        assert_eq!(regions[0].data.len(), 0x300);
+
+        // Blocks for other chips are skipped
+        let mut converted = Vec::new();
+        uf2_to_elf(&uf2, Some(Family::NRF52840), &ehdr, |data| {
+            converted.extend(data);
+            Ok(())
+        })
+        .unwrap();
+        assert_eq!(ElfFile::new(&converted).unwrap().ehdr().e_phnum, 0);
+
+        // Regions must fit 32-bit addresses, and not overlap
+        let mut builder = Builder::new(false, false, ET::EXEC, EM::RISCV);
+        let text = builder.section(".text", SHT::PROGBITS, 0xffff_ff80, vec![1; 0x80]);
+        builder.section(".data", SHT::PROGBITS, 0xffff_ff80, vec![2; 0x81]);
+        builder.segment(PT::LOAD, PF::R | PF::X, &[text]);
+        let elf = builder.build();
+        let file = ElfFile::new(&elf).unwrap();
+        assert_eq!(file.uf2(&options, None, |_| Ok(())), Ok(1));
+        let options = BinaryOptions {
+            sections: &[".data"],
+            ..options
+        };
+        let result = file.uf2(&options, None, |_| Ok(()));
+        assert_eq!(result, Err(Error::Unsupported));
+        let options = BinaryOptions {
+            sections: &[".text", ".data"],
+            ..options
+        };
+        let result = file.uf2(&options, None, |_| Ok(()));
+        assert_eq!(result, Err(Error::Malformed));
+
+        uf2[BLOCK_SIZE - 1] = 0;
+        assert!(matches!(
+            Blocks::new(&uf2).next(),
+            Some(Err(Error::BadMagic))
+        ));
+    }
+}